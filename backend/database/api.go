@@ -33,6 +33,7 @@ type Database interface {
 
 	CreateRender(userId interface{}, projectId interface{}, name string) (interface{}, error)
 	DeleteRender(userId interface{}, projectId interface{}, renderId interface{}) error
+	RestartRender(userId interface{}, projectId interface{}, renderId interface{}) error
 
 	GetTextures() ([]models.Asset, error)
 	GetTexture(id interface{}) (*models.Asset, error)
@@ -9,4 +9,5 @@ type Render struct {
 	Started  time.Time   `json:"started" bson:"started"`
 	Finished *time.Time  `json:"finished, omitempty" bson:"finished, omitempty"`
 	Icon     string      `json:"icon" bson:"icon"`
+	Error    string      `json:"error,omitempty" bson:"error,omitempty"`
 }
@@ -217,6 +217,33 @@ func (m MongoDatabase) DeleteRender(userId interface{}, projectId interface{}, r
 	return err
 }
 
+// RestartRender marks a finished render as running again, it is finished by the raytracer
+func (m MongoDatabase) RestartRender(userId interface{}, projectId interface{}, renderId interface{}) error {
+	ctx, cancel := context.WithTimeout(context.Background(), time.Second*5)
+	defer cancel()
+	res, err := m.Users.UpdateOne(ctx, bson.D{
+		{"_id", userId},
+	}, bson.D{
+		{"$set", bson.D{
+			{"projects.$[project].renders.$[render].status", 0.0},
+		}},
+		{"$unset", bson.D{
+			{"projects.$[project].renders.$[render].finished", ""},
+		}},
+	}, &options.UpdateOptions{
+		ArrayFilters: &options.ArrayFilters{
+			Filters: []interface{}{bson.M{"project._id": projectId}, bson.M{"render._id": renderId}},
+		},
+	})
+	if err != nil {
+		return err
+	}
+	if res.ModifiedCount == 0 {
+		return ErrProjectNotFound
+	}
+	return nil
+}
+
 func (m MongoDatabase) GetSession(id interface{}) (*models.Session, error) {
 	ctx, cancel := context.WithTimeout(context.Background(), time.Second*3)
 	defer cancel()
@@ -87,6 +87,9 @@ func main() {
 	auth.HandleFunc("/project/{id}/data", authenticated.ProjectData).Methods("GET", "POST")
 	auth.HandleFunc("/project/{id}/render/{width}/{height}/{samples}", authenticated.Render).Methods("POST")
 	auth.HandleFunc("/project/{id}/render/{render}", authenticated.Render).Methods("DELETE")
+	auth.HandleFunc("/project/{id}/render/{render}/extend/{samples}", authenticated.ExtendRender).Methods("POST")
+	auth.HandleFunc("/project/{id}/lightmap", authenticated.Lightmap).Methods("GET")
+	auth.HandleFunc("/project/{id}/lightmap/{samples}", authenticated.Lightmap).Methods("POST")
 
 	http.Handle("/", r)
 	fmt.Printf("Listening on port %d\n", port)
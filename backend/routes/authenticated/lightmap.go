@@ -0,0 +1,88 @@
+package authenticated
+
+import (
+	"bytes"
+	"context"
+	"fmt"
+	"io/ioutil"
+	"net/http"
+	"strconv"
+	"time"
+
+	"github.com/Texyfore/Archytex/backend/database"
+	"github.com/Texyfore/Archytex/backend/logging"
+	"github.com/Texyfore/Archytex/backend/projectloaders"
+	"github.com/gorilla/mux"
+	"github.com/streadway/amqp"
+	"go.mongodb.org/mongo-driver/bson/primitive"
+)
+
+// lightmapPath is where the raytracer saves the lightmap baked for a project
+func lightmapPath(projectId string) string {
+	return projectId + ".alm"
+}
+
+// Lightmap bakes the lighting of a project's solids for the editor's lit preview.
+// Until the bake finishes the lightmap is empty.
+func Lightmap(w http.ResponseWriter, r *http.Request) {
+	params := mux.Vars(r)
+	_projectId, err := primitive.ObjectIDFromHex(params["id"])
+	if err != nil {
+		logging.Error(w, r, err, "invalid project id", http.StatusBadRequest)
+		return
+	}
+	projectId := _projectId.Hex()
+	_, err = loadProject(r, projectId)
+	if err == database.ErrProjectNotFound {
+		logging.Error(w, r, err, "Project not found", http.StatusNotFound)
+		return
+	}
+	if err != nil {
+		logging.Error(w, r, err, "Project Unavailable", http.StatusInternalServerError)
+		return
+	}
+	if r.Method == "GET" {
+		w.Header().Add("Content-Type", "application/octet-stream")
+		err = projectloaders.CurrentProjectLoader.GetProject(w, r, lightmapPath(projectId))
+		if err != nil {
+			logging.Error(w, r, err, "Could not get lightmap", http.StatusInternalServerError)
+		}
+		return
+	}
+	samples, err := strconv.Atoi(params["samples"])
+	if err != nil || samples <= 0 {
+		logging.Error(w, r, err, "invalid samples field", http.StatusBadRequest)
+		return
+	}
+	scene, err := ioutil.ReadAll(r.Body)
+	if err != nil {
+		logging.Error(w, r, err, "couldn't bake lightmap", http.StatusBadGateway)
+		return
+	}
+	// The previous lightmap doesn't match the scene anymore
+	err = projectloaders.CurrentProjectLoader.SaveProject(bytes.NewReader(nil), lightmapPath(projectId))
+	if err != nil {
+		logging.Error(w, r, err, "couldn't bake lightmap", http.StatusInternalServerError)
+		return
+	}
+	ctx, cancel := context.WithTimeout(context.Background(), time.Second*5)
+	defer cancel()
+	err = database.RedisClient.Set(ctx, fmt.Sprintf("archyrt:%s:samples", projectId), samples, 0).Err()
+	if err != nil {
+		logging.Error(w, r, err, "couldn't bake lightmap", http.StatusInternalServerError)
+		return
+	}
+	err = database.RedisClient.Set(ctx, fmt.Sprintf("archyrt:%s:scene", projectId), scene, 0).Err()
+	if err != nil {
+		logging.Error(w, r, err, "couldn't bake lightmap", http.StatusInternalServerError)
+		return
+	}
+	err = database.RabbitmqChannel.Publish("", "archyrt:bake", false, false, amqp.Publishing{
+		ContentType: "text/plain",
+		Body:        []byte(projectId),
+	})
+	if err != nil {
+		logging.Error(w, r, err, "couldn't bake lightmap", http.StatusInternalServerError)
+		return
+	}
+}
@@ -2,10 +2,14 @@ package authenticated
 
 import (
 	"context"
+	"encoding/json"
 	"fmt"
 	"io/ioutil"
 	"net/http"
+	"os"
+	"path"
 	"strconv"
+	"strings"
 	"time"
 
 	"github.com/Texyfore/Archytex/backend/database"
@@ -16,6 +20,63 @@ import (
 	"go.mongodb.org/mongo-driver/bson/primitive"
 )
 
+// Render modes understood by the raytracer, see archyrt_core::renderers::mode::RenderMode
+var renderModes = []string{"pathtraced", "spectral", "albedo", "normal", "depth", "depth-normalized", "uv", "texture-id", "instance-id", "ao", "heatmap"}
+
+func validRenderMode(mode string) bool {
+	for _, m := range renderModes {
+		if m == mode {
+			return true
+		}
+	}
+	return false
+}
+
+// Color spaces lit renders can be saved in, see archyrt_core::spectrum::color_space::ColorSpace
+var colorSpaces = []string{"srgb", "display-p3", "xyz"}
+
+func validColorSpace(space string) bool {
+	for _, s := range colorSpaces {
+		if s == space {
+			return true
+		}
+	}
+	return false
+}
+
+// Fields of a render job in redis, kept after the render finishes so it can be extended
+var jobFields = []string{"width", "height", "samples", "mode", "colorspace", "scale", "crop", "base", "settings", "scene"}
+
+// checkpointPath is where the raytracer keeps the accumulated samples of a render, empty if checkpoints are disabled
+func checkpointPath(renderId string) string {
+	directory := os.Getenv("CHECKPOINTS_PATH")
+	if directory == "" {
+		return ""
+	}
+	return path.Join(directory, renderId+".ckpt")
+}
+
+// parseCrop parses a region given as x,y,width,height, which has to be inside the image
+func parseCrop(crop string, width int, height int) (string, bool) {
+	parts := strings.Split(crop, ",")
+	if len(parts) != 4 {
+		return "", false
+	}
+	values := make([]int, 4)
+	for i, part := range parts {
+		value, err := strconv.Atoi(strings.TrimSpace(part))
+		if err != nil || value < 0 {
+			return "", false
+		}
+		values[i] = value
+	}
+	x, y, w, h := values[0], values[1], values[2], values[3]
+	if w == 0 || h == 0 || x+w > width || y+h > height {
+		return "", false
+	}
+	return fmt.Sprintf("%d,%d,%d,%d", x, y, w, h), true
+}
+
 func Render(w http.ResponseWriter, r *http.Request) {
 	session := models.UseSession(r.Context())
 	params := mux.Vars(r)
@@ -49,6 +110,25 @@ func Render(w http.ResponseWriter, r *http.Request) {
 			logging.Error(w, r, err, "could not remove render", http.StatusBadRequest)
 			return
 		}
+		// The job and its checkpoint are only needed to extend the render
+		ctx, cancel := context.WithTimeout(context.Background(), time.Second*5)
+		defer cancel()
+		keys := make([]string, len(jobFields))
+		for i, field := range jobFields {
+			keys[i] = fmt.Sprintf("archyrt:%s:%s", renderId.Hex(), field)
+		}
+		err = database.RedisClient.Del(ctx, keys...).Err()
+		if err != nil {
+			logging.Error(w, r, err, "could not remove render", http.StatusInternalServerError)
+			return
+		}
+		if checkpoint := checkpointPath(renderId.Hex()); checkpoint != "" {
+			err = os.Remove(checkpoint)
+			if err != nil && !os.IsNotExist(err) {
+				logging.Error(w, r, err, "could not remove render", http.StatusInternalServerError)
+				return
+			}
+		}
 	} else if r.Method == "POST" {
 		_projectId, ok := params["id"]
 		if !ok {
@@ -73,8 +153,47 @@ func Render(w http.ResponseWriter, r *http.Request) {
 			logging.Error(w, r, err, "invalid samples field", http.StatusBadRequest)
 			return
 		}
-		if width%4 != 0 || height%4 != 0 {
-			logging.Error(w, r, err, "Width and Height have to be divisible by 4", http.StatusBadRequest)
+		mode := r.URL.Query().Get("mode")
+		if mode != "" && !validRenderMode(mode) {
+			logging.Error(w, r, nil, "invalid mode field", http.StatusBadRequest)
+			return
+		}
+		colorSpace := r.URL.Query().Get("colorspace")
+		if colorSpace != "" && !validColorSpace(colorSpace) {
+			logging.Error(w, r, nil, "invalid colorspace field", http.StatusBadRequest)
+			return
+		}
+		if width <= 0 || height <= 0 {
+			logging.Error(w, r, nil, "Width and Height have to be positive", http.StatusBadRequest)
+			return
+		}
+		//Quick previews are rendered at a fraction of the resolution
+		scale := 1.0
+		if _scale := r.URL.Query().Get("scale"); _scale != "" {
+			scale, err = strconv.ParseFloat(_scale, 64)
+			if err != nil || scale <= 0 || scale > 1 {
+				logging.Error(w, r, err, "invalid scale field", http.StatusBadRequest)
+				return
+			}
+		}
+		//Only a region of the image is rendered, optionally pasted into a finished render
+		crop := r.URL.Query().Get("crop")
+		if crop != "" {
+			crop, ok = parseCrop(crop, width, height)
+			if !ok {
+				logging.Error(w, r, nil, "invalid crop field", http.StatusBadRequest)
+				return
+			}
+		}
+		base := r.URL.Query().Get("base")
+		if base != "" && crop == "" {
+			logging.Error(w, r, nil, "base requires a crop", http.StatusBadRequest)
+			return
+		}
+		// Shutter interval, prop motion and media, checked in full by the raytracer
+		settings := r.URL.Query().Get("settings")
+		if settings != "" && !json.Valid([]byte(settings)) {
+			logging.Error(w, r, nil, "invalid settings field", http.StatusBadRequest)
 			return
 		}
 		projectId, err := primitive.ObjectIDFromHex(_projectId)
@@ -87,6 +206,19 @@ func Render(w http.ResponseWriter, r *http.Request) {
 			logging.Error(w, r, err, "could not find project", http.StatusNotFound)
 			return
 		}
+		if base != "" {
+			baseId, err := primitive.ObjectIDFromHex(base)
+			found := false
+			for _, render := range project.Renders {
+				if err == nil && render.Id == baseId && render.Finished != nil && render.Error == "" {
+					found = true
+				}
+			}
+			if !found {
+				logging.Error(w, r, err, "base render not found", http.StatusNotFound)
+				return
+			}
+		}
 		name := fmt.Sprintf("%s-%d", project.Title, len(project.Renders)+1)
 		id, err := database.CurrentDatabase.CreateRender(session.User.Id, projectId, name)
 		if err != nil {
@@ -116,6 +248,48 @@ func Render(w http.ResponseWriter, r *http.Request) {
 			logging.Error(w, r, err, "couldn't create render", http.StatusInternalServerError)
 			return
 		}
+		if mode != "" {
+			err = database.RedisClient.Set(ctx, fmt.Sprintf("archyrt:%s:mode", task_id), mode, 0).Err()
+			if err != nil {
+				logging.Error(w, r, err, "couldn't create render", http.StatusInternalServerError)
+				return
+			}
+		}
+		if colorSpace != "" {
+			err = database.RedisClient.Set(ctx, fmt.Sprintf("archyrt:%s:colorspace", task_id), colorSpace, 0).Err()
+			if err != nil {
+				logging.Error(w, r, err, "couldn't create render", http.StatusInternalServerError)
+				return
+			}
+		}
+		if scale != 1 {
+			err = database.RedisClient.Set(ctx, fmt.Sprintf("archyrt:%s:scale", task_id), scale, 0).Err()
+			if err != nil {
+				logging.Error(w, r, err, "couldn't create render", http.StatusInternalServerError)
+				return
+			}
+		}
+		if crop != "" {
+			err = database.RedisClient.Set(ctx, fmt.Sprintf("archyrt:%s:crop", task_id), crop, 0).Err()
+			if err != nil {
+				logging.Error(w, r, err, "couldn't create render", http.StatusInternalServerError)
+				return
+			}
+		}
+		if base != "" {
+			err = database.RedisClient.Set(ctx, fmt.Sprintf("archyrt:%s:base", task_id), base, 0).Err()
+			if err != nil {
+				logging.Error(w, r, err, "couldn't create render", http.StatusInternalServerError)
+				return
+			}
+		}
+		if settings != "" {
+			err = database.RedisClient.Set(ctx, fmt.Sprintf("archyrt:%s:settings", task_id), settings, 0).Err()
+			if err != nil {
+				logging.Error(w, r, err, "couldn't create render", http.StatusInternalServerError)
+				return
+			}
+		}
 		err = database.RedisClient.Set(ctx, fmt.Sprintf("archyrt:%s:scene", task_id), bytes, 0).Err()
 		if err != nil {
 			logging.Error(w, r, err, "couldn't create render", http.StatusInternalServerError)
@@ -131,3 +305,73 @@ func Render(w http.ResponseWriter, r *http.Request) {
 		}
 	}
 }
+
+// ExtendRender renders more samples of a finished render, continuing from its checkpoint
+func ExtendRender(w http.ResponseWriter, r *http.Request) {
+	session := models.UseSession(r.Context())
+	params := mux.Vars(r)
+	projectId, err := primitive.ObjectIDFromHex(params["id"])
+	if err != nil {
+		logging.Error(w, r, err, "invalid project id", http.StatusBadRequest)
+		return
+	}
+	renderId, err := primitive.ObjectIDFromHex(params["render"])
+	if err != nil {
+		logging.Error(w, r, err, "invalid render id", http.StatusBadRequest)
+		return
+	}
+	samples, err := strconv.Atoi(params["samples"])
+	if err != nil {
+		logging.Error(w, r, err, "invalid samples field", http.StatusBadRequest)
+		return
+	}
+	project, err := database.CurrentDatabase.GetProject(session.User.Id, projectId)
+	if err != nil || project == nil {
+		logging.Error(w, r, err, "could not find project", http.StatusNotFound)
+		return
+	}
+	var render *models.Render
+	for i := range project.Renders {
+		if project.Renders[i].Id == renderId {
+			render = &project.Renders[i]
+		}
+	}
+	if render == nil {
+		logging.Error(w, r, nil, "render not found", http.StatusNotFound)
+		return
+	}
+	if render.Finished == nil || render.Error != "" {
+		logging.Error(w, r, nil, "only finished renders can be extended", http.StatusConflict)
+		return
+	}
+	ctx, cancel := context.WithTimeout(context.Background(), time.Second*5)
+	defer cancel()
+	task_id := renderId.Hex()
+	current, err := database.RedisClient.Get(ctx, fmt.Sprintf("archyrt:%s:samples", task_id)).Int()
+	if err != nil {
+		logging.Error(w, r, err, "the render can no longer be extended", http.StatusGone)
+		return
+	}
+	if samples <= current {
+		logging.Error(w, r, nil, "samples have to exceed those of the render", http.StatusBadRequest)
+		return
+	}
+	err = database.RedisClient.Set(ctx, fmt.Sprintf("archyrt:%s:samples", task_id), samples, 0).Err()
+	if err != nil {
+		logging.Error(w, r, err, "couldn't extend render", http.StatusInternalServerError)
+		return
+	}
+	err = database.CurrentDatabase.RestartRender(session.User.Id, projectId, renderId)
+	if err != nil {
+		logging.Error(w, r, err, "couldn't extend render", http.StatusInternalServerError)
+		return
+	}
+	err = database.RabbitmqChannel.Publish("", "archyrt:dispatch", false, false, amqp.Publishing{
+		ContentType: "text/plain",
+		Body:        []byte(task_id + "#" + session.User.Id.(primitive.ObjectID).Hex() + "#" + projectId.Hex()),
+	})
+	if err != nil {
+		logging.Error(w, r, err, "couldn't extend render", http.StatusInternalServerError)
+		return
+	}
+}
@@ -21,10 +21,44 @@ init().then(() => {
                 document.exitPointerLock();
                 rightDown = false;
             }
+        },
+        (outline) => {
+            console.log(`[wasm] outline: ${outline.layers.length} layers, ${outline.groups.length} groups, ${outline.objects.length} objects`);
+        },
+        (buf) => {
+            // Kept in local storage so it can be pasted into other scenes and tabs
+            let binary = "";
+            for (const byte of buf) {
+                binary += String.fromCharCode(byte);
+            }
+            localStorage.setItem("clipboard", btoa(binary));
+            console.log(`[wasm] copied (${buf.length})`);
+        },
+        () => {
+            const stored = localStorage.getItem("clipboard");
+            if (stored !== null) {
+                sender.paste(Uint8Array.from(atob(stored), c => c.charCodeAt(0)));
+            }
+        },
+        (entries, position) => {
+            console.log(`[wasm] history: ${position} of ${entries.length} steps`);
+        },
+        (numeric) => {
+            if (numeric !== null) {
+                console.log(`[wasm] ${numeric}`);
+            }
+        },
+        (error) => {
+            console.error(`[wasm] keymap rejected: ${error}`);
         });
 
     const sender = channel.sender();
 
+    const keymap = localStorage.getItem("keymap");
+    if (keymap !== null) {
+        sender.setKeymap(keymap);
+    }
+
     canvas.addEventListener("mousedown", ev => {
         if (ev.button === 2) {
             sender.setPointerLock(true);
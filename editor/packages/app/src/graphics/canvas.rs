@@ -1,4 +1,4 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{collections::HashMap, ops::Range, rc::Rc};
 
 use asset::{GizmoID, PropID, TextureID};
 use gpu::{Buffer, Uniform};
@@ -14,7 +14,7 @@ use super::{
 pub struct Canvas {
     pub(super) camera_matrices: CameraMatrices,
     pub(super) line_meshes: Vec<LineMesh>,
-    pub(super) solids: HashMap<TextureID, HashMap<*const Buffer<SolidVertex>, Vec<u32>>>,
+    pub(super) solids: HashMap<TextureID, HashMap<*const SolidMesh, Vec<u32>>>,
     pub(super) ground_meshes: Vec<GroundMesh>,
     pub(super) props: HashMap<PropID, Vec<PropData>>,
     pub(super) gizmo_groups: Vec<GizmoGroup>,
@@ -31,12 +31,13 @@ impl Canvas {
         self.line_meshes.push(line_mesh);
     }
 
-    pub fn draw_solid(&mut self, textures: [TextureID; 6], mesh: &SolidMesh) {
-        for (i, texture) in textures.into_iter().enumerate() {
+    /// Draws every face of the mesh with its texture
+    pub fn draw_solid(&mut self, textures: &[TextureID], mesh: &SolidMesh) {
+        for (i, texture) in textures.iter().enumerate() {
             self.solids
-                .entry(texture)
+                .entry(*texture)
                 .or_default()
-                .entry(as_key(&mesh.vertices))
+                .entry(as_key(mesh))
                 .or_default()
                 .push(i as u32)
         }
@@ -80,6 +81,9 @@ impl Share for LineMesh {
 
 pub struct SolidMesh {
     pub(super) vertices: Buffer<SolidVertex>,
+    pub(super) triangles: Buffer<[u16; 3]>,
+    /// Triangles of every face
+    pub(super) faces: Vec<Range<u32>>,
 }
 
 pub struct GroundMesh {
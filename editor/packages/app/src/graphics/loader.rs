@@ -1,6 +1,6 @@
 use std::rc::Rc;
 
-use asset::{BoundingBox, GizmoID, PropID, TextureID};
+use asset::{BoundingBox, GizmoID, Lightmap, PropID, TextureID};
 use gpu::{BufferUsages, Gpu, Image, Sampler, Texture};
 
 use crate::{Resource, ResourceKind};
@@ -44,6 +44,11 @@ impl ResourceLoader {
                     let mesh = load_gizmo(&self.gpu, &resource.buf);
                     return Some(LoadedResource::Gizmo { id, mesh });
                 }
+                ResourceKind::Lightmap => {
+                    let lightmap = Lightmap::decode(&resource.buf).unwrap();
+                    let texture = load_lightmap(&self.gpu, &self.sampler, &lightmap);
+                    return Some(LoadedResource::Lightmap { texture, lightmap });
+                }
             }
         }
 
@@ -65,6 +70,10 @@ pub enum LoadedResource {
         id: GizmoID,
         mesh: GizmoMesh,
     },
+    Lightmap {
+        texture: Texture,
+        lightmap: Lightmap,
+    },
 }
 
 fn load_texture(gpu: &Gpu, sampler: &Sampler, buf: &[u8]) -> Texture {
@@ -79,6 +88,17 @@ fn load_texture(gpu: &Gpu, sampler: &Sampler, buf: &[u8]) -> Texture {
     )
 }
 
+fn load_lightmap(gpu: &Gpu, sampler: &Sampler, lightmap: &Lightmap) -> Texture {
+    gpu.create_texture(
+        sampler,
+        Image {
+            width: lightmap.width,
+            height: lightmap.height,
+            buf: &lightmap.rgba8,
+        },
+    )
+}
+
 fn load_prop(gpu: &Gpu, buf: &[u8]) -> (BoundingBox, PropModel) {
     let prop = asset::Prop::decode(buf).unwrap();
     let model = PropModel {
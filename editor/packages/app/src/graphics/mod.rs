@@ -4,9 +4,10 @@ mod renderer;
 
 pub mod structures;
 
-use std::{mem::size_of, rc::Rc};
+use std::{collections::HashMap, mem::size_of, ops::Range, rc::Rc};
 
-use asset::TextureID;
+use asset::{Lightmap, TextureID};
+use cgmath::Vector2;
 use gpu::{BufferUsages, Gpu};
 use winit::window::Window;
 
@@ -31,16 +32,34 @@ pub fn init(window: &Window) -> (Renderer, Graphics, ResourceLoader) {
 
     (
         Renderer::new(gpu.clone(), surface),
-        Graphics { gpu: gpu.clone() },
+        Graphics {
+            gpu: gpu.clone(),
+            lightmap: HashMap::new(),
+        },
         ResourceLoader::new(gpu, sampler),
     )
 }
 
 pub struct Graphics {
     gpu: Rc<Gpu>,
+    lightmap: HashMap<Vec<[i32; 3]>, Vec<Vector2<f32>>>,
 }
 
 impl Graphics {
+    /// Remembers where the faces of the lightmap are, solids have to be recalculated to pick it up
+    pub fn set_lightmap(&mut self, lightmap: &Lightmap) {
+        self.lightmap = lightmap
+            .faces
+            .iter()
+            .map(|face| (face.corners.clone(), lightmap.texcoords(face)))
+            .collect();
+    }
+
+    /// Lightmap coordinates of the corners of a face, if it was baked in this exact position
+    pub fn lightmap_texcoords(&self, corners: &[[i32; 3]]) -> Option<&[Vector2<f32>]> {
+        self.lightmap.get(corners).map(Vec::as_slice)
+    }
+
     pub fn create_line_mesh(&self, descriptor: LineMeshDescriptor) -> LineMesh {
         LineMesh {
             vertices: Rc::new(
@@ -63,12 +82,19 @@ impl Graphics {
         self.gpu.write_buffer(&mesh.vertices, vertices);
     }
 
-    pub fn create_solid_mesh(&self) -> SolidMesh {
+    /// Mesh for the faces of a solid, its vertices are written by [Graphics::write_solid_mesh]
+    pub fn create_solid_mesh(
+        &self,
+        vertices: usize,
+        triangles: &[[u16; 3]],
+        faces: Vec<Range<u32>>,
+    ) -> SolidMesh {
         SolidMesh {
-            vertices: self.gpu.create_buffer_uninit(
-                size_of::<SolidVertex>() * 24,
-                BufferUsages::VERTEX | BufferUsages::COPY_DST,
-            ),
+            vertices: self
+                .gpu
+                .create_buffer_uninit(vertices, BufferUsages::VERTEX | BufferUsages::COPY_DST),
+            triangles: self.gpu.create_buffer(triangles, BufferUsages::INDEX),
+            faces,
         }
     }
 
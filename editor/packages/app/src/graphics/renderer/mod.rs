@@ -4,10 +4,7 @@ mod resources;
 use std::rc::Rc;
 
 use asset::{GizmoID, PropID, TextureID};
-use gpu::{
-    Buffer, BufferUsages, DepthBuffer, Gpu, InstanceConfig, MsaaFramebuffer, Surface, Texture,
-    Uniform,
-};
+use gpu::{DepthBuffer, Gpu, InstanceConfig, MsaaFramebuffer, Surface, Texture, Uniform};
 
 use self::{pipelines::Pipelines, resources::Resources};
 
@@ -24,7 +21,8 @@ pub struct Renderer {
     resources: Resources,
     camera: Uniform<CameraMatrices>,
     grid: Uniform<[i32; 4]>,
-    solid_tris: Buffer<[u16; 3]>,
+    lightmap: Option<Texture>,
+    lit: bool,
 }
 
 impl Renderer {
@@ -35,7 +33,6 @@ impl Renderer {
         let resources = Resources::default();
         let camera = gpu.create_uniform(&CameraMatrices::default());
         let grid = gpu.create_uniform(&[100; 4]);
-        let solid_tris = gpu.create_buffer(&gen_solid_tris(), BufferUsages::INDEX);
 
         Self {
             gpu,
@@ -46,10 +43,20 @@ impl Renderer {
             resources,
             camera,
             grid,
-            solid_tris,
+            lightmap: None,
+            lit: false,
         }
     }
 
+    pub fn set_lightmap(&mut self, lightmap: Texture) {
+        self.lightmap = Some(lightmap);
+    }
+
+    /// Shades solids with the baked lightmap instead of the flat editor lighting
+    pub fn set_lit_preview(&mut self, lit: bool) {
+        self.lit = lit;
+    }
+
     pub fn add_texture(&mut self, id: TextureID, texture: Texture) {
         self.resources.add_texture(id, texture);
     }
@@ -72,7 +79,8 @@ impl Renderer {
 
     pub fn render(&self, canvas: Canvas) {
         self.gpu.set_uniform(&self.camera, &canvas.camera_matrices);
-        self.gpu.set_uniform(&self.grid, &[canvas.grid_len; 4]);
+        self.gpu
+            .set_uniform(&self.grid, &[canvas.grid_len, self.lit as i32, 0, 0]);
 
         let mut frame = self.gpu.begin_frame(&self.surface);
 
@@ -89,17 +97,19 @@ impl Renderer {
 
             pass.set_pipeline(&self.pipelines.solid);
             pass.set_uniform(2, &self.grid);
-            pass.set_triangles(&self.solid_tris);
 
             for (texture, geometry) in canvas.solids {
                 if let Some(texture) = self.resources.texture(texture) {
                     pass.set_texture(1, texture);
-                    for (vertices, faces) in geometry {
+                    // Something has to be bound even before a lightmap is loaded, unbaked faces don't sample it
+                    pass.set_texture(3, self.lightmap.as_ref().unwrap_or(texture));
+                    for (mesh, faces) in geometry {
                         // should be safe
-                        let vertices = unsafe { &*vertices };
-                        pass.set_vertices(vertices);
+                        let mesh = unsafe { &*mesh };
+                        pass.set_vertices(&mesh.vertices);
+                        pass.set_triangles(&mesh.triangles);
                         for face in faces {
-                            pass.draw_face(face);
+                            pass.draw_triangle_range(mesh.faces[face as usize].clone());
                         }
                     }
                 }
@@ -168,14 +178,3 @@ impl Renderer {
         self.gpu.end_frame(frame);
     }
 }
-
-fn gen_solid_tris() -> Vec<[u16; 3]> {
-    (0..6)
-        .into_iter()
-        .map(|t0| {
-            let t0 = t0 * 4;
-            [[t0, t0 + 1, t0 + 2], [t0, t0 + 2, t0 + 3]].into_iter()
-        })
-        .flatten()
-        .collect()
-}
@@ -10,6 +10,9 @@ struct Attribs {
 
     [[location(3)]]
     tint: vec4<f32>;
+
+    [[location(4)]]
+    lightmap: vec2<f32>;
 };
 
 struct Vertex {
@@ -33,6 +36,12 @@ struct Vertex {
 
     [[location(5)]]
     grid_len: i32;
+
+    [[location(6)]]
+    lit: i32;
+
+    [[location(7)]]
+    lightmap: vec2<f32>;
 };
 
 struct Fragment {
@@ -47,7 +56,7 @@ struct Camera {
 
 struct GridParams {
     len: i32;
-    pad1: i32;
+    lit: i32;
     pad2: i32;
     pad3: i32;
 };
@@ -71,6 +80,8 @@ fn vertex(attribs: Attribs) -> Vertex {
     vertex.camera_position = (camera.view_to_world * vec4<f32>(0.0, 0.0, 0.0, 1.0)).xyz;
     vertex.world_position = attribs.position;
     vertex.grid_len = grid.len;
+    vertex.lit = grid.lit;
+    vertex.lightmap = attribs.lightmap;
 
     return vertex;
 }
@@ -110,9 +121,16 @@ var t_diffuse: texture_2d<f32>;
 [[group(1), binding(1)]]
 var s_diffuse: sampler;
 
+[[group(3), binding(0)]]
+var t_lightmap: texture_2d<f32>;
+
+[[group(3), binding(1)]]
+var s_lightmap: sampler;
+
 [[stage(fragment)]]
 fn fragment(vertex: Vertex) -> Fragment {
     var color = textureSample(t_diffuse, s_diffuse, vertex.texcoord);
+    var baked = textureSample(t_lightmap, s_lightmap, vertex.lightmap).rgb;
     var color_rgb = color.rgb;
     var color_a = color.a;
 
@@ -126,9 +144,16 @@ fn fragment(vertex: Vertex) -> Fragment {
 
     color_rgb = color_rgb + vec3<f32>(g * 0.5);
 
-    var light_dir = normalize(vertex.camera_position - vertex.world_position);
-    var diffuse = (max(dot(light_dir, vertex.normal), 0.0) + 0.8) * 0.4;
-    color_rgb = color_rgb * diffuse;
+    if (vertex.lit != 0 && vertex.lightmap.x >= 0.0) {
+        // Baked light is stored as light / (light + 1), tonemapped like the raytracer
+        var light = baked / max(vec3<f32>(1.0) - baked, vec3<f32>(0.004));
+        var exposed = color_rgb * light * 4.0;
+        color_rgb = exposed / (exposed + vec3<f32>(1.0));
+    } else {
+        var light_dir = normalize(vertex.camera_position - vertex.world_position);
+        var diffuse = (max(dot(light_dir, vertex.normal), 0.0) + 0.8) * 0.4;
+        color_rgb = color_rgb * diffuse;
+    }
 
     var dist = distance(vertex.world_position, vertex.camera_position);
     var mixval = pow(clamp(dist / 200.0, 0.0, 1.0), 4.0);
@@ -16,6 +16,7 @@ pub fn pipeline(gpu: &Gpu, surface: &Surface) -> Pipeline {
                 PipelineInput::Uniform, // Camera
                 PipelineInput::Texture, // Texture
                 PipelineInput::Uniform, // Grid
+                PipelineInput::Texture, // Lightmap
             ],
             vertex_buffers: &[VertexBufferLayout {
                 array_stride: size_of::<SolidVertex>() as u64,
@@ -25,6 +26,7 @@ pub fn pipeline(gpu: &Gpu, surface: &Surface) -> Pipeline {
                     1 => Float32x3, // Normal
                     2 => Float32x2, // Texcoord
                     3 => Float32x4, // Tint
+                    4 => Float32x2, // Lightmap
                 ],
             }],
             topology: PipelineTopology::Triangles,
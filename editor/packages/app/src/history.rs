@@ -0,0 +1,18 @@
+/// Steps of the scene's undo history, sent to the host whenever it changes
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct History {
+    /// Names of the steps, oldest first
+    pub entries: Vec<String>,
+    /// Number of steps that can be undone, the rest can be redone
+    pub position: usize,
+}
+
+impl History {
+    pub fn can_undo(&self) -> bool {
+        self.position > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.position < self.entries.len()
+    }
+}
@@ -3,8 +3,10 @@ mod button;
 mod color;
 mod data;
 mod graphics;
+mod history;
 mod logic;
 mod math;
+mod outline;
 
 use std::sync::mpsc::Receiver;
 
@@ -23,6 +25,8 @@ use winit::{
 use self::graphics::Canvas;
 
 pub use ascn::Ascn;
+pub use history::History;
+pub use outline::{Outline, OutlineCommand, OutlineGroup, OutlineLayer, OutlineObject};
 
 pub fn run(init: Init) {
     let window = init.winit.window;
@@ -30,7 +34,7 @@ pub fn run(init: Init) {
     let host = init.host;
     let from_host = init.receiver;
 
-    let (mut renderer, graphics, mut loader) = graphics::init(&window);
+    let (mut renderer, mut graphics, mut loader) = graphics::init(&window);
     let mut prop_info = PropInfoContainer::default();
 
     let mut logic = Logic::init(logic::Context {
@@ -72,6 +76,9 @@ pub fn run(init: Init) {
                 } => {
                     logic.key(code, state);
                 }
+                WindowEvent::ReceivedCharacter(c) => {
+                    logic.character(c);
+                }
                 WindowEvent::CursorMoved {
                     position: PhysicalPosition { x, y },
                     ..
@@ -112,15 +119,7 @@ pub fn run(init: Init) {
                             );
                         }
                         FromHost::LoadScene(ascn) => {
-                            logic.load_scene(
-                                logic::Context {
-                                    host: host.as_ref(),
-                                    graphics: &graphics,
-                                    prop_infos: &prop_info,
-                                    delta,
-                                },
-                                ascn.scene(),
-                            );
+                            logic.load_scene(ascn.scene());
                         }
                         FromHost::Texture(id) => {
                             logic.set_texture(TextureID(id));
@@ -189,6 +188,46 @@ pub fn run(init: Init) {
                         FromHost::LoadResource(resource) => {
                             loader.push_job(resource);
                         }
+                        FromHost::LitPreview(lit) => {
+                            renderer.set_lit_preview(lit);
+                        }
+                        FromHost::TextureLock(lock) => {
+                            logic.set_texture_lock(lock);
+                        }
+                        FromHost::Outline(command) => {
+                            logic.edit_outline(command);
+                        }
+                        FromHost::Undo => {
+                            logic.undo();
+                        }
+                        FromHost::Redo => {
+                            logic.redo();
+                        }
+                        FromHost::JumpHistory(position) => {
+                            logic.jump_history(position);
+                        }
+                        FromHost::HistoryBudget { entries, bytes } => {
+                            logic.set_history_budget(entries, bytes);
+                        }
+                        FromHost::Transform(transform) => {
+                            logic.transform(&transform);
+                        }
+                        FromHost::Keymap(json) => {
+                            if let Err(err) = logic.set_keymap(&json) {
+                                host.callback(ToHost::KeymapError(err));
+                            }
+                        }
+                        FromHost::Paste(buf) => {
+                            logic.paste(
+                                logic::Context {
+                                    host: host.as_ref(),
+                                    graphics: &graphics,
+                                    prop_infos: &prop_info,
+                                    delta,
+                                },
+                                &buf,
+                            );
+                        }
                     }
                 }
 
@@ -204,6 +243,11 @@ pub fn run(init: Init) {
                         LoadedResource::Gizmo { id, mesh } => {
                             renderer.add_gizmo(id, mesh);
                         }
+                        LoadedResource::Lightmap { texture, lightmap } => {
+                            renderer.set_lightmap(texture);
+                            graphics.set_lightmap(&lightmap);
+                            logic.relight();
+                        }
                     }
                 }
 
@@ -248,10 +292,23 @@ pub enum ToHost {
     SceneSaved(i32, Vec<u8>),
     Button(i32),
     PointerLocked(bool),
+    Outline(Outline),
+    /// Copied or cut elements for the host to keep until they are pasted
+    Copied(Vec<u8>),
+    /// The host should send back what was last copied with [FromHost::Paste]
+    PasteRequested,
+    History(History),
+    /// Numbers typed into the active tool, [None] once it ends
+    NumericInput(Option<String>),
+    /// Why the keymap sent with [FromHost::Keymap] was rejected, the previous one stays in use
+    KeymapError(String),
 }
 
 pub enum FromHost {
-    Resolution { width: u32, height: u32 },
+    Resolution {
+        width: u32,
+        height: u32,
+    },
     SaveScene(i32),
     LoadScene(Ascn),
     Prop(u32),
@@ -260,6 +317,32 @@ pub enum FromHost {
     Movement(f32, f32),
     LockPointer(bool),
     LoadResource(Resource),
+    LitPreview(bool),
+    /// Keep textures in place when moving and rotating solids
+    TextureLock(bool),
+    Outline(OutlineCommand),
+    /// Contents of the clipboard, pasted under the cursor
+    Paste(Vec<u8>),
+    /// Exact change to the selection, undone as one step
+    Transform(Transform),
+    /// JSON object of command names and the chords they are bound to, like `{"undo": ["Ctrl+Z"]}`
+    Keymap(String),
+    Undo,
+    Redo,
+    /// Undoes or redoes to a position of [History]
+    JumpHistory(usize),
+    /// Most steps kept for undoing and roughly how many bytes they may take
+    HistoryBudget {
+        entries: usize,
+        bytes: usize,
+    },
+}
+
+/// Lengths are in meters and angles in degrees
+pub struct Transform {
+    pub translation: [f32; 3],
+    /// Euler angles around the X, Y and Z axes, only props can turn freely
+    pub rotation: [f32; 3],
 }
 
 pub struct Resource {
@@ -272,6 +355,8 @@ pub enum ResourceKind {
     Texture,
     Prop,
     Gizmo,
+    /// Baked by the raytracer, the id is ignored
+    Lightmap,
 }
 
 macro_rules! resource {
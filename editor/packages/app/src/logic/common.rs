@@ -100,6 +100,11 @@ impl Snap {
     }
 }
 
+/// Nearest position units to a length in meters
+pub fn units(meters: f32) -> i32 {
+    (meters * 128.0).round() as i32
+}
+
 pub fn calc_angle(origin: Vector2<f32>, pos: Vector2<f32>) -> i32 {
     let vector = pos - origin;
     let rad = vector.y.atan2(vector.x);
@@ -1,9 +1,8 @@
 mod gizmo;
 mod tools;
 
-use asset::{PropID, TextureID};
+use asset::{scene::Clipboard, PropID, TextureID};
 use cgmath::vec3;
-use winit::event::VirtualKeyCode;
 
 use crate::{
     button,
@@ -11,6 +10,7 @@ use crate::{
     graphics::{
         structures::GroundVertex, Canvas, Graphics, GroundMesh, GroundMeshDescriptor, Share,
     },
+    math::Snap,
     Host, ToHost,
 };
 
@@ -20,7 +20,8 @@ use super::{
     camera::Camera,
     elements::ElementKind,
     input::Input,
-    scene::{self, Action, Scene},
+    keymap::{Command, Keymap},
+    scene::{Action, Scene},
 };
 
 pub struct Editor {
@@ -31,6 +32,9 @@ pub struct Editor {
     grid: i32,
     texture: TextureID,
     prop: PropID,
+    keymap: Keymap,
+    // Last typed numbers sent to the host
+    numeric: Option<String>,
 }
 
 impl Editor {
@@ -43,6 +47,8 @@ impl Editor {
             grid: 128,
             texture: TextureID(2),
             prop: PropID(0),
+            keymap: Keymap::default(),
+            numeric: None,
         }
     }
 
@@ -51,6 +57,7 @@ impl Editor {
 
         let new = self.tool.process(tools::Context {
             input: ctx.input,
+            keymap: &self.keymap,
             graphics: ctx.graphics,
             prop_infos: ctx.prop_infos,
             camera: ctx.camera,
@@ -70,21 +77,22 @@ impl Editor {
             }
         }
 
+        let numeric = self.tool.numeric();
+        if numeric != self.numeric {
+            ctx.host.callback(ToHost::NumericInput(numeric.clone()));
+            self.numeric = numeric;
+        }
+
         if self.tool.can_switch() {
-            for (key, mode, button) in [
-                (VirtualKeyCode::Key1, ElementKind::Solid, button::SOLID),
-                (VirtualKeyCode::Key2, ElementKind::Face, button::FACE),
-                (VirtualKeyCode::Key3, ElementKind::Point, button::POINT),
-                (VirtualKeyCode::Key4, ElementKind::Prop, button::PROP),
+            for (command, mode, button) in [
+                (Command::ModeSolid, ElementKind::Solid, button::SOLID),
+                (Command::ModeFace, ElementKind::Face, button::FACE),
+                (Command::ModePoint, ElementKind::Point, button::POINT),
+                (Command::ModeProp, ElementKind::Prop, button::PROP),
             ] {
-                if ctx.input.is_key_down_once(key) {
+                if self.keymap.pressed(ctx.input, command) {
                     if self.mode != mode {
-                        ctx.scene.act(
-                            scene::Context {
-                                graphics: ctx.graphics,
-                            },
-                            Action::DeselectAll(self.mode),
-                        );
+                        ctx.scene.act(Action::DeselectAll(self.mode));
                         self.mode = mode;
                         ctx.host.callback(ToHost::Button(button));
                         println!("[wasm] button {}", button);
@@ -92,6 +100,46 @@ impl Editor {
                     break;
                 }
             }
+
+            // Copy, cut & paste through the host, which keeps the clipboard
+            let copy = self.keymap.pressed(ctx.input, Command::Copy);
+            let cut = self.keymap.pressed(ctx.input, Command::Cut);
+            if copy || cut {
+                if let Some(buf) = ctx.scene.copy(self.grid).and_then(|clip| clip.encode()) {
+                    ctx.host.callback(ToHost::Copied(buf));
+                    if cut {
+                        ctx.scene.begin("Cut");
+                        match self.mode {
+                            ElementKind::Solid => ctx.scene.act(Action::DeleteSolids),
+                            ElementKind::Prop => ctx.scene.act(Action::DeleteProps),
+                            _ => (),
+                        }
+                        ctx.scene.commit();
+                    }
+                }
+            }
+
+            if self.keymap.pressed(ctx.input, Command::Paste) {
+                ctx.host.callback(ToHost::PasteRequested);
+            }
+        }
+    }
+
+    /// Pastes at the grid point under the cursor, or where the elements were copied from if there's nothing there
+    pub fn paste(&mut self, ctx: Context, buf: &[u8]) {
+        if !self.tool.can_switch() {
+            return;
+        }
+
+        if let Some(clipboard) = Clipboard::decode(buf) {
+            let hit = ctx
+                .scene
+                .raycast(ctx.input.mouse_pos(), ctx.camera, ctx.prop_infos);
+            let target = match hit.endpoint {
+                Some(endpoint) => (endpoint.point + endpoint.normal * 0.001).round(self.grid),
+                None => clipboard.origin,
+            };
+            ctx.scene.paste(&clipboard, target, self.mode);
         }
     }
 
@@ -103,18 +151,23 @@ impl Editor {
         self.prop = prop;
     }
 
+    /// Rebinds commands from a JSON object of command names and chord lists
+    pub fn set_keymap(&mut self, json: &str) -> Result<(), String> {
+        self.keymap.apply_overrides(json)
+    }
+
     pub fn set_mode(&mut self, ctx: Context, mode: ElementKind) {
         if self.mode != mode {
-            ctx.scene.act(
-                scene::Context {
-                    graphics: ctx.graphics,
-                },
-                Action::DeselectAll(self.mode),
-            );
+            ctx.scene.act(Action::DeselectAll(self.mode));
             self.mode = mode;
         }
     }
 
+    /// Whether no tool is in the middle of something, so the scene can be changed from outside
+    pub fn idle(&self) -> bool {
+        self.tool.can_switch()
+    }
+
     pub fn mode(&self) -> ElementKind {
         self.mode
     }
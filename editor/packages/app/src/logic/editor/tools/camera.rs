@@ -1,18 +1,22 @@
-use cgmath::{InnerSpace, Vector2};
-use winit::event::{MouseButton, VirtualKeyCode};
+use cgmath::{vec2, InnerSpace, Vector2};
+use winit::event::MouseButton;
 
 use crate::{
     graphics::{Canvas, Graphics},
     logic::{
         editor::gizmo::{RotationGizmo, TranslationGizmo},
-        elements::{ElementKind, Movable, Prop, RaycastEndpoint, RaycastEndpointKind, Solid},
-        scene::{self, Action},
+        elements::{
+            ElementKind, Movable, Prop, RaycastEndpoint, RaycastEndpointKind, Solid,
+            TextureAdjustment,
+        },
+        keymap::Command,
+        scene::Action,
     },
     math::Snap,
 };
 
 use super::{
-    gizmo_move::GizmoMove, gizmo_rotate::GizmoRotate, move_tool::MoveTool,
+    clip_tool::ClipTool, gizmo_move::GizmoMove, gizmo_rotate::GizmoRotate, move_tool::MoveTool,
     rotate_solid::RotateSolid, rotate_tool::RotateTool, Context, NewSolid, Tool,
 };
 
@@ -33,39 +37,27 @@ impl CameraTool {
 
     fn common(&mut self, ctx: &mut Context) -> Option<Box<dyn Tool>> {
         // Undo & Redo
-        if ctx.input.is_key_down(VirtualKeyCode::LControl) {
-            if ctx.input.is_key_down_once(VirtualKeyCode::Y) {
-                ctx.scene.undo(scene::Context {
-                    graphics: ctx.graphics,
-                });
-            } else if ctx.input.is_key_down_once(VirtualKeyCode::Z) {
-                ctx.scene.redo(scene::Context {
-                    graphics: ctx.graphics,
-                });
-            }
+        if ctx.pressed(Command::Undo) {
+            ctx.scene.undo();
+        } else if ctx.pressed(Command::Redo) {
+            ctx.scene.redo();
         }
 
         // Grid
-        if ctx.input.is_key_down_once(VirtualKeyCode::O) {
+        if ctx.pressed(Command::GridFiner) {
             *ctx.grid = (*ctx.grid / 2).clamp(2, 512);
             println!("grid: {}cm", *ctx.grid);
         }
-        if ctx.input.is_key_down_once(VirtualKeyCode::P) {
+        if ctx.pressed(Command::GridCoarser) {
             *ctx.grid = (*ctx.grid * 2).clamp(2, 512);
             println!("grid: {}cm", *ctx.grid);
         }
 
         // Select
-        if ctx.input.was_button_down_once(MouseButton::Left)
-            && !ctx.input.is_key_down(VirtualKeyCode::LControl)
-        {
-            if !ctx.input.is_key_down(VirtualKeyCode::LShift) {
-                ctx.scene.act(
-                    scene::Context {
-                        graphics: ctx.graphics,
-                    },
-                    Action::DeselectAll(ctx.mode),
-                );
+        if ctx.input.was_button_down_once(MouseButton::Left) && !ctx.held(Command::PlaceProp) {
+            ctx.scene.begin("Select");
+            if !ctx.held(Command::ExtendSelection) {
+                ctx.scene.act(Action::DeselectAll(ctx.mode));
             }
 
             let hit = ctx
@@ -78,12 +70,8 @@ impl CameraTool {
                         ..
                     }) = hit.endpoint
                     {
-                        ctx.scene.act(
-                            scene::Context {
-                                graphics: ctx.graphics,
-                            },
-                            Action::SelectSolids(vec![locator.solid]),
-                        );
+                        let ids = ctx.scene.selection_unit(locator.solid);
+                        ctx.scene.act(Action::SelectSolids(ids));
                     }
                 }
                 ElementKind::Face => {
@@ -92,21 +80,15 @@ impl CameraTool {
                         ..
                     }) = hit.endpoint
                     {
-                        ctx.scene.act(
-                            scene::Context {
-                                graphics: ctx.graphics,
-                            },
-                            Action::SelectFaces(vec![locator]),
-                        );
+                        if ctx.scene.pickable(locator.solid) {
+                            ctx.scene.act(Action::SelectFaces(vec![locator]));
+                        }
                     }
                 }
                 ElementKind::Point => {
-                    ctx.scene.act(
-                        scene::Context {
-                            graphics: ctx.graphics,
-                        },
-                        Action::SelectPoints(hit.points),
-                    );
+                    let mut points = hit.points;
+                    points.retain(|locator| ctx.scene.pickable(locator.solid));
+                    ctx.scene.act(Action::SelectPoints(points));
                 }
                 ElementKind::Prop => {
                     if let Some(RaycastEndpoint {
@@ -114,50 +96,30 @@ impl CameraTool {
                         ..
                     }) = hit.endpoint
                     {
-                        ctx.scene.act(
-                            scene::Context {
-                                graphics: ctx.graphics,
-                            },
-                            Action::SelectProps(vec![index]),
-                        );
+                        let ids = ctx.scene.selection_unit(index);
+                        ctx.scene.act(Action::SelectProps(ids));
                     }
                 }
             }
+            ctx.scene.commit();
         }
 
         // Select all
-        if ctx.input.is_key_down_once(VirtualKeyCode::A)
-            && ctx.input.is_key_down(VirtualKeyCode::LShift)
-        {
+        if ctx.pressed(Command::SelectAll) {
             match ctx.mode {
                 ElementKind::Solid | ElementKind::Prop => {
-                    ctx.scene.act(
-                        scene::Context {
-                            graphics: ctx.graphics,
-                        },
-                        Action::SelectAll(ctx.mode),
-                    );
+                    ctx.scene.act(Action::SelectAll(ctx.mode));
                 }
                 _ => (),
             }
         }
 
         // Delete
-        if ctx.input.is_key_down_once(VirtualKeyCode::Delete) {
+        if ctx.pressed(Command::Delete) {
             match ctx.mode {
-                ElementKind::Solid => ctx.scene.act(
-                    scene::Context {
-                        graphics: ctx.graphics,
-                    },
-                    Action::DeleteSolids,
-                ),
+                ElementKind::Solid => ctx.scene.act(Action::DeleteSolids),
                 ElementKind::Prop => {
-                    ctx.scene.act(
-                        scene::Context {
-                            graphics: ctx.graphics,
-                        },
-                        Action::DeleteProps,
-                    );
+                    ctx.scene.act(Action::DeleteProps);
                 }
                 _ => (),
             }
@@ -165,8 +127,8 @@ impl CameraTool {
 
         // Move
 
-        let init_move = ctx.input.is_key_down_once(VirtualKeyCode::G);
-        let init_clone = ctx.input.is_key_down_once(VirtualKeyCode::C) && !init_move;
+        let init_move = ctx.pressed(Command::Move);
+        let init_clone = ctx.pressed(Command::Clone) && !init_move;
 
         if init_move || init_clone {
             match ctx.mode {
@@ -174,9 +136,7 @@ impl CameraTool {
                     let ray = ctx.camera.screen_ray(ctx.input.mouse_pos());
 
                     let elements = if init_clone {
-                        ctx.scene.clone_solids(scene::Context {
-                            graphics: ctx.graphics,
-                        })
+                        ctx.scene.clone_solids()
                     } else {
                         ctx.scene.take_solids(ElementKind::Solid)
                     };
@@ -220,9 +180,7 @@ impl CameraTool {
                     let ray = ctx.camera.screen_ray(ctx.input.mouse_pos());
 
                     let elements = if init_clone {
-                        ctx.scene.clone_props(scene::Context {
-                            graphics: ctx.graphics,
-                        })
+                        ctx.scene.clone_props()
                     } else {
                         ctx.scene.take_props()
                     };
@@ -244,7 +202,7 @@ impl CameraTool {
         // Gizmos
 
         self.rotation_gizmo.set_visible(false);
-        if ctx.mode == ElementKind::Prop && !ctx.input.is_key_down(VirtualKeyCode::LShift) {
+        if ctx.mode == ElementKind::Prop && !ctx.held(Command::ExtendSelection) {
             if let Some(center) = ctx.scene.calc_center(ElementKind::Prop) {
                 self.rotation_gizmo.set_position(center);
                 self.rotation_gizmo.set_visible(true);
@@ -268,7 +226,7 @@ impl CameraTool {
 
         self.translation_gizmo.set_visible(false);
         if let Some(center) = ctx.scene.calc_center(ctx.mode) {
-            if !ctx.input.is_key_down(VirtualKeyCode::LShift) {
+            if !ctx.held(Command::ExtendSelection) {
                 self.translation_gizmo.set_position(center);
                 self.translation_gizmo.set_visible(true);
 
@@ -349,52 +307,68 @@ impl Tool for CameraTool {
                         let tool = NewSolid::new(&mut ctx, last_click);
 
                         if let Some(tool) = tool {
-                            ctx.scene.act(
-                                scene::Context {
-                                    graphics: ctx.graphics,
-                                },
-                                Action::DeselectAll(ElementKind::Solid),
-                            );
+                            ctx.scene.act(Action::DeselectAll(ElementKind::Solid));
                             return Some(Box::new(tool));
                         }
                     }
                 }
 
                 // Rotate
-                if ctx.input.is_key_down_once(VirtualKeyCode::F) && ctx.scene.any_solids_selected()
-                {
+                if ctx.pressed(Command::RotateSolids) && ctx.scene.any_solids_selected() {
                     return Some(Box::new(RotateSolid));
                 }
 
+                // Clip
+                if ctx.pressed(Command::Clip) && ctx.scene.any_solids_selected() {
+                    return Some(Box::new(ClipTool::default()));
+                }
+
+                // Carve the selection out of the solids it overlaps
+                if ctx.pressed(Command::Carve) {
+                    let (ids, solids) = ctx.scene.carve_selected();
+                    if !ids.is_empty() {
+                        ctx.scene.begin("Carve");
+                        ctx.scene.act(Action::ReplaceSolids { ids, solids });
+                        ctx.scene.commit();
+                    }
+                }
+
+                // Merge
+                if ctx.pressed(Command::Merge) {
+                    let (ids, solids) = ctx.scene.merge_selected();
+                    if !ids.is_empty() {
+                        ctx.scene.begin("Merge");
+                        ctx.scene.act(Action::ReplaceSolids { ids, solids });
+                        ctx.scene.commit();
+                    }
+                }
+
                 // Hollow
-                if ctx.input.is_key_down_once(VirtualKeyCode::H) {
+                if ctx.pressed(Command::Hollow) {
                     let ids = ctx.scene.selected_solid_ids();
+                    ctx.scene.begin("Hollow");
                     for id in ids {
-                        ctx.scene.act(
-                            scene::Context {
-                                graphics: ctx.graphics,
-                            },
-                            Action::ReplaceSolids {
-                                ids: vec![id],
-                                solids: ctx.scene.hollow_of(ctx.graphics, id, *ctx.grid),
-                            },
-                        )
+                        ctx.scene.act(Action::ReplaceSolids {
+                            ids: vec![id],
+                            solids: ctx.scene.hollow_of(id, *ctx.grid),
+                        })
                     }
+                    ctx.scene.commit();
                 }
             } else {
                 self.last_click = None;
             }
 
             // Assign texture
-            if matches!(ctx.mode, ElementKind::Face)
-                && ctx.input.is_key_down_once(VirtualKeyCode::T)
-            {
-                ctx.scene.act(
-                    scene::Context {
-                        graphics: ctx.graphics,
-                    },
-                    Action::AssignTexture(ctx.texture),
-                );
+            if matches!(ctx.mode, ElementKind::Face) && ctx.pressed(Command::AssignTexture) {
+                ctx.scene.act(Action::AssignTexture(ctx.texture));
+            }
+
+            // Align texture
+            if matches!(ctx.mode, ElementKind::Face) {
+                if let Some(adjustment) = texture_adjustment(&ctx) {
+                    ctx.scene.act(Action::AdjustTextures(adjustment));
+                }
             }
 
             if let Some(ret) = self.common(&mut ctx) {
@@ -403,8 +377,7 @@ impl Tool for CameraTool {
 
             if matches!(ctx.mode, ElementKind::Prop) {
                 // New prop
-                if ctx.input.is_key_down(VirtualKeyCode::LControl)
-                    && ctx.input.is_button_down_once(MouseButton::Left)
+                if ctx.held(Command::PlaceProp) && ctx.input.is_button_down_once(MouseButton::Left)
                 {
                     let hit = ctx
                         .scene
@@ -413,17 +386,13 @@ impl Tool for CameraTool {
                     if let Some(endpoint) = hit.endpoint {
                         let position = (endpoint.point + endpoint.normal * 0.001).round(*ctx.grid);
 
-                        ctx.scene.act(
-                            scene::Context {
-                                graphics: ctx.graphics,
-                            },
-                            Action::NewProps(vec![Prop::new(ctx.graphics, ctx.prop, position)]),
-                        );
+                        ctx.scene
+                            .act(Action::NewProps(vec![Prop::new(ctx.prop, position)]));
                     }
                 }
 
                 // Rotate
-                if ctx.input.is_key_down_once(VirtualKeyCode::R) {
+                if ctx.pressed(Command::RotateProps) {
                     let props = ctx.scene.take_props();
                     if !props.is_empty() {
                         match RotateTool::new(&ctx, props) {
@@ -451,27 +420,27 @@ impl Tool for CameraTool {
 }
 
 fn control(ctx: &mut Context) {
-    if ctx.input.is_key_down(VirtualKeyCode::W) {
+    if ctx.held(Command::FlyForward) {
         ctx.camera.move_forward(ctx.delta);
     }
 
-    if ctx.input.is_key_down(VirtualKeyCode::S) {
+    if ctx.held(Command::FlyBack) {
         ctx.camera.move_backward(ctx.delta);
     }
 
-    if ctx.input.is_key_down(VirtualKeyCode::A) {
+    if ctx.held(Command::FlyLeft) {
         ctx.camera.move_left(ctx.delta);
     }
 
-    if ctx.input.is_key_down(VirtualKeyCode::D) {
+    if ctx.held(Command::FlyRight) {
         ctx.camera.move_right(ctx.delta);
     }
 
-    if ctx.input.is_key_down(VirtualKeyCode::Q) {
+    if ctx.held(Command::FlyDown) {
         ctx.camera.move_down(ctx.delta);
     }
 
-    if ctx.input.is_key_down(VirtualKeyCode::E) {
+    if ctx.held(Command::FlyUp) {
         ctx.camera.move_up(ctx.delta);
     }
 
@@ -485,3 +454,27 @@ fn control(ctx: &mut Context) {
 
     ctx.camera.look(ctx.input.mouse_delta(), ctx.delta);
 }
+
+/// Arrows shift the texture of the selected faces, with shift they scale and with control they rotate it
+fn texture_adjustment(ctx: &Context) -> Option<TextureAdjustment> {
+    if ctx.pressed(Command::FitTexture) {
+        return Some(TextureAdjustment::Fit);
+    }
+
+    let direction = [
+        (Command::TextureLeft, vec2(-1.0, 0.0)),
+        (Command::TextureRight, vec2(1.0, 0.0)),
+        (Command::TextureDown, vec2(0.0, -1.0)),
+        (Command::TextureUp, vec2(0.0, 1.0)),
+    ]
+    .into_iter()
+    .find_map(|(command, direction)| ctx.pressed(command).then_some(direction))?;
+
+    Some(if ctx.held(Command::ScaleTexture) {
+        TextureAdjustment::Scale(direction.map(|e| 2.0f32.powf(e)))
+    } else if ctx.held(Command::RotateTexture) {
+        TextureAdjustment::Rotate(-15.0 * direction.x)
+    } else {
+        TextureAdjustment::Shift(direction / 16.0)
+    })
+}
@@ -0,0 +1,144 @@
+use cgmath::Vector3;
+use winit::event::MouseButton;
+
+use crate::{
+    color,
+    graphics::{structures::LineVertex, Canvas, LineMesh, LineMeshDescriptor, Share},
+    logic::{
+        elements::{ClipKeep, ClipPlane},
+        keymap::Command,
+        scene::Action,
+    },
+};
+
+use super::{CameraTool, Context, Tool};
+
+/// Cuts the selected solids along a plane. Two clicks define a plane standing on the first clicked
+/// surface, a third click tilts it. Tab switches between keeping the front, the back or both halves,
+/// Enter applies the cut.
+pub struct ClipTool {
+    points: Vec<Vector3<i32>>,
+    normal: Vector3<i32>,
+    keep: ClipKeep,
+    preview: Option<LineMesh>,
+}
+
+impl Default for ClipTool {
+    fn default() -> Self {
+        Self {
+            points: Vec::new(),
+            normal: Vector3::unit_y(),
+            keep: ClipKeep::Both,
+            preview: None,
+        }
+    }
+}
+
+impl ClipTool {
+    fn plane(&self) -> Option<ClipPlane> {
+        match self.points[..] {
+            [a, b] => ClipPlane::new(a, b, a + self.normal),
+            [a, b, c] => ClipPlane::new(a, b, c),
+            _ => None,
+        }
+    }
+
+    // Edges of the pieces the cut would leave and a cross on every clicked point
+    fn update_preview(&mut self, ctx: &Context) {
+        let mut vertices = Vec::new();
+        let mut line = |start: Vector3<f32>, end: Vector3<f32>, color: [f32; 3]| {
+            vertices.push(LineVertex {
+                position: start,
+                color,
+            });
+            vertices.push(LineVertex {
+                position: end,
+                color,
+            });
+        };
+
+        if let Some(plane) = self.plane() {
+            let (_, pieces) = ctx.scene.clip_selected(&plane, self.keep, ctx.texture);
+            for piece in pieces {
+                for [start, end] in piece.edges() {
+                    line(start, end, color!("39a0ed"));
+                }
+            }
+        }
+
+        for point in &self.points {
+            let point = point.map(|e| e as f32 / 128.0);
+            for axis in [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()] {
+                line(point - axis * 0.1, point + axis * 0.1, color!("ffffff"));
+            }
+        }
+
+        self.preview = (!vertices.is_empty()).then(|| {
+            ctx.graphics.create_line_mesh(LineMeshDescriptor {
+                vertices: &vertices,
+            })
+        });
+    }
+}
+
+impl Tool for ClipTool {
+    fn process(&mut self, ctx: Context) -> Option<Box<dyn Tool>> {
+        let mut changed = false;
+
+        if ctx.input.is_button_down_once(MouseButton::Left) {
+            let hit = ctx
+                .scene
+                .raycast(ctx.input.mouse_pos(), ctx.camera, ctx.prop_infos);
+            if let Some(endpoint) = hit.endpoint {
+                let grid = *ctx.grid as f32 / 128.0;
+                let point = endpoint
+                    .point
+                    .map(|e| ((e / grid).round() * grid * 128.0) as i32);
+
+                if self.points.len() == 3 {
+                    self.points.clear();
+                }
+                if self.points.is_empty() {
+                    self.normal = endpoint.normal.map(|e| (e * 128.0).round() as i32);
+                }
+                if self.points.last() != Some(&point) {
+                    self.points.push(point);
+                }
+                changed = true;
+            }
+        }
+
+        if ctx.pressed(Command::CycleClip) {
+            self.keep = self.keep.next();
+            changed = true;
+        }
+
+        if changed {
+            self.update_preview(&ctx);
+        }
+
+        if ctx.pressed(Command::Confirm) {
+            if let Some(plane) = self.plane() {
+                let (ids, solids) = ctx.scene.clip_selected(&plane, self.keep, ctx.texture);
+                if !ids.is_empty() {
+                    ctx.scene.begin("Clip");
+                    ctx.scene.act(Action::ReplaceSolids { ids, solids });
+                    ctx.scene.commit();
+                }
+            }
+            return Some(Box::new(CameraTool::new(ctx.graphics)));
+        }
+
+        if ctx.input.is_button_down_once(MouseButton::Right) || ctx.pressed(Command::Cancel) {
+            return Some(Box::new(CameraTool::new(ctx.graphics)));
+        }
+
+        None
+    }
+
+    fn render(&self, canvas: &mut Canvas) {
+        if let Some(preview) = &self.preview {
+            canvas.draw_lines(preview.share());
+        }
+    }
+}
@@ -107,8 +107,8 @@ where
             ctx.graphics.write_line_mesh(&self.line, &verts);
 
             for (_, element) in &mut self.elements {
-                element.displace(delta2, self.mask);
-                element.recalc(ctx.graphics);
+                element.displace(delta2, self.mask, ctx.scene.texture_lock());
+                element.sync(ctx.graphics);
             }
 
             self.graphics.modify(
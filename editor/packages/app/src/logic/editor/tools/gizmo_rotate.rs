@@ -1,5 +1,5 @@
 use cgmath::{Quaternion, Vector2, Vector3, Zero};
-use winit::event::{MouseButton, VirtualKeyCode};
+use winit::event::MouseButton;
 
 use crate::{
     graphics::{Canvas, Graphics},
@@ -9,6 +9,7 @@ use crate::{
         editor::gizmo::ArcGraphics,
         elements::{Movable, Prop},
         input::Input,
+        keymap::Command,
         ElementKind,
     },
 };
@@ -58,7 +59,7 @@ impl GizmoRotate {
 
 impl Tool for GizmoRotate {
     fn process(&mut self, ctx: Context) -> Option<Box<dyn Tool>> {
-        let snap = if ctx.input.is_key_down(VirtualKeyCode::LControl) {
+        let snap = if ctx.held(Command::SnapRotation) {
             Snap::Deg15
         } else {
             Snap::None
@@ -69,7 +70,7 @@ impl Tool for GizmoRotate {
             for ((_, prop), original) in self.props.iter_mut().zip(self.originals.iter()) {
                 let snapped = snap.snap(delta) as f32;
                 prop.set_rotation(self.axis.angle(snapped, ctx.camera.forward()) * original);
-                prop.recalc(ctx.graphics);
+                prop.sync(ctx.graphics);
             }
             self.angle = delta;
         }
@@ -1,15 +1,23 @@
 mod camera;
+mod clip_tool;
 mod gizmo_move;
 mod gizmo_rotate;
 mod move_tool;
 mod new_solid;
+mod numeric;
 mod rotate_solid;
 mod rotate_tool;
 
 use crate::{
     data::PropInfoContainer,
     graphics::{Canvas, Graphics},
-    logic::{camera::Camera, elements::ElementKind, input::Input, scene::Scene},
+    logic::{
+        camera::Camera,
+        elements::ElementKind,
+        input::Input,
+        keymap::{Command, Keymap},
+        scene::Scene,
+    },
 };
 
 use asset::{PropID, TextureID};
@@ -21,6 +29,11 @@ pub trait Tool {
         None
     }
 
+    /// Numbers typed into the tool as shown to the user, [None] if it doesn't take any
+    fn numeric(&self) -> Option<String> {
+        None
+    }
+
     fn render(&self, _canvas: &mut Canvas) {}
 
     fn can_switch(&self) -> bool {
@@ -34,6 +47,7 @@ pub trait Tool {
 
 pub struct Context<'a> {
     pub input: &'a Input,
+    pub keymap: &'a Keymap,
     pub graphics: &'a Graphics,
     pub prop_infos: &'a PropInfoContainer,
     pub camera: &'a mut Camera,
@@ -44,3 +58,15 @@ pub struct Context<'a> {
     pub texture: TextureID,
     pub prop: PropID,
 }
+
+impl Context<'_> {
+    /// Whether the command's chord was pressed this frame
+    pub fn pressed(&self, command: Command) -> bool {
+        self.keymap.pressed(self.input, command)
+    }
+
+    /// Whether the command's chord is held down
+    pub fn held(&self, command: Command) -> bool {
+        self.keymap.held(self.input, command)
+    }
+}
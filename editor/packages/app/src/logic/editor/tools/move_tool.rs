@@ -1,17 +1,18 @@
 use cgmath::{vec3, Vector3, Zero};
-use winit::event::{MouseButton, VirtualKeyCode};
+use winit::event::MouseButton;
 
 use crate::{
     graphics::{Canvas, LineMesh, LineMeshDescriptor, Share},
     logic::{
-        common::Axis,
+        common::{units, Axis},
         elements::{ElementKind, Movable},
-        scene::{self, Action},
+        keymap::Command,
+        scene::Action,
     },
     math::{Intersects, Plane, Ray, Snap},
 };
 
-use super::{CameraTool, Context, Tool};
+use super::{numeric::NumericInput, CameraTool, Context, Tool};
 
 pub struct MoveTool<E> {
     mask: ElementKind,
@@ -25,6 +26,7 @@ pub struct MoveTool<E> {
     start: Vector3<f32>,
     delta: Vector3<i32>,
     snap: MoveSnap,
+    numeric: NumericInput,
     line_mesh: Option<LineMesh>,
 }
 
@@ -63,6 +65,7 @@ where
                 start: intersection.point + intersection.normal * 0.0001,
                 delta: Vector3::zero(),
                 snap: MoveSnap::None,
+                numeric: NumericInput::new(3),
                 line_mesh: None,
             })
         } else {
@@ -81,6 +84,7 @@ where
         };
 
         self.snap = MoveSnap::Axis(axis);
+        self.numeric = NumericInput::new(1);
 
         if let Some(intersection) = self.ray.intersects(&self.plane) {
             self.start = intersection.point + intersection.normal * 0.0001;
@@ -98,6 +102,7 @@ where
         };
 
         self.snap = MoveSnap::Plane(axis);
+        self.numeric = NumericInput::new(2);
 
         if let Some(intersection) = self.ray.intersects(&self.plane) {
             self.start = intersection.point + intersection.normal * 0.001;
@@ -121,13 +126,13 @@ where
     E: Movable,
 {
     fn process(&mut self, ctx: Context) -> Option<Box<dyn Tool>> {
-        for (axis, key) in [
-            (Axis::X, VirtualKeyCode::X),
-            (Axis::Y, VirtualKeyCode::Z),
-            (Axis::Z, VirtualKeyCode::Y),
+        for (axis, command) in [
+            (Axis::X, Command::AxisX),
+            (Axis::Y, Command::AxisY),
+            (Axis::Z, Command::AxisZ),
         ] {
-            if ctx.input.is_key_down_once(key) {
-                if ctx.input.is_key_down(VirtualKeyCode::LShift) {
+            if ctx.pressed(command) {
+                if ctx.held(Command::ExcludeAxis) {
                     self.snap_exclude(&ctx, axis);
                 } else {
                     self.snap_to_axis(&ctx, axis);
@@ -135,34 +140,47 @@ where
             }
         }
 
-        let mouse_pos = ctx.input.mouse_pos();
-        let ray = ctx.camera.screen_ray(mouse_pos);
+        self.numeric.update(ctx.input, ctx.keymap);
 
-        if let Some(intersection) = ray.intersects(&self.plane) {
-            let start = self.start.snap(*ctx.grid);
-            let end = (intersection.point + intersection.normal * 0.001).snap(*ctx.grid);
+        // Typed distances replace the mouse, along the axes it could move on
+        let delta = if self.numeric.active() {
+            let mut delta = Vector3::zero();
+            for (axis, value) in self.snap.axes().into_iter().zip(self.numeric.values()) {
+                let distance = value.map(units).unwrap_or(0);
+                delta += axis.unit().map(|e| e as i32 * distance);
+            }
+            Some(delta)
+        } else {
+            let mouse_pos = ctx.input.mouse_pos();
+            let ray = ctx.camera.screen_ray(mouse_pos);
 
-            let delta = self.snap.snap_vec(end - start);
+            ray.intersects(&self.plane).map(|intersection| {
+                let start = self.start.snap(*ctx.grid);
+                let end = (intersection.point + intersection.normal * 0.001).snap(*ctx.grid);
+                self.snap.snap_vec(end - start)
+            })
+        };
+
+        if let Some(delta) = delta {
             if delta != self.delta {
                 for (_, element) in &mut self.elements {
-                    element.displace(delta - self.delta, self.mask);
-                    element.recalc(ctx.graphics);
+                    element.displace(delta - self.delta, self.mask, ctx.scene.texture_lock());
                 }
                 self.delta = delta;
             }
         }
 
-        if ctx.input.was_button_down_once(MouseButton::Left) {
+        // Clones get their meshes here
+        for (_, element) in &mut self.elements {
+            element.sync(ctx.graphics);
+        }
+
+        if ctx.input.was_button_down_once(MouseButton::Left) || ctx.pressed(Command::Confirm) {
             let elements = self.elements.drain(..).collect::<Vec<_>>();
 
             if self.clone {
                 E::insert_remove(ctx.scene, elements);
-                ctx.scene.act(
-                    scene::Context {
-                        graphics: ctx.graphics,
-                    },
-                    Action::DeselectAll(self.mask),
-                );
+                ctx.scene.act(Action::DeselectAll(self.mask));
             } else {
                 E::insert_move(ctx.scene, elements, self.delta, self.mask);
             }
@@ -171,25 +189,19 @@ where
         }
 
         if ctx.input.is_button_down_once(MouseButton::Right)
-            || ctx.input.is_key_down_once(VirtualKeyCode::G)
-            || ctx.input.is_key_down_once(VirtualKeyCode::Escape)
+            || ctx.pressed(Command::Move)
+            || ctx.pressed(Command::Cancel)
         {
             let mut elements = self.elements.drain(..).collect::<Vec<_>>();
 
             if !self.clone {
                 for (_, element) in &mut elements {
-                    element.displace(-self.delta, self.mask);
-                    element.recalc(ctx.graphics);
+                    element.displace(-self.delta, self.mask, ctx.scene.texture_lock());
                 }
 
                 E::insert(ctx.scene, elements);
             } else {
-                ctx.scene.act(
-                    scene::Context {
-                        graphics: ctx.graphics,
-                    },
-                    Action::DeselectAll(self.mask),
-                );
+                ctx.scene.act(Action::DeselectAll(self.mask));
             }
 
             return Some(Box::new(CameraTool::new(ctx.graphics)));
@@ -198,6 +210,10 @@ where
         None
     }
 
+    fn numeric(&self) -> Option<String> {
+        Some(format!("move: {} m", self.numeric))
+    }
+
     fn render(&self, canvas: &mut Canvas) {
         for (_, element) in &self.elements {
             element.render(canvas, self.mask);
@@ -216,6 +232,15 @@ enum MoveSnap {
 }
 
 impl MoveSnap {
+    // The axes elements can move along
+    fn axes(&self) -> Vec<Axis> {
+        match self {
+            MoveSnap::None => Axis::all().to_vec(),
+            MoveSnap::Axis(axis) => vec![*axis],
+            MoveSnap::Plane(axis) => axis.others().to_vec(),
+        }
+    }
+
     fn snap_vec(&self, vec: Vector3<i32>) -> Vector3<i32> {
         match self {
             MoveSnap::None => vec,
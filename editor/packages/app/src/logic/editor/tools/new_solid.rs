@@ -4,17 +4,21 @@ use winit::event::MouseButton;
 use crate::{
     graphics::Canvas,
     logic::{
+        common::units,
         elements::{ElementKind, Movable, Solid},
-        scene::{self, Action},
+        keymap::Command,
+        scene::Action,
     },
     math::{MinMax, Snap},
 };
 
-use super::{CameraTool, Context, Tool};
+use super::{numeric::NumericInput, CameraTool, Context, Tool};
 
 pub struct NewSolid {
     start: Vector3<f32>,
+    end: Option<Vector3<i32>>,
     solid: Option<Solid>,
+    numeric: NumericInput,
 }
 
 impl NewSolid {
@@ -22,9 +26,32 @@ impl NewSolid {
         let hit = ctx.scene.raycast(click, ctx.camera, ctx.prop_infos);
         hit.endpoint.map(|endpoint| Self {
             start: endpoint.point + endpoint.normal * 0.001,
+            end: None,
             solid: None,
+            numeric: NumericInput::new(3),
         })
     }
+
+    // Corners of the grid cells between the start and the end, typed sizes
+    // replace the dragged ones and grow the way the drag went
+    fn bounds(&self, start: Vector3<i32>, end: Vector3<i32>, g: i32) -> [Vector3<i32>; 2] {
+        let mut min = start.min(end);
+        let mut max = start.max(end) + vec3(g, g, g);
+
+        for (i, value) in self.numeric.values().into_iter().enumerate() {
+            if let Some(size) = value.map(units).filter(|size| *size != 0) {
+                let (anchor, extent) = if end[i] >= start[i] {
+                    (start[i], size)
+                } else {
+                    (start[i] + g, -size)
+                };
+                min[i] = anchor.min(anchor + extent);
+                max[i] = anchor.max(anchor + extent);
+            }
+        }
+
+        [min, max]
+    }
 }
 
 impl Tool for NewSolid {
@@ -32,25 +59,35 @@ impl Tool for NewSolid {
         let hit = ctx
             .scene
             .raycast(ctx.input.mouse_pos(), ctx.camera, ctx.prop_infos);
+        let g = *ctx.grid;
         if let Some(endpoint) = hit.endpoint {
-            let g = *ctx.grid;
             let scaled_normal = endpoint.normal * g as f32 / 128.0 * 0.1;
+            self.end = Some((endpoint.point + scaled_normal).snap(g));
+        }
 
-            let start = self.start.snap(g);
-            let end = (endpoint.point + scaled_normal).snap(g);
-            let min = start.min(end);
-            let max = start.max(end) + vec3(g, g, g);
-            self.solid = Some(Solid::new(ctx.graphics, min, max - min));
+        self.numeric.update(ctx.input, ctx.keymap);
+
+        if let Some(end) = self.end {
+            let [min, max] = self.bounds(self.start.snap(g), end, g);
+            let mut solid = Solid::new(min, max - min);
+            solid.sync(ctx.graphics);
+            self.solid = Some(solid);
+        }
+
+        if ctx.pressed(Command::Cancel) {
+            return Some(Box::new(CameraTool::new(ctx.graphics)));
         }
 
-        if ctx.input.was_button_down_once(MouseButton::Left) {
+        // Once sizes are typed the button can be let go, the next click confirms
+        let confirm = if self.numeric.active() {
+            ctx.input.is_button_down_once(MouseButton::Left)
+        } else {
+            ctx.input.was_button_down_once(MouseButton::Left)
+        };
+
+        if confirm || ctx.pressed(Command::Confirm) {
             if let Some(solid) = self.solid.take() {
-                ctx.scene.act(
-                    scene::Context {
-                        graphics: ctx.graphics,
-                    },
-                    Action::NewSolids(vec![solid]),
-                );
+                ctx.scene.act(Action::NewSolids(vec![solid]));
             }
             Some(Box::new(CameraTool::new(ctx.graphics)))
         } else {
@@ -58,6 +95,10 @@ impl Tool for NewSolid {
         }
     }
 
+    fn numeric(&self) -> Option<String> {
+        Some(format!("solid: {} m", self.numeric))
+    }
+
     fn render(&self, canvas: &mut Canvas) {
         if let Some(solid) = &self.solid {
             solid.render(canvas, ElementKind::Solid);
@@ -0,0 +1,132 @@
+use std::fmt;
+
+use crate::logic::{
+    input::Input,
+    keymap::{Command, Keymap},
+};
+
+/// Numbers typed while a tool is active, one field for each axis the tool works on.
+/// Commas count as decimal points, so both conventions work.
+pub struct NumericInput {
+    fields: Vec<String>,
+    current: usize,
+}
+
+impl NumericInput {
+    pub fn new(count: usize) -> Self {
+        Self {
+            fields: vec![String::new(); count],
+            current: 0,
+        }
+    }
+
+    /// Takes what was typed this frame, returns whether the fields changed
+    pub fn update(&mut self, input: &Input, keymap: &Keymap) -> bool {
+        let mut changed = false;
+
+        for c in input.typed().chars() {
+            let field = &mut self.fields[self.current];
+            match c {
+                '0'..='9' => field.push(c),
+                '.' | ',' if !field.contains('.') => field.push('.'),
+                // Minus flips the sign wherever it's typed
+                '-' => match field.strip_prefix('-') {
+                    Some(rest) => *field = rest.to_owned(),
+                    None => field.insert(0, '-'),
+                },
+                _ => continue,
+            }
+            changed = true;
+        }
+
+        if keymap.pressed(input, Command::Erase) {
+            if self.fields[self.current].pop().is_none() && self.current > 0 {
+                self.current -= 1;
+            }
+            changed = true;
+        }
+
+        if keymap.pressed(input, Command::NextField) {
+            self.current = (self.current + 1) % self.fields.len();
+            changed = true;
+        }
+
+        changed
+    }
+
+    /// Whether anything was typed, tools follow the mouse until then
+    pub fn active(&self) -> bool {
+        self.fields.iter().any(|field| !field.is_empty())
+    }
+
+    /// Values of the fields, `None` where nothing usable was typed yet
+    pub fn values(&self) -> Vec<Option<f32>> {
+        self.fields.iter().map(|field| field.parse().ok()).collect()
+    }
+}
+
+// Fields separated by spaces, the one being typed in bracketed
+impl fmt::Display for NumericInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, field) in self.fields.iter().enumerate() {
+            let field = if field.is_empty() { "_" } else { field };
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            if i == self.current {
+                write!(f, "[{}]", field)?;
+            } else {
+                write!(f, "{}", field)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::{ElementState, VirtualKeyCode};
+
+    use super::NumericInput;
+    use crate::logic::{input::Input, keymap::Keymap};
+
+    fn frame(numeric: &mut NumericInput, text: &str, key: Option<VirtualKeyCode>) -> bool {
+        let mut input = Input::default();
+        for c in text.chars() {
+            input.character(c);
+        }
+        if let Some(key) = key {
+            input.key(key, ElementState::Pressed);
+        }
+        numeric.update(&input, &Keymap::default())
+    }
+
+    #[test]
+    fn typing() {
+        let keymap = Keymap::default();
+        let mut numeric = NumericInput::new(3);
+        assert!(!numeric.active());
+        assert!(!numeric.update(&Input::default(), &keymap));
+
+        // Letters are left for the tools, a second decimal point is ignored
+        assert!(frame(&mut numeric, "2,4x.5", None));
+        assert_eq!(numeric.values(), [Some(2.45), None, None]);
+        assert!(numeric.active());
+
+        assert!(frame(&mut numeric, "", Some(VirtualKeyCode::Tab)));
+        frame(&mut numeric, "-", None);
+        assert_eq!(numeric.values(), [Some(2.45), None, None]);
+        frame(&mut numeric, "30", None);
+        assert_eq!(numeric.values(), [Some(2.45), Some(-30.0), None]);
+        frame(&mut numeric, "-", None);
+        assert_eq!(numeric.to_string(), "2.45 [30] _");
+
+        // Erasing an empty field goes back to the one before
+        frame(&mut numeric, "", Some(VirtualKeyCode::Back));
+        frame(&mut numeric, "", Some(VirtualKeyCode::Back));
+        frame(&mut numeric, "", Some(VirtualKeyCode::Back));
+        assert_eq!(numeric.to_string(), "[2.45] _ _");
+        frame(&mut numeric, "", Some(VirtualKeyCode::Back));
+        assert_eq!(numeric.values(), [Some(2.4), None, None]);
+    }
+}
@@ -1,16 +1,17 @@
-use cgmath::{Quaternion, Vector2, Vector3, Zero};
-use winit::event::{MouseButton, VirtualKeyCode};
+use cgmath::{Deg, Quaternion, Rotation3, Vector2, Vector3, Zero};
+use winit::event::MouseButton;
 
 use crate::{
     graphics::{structures::LineVertex, Canvas, LineMesh, LineMeshDescriptor, Share},
     logic::{
         common::{calc_angle, Axis, Snap},
         elements::{ElementKind, Movable, Prop},
-        scene::{self, Action},
+        keymap::Command,
+        scene::Action,
     },
 };
 
-use super::{CameraTool, Context, Tool};
+use super::{numeric::NumericInput, CameraTool, Context, Tool};
 
 pub struct RotateTool {
     origin: Vector2<f32>,
@@ -19,6 +20,7 @@ pub struct RotateTool {
     orientation: Orientation,
     angle: i32,
     init_angle: i32,
+    numeric: NumericInput,
 }
 
 impl RotateTool {
@@ -44,6 +46,7 @@ impl RotateTool {
                 orientation: Orientation::Undecided,
                 angle: 0,
                 init_angle,
+                numeric: NumericInput::new(1),
             })
         } else {
             Err(props)
@@ -54,37 +57,40 @@ impl RotateTool {
 impl Tool for RotateTool {
     fn process(&mut self, ctx: Context) -> Option<Box<dyn Tool>> {
         if self.orientation.decided() {
-            let snap = if ctx.input.is_key_down(VirtualKeyCode::LControl) {
+            let snap = if ctx.held(Command::SnapRotation) {
                 Snap::Deg15
             } else {
                 Snap::None
             };
 
+            let typed = self.numeric.update(ctx.input, ctx.keymap);
+
+            // Typed degrees replace the mouse
             let delta = calc_angle(self.origin, ctx.input.mouse_pos()) - self.init_angle;
-            if delta != self.angle {
+            let rotation = match self.numeric.values()[0] {
+                Some(degrees) => self.orientation.exact(degrees),
+                None => self
+                    .orientation
+                    .angle(snap.snap(delta), ctx.camera.forward()),
+            };
+
+            if typed || delta != self.angle {
                 for ((_, prop), original) in self.props.iter_mut().zip(self.originals.iter()) {
-                    let snapped = snap.snap(delta);
-                    prop.set_rotation(
-                        self.orientation.angle(snapped, ctx.camera.forward()) * original,
-                    );
-                    prop.recalc(ctx.graphics);
+                    prop.set_rotation(rotation * original);
+                    prop.sync(ctx.graphics);
                 }
                 self.angle = delta;
             }
 
-            if ctx.input.is_button_down_once(MouseButton::Left) {
+            if ctx.input.is_button_down_once(MouseButton::Left) || ctx.pressed(Command::Confirm) {
                 let props = self.props.drain(..).collect();
-
-                let delta = self
-                    .orientation
-                    .angle(snap.snap(delta), ctx.camera.forward());
-                ctx.scene.insert_props_with_rotate(props, delta);
+                ctx.scene.insert_props_with_rotate(props, rotation);
 
                 return Some(Box::new(CameraTool::new(ctx.graphics)));
             }
         } else {
             self.orientation.update(&ctx, &mut self.props);
-            if ctx.input.is_key_down_once(VirtualKeyCode::R) {
+            if ctx.pressed(Command::ResetRotation) {
                 let props = self.props.drain(..).collect::<Vec<_>>();
                 let rotations = props
                     .iter()
@@ -92,23 +98,16 @@ impl Tool for RotateTool {
                     .collect();
 
                 ctx.scene.insert_props(props);
-                ctx.scene.act(
-                    scene::Context {
-                        graphics: ctx.graphics,
-                    },
-                    Action::SetPropRotations(rotations),
-                );
+                ctx.scene.act(Action::SetPropRotations(rotations));
 
                 return Some(Box::new(CameraTool::new(ctx.graphics)));
             }
         }
 
-        if ctx.input.is_button_down_once(MouseButton::Right)
-            || ctx.input.is_key_down_once(VirtualKeyCode::Escape)
-        {
+        if ctx.input.is_button_down_once(MouseButton::Right) || ctx.pressed(Command::Cancel) {
             for ((_, prop), original) in self.props.iter_mut().zip(self.originals.iter()) {
                 prop.set_rotation(*original);
-                prop.recalc(ctx.graphics);
+                prop.sync(ctx.graphics);
             }
 
             let props = self.props.drain(..).collect();
@@ -119,6 +118,13 @@ impl Tool for RotateTool {
         None
     }
 
+    // Degrees can only be typed once the axis is decided
+    fn numeric(&self) -> Option<String> {
+        self.orientation
+            .decided()
+            .then(|| format!("rotate: {}°", self.numeric))
+    }
+
     fn render(&self, canvas: &mut Canvas) {
         for (_, prop) in &self.props {
             prop.render(canvas, ElementKind::Prop);
@@ -138,12 +144,12 @@ impl Orientation {
     }
 
     fn update(&mut self, ctx: &Context, props: &mut [(usize, Prop)]) {
-        for (key, axis) in [
-            (VirtualKeyCode::X, Axis::X),
-            (VirtualKeyCode::Z, Axis::Y),
-            (VirtualKeyCode::Y, Axis::Z),
+        for (command, axis) in [
+            (Command::AxisX, Axis::X),
+            (Command::AxisY, Axis::Y),
+            (Command::AxisZ, Axis::Z),
         ] {
-            if ctx.input.is_key_down_once(key) {
+            if ctx.pressed(command) {
                 let mut vertices = Vec::with_capacity(props.len() * 2);
 
                 for (_, prop) in props {
@@ -155,7 +161,7 @@ impl Orientation {
                         position: prop.meters() + axis.unit() * 10.0,
                         color: axis.color(),
                     });
-                    prop.recalc(ctx.graphics);
+                    prop.sync(ctx.graphics);
                 }
 
                 *self = Self::Decided {
@@ -179,6 +185,14 @@ impl Orientation {
         }
     }
 
+    // Counterclockwise looking down the axis, whichever way the camera faces
+    fn exact(&self, degrees: f32) -> Quaternion<f32> {
+        match self {
+            Self::Undecided => Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            Self::Decided { axis, .. } => Quaternion::from_axis_angle(axis.unit(), Deg(degrees)),
+        }
+    }
+
     fn angle(&self, angle: i32, forward: Vector3<f32>) -> Quaternion<f32> {
         match self {
             Self::Undecided => Quaternion::new(1.0, 0.0, 0.0, 0.0),
@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use asset::TextureID;
+use cgmath::{vec3, InnerSpace, Vector3};
+
+use super::{Face, Point, Solid, SolidGeometry};
+
+/// Plane through grid points. The side its normal points to is the front.
+#[derive(Clone, Copy)]
+pub struct ClipPlane {
+    pub(super) origin: Vector3<i32>,
+    pub(super) normal: Vector3<i64>,
+}
+
+impl ClipPlane {
+    /// Plane through three points, counterclockwise when seen from the front
+    pub fn new(a: Vector3<i32>, b: Vector3<i32>, c: Vector3<i32>) -> Option<Self> {
+        let u = (b - a).map(|e| e as i64);
+        let v = (c - a).map(|e| e as i64);
+        let normal = u.cross(v);
+        (normal != vec3(0, 0, 0)).then_some(Self { origin: a, normal })
+    }
+
+    pub fn flipped(self) -> Self {
+        Self {
+            origin: self.origin,
+            normal: -self.normal,
+        }
+    }
+
+    /// Signed distance scaled by the length of the normal, exact for grid points
+    pub(super) fn distance(&self, point: Vector3<i32>) -> i64 {
+        let d = (point - self.origin).map(|e| e as i64);
+        d.x * self.normal.x + d.y * self.normal.y + d.z * self.normal.z
+    }
+
+    /// Point where the edge from `a` to `b` crosses the plane, or [None] if it lies between grid units
+    pub(super) fn crossing(&self, a: Vector3<i32>, b: Vector3<i32>) -> Option<Vector3<i32>> {
+        let (da, db) = (self.distance(a) as i128, self.distance(b) as i128);
+        let offset = (b - a).map(|e| e as i128 * da);
+        let divisor = da - db;
+        if [offset.x, offset.y, offset.z]
+            .iter()
+            .any(|e| e % divisor != 0)
+        {
+            return None;
+        }
+        Some(a + offset.map(|e| (e / divisor) as i32))
+    }
+}
+
+/// Which side of a [ClipPlane] is kept
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ClipKeep {
+    Front,
+    Back,
+    Both,
+}
+
+impl ClipKeep {
+    pub fn next(self) -> Self {
+        match self {
+            ClipKeep::Front => ClipKeep::Back,
+            ClipKeep::Back => ClipKeep::Both,
+            ClipKeep::Both => ClipKeep::Front,
+        }
+    }
+}
+
+impl Solid {
+    /// Pieces left after cutting the solid along the plane, or [None] if the plane misses it or crosses
+    /// an edge between grid units, where the pieces couldn't keep their faces flat.
+    /// Cut faces keep their textures, the new face closing each piece gets the `cap` texture.
+    pub fn clip(&self, plane: &ClipPlane, keep: ClipKeep, cap: TextureID) -> Option<Vec<Self>> {
+        let distances = self
+            .geometry
+            .points
+            .iter()
+            .map(|point| plane.distance(point.position))
+            .collect::<Vec<_>>();
+        if !distances.iter().any(|d| *d < 0) || !distances.iter().any(|d| *d > 0) {
+            return None;
+        }
+        if !self.geometry.cuts_exactly(plane) {
+            return None;
+        }
+
+        let back = || self.geometry.clip_back(plane, cap);
+        let front = || self.geometry.clip_back(&plane.flipped(), cap);
+        let pieces = match keep {
+            ClipKeep::Front => vec![front()],
+            ClipKeep::Back => vec![back()],
+            ClipKeep::Both => vec![back(), front()],
+        };
+
+        Some(
+            pieces
+                .into_iter()
+                .flatten()
+                .map(|geometry| Self::from_geometry(geometry, self.selected))
+                .collect(),
+        )
+    }
+}
+
+impl SolidGeometry {
+    /// Whether every edge the plane crosses is crossed at a grid point
+    pub(super) fn cuts_exactly(&self, plane: &ClipPlane) -> bool {
+        self.faces.iter().all(|face| {
+            face.indices.iter().enumerate().all(|(k, &i)| {
+                let j = face.indices[(k + 1) % face.indices.len()];
+                let (pi, pj) = (self.points[i].position, self.points[j].position);
+                let (di, dj) = (plane.distance(pi), plane.distance(pj));
+                !((di < 0 && dj > 0) || (di > 0 && dj < 0)) || plane.crossing(pi, pj).is_some()
+            })
+        })
+    }
+
+    /// Part of the solid behind the plane. The plane must [cut it exactly](SolidGeometry::cuts_exactly).
+    pub(super) fn clip_back(&self, plane: &ClipPlane, cap: TextureID) -> Option<Self> {
+        let distances = self
+            .points
+            .iter()
+            .map(|point| plane.distance(point.position))
+            .collect::<Vec<_>>();
+        if !distances.iter().any(|d| *d < 0) {
+            return None;
+        }
+        if !distances.iter().any(|d| *d > 0) {
+            return Some(self.clone());
+        }
+
+        let mut points: Vec<Point> = Vec::new();
+        let mut lookup = HashMap::new();
+        let mut cap_indices = Vec::new();
+        let mut add = |position: Vector3<i32>, on_plane: bool| {
+            let index = *lookup.entry(position).or_insert_with(|| {
+                points.push(position.into());
+                points.len() - 1
+            });
+            if on_plane && !cap_indices.contains(&index) {
+                cap_indices.push(index);
+            }
+            index
+        };
+
+        let mut faces = Vec::new();
+        for face in &self.faces {
+            let mut indices = Vec::new();
+            for (k, &i) in face.indices.iter().enumerate() {
+                let j = face.indices[(k + 1) % face.indices.len()];
+                let (di, dj) = (distances[i], distances[j]);
+                let (pi, pj) = (self.points[i].position, self.points[j].position);
+
+                if di <= 0 {
+                    indices.push(add(pi, di == 0));
+                }
+
+                if (di < 0 && dj > 0) || (di > 0 && dj < 0) {
+                    let crossing = plane
+                        .crossing(pi, pj)
+                        .expect("edges are crossed at grid points");
+                    indices.push(add(crossing, true));
+                }
+            }
+
+            indices.dedup();
+            if indices.len() > 1 && indices.first() == indices.last() {
+                indices.pop();
+            }
+
+            if indices.len() >= 3 {
+                faces.push(Face {
+                    indices,
+                    ..face.clone()
+                });
+            }
+        }
+
+        if cap_indices.len() >= 3 {
+            faces.push(Face::from((cap, sort_around(&points, cap_indices, plane))));
+        }
+
+        Some(Self { points, faces })
+    }
+}
+
+// Winds the points of the cap counterclockwise around the plane's normal, so the cap faces the removed side
+fn sort_around(points: &[Point], mut indices: Vec<usize>, plane: &ClipPlane) -> Vec<usize> {
+    let position = |i: usize| points[i].position.map(|e| e as f64);
+    let center = indices
+        .iter()
+        .fold(vec3(0.0, 0.0, 0.0), |sum, i| sum + position(*i))
+        / indices.len() as f64;
+    let normal = plane.normal.map(|e| e as f64);
+    let u = position(indices[0]) - center;
+    let v = normal.cross(u);
+
+    let angle = |i: usize| {
+        let d = position(i) - center;
+        d.dot(v).atan2(d.dot(u))
+    };
+    indices.sort_by(|a, b| angle(*a).total_cmp(&angle(*b)));
+    indices
+}
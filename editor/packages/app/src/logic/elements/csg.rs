@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use asset::TextureID;
+use cgmath::Vector3;
+
+use super::{ClipPlane, Face, Point, Solid, SolidGeometry};
+
+impl Solid {
+    /// Pieces of the solid left outside `carver`, or [None] if they don't overlap
+    /// or a face of the carver would cut an edge between grid units.
+    /// Each piece is cut off by one face of the carver, whose texture covers the new faces.
+    pub fn carve(&self, carver: &Solid) -> Option<Vec<Self>> {
+        let mut pieces = Vec::new();
+        let mut rest = self.geometry.clone();
+        for (face, plane) in carver.geometry.planes() {
+            let texture = carver.geometry.faces[face].texture;
+            let distances = rest.distances(&plane);
+            if !distances.iter().any(|d| *d < 0) {
+                return None;
+            }
+
+            if distances.iter().any(|d| *d > 0) {
+                if !rest.cuts_exactly(&plane) {
+                    return None;
+                }
+                pieces.extend(rest.clip_back(&plane.flipped(), texture));
+                rest = rest.clip_back(&plane, texture)?;
+            }
+        }
+
+        (!pieces.is_empty()).then(|| {
+            pieces
+                .into_iter()
+                .map(|geometry| Self::from_geometry(geometry, self.selected))
+                .collect()
+        })
+    }
+
+    /// Single solid covering both, if their union is convex.
+    /// [None] as well if their overlap can't be cut out on grid units.
+    /// Faces of the union take the texture of a face of either solid lying in the same plane.
+    pub fn merge(&self, other: &Solid) -> Option<Self> {
+        let hull = SolidGeometry::hull(&[&self.geometry, &other.geometry])?;
+
+        let mut overlap = Some(self.geometry.clone());
+        for (_, plane) in other.geometry.planes() {
+            if overlap
+                .as_ref()
+                .is_some_and(|overlap| !overlap.cuts_exactly(&plane))
+            {
+                return None;
+            }
+            overlap = overlap.and_then(|overlap| overlap.clip_back(&plane, TextureID(0)));
+        }
+        let overlap = overlap.map_or(0, |overlap| overlap.volume());
+
+        (hull.volume() == self.geometry.volume() + other.geometry.volume() - overlap)
+            .then(|| Self::from_geometry(hull, self.selected))
+    }
+}
+
+impl SolidGeometry {
+    /// Planes of the faces with their normals pointing outwards, degenerate faces are skipped
+    fn planes(&self) -> Vec<(usize, ClipPlane)> {
+        self.faces
+            .iter()
+            .enumerate()
+            .filter_map(|(i, face)| {
+                let corner = |k: usize| self.points[face.indices[k]].position;
+                ClipPlane::new(corner(0), corner(1), corner(face.indices.len() - 1))
+                    .map(|plane| (i, plane))
+            })
+            .collect()
+    }
+
+    fn distances(&self, plane: &ClipPlane) -> Vec<i64> {
+        self.points
+            .iter()
+            .map(|point| plane.distance(point.position))
+            .collect()
+    }
+
+    /// Six times the volume, exact in grid units
+    fn volume(&self) -> i64 {
+        self.faces
+            .iter()
+            .map(|face| {
+                let corner = |k: usize| self.points[face.indices[k]].position.map(|e| e as i64);
+                (1..face.indices.len() - 1)
+                    .map(|k| {
+                        let (a, b, c) = (corner(0), corner(k), corner(k + 1));
+                        let n = b.cross(c);
+                        a.x * n.x + a.y * n.y + a.z * n.z
+                    })
+                    .sum::<i64>()
+            })
+            .sum()
+    }
+
+    /// Convex hull of the points of the solids, or [None] if they are all in one plane
+    fn hull(solids: &[&SolidGeometry]) -> Option<Self> {
+        let mut positions = solids
+            .iter()
+            .flat_map(|solid| solid.points.iter().map(|point| point.position))
+            .collect::<Vec<_>>();
+        positions.sort_unstable_by_key(|p| (p.x, p.y, p.z));
+        positions.dedup();
+
+        // Every plane through three of the points with all others behind it holds a face
+        let mut planes = HashMap::new();
+        for (i, &a) in positions.iter().enumerate() {
+            for (j, &b) in positions.iter().enumerate().skip(i + 1) {
+                for &c in positions.iter().skip(j + 1) {
+                    let plane = match ClipPlane::new(a, b, c) {
+                        Some(plane) => plane,
+                        None => continue,
+                    };
+                    let plane = if positions.iter().all(|p| plane.distance(*p) <= 0) {
+                        plane
+                    } else if positions.iter().all(|p| plane.distance(*p) >= 0) {
+                        plane.flipped()
+                    } else {
+                        continue;
+                    };
+                    planes.entry(plane_key(&plane)).or_insert(plane);
+                }
+            }
+        }
+
+        if planes.len() < 4 {
+            return None;
+        }
+
+        let sources = solids
+            .iter()
+            .flat_map(|solid| {
+                solid
+                    .planes()
+                    .into_iter()
+                    .map(|(i, plane)| (plane_key(&plane), &solid.faces[i]))
+            })
+            .collect::<Vec<_>>();
+
+        let mut points: Vec<Point> = Vec::new();
+        let mut lookup = HashMap::new();
+        let mut planes = planes.into_iter().collect::<Vec<_>>();
+        planes.sort_unstable_by_key(|(key, _)| *key);
+        let faces = planes
+            .into_iter()
+            .map(|(key, plane)| {
+                let on_plane = positions
+                    .iter()
+                    .copied()
+                    .filter(|p| plane.distance(*p) == 0)
+                    .collect::<Vec<_>>();
+                let indices = wrap(&on_plane, &plane)
+                    .into_iter()
+                    .map(|position| {
+                        *lookup.entry(position).or_insert_with(|| {
+                            points.push(position.into());
+                            points.len() - 1
+                        })
+                    })
+                    .collect();
+
+                match sources.iter().find(|(source, _)| *source == key) {
+                    Some((_, face)) => Face {
+                        indices,
+                        selected: false,
+                        ..(*face).clone()
+                    },
+                    None => Face::from((TextureID(0), indices)),
+                }
+            })
+            .collect();
+
+        Some(Self { points, faces })
+    }
+}
+
+// Normal divided by the greatest common divisor of its components, and the plane's offset along it
+fn plane_key(plane: &ClipPlane) -> ([i64; 3], i64) {
+    let n = plane.normal;
+    let mut divisor = gcd(gcd(n.x.abs(), n.y.abs()), n.z.abs());
+    if divisor == 0 {
+        divisor = 1;
+    }
+    let n = n / divisor;
+    let o = plane.origin.map(|e| e as i64);
+    ([n.x, n.y, n.z], n.x * o.x + n.y * o.y + n.z * o.z)
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// Corners of the convex polygon around points in a plane, counterclockwise around its normal.
+// Points on the edges between corners are left out.
+fn wrap(points: &[Vector3<i32>], plane: &ClipPlane) -> Vec<Vector3<i32>> {
+    let orient = |p: Vector3<i32>, q: Vector3<i32>, r: Vector3<i32>| {
+        let u = (q - p).map(|e| e as i128);
+        let v = (r - p).map(|e| e as i128);
+        let n = plane.normal.map(|e| e as i128);
+        (u.y * v.z - u.z * v.y) * n.x
+            + (u.z * v.x - u.x * v.z) * n.y
+            + (u.x * v.y - u.y * v.x) * n.z
+    };
+    let distance = |p: Vector3<i32>, q: Vector3<i32>| {
+        let d = (q - p).map(|e| e as i64);
+        d.x * d.x + d.y * d.y + d.z * d.z
+    };
+
+    // The points come sorted, so the first one is always a corner
+    let start = points[0];
+    let mut corners = vec![start];
+    let mut current = start;
+    while corners.len() <= points.len() {
+        let mut next = if points[1] == current {
+            points[0]
+        } else {
+            points[1]
+        };
+        for &candidate in points {
+            if candidate == current {
+                continue;
+            }
+            let turn = orient(current, next, candidate);
+            if turn < 0 || (turn == 0 && distance(current, candidate) > distance(current, next)) {
+                next = candidate;
+            }
+        }
+
+        if next == start {
+            break;
+        }
+        corners.push(next);
+        current = next;
+    }
+    corners
+}
@@ -1,7 +1,9 @@
+mod clip;
+mod csg;
 mod raycast;
 
-use asset::{scene, GizmoID, PropID, TextureID};
-use cgmath::{vec2, vec3, ElementWise, InnerSpace, Matrix4, Quaternion, Transform, Vector3, Zero};
+use asset::{scene, scene::TextureAlignment, GizmoID, PropID, TextureID};
+use cgmath::{vec2, vec3, ElementWise, Matrix4, Quaternion, Transform, Vector2, Vector3, Zero};
 
 use crate::{
     color,
@@ -13,6 +15,7 @@ use crate::{
     math::{MinMax, Ray},
 };
 
+pub use clip::*;
 pub use raycast::*;
 
 use super::{common::Axis, scene::Scene};
@@ -25,23 +28,26 @@ pub enum ElementKind {
     Prop,
 }
 
+/// Geometry and selection of a solid. The GPU mesh is only created and updated by [Movable::sync],
+/// so solids can be edited without a [Graphics].
 pub struct Solid {
     geometry: SolidGeometry,
     selected: bool,
-    graphics: SolidGraphics,
+    dirty: bool,
+    graphics: Option<SolidGraphics>,
 }
 
 impl Solid {
-    pub fn new(gfx: &Graphics, origin: Vector3<i32>, extent: Vector3<i32>) -> Self {
-        let geometry = SolidGeometry::new(origin, extent);
-        let selected = false;
-        let mut graphics = SolidGraphics::new(gfx);
-        graphics.recalc(gfx, &geometry, selected);
+    pub fn new(origin: Vector3<i32>, extent: Vector3<i32>) -> Self {
+        Self::from_geometry(SolidGeometry::new(origin, extent), false)
+    }
 
+    fn from_geometry(geometry: SolidGeometry, selected: bool) -> Self {
         Self {
             geometry,
             selected,
-            graphics,
+            dirty: true,
+            graphics: None,
         }
     }
 
@@ -51,6 +57,7 @@ impl Solid {
 
     pub fn set_selected(&mut self, selected: bool) {
         self.selected = selected;
+        self.dirty = true;
     }
 
     pub fn face_selected(&self, index: usize) -> bool {
@@ -59,6 +66,7 @@ impl Solid {
 
     pub fn set_face_selected(&mut self, index: usize, selected: bool) {
         self.geometry.faces[index].selected = selected;
+        self.dirty = true;
     }
 
     pub fn point_selected(&self, index: usize) -> bool {
@@ -67,6 +75,32 @@ impl Solid {
 
     pub fn set_point_selected(&mut self, index: usize, selected: bool) {
         self.geometry.points[index].selected = selected;
+        self.dirty = true;
+    }
+
+    /// Rewrites the mesh on the next sync, e.g. after a lightmap was loaded
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.geometry.faces.len()
+    }
+
+    pub fn point_count(&self) -> usize {
+        self.geometry.points.len()
+    }
+
+    /// Start and end of every edge of every face in meters, so shared edges appear twice
+    pub fn edges(&self) -> Vec<[Vector3<f32>; 2]> {
+        (0..self.face_count())
+            .flat_map(|face| {
+                let corners = self.geometry.corners(face);
+                (0..corners.len())
+                    .map(|i| [corners[i], corners[(i + 1) % corners.len()]])
+                    .collect::<Vec<_>>()
+            })
+            .collect()
     }
 
     pub fn any_face_selected(&self) -> bool {
@@ -78,9 +112,42 @@ impl Solid {
     }
 
     pub fn retexture(&mut self, face: usize, texture: TextureID) -> TextureID {
+        self.dirty = true;
         self.geometry.retexture(face, texture)
     }
 
+    /// Replaces the alignment of a face's texture, returning the old one
+    pub fn realign(&mut self, face: usize, alignment: TextureAlignment) -> TextureAlignment {
+        self.dirty = true;
+        std::mem::replace(&mut self.geometry.faces[face].alignment, alignment)
+    }
+
+    /// Alignment of a face's texture after an adjustment, the face is left unchanged
+    pub fn adjusted_alignment(
+        &self,
+        face: usize,
+        adjustment: TextureAdjustment,
+    ) -> TextureAlignment {
+        let alignment = self.geometry.faces[face].alignment;
+        match adjustment {
+            TextureAdjustment::Shift(delta) => TextureAlignment {
+                offset: alignment.offset + delta,
+                ..alignment
+            },
+            TextureAdjustment::Scale(factor) => TextureAlignment {
+                scale: alignment.scale.mul_element_wise(factor),
+                ..alignment
+            },
+            TextureAdjustment::Rotate(degrees) => TextureAlignment {
+                rotation: (alignment.rotation + degrees).rem_euclid(360.0),
+                ..alignment
+            },
+            TextureAdjustment::Fit => {
+                alignment.fit(&self.geometry.corners(face), self.geometry.normal(face))
+            }
+        }
+    }
+
     pub fn save(&self) -> scene::Solid {
         let points = self
             .geometry
@@ -89,10 +156,7 @@ impl Solid {
             .map(|point| scene::Point {
                 position: point.position,
             })
-            .collect::<Vec<_>>()
-            .try_into()
-            .ok()
-            .unwrap();
+            .collect();
 
         let faces = self
             .geometry
@@ -100,40 +164,28 @@ impl Solid {
             .iter()
             .map(|face| scene::Face {
                 texture: face.texture,
-                indices: face.indices.map(|i| i as u32),
+                indices: face.indices.iter().map(|i| *i as u32).collect(),
+                alignment: face.alignment,
             })
-            .collect::<Vec<_>>()
-            .try_into()
-            .ok()
-            .unwrap();
+            .collect();
 
         scene::Solid { points, faces }
     }
 
-    pub fn clone(&self, gfx: &Graphics) -> Self {
-        let mut graphics = SolidGraphics::new(gfx);
-        graphics.recalc(gfx, &self.geometry, self.selected);
-
-        Self {
-            geometry: self.geometry.clone(),
-            selected: self.selected,
-            graphics,
-        }
-    }
-
-    pub fn load(gfx: &Graphics, solid: &scene::Solid) -> Self {
-        let geometry = SolidGeometry::load(solid);
-        let mut graphics = SolidGraphics::new(gfx);
-        graphics.recalc(gfx, &geometry, false);
-
-        Self {
-            geometry,
-            selected: false,
-            graphics,
-        }
+    pub fn load(solid: &scene::Solid) -> Self {
+        Self::from_geometry(SolidGeometry::load(solid), false)
     }
 
-    pub fn rotate(&mut self, center: Vector3<i32>, axis: Axis, iters: u32, reverse: bool) {
+    pub fn rotate(
+        &mut self,
+        center: Vector3<i32>,
+        axis: Axis,
+        iters: u32,
+        reverse: bool,
+        texture_lock: bool,
+    ) {
+        self.dirty = true;
+        let before = self.geometry.clone();
         for _ in 0..iters {
             for point in &mut self.geometry.points {
                 let translated = point.position - center;
@@ -141,6 +193,10 @@ impl Solid {
             }
         }
 
+        if texture_lock {
+            self.geometry.lock_textures(&before);
+        }
+
         fn rot(v: Vector3<i32>, axis: Axis, reverse: bool) -> Vector3<i32> {
             match (axis, reverse) {
                 (Axis::X, false) => vec3(v.x, v.z, -v.y),
@@ -155,7 +211,8 @@ impl Solid {
         }
     }
 
-    pub fn make_hollow(&self, gfx: &Graphics, grid: i32) -> [Self; 26] {
+    /// Walls of the box around the solid
+    pub fn make_hollow(&self, grid: i32) -> [Self; 26] {
         // 26
 
         let (min, max) = self.geometry.min_max();
@@ -163,76 +220,61 @@ impl Solid {
         let l = grid;
 
         [
+            Self::new(min + vec3(0, l, l), vec3(l, ext.y - l * 2, ext.z - l * 2)),
             Self::new(
-                gfx,
-                min + vec3(0, l, l),
-                vec3(l, ext.y - l * 2, ext.z - l * 2),
-            ),
-            Self::new(
-                gfx,
                 min + vec3(ext.x - l, l, l),
                 vec3(l, ext.y - l * 2, ext.z - l * 2),
             ),
+            Self::new(min + vec3(l, 0, l), vec3(ext.x - l * 2, l, ext.z - l * 2)),
             Self::new(
-                gfx,
-                min + vec3(l, 0, l),
-                vec3(ext.x - l * 2, l, ext.z - l * 2),
-            ),
-            Self::new(
-                gfx,
                 min + vec3(l, ext.y - l, l),
                 vec3(ext.x - l * 2, l, ext.z - l * 2),
             ),
+            Self::new(min + vec3(l, l, 0), vec3(ext.x - l * 2, ext.y - l * 2, l)),
             Self::new(
-                gfx,
-                min + vec3(l, l, 0),
-                vec3(ext.x - l * 2, ext.y - l * 2, l),
-            ),
-            Self::new(
-                gfx,
                 min + vec3(l, l, ext.z - l),
                 vec3(ext.x - l * 2, ext.y - l * 2, l),
             ),
-            Self::new(gfx, min + vec3(l, 0, 0), vec3(ext.x - l * 2, l, l)),
-            Self::new(gfx, min + vec3(l, 0, ext.z - l), vec3(ext.x - l * 2, l, l)),
-            Self::new(gfx, min + vec3(l, ext.y - l, 0), vec3(ext.x - l * 2, l, l)),
+            Self::new(min + vec3(l, 0, 0), vec3(ext.x - l * 2, l, l)),
+            Self::new(min + vec3(l, 0, ext.z - l), vec3(ext.x - l * 2, l, l)),
+            Self::new(min + vec3(l, ext.y - l, 0), vec3(ext.x - l * 2, l, l)),
             Self::new(
-                gfx,
                 min + vec3(l, ext.y - l, ext.z - l),
                 vec3(ext.x - l * 2, l, l),
             ),
-            Self::new(gfx, min + vec3(0, l, 0), vec3(l, ext.y - l * 2, l)),
-            Self::new(gfx, min + vec3(0, l, ext.z - l), vec3(l, ext.y - l * 2, l)),
-            Self::new(gfx, min + vec3(ext.x - l, l, 0), vec3(l, ext.y - l * 2, l)),
+            Self::new(min + vec3(0, l, 0), vec3(l, ext.y - l * 2, l)),
+            Self::new(min + vec3(0, l, ext.z - l), vec3(l, ext.y - l * 2, l)),
+            Self::new(min + vec3(ext.x - l, l, 0), vec3(l, ext.y - l * 2, l)),
             Self::new(
-                gfx,
                 min + vec3(ext.x - l, l, ext.z - l),
                 vec3(l, ext.y - l * 2, l),
             ),
-            Self::new(gfx, min + vec3(0, 0, l), vec3(l, l, ext.z - l * 2)),
-            Self::new(gfx, min + vec3(ext.x - l, 0, l), vec3(l, l, ext.z - l * 2)),
-            Self::new(gfx, min + vec3(0, ext.y - l, l), vec3(l, l, ext.z - l * 2)),
+            Self::new(min + vec3(0, 0, l), vec3(l, l, ext.z - l * 2)),
+            Self::new(min + vec3(ext.x - l, 0, l), vec3(l, l, ext.z - l * 2)),
+            Self::new(min + vec3(0, ext.y - l, l), vec3(l, l, ext.z - l * 2)),
             Self::new(
-                gfx,
                 min + vec3(ext.x - l, ext.y - l, l),
                 vec3(l, l, ext.z - l * 2),
             ),
-            Self::new(gfx, min + vec3(0, 0, 0), vec3(l, l, l)),
-            Self::new(gfx, min + vec3(ext.x - l, 0, 0), vec3(l, l, l)),
-            Self::new(gfx, min + vec3(ext.x - l, ext.y - l, 0), vec3(l, l, l)),
-            Self::new(gfx, min + vec3(0, ext.y - l, 0), vec3(l, l, l)),
-            Self::new(gfx, min + vec3(0, 0, ext.z - l), vec3(l, l, l)),
-            Self::new(gfx, min + vec3(ext.x - l, 0, ext.z - l), vec3(l, l, l)),
-            Self::new(
-                gfx,
-                min + vec3(ext.x - l, ext.y - l, ext.z - l),
-                vec3(l, l, l),
-            ),
-            Self::new(gfx, min + vec3(0, ext.y - l, ext.z - l), vec3(l, l, l)),
+            Self::new(min + vec3(0, 0, 0), vec3(l, l, l)),
+            Self::new(min + vec3(ext.x - l, 0, 0), vec3(l, l, l)),
+            Self::new(min + vec3(ext.x - l, ext.y - l, 0), vec3(l, l, l)),
+            Self::new(min + vec3(0, ext.y - l, 0), vec3(l, l, l)),
+            Self::new(min + vec3(0, 0, ext.z - l), vec3(l, l, l)),
+            Self::new(min + vec3(ext.x - l, 0, ext.z - l), vec3(l, l, l)),
+            Self::new(min + vec3(ext.x - l, ext.y - l, ext.z - l), vec3(l, l, l)),
+            Self::new(min + vec3(0, ext.y - l, ext.z - l), vec3(l, l, l)),
         ]
     }
 }
 
+impl Clone for Solid {
+    /// The copy gets its own mesh on its first sync
+    fn clone(&self) -> Self {
+        Self::from_geometry(self.geometry.clone(), self.selected)
+    }
+}
+
 #[derive(Clone)]
 struct Point {
     position: Vector3<i32>,
@@ -263,30 +305,46 @@ pub struct PointLocator {
 #[derive(Clone)]
 struct Face {
     texture: TextureID,
-    indices: [usize; 4],
+    indices: Vec<usize>,
+    alignment: TextureAlignment,
     selected: bool,
 }
 
-impl From<(TextureID, [usize; 4])> for Face {
-    fn from(tuple: (TextureID, [usize; 4])) -> Self {
+impl From<(TextureID, Vec<usize>)> for Face {
+    fn from(tuple: (TextureID, Vec<usize>)) -> Self {
         Self {
             texture: tuple.0,
             indices: tuple.1,
+            alignment: Default::default(),
             selected: false,
         }
     }
 }
 
+/// Change to the alignment of a face's texture
+#[derive(Clone, Copy)]
+pub enum TextureAdjustment {
+    /// In texture repeats
+    Shift(Vector2<f32>),
+    /// Multiplies the scale, stretching the texture
+    Scale(Vector2<f32>),
+    /// Degrees, counterclockwise
+    Rotate(f32),
+    /// One repeat of the texture over the whole face
+    Fit,
+}
+
 #[derive(Clone, Copy)]
 pub struct FaceLocator {
     pub solid: usize,
     pub face: usize,
 }
 
+/// Convex polyhedron, every face is a convex polygon wound so its normal points outwards
 #[derive(Clone)]
 struct SolidGeometry {
-    points: [Point; 8],
-    faces: [Face; 6],
+    points: Vec<Point>,
+    faces: Vec<Face>,
 }
 
 impl SolidGeometry {
@@ -301,7 +359,8 @@ impl SolidGeometry {
             vec3(1, 1, 1),
             vec3(0, 1, 1),
         ]
-        .map(|point| (origin + point.mul_element_wise(extent)).into());
+        .map(|point| (origin + point.mul_element_wise(extent)).into())
+        .into();
 
         let faces = [
             [1, 5, 6, 2],
@@ -311,7 +370,8 @@ impl SolidGeometry {
             [3, 2, 6, 7],
             [1, 0, 4, 5],
         ]
-        .map(|indices| (TextureID(0), indices).into());
+        .map(|indices| (TextureID(0), indices.into()).into())
+        .into();
 
         Self { points, faces }
     }
@@ -324,28 +384,29 @@ impl SolidGeometry {
                 position: point.position,
                 selected: false,
             })
-            .collect::<Vec<_>>()
-            .try_into()
-            .ok()
-            .unwrap();
+            .collect();
 
         let faces = solid
             .faces
             .iter()
             .map(|face| Face {
                 texture: face.texture,
-                indices: face.indices.map(|i| i as usize),
+                indices: face.indices.iter().map(|i| *i as usize).collect(),
+                alignment: face.alignment,
                 selected: false,
             })
-            .collect::<Vec<_>>()
-            .try_into()
-            .ok()
-            .unwrap();
+            .collect();
 
         Self { points, faces }
     }
 
-    fn displace(&mut self, selected: bool, delta: Vector3<i32>, mask: ElementKind) -> bool {
+    fn displace(
+        &mut self,
+        selected: bool,
+        delta: Vector3<i32>,
+        mask: ElementKind,
+        texture_lock: bool,
+    ) -> bool {
         if delta == Vector3::zero() {
             return false;
         }
@@ -353,20 +414,22 @@ impl SolidGeometry {
         match mask {
             ElementKind::Solid => {
                 if selected {
-                    let mut changed = false;
+                    let before = texture_lock.then(|| self.clone());
                     for point in &mut self.points {
                         point.position += delta;
-                        changed = true;
                     }
-                    changed
+                    if let Some(before) = before {
+                        self.lock_textures(&before);
+                    }
+                    true
                 } else {
                     false
                 }
             }
             ElementKind::Face => {
-                let mut changed = [false; 8];
+                let mut changed = vec![false; self.points.len()];
                 for face in self.faces.iter().filter(|face| face.selected) {
-                    for index in face.indices {
+                    for &index in &face.indices {
                         let point = &mut self.points[index];
                         if !changed[index] {
                             point.position += delta;
@@ -394,6 +457,35 @@ impl SolidGeometry {
         old
     }
 
+    /// Corners of a face in meters, in the order of its indices
+    fn corners(&self, face: usize) -> Vec<Vector3<f32>> {
+        self.faces[face]
+            .indices
+            .iter()
+            .map(|index| self.points[*index].meters())
+            .collect()
+    }
+
+    fn normal(&self, face: usize) -> Vector3<f32> {
+        scene::face_normal(&self.corners(face))
+    }
+
+    /// Realigns every texture so it stays where it was on the geometry before a move or rotation
+    fn lock_textures(&mut self, before: &SolidGeometry) {
+        for i in 0..self.faces.len() {
+            let corners = |geometry: &SolidGeometry| {
+                let corners = geometry.corners(i);
+                [corners[0], corners[1], corners[corners.len() - 1]]
+            };
+            self.faces[i].alignment = before.faces[i].alignment.locked(
+                corners(before),
+                before.normal(i),
+                corners(self),
+                self.normal(i),
+            );
+        }
+    }
+
     fn min_max(&self) -> (Vector3<i32>, Vector3<i32>) {
         let mut min = [i32::MAX; 3];
         let mut max = [i32::MIN; 3];
@@ -416,22 +508,52 @@ impl SolidGeometry {
 }
 
 struct SolidGraphics {
-    textures: [TextureID; 6],
+    textures: Vec<TextureID>,
+    /// Corners of every face and the number of points the buffers were made for
+    topology: (Vec<usize>, usize),
     mesh: SolidMesh,
     verts: GizmoInstances,
 }
 
 impl SolidGraphics {
-    fn new(graphics: &Graphics) -> Self {
+    fn new(graphics: &Graphics, geometry: &SolidGeometry) -> Self {
+        let mut triangles = Vec::new();
+        let mut faces = Vec::with_capacity(geometry.faces.len());
+        let mut base = 0;
+        for face in &geometry.faces {
+            let start = triangles.len() as u32;
+            triangles.extend(
+                scene::face_triangles(face.indices.len())
+                    .map(|triangle| triangle.map(|corner| (base + corner) as u16)),
+            );
+            faces.push(start..triangles.len() as u32);
+            base += face.indices.len();
+        }
+
         Self {
-            textures: [TextureID(0); 6],
-            mesh: graphics.create_solid_mesh(),
-            verts: graphics.create_gizmo_instances(8),
+            textures: vec![TextureID(0); geometry.faces.len()],
+            topology: Self::topology(geometry),
+            mesh: graphics.create_solid_mesh(base, &triangles, faces),
+            verts: graphics.create_gizmo_instances(geometry.points.len()),
         }
     }
 
+    fn topology(geometry: &SolidGeometry) -> (Vec<usize>, usize) {
+        let corners = geometry
+            .faces
+            .iter()
+            .map(|face| face.indices.len())
+            .collect();
+        (corners, geometry.points.len())
+    }
+
+    /// Whether the buffers still have the right size after the geometry changed
+    fn fits(&self, geometry: &SolidGeometry) -> bool {
+        self.topology == Self::topology(geometry)
+    }
+
     fn render(&self, canvas: &mut Canvas, draw_verts: bool) {
-        canvas.draw_solid(self.textures, &self.mesh);
+        canvas.draw_solid(&self.textures, &self.mesh);
         if draw_verts {
             canvas.draw_gizmos(GizmoGroup {
                 gizmo: GizmoID(0),
@@ -441,44 +563,33 @@ impl SolidGraphics {
     }
 
     fn recalc(&mut self, graphics: &Graphics, geometry: &SolidGeometry, selected: bool) {
-        let mut vertices = Vec::with_capacity(24);
+        let mut vertices = Vec::new();
 
         for (i, face) in geometry.faces.iter().enumerate() {
             self.textures[i] = face.texture;
 
-            let normal = {
-                let edge0 = geometry.points[face.indices[1]].meters()
-                    - geometry.points[face.indices[0]].meters();
-
-                let edge1 = geometry.points[face.indices[3]].meters()
-                    - geometry.points[face.indices[0]].meters();
+            let normal = geometry.normal(i);
 
-                edge0.cross(edge1).normalize()
-            };
+            let corners: Vec<[i32; 3]> = face
+                .indices
+                .iter()
+                .map(|&index| geometry.points[index].position.into())
+                .collect();
+            let lightmap = graphics.lightmap_texcoords(&corners);
 
-            for index in face.indices {
+            for (corner, &index) in face.indices.iter().enumerate() {
                 let position = geometry.points[index].meters();
-                let texcoord = if normal.x.abs() > normal.y.abs() {
-                    if normal.x.abs() > normal.z.abs() {
-                        vec2(position.z, position.y)
-                    } else {
-                        vec2(position.x, position.y)
-                    }
-                } else if normal.y.abs() > normal.z.abs() {
-                    vec2(position.x, position.z)
-                } else {
-                    vec2(position.x, position.y)
-                };
 
                 vertices.push(SolidVertex {
                     position,
                     normal,
-                    texcoord,
+                    texcoord: face.alignment.texcoord(position, normal),
                     tint: if selected || face.selected {
                         color!("39a0ed80")
                     } else {
                         [0.0; 4]
                     },
+                    lightmap: lightmap.map_or(vec2(-1.0, -1.0), |lightmap| lightmap[corner]),
                 })
             }
         }
@@ -503,27 +614,34 @@ impl SolidGraphics {
     }
 }
 
+/// Placed prop, its uniform is created and updated by [Movable::sync] like the mesh of a [Solid]
 pub struct Prop {
     asset: PropID,
     position: Vector3<i32>,
     rotation: Quaternion<f32>,
     selected: bool,
-    data: PropData,
+    dirty: bool,
+    data: Option<PropData>,
 }
 
 impl Prop {
-    pub fn new(graphics: &Graphics, asset: PropID, position: Vector3<i32>) -> Self {
-        let rotation = Quaternion::new(1.0, 0.0, 0.0, 0.0);
+    pub fn new(asset: PropID, position: Vector3<i32>) -> Self {
+        Self::from_parts(asset, position, Quaternion::new(1.0, 0.0, 0.0, 0.0), false)
+    }
 
+    fn from_parts(
+        asset: PropID,
+        position: Vector3<i32>,
+        rotation: Quaternion<f32>,
+        selected: bool,
+    ) -> Self {
         Self {
             asset,
             position,
             rotation,
-            selected: false,
-            data: graphics.create_prop_data(&TransformTint {
-                transform: prop_transform(position, rotation),
-                tint: [0.0; 4],
-            }),
+            selected,
+            dirty: true,
+            data: None,
         }
     }
 
@@ -533,6 +651,7 @@ impl Prop {
 
     pub fn set_selected(&mut self, selected: bool) {
         self.selected = selected;
+        self.dirty = true;
     }
 
     pub fn intersects(&self, infos: &PropInfoContainer, ray: &Ray) -> Option<Vector3<f32>> {
@@ -571,6 +690,7 @@ impl Prop {
 
     pub fn set_rotation(&mut self, rotation: Quaternion<f32>) {
         self.rotation = rotation;
+        self.dirty = true;
     }
 
     pub fn save(&self) -> scene::Prop {
@@ -581,41 +701,23 @@ impl Prop {
         }
     }
 
-    pub fn load(graphics: &Graphics, prop: &scene::Prop) -> Self {
-        Self {
-            asset: prop.asset,
-            position: prop.position,
-            rotation: prop.rotation,
-            selected: false,
-            data: graphics.create_prop_data(&TransformTint {
-                transform: prop_transform(prop.position, prop.rotation),
-                tint: [0.0; 4],
-            }),
-        }
+    pub fn load(prop: &scene::Prop) -> Self {
+        Self::from_parts(prop.asset, prop.position, prop.rotation, false)
     }
+}
 
-    pub fn clone(&self, gfx: &Graphics) -> Self {
-        Self {
-            asset: self.asset,
-            position: self.position,
-            rotation: self.rotation,
-            selected: self.selected,
-            data: gfx.create_prop_data(&TransformTint {
-                transform: prop_transform(self.position, self.rotation),
-                tint: if self.selected {
-                    [0.04, 0.36, 0.85, 0.5]
-                } else {
-                    [0.0; 4]
-                },
-            }),
-        }
+impl Clone for Prop {
+    fn clone(&self) -> Self {
+        Self::from_parts(self.asset, self.position, self.rotation, self.selected)
     }
 }
 
 pub trait Movable: Sized {
     fn center(&self, mask: ElementKind) -> Vector3<f32>;
-    fn displace(&mut self, delta: Vector3<i32>, mask: ElementKind) -> bool;
-    fn recalc(&mut self, graphics: &Graphics);
+    /// Texture lock keeps the textures of moved solids in place, other elements ignore it
+    fn displace(&mut self, delta: Vector3<i32>, mask: ElementKind, texture_lock: bool) -> bool;
+    /// Brings the GPU resources up to date if anything changed since the last sync
+    fn sync(&mut self, graphics: &Graphics);
     fn render(&self, canvas: &mut Canvas, mask: ElementKind);
     fn insert_move(
         scene: &mut Scene,
@@ -642,7 +744,7 @@ impl Movable for Solid {
             ElementKind::Face => {
                 for face in &self.geometry.faces {
                     if face.selected {
-                        for pid in face.indices {
+                        for &pid in &face.indices {
                             let point = &self.geometry.points[pid];
                             center += point.meters();
                             div += 1.0;
@@ -662,18 +764,37 @@ impl Movable for Solid {
         center / div
     }
 
-    fn displace(&mut self, delta: Vector3<i32>, mask: ElementKind) -> bool {
-        self.geometry.displace(self.selected, delta, mask)
+    fn displace(&mut self, delta: Vector3<i32>, mask: ElementKind, texture_lock: bool) -> bool {
+        let changed = self
+            .geometry
+            .displace(self.selected, delta, mask, texture_lock);
+        self.dirty |= changed;
+        changed
     }
 
-    fn recalc(&mut self, graphics: &Graphics) {
+    fn sync(&mut self, graphics: &Graphics) {
+        if !self.dirty && self.graphics.is_some() {
+            return;
+        }
+
+        // Buffers are sized for the faces and points, reshaped solids need new ones
+        if let Some(solid_graphics) = &self.graphics {
+            if !solid_graphics.fits(&self.geometry) {
+                self.graphics = None;
+            }
+        }
+
         self.graphics
+            .get_or_insert_with(|| SolidGraphics::new(graphics, &self.geometry))
             .recalc(graphics, &self.geometry, self.selected);
+        self.dirty = false;
     }
 
     fn render(&self, canvas: &mut Canvas, mask: ElementKind) {
-        let draw_verts = matches!(mask, ElementKind::Point);
-        self.graphics.render(canvas, draw_verts);
+        if let Some(graphics) = &self.graphics {
+            let draw_verts = matches!(mask, ElementKind::Point);
+            graphics.render(canvas, draw_verts);
+        }
     }
 
     fn insert_move(
@@ -699,31 +820,39 @@ impl Movable for Prop {
         self.position.map(|e| e as f32 / 128.0)
     }
 
-    fn displace(&mut self, delta: Vector3<i32>, _mask: ElementKind) -> bool {
+    fn displace(&mut self, delta: Vector3<i32>, _mask: ElementKind, _texture_lock: bool) -> bool {
         if delta == Vector3::zero() {
             return false;
         }
 
         self.position += delta;
+        self.dirty = true;
         true
     }
 
-    fn recalc(&mut self, graphics: &Graphics) {
-        self.data = graphics.create_prop_data(&TransformTint {
+    fn sync(&mut self, graphics: &Graphics) {
+        if !self.dirty && self.data.is_some() {
+            return;
+        }
+
+        self.data = Some(graphics.create_prop_data(&TransformTint {
             transform: prop_transform(self.position, self.rotation),
             tint: if self.selected {
                 [0.04, 0.36, 0.85, 0.5]
             } else {
                 [0.0; 4]
             },
-        })
+        }));
+        self.dirty = false;
     }
 
     fn render(&self, canvas: &mut Canvas, _mask: ElementKind) {
-        canvas.draw_prop(PropInstance {
-            prop: self.asset,
-            data: self.data.share(),
-        });
+        if let Some(data) = &self.data {
+            canvas.draw_prop(PropInstance {
+                prop: self.asset,
+                data: data.share(),
+            });
+        }
     }
 
     fn insert_move(
@@ -1,5 +1,4 @@
-use std::collections::HashMap;
-
+use asset::scene;
 use cgmath::{InnerSpace, MetricSpace, Vector2, Vector3, Zero};
 
 use crate::{
@@ -136,24 +135,18 @@ pub fn raycast(input: RaycastInput) -> RaycastHit {
     }
 }
 
-fn raycast_faces(solids: &HashMap<usize, Solid>, ray: &Ray) -> Vec<HitFace> {
+fn raycast_faces(solids: &[(usize, &Solid)], ray: &Ray) -> Vec<HitFace> {
     let mut hit_faces = Vec::new();
 
     for (index, solid) in solids {
         let geometry = &solid.geometry;
-        for (i, face) in geometry.faces.iter().enumerate() {
-            let triangles = [
-                Triangle {
-                    a: geometry.points[face.indices[0]].meters(),
-                    b: geometry.points[face.indices[1]].meters(),
-                    c: geometry.points[face.indices[2]].meters(),
-                },
-                Triangle {
-                    a: geometry.points[face.indices[0]].meters(),
-                    b: geometry.points[face.indices[2]].meters(),
-                    c: geometry.points[face.indices[3]].meters(),
-                },
-            ];
+        for i in 0..geometry.faces.len() {
+            let corners = geometry.corners(i);
+            let triangles = scene::face_triangles(corners.len()).map(|[a, b, c]| Triangle {
+                a: corners[a],
+                b: corners[b],
+                c: corners[c],
+            });
 
             for triangle in triangles {
                 if let Some(intersection) = ray.intersects(&triangle) {
@@ -174,11 +167,7 @@ fn raycast_faces(solids: &HashMap<usize, Solid>, ray: &Ray) -> Vec<HitFace> {
     hit_faces
 }
 
-fn raycast_props(
-    props: &HashMap<usize, Prop>,
-    infos: &PropInfoContainer,
-    ray: &Ray,
-) -> Vec<HitProp> {
+fn raycast_props(props: &[(usize, &Prop)], infos: &PropInfoContainer, ray: &Ray) -> Vec<HitProp> {
     let mut hit_props = Vec::new();
 
     for (index, prop) in props {
@@ -193,9 +182,10 @@ fn raycast_props(
     hit_props
 }
 
+/// Only the solids and props given can be hit
 pub struct RaycastInput<'a> {
-    pub solids: &'a HashMap<usize, Solid>,
-    pub props: &'a HashMap<usize, Prop>,
+    pub solids: &'a [(usize, &'a Solid)],
+    pub props: &'a [(usize, &'a Prop)],
     pub camera: &'a Camera,
     pub prop_infos: &'a PropInfoContainer,
     pub screen_pos: Vector2<f32>,
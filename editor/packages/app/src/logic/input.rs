@@ -10,6 +10,7 @@ pub struct Input {
     mouse_pos: Vector2<f32>,
     mouse_wheel: f32,
     delta_override: Option<Vector2<f32>>,
+    typed: String,
 }
 
 impl Default for Input {
@@ -21,6 +22,7 @@ impl Default for Input {
             mouse_pos: Vector2::zero(),
             mouse_wheel: 0.0,
             delta_override: None,
+            typed: String::new(),
         }
     }
 }
@@ -82,6 +84,11 @@ impl Input {
         self.mouse_wheel
     }
 
+    /// Characters typed this frame, as the keyboard layout produces them
+    pub fn typed(&self) -> &str {
+        &self.typed
+    }
+
     pub fn process(&mut self) {
         for state in self.key_states.values_mut() {
             state.increment();
@@ -94,6 +101,7 @@ impl Input {
         self.mouse_pos_before = self.mouse_pos;
         self.mouse_wheel = 0.0;
         self.delta_override = None;
+        self.typed.clear();
     }
 
     pub fn key(&mut self, key: VirtualKeyCode, state: ElementState) {
@@ -115,6 +123,10 @@ impl Input {
     pub fn scroll(&mut self, movement: f32) {
         self.mouse_wheel = movement;
     }
+
+    pub fn character(&mut self, c: char) {
+        self.typed.push(c);
+    }
 }
 
 enum ActionState {
@@ -281,4 +293,15 @@ mod tests {
         input.process();
         assert_relative_eq!(input.mouse_wheel(), 0.0);
     }
+
+    #[test]
+    fn typed() {
+        let mut input = Input::default();
+
+        input.character('2');
+        input.character(',');
+        assert_eq!(input.typed(), "2,");
+        input.process();
+        assert_eq!(input.typed(), "");
+    }
 }
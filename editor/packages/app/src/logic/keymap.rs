@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use winit::event::VirtualKeyCode;
+
+use super::input::Input;
+
+/// Everything a key can be bound to, named in snake case in keymap overrides
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Command {
+    Undo,
+    Redo,
+    Copy,
+    Cut,
+    Paste,
+    GridFiner,
+    GridCoarser,
+    ModeSolid,
+    ModeFace,
+    ModePoint,
+    ModeProp,
+    SelectAll,
+    /// Held to add clicked elements to the selection
+    ExtendSelection,
+    Delete,
+    Move,
+    Clone,
+    RotateSolids,
+    Clip,
+    Carve,
+    Merge,
+    Hollow,
+    AssignTexture,
+    FitTexture,
+    TextureLeft,
+    TextureRight,
+    TextureDown,
+    TextureUp,
+    /// Held to scale textures with the texture directions instead of shifting them
+    ScaleTexture,
+    /// Held to rotate textures with the texture directions instead of shifting them
+    RotateTexture,
+    /// Held to place props by clicking
+    PlaceProp,
+    RotateProps,
+    ResetRotation,
+    /// Held to rotate props in steps of 15 degrees
+    SnapRotation,
+    /// Held to rotate solids the other way
+    ReverseRotation,
+    /// Held to pick the plane across an axis instead of the axis
+    ExcludeAxis,
+    AxisX,
+    AxisY,
+    AxisZ,
+    Confirm,
+    Cancel,
+    CycleClip,
+    /// Moves typed numbers on to the next axis
+    NextField,
+    /// Erases the last typed character
+    Erase,
+    FlyForward,
+    FlyBack,
+    FlyLeft,
+    FlyRight,
+    FlyDown,
+    FlyUp,
+}
+
+/// A key with the modifiers held along with it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Chord {
+    key: VirtualKeyCode,
+    ctrl: bool,
+    shift: bool,
+    alt: bool,
+}
+
+impl Chord {
+    /// Reads chords like `G`, `Ctrl+Shift+Z` or `Shift`, modifiers match either side of the keyboard
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.split('+').map(str::trim).rev();
+        let mut chord = Self {
+            key: parse_key(parts.next()?)?,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        };
+        for modifier in parts {
+            match parse_key(modifier)? {
+                VirtualKeyCode::LControl => chord.ctrl = true,
+                VirtualKeyCode::LShift => chord.shift = true,
+                VirtualKeyCode::LAlt => chord.alt = true,
+                _ => return None,
+            }
+        }
+        Some(chord)
+    }
+
+    fn modifiers(&self) -> [bool; 3] {
+        [self.ctrl, self.shift, self.alt]
+    }
+
+    // Whether the modifiers of the chord are held, others may be too
+    fn modifiers_held(&self, input: &Input) -> bool {
+        self.modifiers()
+            .into_iter()
+            .zip(MODIFIERS)
+            .all(|(needed, key)| !needed || key_down(input, key))
+    }
+
+    // Whether the other chord is on the same key with more modifiers
+    fn narrowed_by(&self, other: &Chord) -> bool {
+        other.key == self.key
+            && other.modifiers() != self.modifiers()
+            && self
+                .modifiers()
+                .into_iter()
+                .zip(other.modifiers())
+                .all(|(mine, theirs)| !mine || theirs)
+    }
+}
+
+const MODIFIERS: [VirtualKeyCode; 3] = [
+    VirtualKeyCode::LControl,
+    VirtualKeyCode::LShift,
+    VirtualKeyCode::LAlt,
+];
+
+/// Bindings of commands to chords, starting from the default profile
+pub struct Keymap {
+    bindings: Vec<(Command, Chord)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use Command::*;
+
+        let bindings = [
+            (Undo, "Ctrl+Z"),
+            (Redo, "Ctrl+Y"),
+            (Redo, "Ctrl+Shift+Z"),
+            (Copy, "Ctrl+C"),
+            (Cut, "Ctrl+X"),
+            (Paste, "Ctrl+V"),
+            (GridFiner, "O"),
+            (GridCoarser, "P"),
+            (ModeSolid, "1"),
+            (ModeFace, "2"),
+            (ModePoint, "3"),
+            (ModeProp, "4"),
+            (SelectAll, "Shift+A"),
+            (ExtendSelection, "Shift"),
+            (Delete, "Delete"),
+            (Move, "G"),
+            (Clone, "C"),
+            (RotateSolids, "F"),
+            (Clip, "K"),
+            (Carve, "V"),
+            (Merge, "M"),
+            (Hollow, "H"),
+            (AssignTexture, "T"),
+            (FitTexture, "F"),
+            (TextureLeft, "Left"),
+            (TextureRight, "Right"),
+            (TextureDown, "Down"),
+            (TextureUp, "Up"),
+            (ScaleTexture, "Shift"),
+            (RotateTexture, "Ctrl"),
+            (PlaceProp, "Ctrl"),
+            (RotateProps, "R"),
+            (ResetRotation, "R"),
+            (SnapRotation, "Ctrl"),
+            (ReverseRotation, "Shift"),
+            (ExcludeAxis, "Shift"),
+            (AxisX, "X"),
+            (AxisY, "Z"),
+            (AxisZ, "Y"),
+            (Confirm, "Return"),
+            (Cancel, "Escape"),
+            (CycleClip, "Tab"),
+            (NextField, "Tab"),
+            (Erase, "Backspace"),
+            (FlyForward, "W"),
+            (FlyBack, "S"),
+            (FlyLeft, "A"),
+            (FlyRight, "D"),
+            (FlyDown, "Q"),
+            (FlyUp, "E"),
+        ]
+        .into_iter()
+        .map(|(command, chord)| (command, Chord::parse(chord).unwrap()))
+        .collect();
+
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Rebinds the commands of a JSON object like `{"undo": ["Ctrl+Z"], "clone": []}`.
+    /// Commands left out keep their chords, nothing changes if any of it is invalid.
+    pub fn apply_overrides(&mut self, json: &str) -> Result<(), String> {
+        let overrides = serde_json::from_str::<HashMap<Command, Vec<String>>>(json)
+            .map_err(|err| err.to_string())?;
+
+        let mut rebound = Vec::new();
+        for (command, chords) in &overrides {
+            for chord in chords {
+                let chord = Chord::parse(chord).ok_or_else(|| format!("bad chord `{}`", chord))?;
+                rebound.push((*command, chord));
+            }
+        }
+
+        self.bindings
+            .retain(|(command, _)| !overrides.contains_key(command));
+        self.bindings.extend(rebound);
+        Ok(())
+    }
+
+    /// Whether a chord of the command was pressed this frame.
+    /// Extra modifiers are allowed unless they make up a chord bound on the same key.
+    pub fn pressed(&self, input: &Input, command: Command) -> bool {
+        self.chords(command).any(|chord| {
+            key_down_once(input, chord.key)
+                && chord.modifiers_held(input)
+                && !self
+                    .bindings
+                    .iter()
+                    .any(|(_, other)| chord.narrowed_by(other) && other.modifiers_held(input))
+        })
+    }
+
+    /// Whether a chord of the command is held down
+    pub fn held(&self, input: &Input, command: Command) -> bool {
+        self.chords(command)
+            .any(|chord| key_down(input, chord.key) && chord.modifiers_held(input))
+    }
+
+    fn chords(&self, command: Command) -> impl Iterator<Item = &Chord> {
+        self.bindings
+            .iter()
+            .filter(move |(bound, _)| *bound == command)
+            .map(|(_, chord)| chord)
+    }
+}
+
+// The right side modifiers count as the left side ones
+fn other_side(key: VirtualKeyCode) -> Option<VirtualKeyCode> {
+    match key {
+        VirtualKeyCode::LControl => Some(VirtualKeyCode::RControl),
+        VirtualKeyCode::LShift => Some(VirtualKeyCode::RShift),
+        VirtualKeyCode::LAlt => Some(VirtualKeyCode::RAlt),
+        _ => None,
+    }
+}
+
+fn key_down(input: &Input, key: VirtualKeyCode) -> bool {
+    input.is_key_down(key) || other_side(key).is_some_and(|key| input.is_key_down(key))
+}
+
+fn key_down_once(input: &Input, key: VirtualKeyCode) -> bool {
+    input.is_key_down_once(key) || other_side(key).is_some_and(|key| input.is_key_down_once(key))
+}
+
+fn parse_key(name: &str) -> Option<VirtualKeyCode> {
+    use VirtualKeyCode::*;
+
+    const LETTERS: [VirtualKeyCode; 26] = [
+        A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    ];
+    const DIGITS: [VirtualKeyCode; 10] =
+        [Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+    const FUNCTIONS: [VirtualKeyCode; 12] = [F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12];
+
+    let upper = name.to_ascii_uppercase();
+    let mut chars = upper.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return match c {
+            'A'..='Z' => Some(LETTERS[c as usize - 'A' as usize]),
+            '0'..='9' => Some(DIGITS[c as usize - '0' as usize]),
+            _ => None,
+        };
+    }
+
+    if let Some(n) = upper
+        .strip_prefix('F')
+        .and_then(|n| n.parse::<usize>().ok())
+    {
+        return FUNCTIONS.get(n.checked_sub(1)?).copied();
+    }
+
+    Some(match upper.as_str() {
+        "CTRL" | "CONTROL" => LControl,
+        "SHIFT" => LShift,
+        "ALT" => LAlt,
+        "ESC" | "ESCAPE" => Escape,
+        "ENTER" | "RETURN" => Return,
+        "TAB" => Tab,
+        "SPACE" => Space,
+        "BACKSPACE" => Back,
+        "DELETE" | "DEL" => Delete,
+        "INSERT" => Insert,
+        "HOME" => Home,
+        "END" => End,
+        "PAGEUP" => PageUp,
+        "PAGEDOWN" => PageDown,
+        "LEFT" => Left,
+        "RIGHT" => Right,
+        "UP" => Up,
+        "DOWN" => Down,
+        "MINUS" => Minus,
+        "EQUALS" => Equals,
+        "COMMA" => Comma,
+        "PERIOD" => Period,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::{ElementState, VirtualKeyCode};
+
+    use super::{Chord, Command, Keymap};
+    use crate::logic::input::Input;
+
+    fn press(keys: &[VirtualKeyCode]) -> Input {
+        let mut input = Input::default();
+        for key in keys {
+            input.key(*key, ElementState::Pressed);
+        }
+        input
+    }
+
+    #[test]
+    fn parse_chords() {
+        let chord = Chord::parse("ctrl + shift + z").unwrap();
+        assert_eq!(chord, Chord::parse("Shift+Ctrl+Z").unwrap());
+        assert!(chord.ctrl && chord.shift && !chord.alt);
+        assert_eq!(chord.key, VirtualKeyCode::Z);
+        assert_eq!(Chord::parse("F11").unwrap().key, VirtualKeyCode::F11);
+        assert_eq!(Chord::parse("7").unwrap().key, VirtualKeyCode::Key7);
+        assert!(Chord::parse("Z+Ctrl").is_none());
+        assert!(Chord::parse("Hyper+A").is_none());
+        assert!(Chord::parse("F13").is_none());
+    }
+
+    #[test]
+    fn resolve_chords() {
+        let keymap = Keymap::default();
+
+        // Control narrows C from cloning to copying
+        let input = press(&[VirtualKeyCode::LControl, VirtualKeyCode::C]);
+        assert!(keymap.pressed(&input, Command::Copy));
+        assert!(!keymap.pressed(&input, Command::Clone));
+        let input = press(&[VirtualKeyCode::C]);
+        assert!(keymap.pressed(&input, Command::Clone));
+        assert!(!keymap.pressed(&input, Command::Copy));
+
+        // The most specific chord wins, on either side of the keyboard
+        let input = press(&[
+            VirtualKeyCode::RControl,
+            VirtualKeyCode::LShift,
+            VirtualKeyCode::Z,
+        ]);
+        assert!(keymap.pressed(&input, Command::Redo));
+        assert!(!keymap.pressed(&input, Command::Undo));
+
+        // Modifiers nothing is bound with don't get in the way
+        let input = press(&[VirtualKeyCode::LShift, VirtualKeyCode::X]);
+        assert!(keymap.pressed(&input, Command::AxisX));
+        assert!(keymap.held(&input, Command::ExcludeAxis));
+
+        // Pressing only counts on the first frame, holding for as long as it's down
+        let mut input = press(&[VirtualKeyCode::W]);
+        input.process();
+        assert!(!keymap.pressed(&input, Command::FlyForward));
+        assert!(keymap.held(&input, Command::FlyForward));
+    }
+
+    #[test]
+    fn overrides() {
+        let mut keymap = Keymap::default();
+        keymap
+            .apply_overrides(r#"{"fly_forward": ["Z"], "fly_left": ["Q"], "clone": []}"#)
+            .unwrap();
+
+        let input = press(&[VirtualKeyCode::Z]);
+        assert!(keymap.held(&input, Command::FlyForward));
+        assert!(keymap.pressed(&input, Command::AxisY));
+        let input = press(&[VirtualKeyCode::C]);
+        assert!(!keymap.pressed(&input, Command::Clone));
+        let input = press(&[VirtualKeyCode::G]);
+        assert!(keymap.pressed(&input, Command::Move));
+
+        // Mistakes leave the keymap as it was
+        assert!(keymap.apply_overrides(r#"{"fly": ["W"]}"#).is_err());
+        assert!(keymap
+            .apply_overrides(r#"{"move": ["M"], "clip": ["Ctrl+"]}"#)
+            .is_err());
+        assert!(keymap.pressed(&input, Command::Move));
+    }
+}
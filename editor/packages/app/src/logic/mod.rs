@@ -3,16 +3,17 @@ mod common;
 mod editor;
 mod elements;
 mod input;
+mod keymap;
 mod scene;
 
 use asset::{GizmoID, PropID, TextureID};
-use cgmath::{vec2, Matrix4, Zero};
+use cgmath::{vec2, Deg, Euler, Matrix4, Quaternion, Vector3, Zero};
 use winit::event::{ElementState, MouseButton, VirtualKeyCode};
 
 use crate::{
     data::PropInfoContainer,
     graphics::{structures::GizmoInstance, Canvas, GizmoGroup, GizmoInstances, Graphics, Share},
-    Host, ToHost,
+    Host, OutlineCommand, ToHost, Transform,
 };
 
 use self::{camera::Camera, editor::Editor, input::Input, scene::Scene};
@@ -72,6 +73,13 @@ impl Logic {
             scene: &mut self.scene,
             delta: ctx.delta,
         });
+        self.scene.sync(ctx.graphics);
+        if self.scene.take_outline_changed() {
+            ctx.host.callback(ToHost::Outline(self.scene.outline()));
+        }
+        if self.scene.take_history_changed() {
+            ctx.host.callback(ToHost::History(self.scene.history()));
+        }
         self.input.process();
     }
 
@@ -83,6 +91,10 @@ impl Logic {
         self.input.key(key, state);
     }
 
+    pub fn character(&mut self, c: char) {
+        self.input.character(c);
+    }
+
     pub fn button(&mut self, button: MouseButton, state: ElementState) {
         self.input.button(button, state);
     }
@@ -109,16 +121,77 @@ impl Logic {
         ctx.host.callback(ToHost::SceneSaved(id, buf));
     }
 
-    pub fn load_scene(&mut self, ctx: Context, scene: &asset::scene::Scene) {
+    pub fn load_scene(&mut self, scene: &asset::scene::Scene) {
         self.camera.load(&scene.camera);
-        self.scene.load(
-            scene::Context {
+        self.scene.load(&scene.world);
+    }
+
+    /// Picks up a newly loaded lightmap on the next sync
+    pub fn relight(&mut self) {
+        self.scene.invalidate_solids();
+    }
+
+    pub fn set_texture_lock(&mut self, lock: bool) {
+        self.scene.set_texture_lock(lock);
+    }
+
+    pub fn undo(&mut self) {
+        if self.editor.idle() {
+            self.scene.undo();
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if self.editor.idle() {
+            self.scene.redo();
+        }
+    }
+
+    pub fn jump_history(&mut self, position: usize) {
+        if self.editor.idle() {
+            self.scene.jump_history(position);
+        }
+    }
+
+    pub fn set_history_budget(&mut self, entries: usize, bytes: usize) {
+        self.scene.set_history_budget(entries, bytes);
+    }
+
+    pub fn paste(&mut self, ctx: Context, buf: &[u8]) {
+        self.editor.paste(
+            editor::Context {
+                host: ctx.host,
+                input: &self.input,
                 graphics: ctx.graphics,
+                prop_infos: ctx.prop_infos,
+                camera: &mut self.camera,
+                scene: &mut self.scene,
+                delta: ctx.delta,
             },
-            &scene.world,
+            buf,
         );
     }
 
+    pub fn transform(&mut self, transform: &Transform) {
+        if self.editor.idle() {
+            let [x, y, z] = transform.rotation.map(Deg);
+            self.scene.transform(
+                self.editor.mode(),
+                Vector3::from(transform.translation.map(common::units)),
+                Quaternion::from(Euler { x, y, z }),
+            );
+        }
+    }
+
+    pub fn edit_outline(&mut self, command: OutlineCommand) {
+        self.scene.edit_outline(command, self.editor.mode());
+    }
+
+    /// Rebinds commands from a JSON object of command names and chord lists
+    pub fn set_keymap(&mut self, json: &str) -> Result<(), String> {
+        self.editor.set_keymap(json)
+    }
+
     pub fn set_texture(&mut self, texture: TextureID) {
         self.editor.set_texture(texture);
     }
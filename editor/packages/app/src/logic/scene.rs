@@ -1,20 +1,24 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    mem::size_of,
+};
 
-use asset::{scene, TextureID};
-use cgmath::{Quaternion, Rotation, Vector2, Vector3, Zero};
+use asset::{scene, scene::TextureAlignment, TextureID};
+use cgmath::{InnerSpace, One, Quaternion, Rotation, Vector2, Vector3, Zero};
 
 use crate::{
     data::PropInfoContainer,
     graphics::{Canvas, Graphics},
     math::Snap,
+    History, Outline, OutlineCommand, OutlineGroup, OutlineLayer, OutlineObject,
 };
 
 use super::{
     camera::Camera,
     common::Axis,
     elements::{
-        self, ElementKind, FaceLocator, Movable, PointLocator, Prop, RaycastHit, RaycastInput,
-        Solid,
+        self, ClipKeep, ClipPlane, ElementKind, FaceLocator, Movable, PointLocator, Prop,
+        RaycastHit, RaycastInput, Solid, TextureAdjustment,
     },
 };
 
@@ -24,31 +28,464 @@ pub struct Scene {
     props: HashMap<usize, Prop>,
     next_elem_id: usize,
     undo_stack: UndoStack,
-    redo_stack: Vec<Action>,
+    texture_lock: bool,
+    layers: Vec<scene::Layer>,
+    /// Group ids are kept while editing, saving numbers them from 0
+    groups: BTreeMap<u32, scene::Group>,
+    /// Name, layer and group of solids and props by id, missing ones are in none
+    objects: HashMap<usize, scene::Object>,
+    outline_changed: bool,
 }
 
 impl Scene {
-    pub fn act(&mut self, ctx: Context, action: Action) {
-        if let Some(reaction) = self.execute(ctx, action) {
-            self.undo_stack.push(reaction);
-            self.redo_stack.clear();
+    pub fn act(&mut self, action: Action) {
+        let name = action.name();
+        if let Some(reaction) = self.execute(action) {
+            self.undo_stack.record(name, reaction);
+        }
+    }
+
+    /// Actions taken until the matching [Scene::commit] are undone as one step with the given name.
+    /// Transactions can be nested, only the outermost one's name is kept.
+    pub fn begin(&mut self, name: &str) {
+        self.undo_stack.begin(name);
+    }
+
+    pub fn commit(&mut self) {
+        self.undo_stack.commit();
+    }
+
+    /// Does nothing while a transaction is open
+    pub fn undo(&mut self) {
+        if let Some(entry) = self.undo_stack.pop_undo() {
+            let entry = self.apply(entry);
+            self.undo_stack.push_redo(entry);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(entry) = self.undo_stack.pop_redo() {
+            let entry = self.apply(entry);
+            self.undo_stack.push_undo(entry);
+        }
+    }
+
+    /// Undoes or redoes until `position` steps are left to undo, see [History::position]
+    pub fn jump_history(&mut self, position: usize) {
+        while self.undo_stack.undo.len() > position && self.undo_stack.transaction.is_none() {
+            self.undo();
+        }
+        while self.undo_stack.undo.len() < position && !self.undo_stack.redo.is_empty() {
+            self.redo();
+        }
+    }
+
+    /// Limits the steps kept for undoing, by count and by a rough estimate of their size in bytes
+    pub fn set_history_budget(&mut self, entries: usize, bytes: usize) {
+        self.undo_stack.limit = entries.max(1);
+        self.undo_stack.budget = bytes;
+        self.undo_stack.evict();
+    }
+
+    /// Names of the steps that can be undone, oldest first, then of those that can be redone
+    pub fn history(&self) -> History {
+        History {
+            entries: self
+                .undo_stack
+                .undo
+                .iter()
+                .chain(self.undo_stack.redo.iter().rev())
+                .map(|entry| entry.name.clone())
+                .collect(),
+            position: self.undo_stack.undo.len(),
+        }
+    }
+
+    /// Whether the history changed since this was last called
+    pub fn take_history_changed(&mut self) -> bool {
+        std::mem::take(&mut self.undo_stack.changed)
+    }
+
+    // Executes the actions of a step last to first, giving the step that reverts it
+    fn apply(&mut self, entry: Entry) -> Entry {
+        let actions = entry
+            .actions
+            .into_iter()
+            .rev()
+            .filter_map(|action| self.execute(action))
+            .collect();
+        Entry::new(entry.name, actions)
+    }
+
+    /// Whether moving and rotating solids keeps their textures in place
+    pub fn texture_lock(&self) -> bool {
+        self.texture_lock
+    }
+
+    pub fn set_texture_lock(&mut self, texture_lock: bool) {
+        self.texture_lock = texture_lock;
+    }
+
+    fn object(&self, id: usize) -> scene::Object {
+        self.objects.get(&id).cloned().unwrap_or_default()
+    }
+
+    fn copy_object(&mut self, from: usize, to: usize) {
+        if let Some(object) = self.objects.get(&from).cloned() {
+            self.objects.insert(to, object);
+        }
+    }
+
+    fn layer(&self, id: usize) -> Option<&scene::Layer> {
+        self.objects
+            .get(&id)
+            .and_then(|object| object.layer)
+            .and_then(|layer| self.layers.get(layer as usize))
+    }
+
+    /// Whether the solid or prop is on a hidden layer, which keeps it from being drawn and hit
+    pub fn hidden(&self, id: usize) -> bool {
+        self.layer(id).is_some_and(|layer| layer.hidden)
+    }
+
+    /// Whether the solid or prop can be selected, which hidden and locked layers prevent
+    pub fn pickable(&self, id: usize) -> bool {
+        self.layer(id)
+            .is_none_or(|layer| !layer.hidden && !layer.locked)
+    }
+
+    // Outermost group containing a group
+    fn root_group(&self, mut group: u32) -> u32 {
+        // Bounded in case of a cycle, which edits never make but a file could hold
+        for _ in 0..=self.groups.len() {
+            match self.groups.get(&group).and_then(|group| group.parent) {
+                Some(parent) => group = parent,
+                None => break,
+            }
         }
+        group
     }
 
-    pub fn undo(&mut self, ctx: Context) {
-        if let Some(action) = self.undo_stack.pop() {
-            if let Some(reaction) = self.execute(ctx, action) {
-                self.redo_stack.push(reaction);
+    // Whether a group is the given one or nested somewhere inside it
+    fn in_group(&self, mut group: u32, ancestor: u32) -> bool {
+        for _ in 0..=self.groups.len() {
+            if group == ancestor {
+                return true;
             }
+            match self.groups.get(&group).and_then(|group| group.parent) {
+                Some(parent) => group = parent,
+                None => break,
+            }
+        }
+        false
+    }
+
+    // Solids or props of the same kind as the id that are in a group, sorted
+    fn group_members(&self, id: usize, group: u32) -> Vec<usize> {
+        let mut ids = if self.solids.contains_key(&id) {
+            self.solids.keys().copied().collect::<Vec<_>>()
+        } else {
+            self.props.keys().copied().collect::<Vec<_>>()
+        };
+        ids.retain(|id| {
+            self.pickable(*id)
+                && self
+                    .objects
+                    .get(id)
+                    .and_then(|object| object.group)
+                    .is_some_and(|member| self.in_group(member, group))
+        });
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Solids or props picked together with the given one, which are the members of its outermost group.
+    /// Empty if it can't be picked.
+    pub fn selection_unit(&self, id: usize) -> Vec<usize> {
+        if !self.pickable(id) {
+            return Vec::new();
+        }
+
+        match self.object(id).group {
+            Some(group) => self.group_members(id, self.root_group(group)),
+            None => vec![id],
+        }
+    }
+
+    fn selected_ids(&self, mode: ElementKind) -> Vec<usize> {
+        let mut ids = match mode {
+            ElementKind::Solid => self.selected_solid_ids(),
+            ElementKind::Face => self
+                .solids
+                .iter()
+                .filter_map(|(id, solid)| solid.any_face_selected().then_some(*id))
+                .collect(),
+            ElementKind::Point => self
+                .solids
+                .iter()
+                .filter_map(|(id, solid)| solid.any_point_selected().then_some(*id))
+                .collect(),
+            ElementKind::Prop => self
+                .props
+                .iter()
+                .filter_map(|(id, prop)| prop.selected().then_some(*id))
+                .collect(),
+        };
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Layers, groups and every solid and prop, for the host's outliner
+    pub fn outline(&self) -> Outline {
+        let mut solids = self.solids.keys().copied().collect::<Vec<_>>();
+        solids.sort_unstable();
+        let mut props = self.props.keys().copied().collect::<Vec<_>>();
+        props.sort_unstable();
+
+        Outline {
+            layers: self
+                .layers
+                .iter()
+                .map(|layer| OutlineLayer {
+                    name: layer.name.clone(),
+                    hidden: layer.hidden,
+                    locked: layer.locked,
+                })
+                .collect(),
+            groups: self
+                .groups
+                .iter()
+                .map(|(id, group)| OutlineGroup {
+                    id: *id,
+                    name: group.name.clone(),
+                    parent: group.parent,
+                })
+                .collect(),
+            objects: solids
+                .into_iter()
+                .map(|id| (id, false))
+                .chain(props.into_iter().map(|id| (id, true)))
+                .map(|(id, prop)| {
+                    let object = self.object(id);
+                    OutlineObject {
+                        id,
+                        prop,
+                        name: object.name,
+                        layer: object.layer,
+                        group: object.group,
+                    }
+                })
+                .collect(),
         }
     }
 
-    pub fn redo(&mut self, ctx: Context) {
-        if let Some(action) = self.redo_stack.pop() {
-            if let Some(reaction) = self.execute(ctx, action) {
-                self.undo_stack.push(reaction);
+    /// Whether the outline changed since this was last called
+    pub fn take_outline_changed(&mut self) -> bool {
+        std::mem::take(&mut self.outline_changed)
+    }
+
+    /// Applies a change from the host's outliner, `mode` decides what is selected
+    pub fn edit_outline(&mut self, command: OutlineCommand, mode: ElementKind) {
+        let mut edit = OutlineEdit::default();
+        let mut layers = self.layers.clone();
+        match command {
+            OutlineCommand::NewLayer(name) => {
+                layers.push(scene::Layer {
+                    name,
+                    ..Default::default()
+                });
+                edit.layers = Some(layers);
+            }
+            OutlineCommand::RenameLayer(index, name) => {
+                if let Some(layer) = layers.get_mut(index as usize) {
+                    layer.name = name;
+                    edit.layers = Some(layers);
+                }
+            }
+            OutlineCommand::HideLayer(index, hidden) => {
+                if let Some(layer) = layers.get_mut(index as usize) {
+                    layer.hidden = hidden;
+                    edit.layers = Some(layers);
+                }
+            }
+            OutlineCommand::LockLayer(index, locked) => {
+                if let Some(layer) = layers.get_mut(index as usize) {
+                    layer.locked = locked;
+                    edit.layers = Some(layers);
+                }
+            }
+            OutlineCommand::AssignLayer(layer) => {
+                if layer.is_none_or(|layer| (layer as usize) < self.layers.len()) {
+                    edit.objects = self
+                        .selected_ids(mode)
+                        .into_iter()
+                        .map(|id| {
+                            (
+                                id,
+                                scene::Object {
+                                    layer,
+                                    ..self.object(id)
+                                },
+                            )
+                        })
+                        .collect();
+                }
+            }
+            OutlineCommand::Group(name) => {
+                let ids = self.selected_ids(mode);
+                if !ids.is_empty() {
+                    let group = self.groups.keys().next_back().map_or(0, |id| id + 1);
+                    // Nested in the group the members share, if they do
+                    let parents = ids
+                        .iter()
+                        .map(|id| self.object(*id).group)
+                        .collect::<HashSet<_>>();
+                    let parent = match parents.len() {
+                        1 => parents.into_iter().next().flatten(),
+                        _ => None,
+                    };
+
+                    edit.groups
+                        .push((group, Some(scene::Group { name, parent })));
+                    edit.objects = ids
+                        .into_iter()
+                        .map(|id| {
+                            let object = scene::Object {
+                                group: Some(group),
+                                ..self.object(id)
+                            };
+                            (id, object)
+                        })
+                        .collect();
+                }
+            }
+            OutlineCommand::Ungroup(group) => {
+                if let Some(parent) = self.groups.get(&group).map(|group| group.parent) {
+                    edit.groups.push((group, None));
+                    edit.groups.extend(
+                        self.groups
+                            .iter()
+                            .filter(|(_, child)| child.parent == Some(group))
+                            .map(|(id, child)| {
+                                let child = scene::Group {
+                                    parent,
+                                    ..child.clone()
+                                };
+                                (*id, Some(child))
+                            }),
+                    );
+                    edit.objects = self
+                        .objects
+                        .iter()
+                        .filter(|(_, object)| object.group == Some(group))
+                        .map(|(id, object)| {
+                            let object = scene::Object {
+                                group: parent,
+                                ..object.clone()
+                            };
+                            (*id, object)
+                        })
+                        .collect();
+                }
+            }
+            OutlineCommand::RenameGroup(group, name) => {
+                if let Some(old) = self.groups.get(&group) {
+                    let renamed = scene::Group {
+                        name,
+                        ..old.clone()
+                    };
+                    edit.groups.push((group, Some(renamed)));
+                }
+            }
+            OutlineCommand::RenameObject(id, name) => {
+                if self.solids.contains_key(&id) || self.props.contains_key(&id) {
+                    edit.objects.push((
+                        id,
+                        scene::Object {
+                            name,
+                            ..self.object(id)
+                        },
+                    ));
+                }
+            }
+            OutlineCommand::SelectGroup(group) => {
+                if !self.groups.contains_key(&group) {
+                    return;
+                }
+
+                let members = |ids: Vec<usize>| {
+                    let mut ids = ids;
+                    ids.retain(|id| {
+                        self.pickable(*id)
+                            && self
+                                .object(*id)
+                                .group
+                                .is_some_and(|member| self.in_group(member, group))
+                    });
+                    ids.sort_unstable();
+                    ids
+                };
+                let action = match mode {
+                    ElementKind::Solid => {
+                        Action::SelectSolids(members(self.solids.keys().copied().collect()))
+                    }
+                    ElementKind::Prop => {
+                        Action::SelectProps(members(self.props.keys().copied().collect()))
+                    }
+                    _ => return,
+                };
+
+                self.begin("Select group");
+                self.act(Action::DeselectAll(mode));
+                self.act(action);
+                self.commit();
+                return;
             }
         }
+
+        self.act(Action::EditOutline(edit));
+    }
+
+    // Selections of solids, faces, points and props which can no longer be picked
+    fn unpickable_selections(&self) -> Vec<Action> {
+        let unpickable = self
+            .solids
+            .iter()
+            .filter(|(id, _)| !self.pickable(**id))
+            .collect::<Vec<_>>();
+
+        let solids = unpickable
+            .iter()
+            .filter_map(|(id, solid)| solid.selected().then_some(**id))
+            .collect();
+        let faces = unpickable
+            .iter()
+            .flat_map(|(id, solid)| {
+                (0..solid.face_count())
+                    .filter(|face| solid.face_selected(*face))
+                    .map(|face| FaceLocator { solid: **id, face })
+            })
+            .collect();
+        let points = unpickable
+            .iter()
+            .flat_map(|(id, solid)| {
+                (0..solid.point_count())
+                    .filter(|point| solid.point_selected(*point))
+                    .map(|point| PointLocator { solid: **id, point })
+            })
+            .collect();
+        let props = self
+            .props
+            .iter()
+            .filter_map(|(id, prop)| (prop.selected() && !self.pickable(*id)).then_some(*id))
+            .collect();
+
+        vec![
+            Action::SelectSolids(solids),
+            Action::SelectFaces(faces),
+            Action::SelectPoints(points),
+            Action::SelectProps(props),
+        ]
     }
 
     pub fn raycast(
@@ -57,9 +494,22 @@ impl Scene {
         camera: &Camera,
         prop_infos: &PropInfoContainer,
     ) -> RaycastHit {
+        let solids = self
+            .solids
+            .iter()
+            .filter(|(id, _)| !self.hidden(**id))
+            .map(|(id, solid)| (*id, solid))
+            .collect::<Vec<_>>();
+        let props = self
+            .props
+            .iter()
+            .filter(|(id, _)| !self.hidden(**id))
+            .map(|(id, prop)| (*id, prop))
+            .collect::<Vec<_>>();
+
         elements::raycast(RaycastInput {
-            solids: &self.solids,
-            props: &self.props,
+            solids: &solids,
+            props: &props,
             camera,
             prop_infos,
             screen_pos,
@@ -87,10 +537,10 @@ impl Scene {
             .collect()
     }
 
-    pub fn clone_solids(&mut self, ctx: Context) -> Vec<(usize, Solid)> {
+    pub fn clone_solids(&mut self) -> Vec<(usize, Solid)> {
         self.solids
             .iter()
-            .filter_map(|(id, solid)| solid.selected().then(|| (*id, solid.clone(ctx.graphics))))
+            .filter_map(|(id, solid)| solid.selected().then(|| (*id, solid.clone())))
             .collect()
     }
 
@@ -104,11 +554,14 @@ impl Scene {
             self.solids.insert(id, solid);
         }
 
-        self.undo_stack.push(Action::Move {
-            kind: mask,
-            delta: -delta,
-        });
-        self.redo_stack.clear();
+        self.undo_stack.record(
+            "Move",
+            Action::Move {
+                kind: mask,
+                delta: -delta,
+                texture_lock: self.texture_lock,
+            },
+        );
     }
 
     pub fn insert_solids(&mut self, solids: Vec<(usize, Solid)>) {
@@ -117,16 +570,19 @@ impl Scene {
         }
     }
 
+    /// Inserts copies under new ids, in the same layer and group as the originals
     pub fn insert_solids_with_remove(&mut self, solids: Vec<(usize, Solid)>) {
         let mut ids = Vec::new();
-        for (_, solid) in solids {
+        for (original, solid) in solids {
             let id = self.next_elem_id;
             self.next_elem_id += 1;
             self.solids.insert(id, solid);
+            self.copy_object(original, id);
             ids.push(id);
         }
-        self.undo_stack.push(Action::RemoveSolids(ids));
-        self.redo_stack.clear();
+        self.outline_changed = true;
+        self.undo_stack
+            .record("Clone solids", Action::RemoveSolids(ids));
     }
 
     pub fn take_props(&mut self) -> Vec<(usize, Prop)> {
@@ -142,10 +598,10 @@ impl Scene {
             .collect()
     }
 
-    pub fn clone_props(&mut self, ctx: Context) -> Vec<(usize, Prop)> {
+    pub fn clone_props(&mut self) -> Vec<(usize, Prop)> {
         self.props
             .iter()
-            .filter_map(|(id, prop)| prop.selected().then(|| (*id, prop.clone(ctx.graphics))))
+            .filter_map(|(id, prop)| prop.selected().then(|| (*id, prop.clone())))
             .collect()
     }
 
@@ -155,11 +611,14 @@ impl Scene {
         }
 
         if delta != Vector3::zero() {
-            self.undo_stack.push(Action::Move {
-                kind: ElementKind::Prop,
-                delta: -delta,
-            });
-            self.redo_stack.clear();
+            self.undo_stack.record(
+                "Move",
+                Action::Move {
+                    kind: ElementKind::Prop,
+                    delta: -delta,
+                    texture_lock: false,
+                },
+            );
         }
     }
 
@@ -168,8 +627,8 @@ impl Scene {
             self.props.insert(id, prop);
         }
 
-        self.undo_stack.push(Action::RotateProps(delta.invert()));
-        self.redo_stack.clear();
+        self.undo_stack
+            .record("Rotate props", Action::RotateProps(delta.invert()));
     }
 
     pub fn insert_props(&mut self, props: Vec<(usize, Prop)>) {
@@ -178,18 +637,21 @@ impl Scene {
         }
     }
 
+    /// Inserts copies under new ids, in the same layer and group as the originals
     pub fn insert_props_with_remove(&mut self, props: Vec<(usize, Prop)>) {
         let mut ids = Vec::new();
 
-        for (_, prop) in props {
+        for (original, prop) in props {
             let id = self.next_elem_id;
             self.next_elem_id += 1;
             self.props.insert(id, prop);
+            self.copy_object(original, id);
             ids.push(id);
         }
+        self.outline_changed = true;
 
-        self.undo_stack.push(Action::RemoveProps(ids));
-        self.redo_stack.clear();
+        self.undo_stack
+            .record("Clone props", Action::RemoveProps(ids));
     }
 
     pub fn calc_center(&self, mask: ElementKind) -> Option<Vector3<f32>> {
@@ -253,28 +715,295 @@ impl Scene {
             .collect()
     }
 
-    pub fn hollow_of(&self, gfx: &Graphics, id: usize, grid: i32) -> Vec<Solid> {
+    pub fn hollow_of(&self, id: usize, grid: i32) -> Vec<Solid> {
         let solid = self.solids.get(&id).unwrap();
-        solid.make_hollow(gfx, grid).into()
+        solid.make_hollow(grid).into()
+    }
+
+    /// Selected solids the plane cuts through and the pieces that replace them
+    pub fn clip_selected(
+        &self,
+        plane: &ClipPlane,
+        keep: ClipKeep,
+        cap: TextureID,
+    ) -> (Vec<usize>, Vec<Solid>) {
+        let mut ids = Vec::new();
+        let mut pieces = Vec::new();
+        for (id, solid) in self.solids.iter().filter(|(_, solid)| solid.selected()) {
+            if let Some(clipped) = solid.clip(plane, keep, cap) {
+                ids.push(*id);
+                pieces.extend(clipped);
+            }
+        }
+        (ids, pieces)
+    }
+
+    /// Solids overlapping the selected ones and what is left of them once the selection is cut out
+    pub fn carve_selected(&self) -> (Vec<usize>, Vec<Solid>) {
+        let carvers = self
+            .solids
+            .values()
+            .filter(|solid| solid.selected())
+            .collect::<Vec<_>>();
+
+        let mut ids = Vec::new();
+        let mut pieces = Vec::new();
+        for (id, solid) in self.solids.iter().filter(|(_, solid)| !solid.selected()) {
+            let mut carved = vec![solid.clone()];
+            let mut changed = false;
+            for carver in &carvers {
+                carved = carved
+                    .into_iter()
+                    .flat_map(|piece| match piece.carve(carver) {
+                        Some(remains) => {
+                            changed = true;
+                            remains
+                        }
+                        None => vec![piece],
+                    })
+                    .collect();
+            }
+
+            if changed {
+                ids.push(*id);
+                pieces.extend(carved);
+            }
+        }
+        (ids, pieces)
+    }
+
+    /// Selected solids that can be joined into convex ones, and the joined solids
+    pub fn merge_selected(&self) -> (Vec<usize>, Vec<Solid>) {
+        let mut groups = self
+            .solids
+            .iter()
+            .filter(|(_, solid)| solid.selected())
+            .map(|(id, solid)| (vec![*id], solid.clone()))
+            .collect::<Vec<_>>();
+        groups.sort_unstable_by_key(|(ids, _)| ids[0]);
+
+        'merge: loop {
+            for i in 0..groups.len() {
+                for j in i + 1..groups.len() {
+                    if let Some(merged) = groups[i].1.merge(&groups[j].1) {
+                        let (ids, _) = groups.remove(j);
+                        groups[i].0.extend(ids);
+                        groups[i].1 = merged;
+                        continue 'merge;
+                    }
+                }
+            }
+            break;
+        }
+
+        groups.into_iter().filter(|(ids, _)| ids.len() > 1).fold(
+            (Vec::new(), Vec::new()),
+            |(mut ids, mut solids), group| {
+                ids.extend(group.0);
+                solids.push(group.1);
+                (ids, solids)
+            },
+        )
+    }
+
+    /// Selected solids and props, around the grid point under the middle of their bottom
+    pub fn copy(&self, grid: i32) -> Option<scene::Clipboard> {
+        let mut solids = self
+            .solids
+            .iter()
+            .filter(|(_, solid)| solid.selected())
+            .collect::<Vec<_>>();
+        solids.sort_unstable_by_key(|(id, _)| **id);
+        let solids = solids
+            .into_iter()
+            .map(|(_, solid)| solid.save())
+            .collect::<Vec<_>>();
+        let mut props = self
+            .props
+            .iter()
+            .filter(|(_, prop)| prop.selected())
+            .collect::<Vec<_>>();
+        props.sort_unstable_by_key(|(id, _)| **id);
+        let props = props
+            .into_iter()
+            .map(|(_, prop)| prop.save())
+            .collect::<Vec<_>>();
+
+        let mut positions = solids
+            .iter()
+            .flat_map(|solid| solid.points.iter().map(|point| point.position))
+            .chain(props.iter().map(|prop| prop.position));
+        let first = positions.next()?;
+        let (min, max) = positions.fold((first, first), |(min, max), p| {
+            (
+                Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        });
+        let center = (min + max).map(|e| e as f32 / 256.0);
+        let origin = Vector3::new(center.x, min.y as f32 / 128.0, center.z).round(grid);
+
+        Some(scene::Clipboard {
+            origin,
+            solids,
+            props,
+        })
+    }
+
+    /// Adds the clipboard's contents with its origin moved to `target`.
+    /// They replace the selection if `mode` edits them.
+    pub fn paste(&mut self, clipboard: &scene::Clipboard, target: Vector3<i32>, mode: ElementKind) {
+        let delta = target - clipboard.origin;
+        let texture_lock = self.texture_lock;
+
+        let solids = clipboard
+            .solids
+            .iter()
+            .map(|solid| {
+                let mut solid = Solid::load(solid);
+                solid.set_selected(true);
+                solid.displace(delta, ElementKind::Solid, texture_lock);
+                solid.set_selected(mode == ElementKind::Solid);
+                solid
+            })
+            .collect::<Vec<_>>();
+        let props = clipboard
+            .props
+            .iter()
+            .map(|prop| {
+                let mut prop = Prop::load(prop);
+                prop.set_selected(true);
+                prop.displace(delta, ElementKind::Prop, false);
+                prop.set_selected(mode == ElementKind::Prop);
+                prop
+            })
+            .collect::<Vec<_>>();
+
+        self.begin("Paste");
+        if (mode == ElementKind::Solid && !solids.is_empty())
+            || (mode == ElementKind::Prop && !props.is_empty())
+        {
+            self.act(Action::DeselectAll(mode));
+        }
+        self.act(Action::NewSolids(solids));
+        self.act(Action::NewProps(props));
+        self.commit();
+    }
+
+    /// Moves the selection and turns the selected props around their origins as one step.
+    /// Solids only turn in right angles, so the rotation leaves them alone.
+    pub fn transform(&mut self, mode: ElementKind, delta: Vector3<i32>, rotation: Quaternion<f32>) {
+        self.begin("Transform");
+        if delta != Vector3::zero() {
+            self.act(Action::Move {
+                kind: mode,
+                delta,
+                texture_lock: self.texture_lock,
+            });
+        }
+        if mode == ElementKind::Prop && rotation != Quaternion::one() {
+            let rotations = self
+                .props
+                .iter()
+                .filter(|(_, prop)| prop.selected())
+                .map(|(id, prop)| (*id, (rotation * prop.rotation()).normalize()))
+                .collect();
+            self.act(Action::SetPropRotations(rotations));
+        }
+        self.commit();
+    }
+
+    /// Creates and updates the GPU resources of everything that changed, once per frame before rendering
+    pub fn sync(&mut self, graphics: &Graphics) {
+        for solid in self.solids.values_mut() {
+            solid.sync(graphics);
+        }
+
+        for prop in self.props.values_mut() {
+            prop.sync(graphics);
+        }
     }
 
     pub fn render(&self, canvas: &mut Canvas, mask: ElementKind) {
-        for solid in self.solids.values() {
+        for (_, solid) in self.solids.iter().filter(|(id, _)| !self.hidden(**id)) {
             solid.render(canvas, mask);
         }
 
-        for prop in self.props.values() {
+        for (_, prop) in self.props.iter().filter(|(id, _)| !self.hidden(**id)) {
             prop.render(canvas, ElementKind::Prop);
         }
     }
 
+    pub fn invalidate_solids(&mut self) {
+        for solid in self.solids.values_mut() {
+            solid.invalidate();
+        }
+    }
+
     pub fn save(&self) -> scene::World {
-        let solids = self.solids.values().map(|solid| solid.save()).collect();
-        let props = self.props.values().map(|prop| prop.save()).collect();
-        scene::World { solids, props }
+        let mut solids = self.solids.iter().collect::<Vec<_>>();
+        solids.sort_unstable_by_key(|(id, _)| **id);
+        let mut props = self.props.iter().collect::<Vec<_>>();
+        props.sort_unstable_by_key(|(id, _)| **id);
+
+        let group_indices = self
+            .groups
+            .keys()
+            .enumerate()
+            .map(|(index, id)| (*id, index as u32))
+            .collect::<HashMap<_, _>>();
+        let group_index = |group: Option<u32>| group.and_then(|id| group_indices.get(&id).copied());
+        let object = |id: usize| {
+            let object = self.object(id);
+            scene::Object {
+                group: group_index(object.group),
+                ..object
+            }
+        };
+
+        let outline = scene::Outline {
+            layers: self.layers.clone(),
+            groups: self
+                .groups
+                .values()
+                .map(|group| scene::Group {
+                    name: group.name.clone(),
+                    parent: group_index(group.parent),
+                })
+                .collect(),
+            solids: solids.iter().map(|(id, _)| object(**id)).collect(),
+            props: props.iter().map(|(id, _)| object(**id)).collect(),
+        };
+
+        scene::World {
+            solids: solids.iter().map(|(_, solid)| solid.save()).collect(),
+            props: props.iter().map(|(_, prop)| prop.save()).collect(),
+            outline,
+        }
     }
 
-    pub fn load(&mut self, ctx: Context, world: &scene::World) {
+    pub fn load(&mut self, world: &scene::World) {
+        let outline = &world.outline;
+        self.layers = outline.layers.clone();
+        self.groups = (0..).zip(outline.groups.iter().cloned()).collect();
+        self.objects = outline
+            .solids
+            .iter()
+            .cloned()
+            .enumerate()
+            .chain(
+                outline
+                    .props
+                    .iter()
+                    .cloned()
+                    .enumerate()
+                    .map(|(index, object)| (world.solids.len() + index, object)),
+            )
+            .collect();
+        self.outline_changed = true;
+        // Steps of the previous scene can't be undone in this one
+        self.undo_stack.clear();
+
         self.next_elem_id = 0;
         self.solids = world
             .solids
@@ -282,7 +1011,7 @@ impl Scene {
             .map(|solid| {
                 let index = self.next_elem_id;
                 self.next_elem_id += 1;
-                let solid = Solid::load(ctx.graphics, solid);
+                let solid = Solid::load(solid);
                 (index, solid)
             })
             .collect();
@@ -293,13 +1022,29 @@ impl Scene {
             .map(|prop| {
                 let index = self.next_elem_id;
                 self.next_elem_id += 1;
-                let prop = Prop::load(ctx.graphics, prop);
+                let prop = Prop::load(prop);
                 (index, prop)
             })
             .collect()
     }
 
-    fn execute(&mut self, ctx: Context, action: Action) -> Option<Action> {
+    fn execute(&mut self, action: Action) -> Option<Action> {
+        if matches!(
+            action,
+            Action::NewSolids(_)
+                | Action::NewProps(_)
+                | Action::AddSolids(_)
+                | Action::AddProps(_)
+                | Action::RemoveSolids(_)
+                | Action::RemoveProps(_)
+                | Action::DeleteSolids
+                | Action::DeleteProps
+                | Action::ReplaceSolids { .. }
+                | Action::ReplaceSolidsExact { .. }
+        ) {
+            self.outline_changed = true;
+        }
+
         match action {
             Action::NewSolids(solids) => {
                 let ids = solids
@@ -373,7 +1118,6 @@ impl Scene {
                 for id in &ids {
                     let solid = self.solids.get_mut(id).unwrap();
                     solid.set_selected(!solid.selected());
-                    solid.recalc(ctx.graphics);
                 }
                 (!ids.is_empty()).then(|| Action::SelectSolids(ids))
             }
@@ -382,7 +1126,6 @@ impl Scene {
                 for locator in &locators {
                     let solid = self.solids.get_mut(&locator.solid).unwrap();
                     solid.set_face_selected(locator.face, !solid.face_selected(locator.face));
-                    solid.recalc(ctx.graphics);
                 }
                 (!locators.is_empty()).then(|| Action::SelectFaces(locators))
             }
@@ -391,7 +1134,6 @@ impl Scene {
                 for locator in &locators {
                     let solid = self.solids.get_mut(&locator.solid).unwrap();
                     solid.set_point_selected(locator.point, !solid.point_selected(locator.point));
-                    solid.recalc(ctx.graphics);
                 }
                 (!locators.is_empty()).then(|| Action::SelectPoints(locators))
             }
@@ -400,31 +1142,39 @@ impl Scene {
                 for id in &ids {
                     let prop = self.props.get_mut(id).unwrap();
                     prop.set_selected(!prop.selected());
-                    prop.recalc(ctx.graphics);
                 }
                 (!ids.is_empty()).then(|| Action::SelectProps(ids))
             }
 
             Action::SelectAll(kind) => match kind {
                 ElementKind::Solid => {
+                    let pickable = self
+                        .solids
+                        .keys()
+                        .copied()
+                        .filter(|id| self.pickable(*id))
+                        .collect::<HashSet<_>>();
                     let selected = self
                         .solids
                         .iter()
+                        .filter(|(id, _)| pickable.contains(*id))
                         .filter_map(|(id, solid)| solid.selected().then(|| *id))
                         .collect::<Vec<_>>();
 
-                    let len_max = self.solids.len();
+                    let len_max = pickable.len();
                     let mut ids = Vec::new();
 
                     for (id, solid) in &mut self.solids {
+                        if !pickable.contains(id) {
+                            continue;
+                        }
+
                         if selected.is_empty() || selected.len() == len_max {
                             solid.set_selected(!solid.selected());
-                            solid.recalc(ctx.graphics);
                             ids.push(*id);
                         } else {
                             solid.set_selected(true);
                             if !selected.contains(id) {
-                                solid.recalc(ctx.graphics);
                                 ids.push(*id);
                             }
                         }
@@ -433,24 +1183,33 @@ impl Scene {
                     (!ids.is_empty()).then(|| Action::SelectSolids(ids))
                 }
                 ElementKind::Prop => {
+                    let pickable = self
+                        .props
+                        .keys()
+                        .copied()
+                        .filter(|id| self.pickable(*id))
+                        .collect::<HashSet<_>>();
                     let selected = self
                         .props
                         .iter()
+                        .filter(|(id, _)| pickable.contains(*id))
                         .filter_map(|(id, prop)| prop.selected().then(|| *id))
                         .collect::<Vec<_>>();
 
-                    let len_max = self.props.len();
+                    let len_max = pickable.len();
                     let mut ids = Vec::new();
 
                     for (id, prop) in &mut self.props {
+                        if !pickable.contains(id) {
+                            continue;
+                        }
+
                         if selected.is_empty() || selected.len() == len_max {
                             prop.set_selected(!prop.selected());
-                            prop.recalc(ctx.graphics);
                             ids.push(*id);
                         } else {
                             prop.set_selected(true);
                             if !selected.contains(id) {
-                                prop.recalc(ctx.graphics);
                                 ids.push(*id);
                             }
                         }
@@ -467,7 +1226,6 @@ impl Scene {
                     for (id, solid) in &mut self.solids {
                         if solid.selected() {
                             solid.set_selected(false);
-                            solid.recalc(ctx.graphics);
                             ids.push(*id);
                         }
                     }
@@ -477,22 +1235,15 @@ impl Scene {
                     let mut locators = Vec::new();
 
                     for (sid, solid) in &mut self.solids {
-                        let mut recalc = false;
-
-                        for fid in 0..6 {
+                        for fid in 0..solid.face_count() {
                             if solid.face_selected(fid) {
                                 solid.set_face_selected(fid, false);
                                 locators.push(FaceLocator {
                                     solid: *sid,
                                     face: fid,
                                 });
-                                recalc = true;
                             }
                         }
-
-                        if recalc {
-                            solid.recalc(ctx.graphics);
-                        }
                     }
 
                     (!locators.is_empty()).then(|| Action::SelectFaces(locators))
@@ -501,22 +1252,15 @@ impl Scene {
                     let mut locators = Vec::new();
 
                     for (sid, solid) in &mut self.solids {
-                        let mut recalc = false;
-
-                        for pid in 0..8 {
+                        for pid in 0..solid.point_count() {
                             if solid.point_selected(pid) {
                                 solid.set_point_selected(pid, false);
                                 locators.push(PointLocator {
                                     solid: *sid,
                                     point: pid,
                                 });
-                                recalc = true;
                             }
                         }
-
-                        if recalc {
-                            solid.recalc(ctx.graphics);
-                        }
                     }
 
                     (!locators.is_empty()).then(|| Action::SelectPoints(locators))
@@ -526,7 +1270,6 @@ impl Scene {
                     for (pid, prop) in &mut self.props {
                         if prop.selected() {
                             prop.set_selected(false);
-                            prop.recalc(ctx.graphics);
                             ids.push(*pid);
                         }
                     }
@@ -534,31 +1277,35 @@ impl Scene {
                 }
             },
 
-            Action::Move { kind, delta } => match kind {
+            Action::Move {
+                kind,
+                delta,
+                texture_lock,
+            } => match kind {
                 ElementKind::Solid | ElementKind::Face | ElementKind::Point => {
                     let mut changed = false;
                     for solid in self.solids.values_mut() {
-                        if solid.displace(delta, kind) {
-                            solid.recalc(ctx.graphics);
+                        if solid.displace(delta, kind, texture_lock) {
                             changed = true;
                         }
                     }
                     changed.then(|| Action::Move {
                         kind,
                         delta: -delta,
+                        texture_lock,
                     })
                 }
                 ElementKind::Prop => {
                     let mut changed = false;
                     for prop in self.props.values_mut().filter(|prop| prop.selected()) {
-                        if prop.displace(delta, ElementKind::Prop) {
-                            prop.recalc(ctx.graphics);
+                        if prop.displace(delta, ElementKind::Prop, false) {
                             changed = true;
                         }
                     }
                     changed.then(|| Action::Move {
                         kind: ElementKind::Prop,
                         delta: -delta,
+                        texture_lock,
                     })
                 }
             },
@@ -567,7 +1314,6 @@ impl Scene {
                 let mut changed = false;
                 for prop in self.props.values_mut().filter(|prop| prop.selected()) {
                     prop.set_rotation(quat * prop.rotation());
-                    prop.recalc(ctx.graphics);
                     changed = true;
                 }
 
@@ -581,7 +1327,6 @@ impl Scene {
                         let prop = self.props.get_mut(&index).unwrap();
                         let old = prop.rotation();
                         prop.set_rotation(quat);
-                        prop.recalc(ctx.graphics);
                         (index, old)
                     })
                     .collect::<Vec<_>>();
@@ -592,11 +1337,10 @@ impl Scene {
             Action::AssignTexture(texture) => {
                 let mut changes = Vec::new();
                 for (sid, solid) in &mut self.solids {
-                    for fid in 0..6 {
+                    for fid in 0..solid.face_count() {
                         if solid.face_selected(fid) {
                             let old = solid.retexture(fid, texture);
                             if old != texture {
-                                solid.recalc(ctx.graphics);
                                 changes.push((
                                     FaceLocator {
                                         solid: *sid,
@@ -617,13 +1361,42 @@ impl Scene {
                     let solid = self.solids.get_mut(&locator.solid).unwrap();
                     let old = solid.retexture(locator.face, texture);
                     if old != texture {
-                        solid.recalc(ctx.graphics);
                         changes.push((locator, old));
                     }
                 }
                 (!changes.is_empty()).then(|| Action::AssignTextures(changes))
             }
 
+            Action::AdjustTextures(adjustment) => {
+                let mut alignments = Vec::new();
+                for (sid, solid) in &self.solids {
+                    for fid in 0..solid.face_count() {
+                        if solid.face_selected(fid) {
+                            alignments.push((
+                                FaceLocator {
+                                    solid: *sid,
+                                    face: fid,
+                                },
+                                solid.adjusted_alignment(fid, adjustment),
+                            ));
+                        }
+                    }
+                }
+                self.execute(Action::AlignTextures(alignments))
+            }
+
+            Action::AlignTextures(alignments) => {
+                let mut changes = Vec::new();
+                for (locator, alignment) in alignments {
+                    let solid = self.solids.get_mut(&locator.solid).unwrap();
+                    let old = solid.realign(locator.face, alignment);
+                    if old != alignment {
+                        changes.push((locator, old));
+                    }
+                }
+                (!changes.is_empty()).then(|| Action::AlignTextures(changes))
+            }
+
             Action::DeleteSolids => {
                 let ids = self
                     .solids
@@ -668,10 +1441,10 @@ impl Scene {
             } => {
                 if let Some(center) = self.calc_center(ElementKind::Solid) {
                     let center = center.round(snap);
+                    let texture_lock = self.texture_lock;
                     let mut changed = false;
                     for solid in self.solids.values_mut().filter(|solid| solid.selected()) {
-                        solid.rotate(center, axis, iters, reverse);
-                        solid.recalc(ctx.graphics);
+                        solid.rotate(center, axis, iters, reverse, texture_lock);
                         changed = true;
                     }
 
@@ -680,6 +1453,7 @@ impl Scene {
                         iters,
                         reverse: !reverse,
                         center,
+                        texture_lock,
                     })
                 } else {
                     None
@@ -691,11 +1465,11 @@ impl Scene {
                 iters,
                 reverse,
                 center,
+                texture_lock,
             } => {
                 let mut changed = false;
                 for solid in self.solids.values_mut().filter(|solid| solid.selected()) {
-                    solid.rotate(center, axis, iters, reverse);
-                    solid.recalc(ctx.graphics);
+                    solid.rotate(center, axis, iters, reverse, texture_lock);
                     changed = true;
                 }
 
@@ -704,6 +1478,7 @@ impl Scene {
                     iters,
                     reverse: !reverse,
                     center,
+                    texture_lock,
                 })
             }
 
@@ -719,6 +1494,17 @@ impl Scene {
                     self.next_elem_id += 1;
                     self.solids.insert(id, solid);
                     new_ids.push(id);
+                    // Pieces stay where the first solid they replace was, without its name
+                    if let Some((old, _)) = old_solids.first() {
+                        let object = self.object(*old);
+                        self.objects.insert(
+                            id,
+                            scene::Object {
+                                name: String::new(),
+                                ..object
+                            },
+                        );
+                    }
                 }
 
                 (!old_solids.is_empty()).then(|| Action::ReplaceSolidsExact {
@@ -744,12 +1530,66 @@ impl Scene {
                     solids: old_solids,
                 })
             }
+
+            Action::EditOutline(edit) => {
+                if edit.layers.is_none()
+                    && edit.groups.is_empty()
+                    && edit.objects.is_empty()
+                    && edit.selections.is_empty()
+                {
+                    return None;
+                }
+                self.outline_changed = true;
+
+                let layers = edit
+                    .layers
+                    .map(|layers| std::mem::replace(&mut self.layers, layers));
+                let mut groups = edit
+                    .groups
+                    .into_iter()
+                    .map(|(id, group)| {
+                        let old = match group {
+                            Some(group) => self.groups.insert(id, group),
+                            None => self.groups.remove(&id),
+                        };
+                        (id, old)
+                    })
+                    .collect::<Vec<_>>();
+                groups.reverse();
+                let mut objects = edit
+                    .objects
+                    .into_iter()
+                    .map(|(id, object)| (id, self.objects.insert(id, object).unwrap_or_default()))
+                    .collect::<Vec<_>>();
+                objects.reverse();
+
+                // Hidden and locked elements don't stay selected, the same toggles undo it
+                let selections = edit
+                    .selections
+                    .into_iter()
+                    .chain(self.unpickable_selections())
+                    .filter_map(|selection| self.execute(selection))
+                    .collect();
+
+                Some(Action::EditOutline(OutlineEdit {
+                    layers,
+                    groups,
+                    objects,
+                    selections,
+                }))
+            }
         }
     }
 }
 
-pub struct Context<'a> {
-    pub graphics: &'a Graphics,
+/// Changes to layers, groups and objects, anything not given stays the same
+#[derive(Default)]
+pub struct OutlineEdit {
+    layers: Option<Vec<scene::Layer>>,
+    /// Groups are removed when [None]
+    groups: Vec<(u32, Option<scene::Group>)>,
+    objects: Vec<(usize, scene::Object)>,
+    selections: Vec<Action>,
 }
 
 pub enum Action {
@@ -769,15 +1609,19 @@ pub enum Action {
     SelectAll(ElementKind),
     DeselectAll(ElementKind),
 
+    /// Texture lock is remembered, so undoing a move restores the textures it kept in place
     Move {
         kind: ElementKind,
         delta: Vector3<i32>,
+        texture_lock: bool,
     },
 
     RotateProps(Quaternion<f32>),
     SetPropRotations(Vec<(usize, Quaternion<f32>)>),
     AssignTexture(TextureID),
     AssignTextures(Vec<(FaceLocator, TextureID)>),
+    AdjustTextures(TextureAdjustment),
+    AlignTextures(Vec<(FaceLocator, TextureAlignment)>),
 
     DeleteSolids,
     DeleteProps,
@@ -792,6 +1636,7 @@ pub enum Action {
         iters: u32,
         reverse: bool,
         center: Vector3<i32>,
+        texture_lock: bool,
     },
     ReplaceSolids {
         ids: Vec<usize>,
@@ -801,29 +1646,1254 @@ pub enum Action {
         ids: Vec<usize>,
         solids: Vec<(usize, Solid)>,
     },
+    EditOutline(OutlineEdit),
+}
+
+impl Action {
+    // What the history calls a step made of this action alone
+    fn name(&self) -> &'static str {
+        match self {
+            Action::NewSolids(_) | Action::AddSolids(_) => "Add solids",
+            Action::NewProps(_) | Action::AddProps(_) => "Add props",
+            Action::RemoveSolids(_) | Action::DeleteSolids => "Delete solids",
+            Action::RemoveProps(_) | Action::DeleteProps => "Delete props",
+            Action::SelectSolids(_)
+            | Action::SelectFaces(_)
+            | Action::SelectPoints(_)
+            | Action::SelectProps(_)
+            | Action::SelectAll(_)
+            | Action::DeselectAll(_) => "Select",
+            Action::Move { .. } => "Move",
+            Action::RotateProps(_) | Action::SetPropRotations(_) => "Rotate props",
+            Action::AssignTexture(_) | Action::AssignTextures(_) => "Assign texture",
+            Action::AdjustTextures(_) | Action::AlignTextures(_) => "Align textures",
+            Action::RotateSolids { .. } | Action::UnrotateSolids { .. } => "Rotate solids",
+            Action::ReplaceSolids { .. } | Action::ReplaceSolidsExact { .. } => "Replace solids",
+            Action::EditOutline(_) => "Edit outline",
+        }
+    }
+
+    // Rough number of bytes the action holds on to, for the history's budget
+    fn size(&self) -> usize {
+        fn solid(solid: &Solid) -> usize {
+            size_of::<Solid>() + solid.point_count() * 16 + solid.face_count() * 64
+        }
+
+        size_of::<Self>()
+            + match self {
+                Action::NewSolids(solids) | Action::ReplaceSolids { solids, .. } => {
+                    solids.iter().map(solid).sum()
+                }
+                Action::AddSolids(solids) | Action::ReplaceSolidsExact { solids, .. } => solids
+                    .iter()
+                    .map(|(_, s)| size_of::<usize>() + solid(s))
+                    .sum(),
+                Action::NewProps(props) => props.len() * size_of::<Prop>(),
+                Action::AddProps(props) => props.len() * size_of::<(usize, Prop)>(),
+                Action::RemoveSolids(ids)
+                | Action::RemoveProps(ids)
+                | Action::SelectSolids(ids)
+                | Action::SelectProps(ids) => ids.len() * size_of::<usize>(),
+                Action::SelectFaces(locators) => locators.len() * size_of::<FaceLocator>(),
+                Action::SelectPoints(locators) => locators.len() * size_of::<PointLocator>(),
+                Action::SetPropRotations(rotations) => {
+                    rotations.len() * size_of::<(usize, Quaternion<f32>)>()
+                }
+                Action::AssignTextures(textures) => {
+                    textures.len() * size_of::<(FaceLocator, TextureID)>()
+                }
+                Action::AlignTextures(alignments) => {
+                    alignments.len() * size_of::<(FaceLocator, TextureAlignment)>()
+                }
+                Action::EditOutline(edit) => {
+                    edit.layers
+                        .as_ref()
+                        .map_or(0, |layers| layers.len() * size_of::<scene::Layer>())
+                        + edit.groups.len() * size_of::<(u32, Option<scene::Group>)>()
+                        + edit.objects.len() * size_of::<(usize, scene::Object)>()
+                        + edit.selections.iter().map(Action::size).sum::<usize>()
+                }
+                _ => 0,
+            }
+    }
+}
+
+/// One step of the history, the reactions of every action taken in it in order
+struct Entry {
+    name: String,
+    actions: Vec<Action>,
+    size: usize,
+}
+
+impl Entry {
+    fn new(name: String, actions: Vec<Action>) -> Self {
+        let size = actions.iter().map(Action::size).sum();
+        Self {
+            name,
+            actions,
+            size,
+        }
+    }
+
+    // Reaction of a lone move, which following moves of the same kind are merged into
+    fn lone_move(&self) -> Option<(ElementKind, Vector3<i32>, bool)> {
+        match self.actions.as_slice() {
+            [Action::Move {
+                kind,
+                delta,
+                texture_lock,
+            }] => Some((*kind, *delta, *texture_lock)),
+            _ => None,
+        }
+    }
 }
 
 struct UndoStack {
-    vec: Vec<Action>,
+    undo: VecDeque<Entry>,
+    redo: Vec<Entry>,
+    /// Most steps kept for undoing
+    limit: usize,
+    /// Bytes the steps may take before the oldest are dropped, the last one is always kept
+    budget: usize,
+    /// Bytes of the steps for undoing. Redo steps were undone within the budget, so they aren't counted.
+    size: usize,
+    /// Open transactions and the step they're collecting
+    transaction: Option<(usize, Entry)>,
+    changed: bool,
 }
 
 impl Default for UndoStack {
     fn default() -> Self {
         Self {
-            vec: Vec::with_capacity(64),
+            undo: VecDeque::with_capacity(64),
+            redo: Vec::new(),
+            limit: 64,
+            budget: 64 << 20,
+            size: 0,
+            transaction: None,
+            changed: false,
         }
     }
 }
 
 impl UndoStack {
-    fn push(&mut self, value: Action) {
-        if self.vec.len() == 64 {
-            self.vec.remove(0);
+    fn record(&mut self, name: &str, reaction: Action) {
+        match &mut self.transaction {
+            Some((_, entry)) => {
+                entry.size += reaction.size();
+                entry.actions.push(reaction);
+            }
+            None => {
+                self.clear_redo();
+                self.push_undo(Entry::new(name.to_owned(), vec![reaction]));
+            }
+        }
+    }
+
+    fn begin(&mut self, name: &str) {
+        match &mut self.transaction {
+            Some((depth, _)) => *depth += 1,
+            None => self.transaction = Some((1, Entry::new(name.to_owned(), Vec::new()))),
+        }
+    }
+
+    fn commit(&mut self) {
+        match self.transaction.take() {
+            Some((depth, entry)) if depth > 1 => self.transaction = Some((depth - 1, entry)),
+            Some((_, entry)) if !entry.actions.is_empty() => {
+                self.clear_redo();
+                self.push_undo(entry);
+            }
+            _ => (),
+        }
+    }
+
+    fn push_undo(&mut self, entry: Entry) {
+        self.changed = true;
+
+        // Consecutive moves are one step, and none at all once they cancel out
+        if let (Some((kind, delta, texture_lock)), Some(last)) =
+            (entry.lone_move(), self.undo.back_mut())
+        {
+            if let Some((last_kind, last_delta, last_lock)) = last.lone_move() {
+                if last.name == entry.name && last_kind == kind && last_lock == texture_lock {
+                    let delta = last_delta + delta;
+                    if delta == Vector3::zero() {
+                        let last = self.undo.pop_back().unwrap();
+                        self.size -= last.size;
+                    } else {
+                        last.actions[0] = Action::Move {
+                            kind,
+                            delta,
+                            texture_lock,
+                        };
+                    }
+                    return;
+                }
+            }
+        }
+
+        self.size += entry.size;
+        self.undo.push_back(entry);
+        self.evict();
+    }
+
+    fn push_redo(&mut self, entry: Entry) {
+        self.changed = true;
+        self.redo.push(entry);
+    }
+
+    fn pop_undo(&mut self) -> Option<Entry> {
+        if self.transaction.is_some() {
+            return None;
+        }
+
+        let entry = self.undo.pop_back()?;
+        self.changed = true;
+        self.size -= entry.size;
+        Some(entry)
+    }
+
+    fn pop_redo(&mut self) -> Option<Entry> {
+        if self.transaction.is_some() {
+            return None;
+        }
+
+        let entry = self.redo.pop()?;
+        self.changed = true;
+        Some(entry)
+    }
+
+    fn clear_redo(&mut self) {
+        if !self.redo.is_empty() {
+            self.changed = true;
+        }
+        self.redo.clear();
+    }
+
+    /// Forgets every step, an open transaction included
+    fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.size = 0;
+        self.transaction = None;
+        self.changed = true;
+    }
+
+    fn evict(&mut self) {
+        while self.undo.len() > self.limit || (self.size > self.budget && self.undo.len() > 1) {
+            let entry = self.undo.pop_front().unwrap();
+            self.size -= entry.size;
+            self.changed = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use asset::{scene::TextureAlignment, PropID, TextureID};
+    use cgmath::{vec2, vec3, Deg, InnerSpace, Quaternion, Rotation3, Vector3};
+
+    use super::{scene, Action, Scene};
+    use crate::{
+        logic::{
+            common::Axis,
+            elements::{
+                ClipKeep, ClipPlane, ElementKind, FaceLocator, Movable, Prop, Solid,
+                TextureAdjustment,
+            },
+        },
+        OutlineCommand,
+    };
+
+    fn cube(origin: Vector3<i32>, size: i32) -> Solid {
+        Solid::new(origin, vec3(size, size, size))
+    }
+
+    // Corners of every solid, sorted so scenes can be compared regardless of ids
+    fn corners(scene: &Scene) -> Vec<Vec<[i32; 3]>> {
+        let mut solids = scene
+            .save()
+            .solids
+            .iter()
+            .map(|solid| {
+                let mut points = solid
+                    .points
+                    .iter()
+                    .map(|point| point.position.into())
+                    .collect::<Vec<[i32; 3]>>();
+                points.sort_unstable();
+                points
+            })
+            .collect::<Vec<_>>();
+        solids.sort();
+        solids
+    }
+
+    fn selected_solids(scene: &Scene) -> usize {
+        scene
+            .solids
+            .values()
+            .filter(|solid| solid.selected())
+            .count()
+    }
+
+    #[test]
+    fn new_solids() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![
+            cube(vec3(0, 0, 0), 128),
+            cube(vec3(256, 0, 0), 128),
+        ]));
+        assert_eq!(scene.solids.len(), 2);
+
+        scene.undo();
+        assert!(scene.solids.is_empty());
+        scene.redo();
+        assert_eq!(scene.solids.len(), 2);
+
+        // Nothing is recorded when nothing changes
+        scene.act(Action::NewSolids(Vec::new()));
+        scene.undo();
+        assert!(scene.solids.is_empty());
+    }
+
+    #[test]
+    fn selection() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![
+            cube(vec3(0, 0, 0), 128),
+            cube(vec3(256, 0, 0), 128),
+        ]));
+
+        scene.act(Action::SelectSolids(vec![0]));
+        assert_eq!(selected_solids(&scene), 1);
+        assert!(scene.any_solids_selected());
+        assert_eq!(scene.selected_solid_ids(), vec![0]);
+
+        scene.act(Action::SelectAll(ElementKind::Solid));
+        assert_eq!(selected_solids(&scene), 2);
+        scene.act(Action::DeselectAll(ElementKind::Solid));
+        assert_eq!(selected_solids(&scene), 0);
+
+        scene.undo();
+        assert_eq!(selected_solids(&scene), 2);
+        scene.undo();
+        assert_eq!(selected_solids(&scene), 1);
+    }
+
+    #[test]
+    fn move_solids() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![
+            cube(vec3(0, 0, 0), 128),
+            cube(vec3(256, 0, 0), 128),
+        ]));
+        let before = corners(&scene);
+
+        scene.act(Action::SelectSolids(vec![0]));
+        scene.act(Action::Move {
+            kind: ElementKind::Solid,
+            delta: vec3(0, 128, 0),
+            texture_lock: false,
+        });
+        let moved = corners(&scene);
+        assert_ne!(moved, before);
+        assert_eq!(
+            scene.calc_center(ElementKind::Solid),
+            Some(vec3(0.5, 1.5, 0.5))
+        );
+
+        scene.undo();
+        assert_eq!(corners(&scene), before);
+        scene.redo();
+        assert_eq!(corners(&scene), moved);
+    }
+
+    #[test]
+    fn move_faces() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![cube(vec3(0, 0, 0), 128)]));
+        // The top face
+        scene.act(Action::SelectFaces(vec![FaceLocator { solid: 0, face: 2 }]));
+        scene.act(Action::Move {
+            kind: ElementKind::Face,
+            delta: vec3(0, 128, 0),
+            texture_lock: false,
+        });
+
+        let solid = &scene.save().solids[0];
+        let top = solid.points.iter().map(|point| point.position.y).max();
+        let bottom = solid.points.iter().map(|point| point.position.y).min();
+        assert_eq!((bottom, top), (Some(0), Some(256)));
+    }
+
+    #[test]
+    fn move_tool_commit() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![cube(vec3(0, 0, 0), 128)]));
+        scene.act(Action::SelectSolids(vec![0]));
+        let before = corners(&scene);
+
+        // What the move tool does: take the selection out, displace it and put it back
+        let mut solids = scene.take_solids(ElementKind::Solid);
+        assert!(scene.solids.is_empty());
+        for (_, solid) in &mut solids {
+            solid.displace(vec3(128, 0, 0), ElementKind::Solid, false);
         }
-        self.vec.push(value);
+        scene.insert_solids_with_move(solids, vec3(128, 0, 0), ElementKind::Solid);
+        assert_ne!(corners(&scene), before);
+
+        scene.undo();
+        assert_eq!(corners(&scene), before);
+    }
+
+    #[test]
+    fn clone_solids() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![cube(vec3(0, 0, 0), 128)]));
+        scene.act(Action::SelectSolids(vec![0]));
+
+        let mut clones = scene.clone_solids();
+        assert_eq!(clones.len(), 1);
+        for (_, solid) in &mut clones {
+            solid.displace(vec3(0, 0, 256), ElementKind::Solid, false);
+        }
+        scene.insert_solids_with_remove(clones);
+        assert_eq!(scene.solids.len(), 2);
+
+        scene.undo();
+        assert_eq!(scene.solids.len(), 1);
+        assert_eq!(corners(&scene)[0][0], [0, 0, 0]);
+    }
+
+    #[test]
+    fn delete_solids() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![
+            cube(vec3(0, 0, 0), 128),
+            cube(vec3(256, 0, 0), 128),
+        ]));
+        let before = corners(&scene);
+
+        scene.act(Action::SelectSolids(vec![1]));
+        scene.act(Action::DeleteSolids);
+        assert_eq!(scene.solids.len(), 1);
+
+        scene.undo();
+        assert_eq!(corners(&scene), before);
+        assert!(scene.solids[&1].selected());
+    }
+
+    #[test]
+    fn assign_texture() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![cube(vec3(0, 0, 0), 128)]));
+        scene.act(Action::SelectFaces(vec![FaceLocator { solid: 0, face: 3 }]));
+        scene.act(Action::AssignTexture(TextureID(5)));
+
+        let textures = |scene: &Scene| {
+            let solid = &scene.save().solids[0];
+            [0, 1, 2, 3, 4, 5].map(|i| solid.faces[i].texture.0)
+        };
+        assert_eq!(textures(&scene), [0, 0, 0, 5, 0, 0]);
+
+        // Assigning the same texture again isn't an undo step
+        scene.act(Action::AssignTexture(TextureID(5)));
+        scene.undo();
+        assert_eq!(textures(&scene), [0; 6]);
+        scene.redo();
+        assert_eq!(textures(&scene), [0, 0, 0, 5, 0, 0]);
+    }
+
+    fn alignment(scene: &Scene, face: usize) -> TextureAlignment {
+        scene.save().solids[0].faces[face].alignment
+    }
+
+    #[test]
+    fn adjust_textures() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![Solid::new(
+            vec3(0, 0, 0),
+            vec3(256, 128, 512),
+        )]));
+        scene.act(Action::SelectFaces(vec![FaceLocator { solid: 0, face: 2 }]));
+
+        scene.act(Action::AdjustTextures(TextureAdjustment::Shift(vec2(
+            0.5, 0.0,
+        ))));
+        scene.act(Action::AdjustTextures(TextureAdjustment::Rotate(-90.0)));
+        assert_eq!(alignment(&scene, 2).offset, vec2(0.5, 0.0));
+        assert_eq!(alignment(&scene, 2).rotation, 270.0);
+        assert_eq!(alignment(&scene, 0), TextureAlignment::default());
+
+        scene.act(Action::AdjustTextures(TextureAdjustment::Fit));
+        let fitted = alignment(&scene, 2);
+        assert!((fitted.scale.x - 4.0).abs() < 1e-4 && (fitted.scale.y - 2.0).abs() < 1e-4);
+
+        scene.undo();
+        scene.undo();
+        assert_eq!(alignment(&scene, 2).rotation, 0.0);
+        scene.undo();
+        assert_eq!(alignment(&scene, 2), TextureAlignment::default());
+        scene.redo();
+        assert_eq!(alignment(&scene, 2).offset, vec2(0.5, 0.0));
+    }
+
+    #[test]
+    fn texture_lock() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![cube(vec3(0, 0, 0), 128)]));
+        scene.act(Action::SelectSolids(vec![0]));
+        scene.act(Action::Move {
+            kind: ElementKind::Solid,
+            delta: vec3(128, 0, 0),
+            texture_lock: true,
+        });
+
+        // The top is projected onto x and z, its texture follows the move along x
+        let offset = alignment(&scene, 2).offset;
+        assert!((offset.x + 1.0).abs() < 1e-5 && offset.y.abs() < 1e-5);
+
+        scene.undo();
+        let offset = alignment(&scene, 2).offset;
+        assert!(offset.x.abs() < 1e-5 && offset.y.abs() < 1e-5);
+
+        scene.set_texture_lock(true);
+        scene.act(Action::RotateSolids {
+            axis: Axis::Y,
+            iters: 1,
+            reverse: false,
+            snap: 64,
+        });
+        assert!((alignment(&scene, 2).rotation - 90.0).abs() < 1e-3);
+
+        // Undoing remembers the lock, even after it's turned off
+        scene.set_texture_lock(false);
+        scene.undo();
+        assert!(alignment(&scene, 2).rotation.abs() < 1e-3);
+    }
+
+    #[test]
+    fn rotate_solids() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![Solid::new(
+            vec3(0, 0, 0),
+            vec3(256, 128, 128),
+        )]));
+        let before = corners(&scene);
+
+        scene.act(Action::SelectSolids(vec![0]));
+        scene.act(Action::RotateSolids {
+            axis: Axis::Y,
+            iters: 1,
+            reverse: false,
+            snap: 64,
+        });
+
+        let solid = &scene.save().solids[0];
+        let extent = |axis: usize| {
+            let values = solid.points.iter().map(|point| point.position[axis]);
+            values.clone().max().unwrap() - values.min().unwrap()
+        };
+        assert_eq!([extent(0), extent(1), extent(2)], [128, 128, 256]);
+
+        scene.undo();
+        assert_eq!(corners(&scene), before);
+    }
+
+    #[test]
+    fn hollow() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![cube(vec3(0, 0, 0), 512)]));
+        let before = corners(&scene);
+
+        scene.act(Action::ReplaceSolids {
+            ids: vec![0],
+            solids: scene.hollow_of(0, 128),
+        });
+        assert_eq!(scene.solids.len(), 26);
+        assert!(!scene.solids.contains_key(&0));
+
+        scene.undo();
+        assert_eq!(corners(&scene), before);
+        scene.redo();
+        assert_eq!(scene.solids.len(), 26);
+    }
+
+    // Triangular prism along z, the shape of a ramp
+    fn wedge() -> scene::Solid {
+        let points = [
+            (0, 0, 0),
+            (1, 0, 0),
+            (0, 1, 0),
+            (0, 0, 1),
+            (1, 0, 1),
+            (0, 1, 1),
+        ];
+        let faces: [&[u32]; 5] = [
+            &[0, 2, 1],
+            &[3, 4, 5],
+            &[0, 1, 4, 3],
+            &[0, 3, 5, 2],
+            &[1, 2, 5, 4],
+        ];
+        scene::Solid {
+            points: points
+                .map(|(x, y, z)| scene::Point {
+                    position: vec3(x, y, z) * 128,
+                })
+                .into(),
+            faces: faces
+                .map(|indices| scene::Face {
+                    texture: TextureID(1),
+                    indices: indices.to_vec(),
+                    alignment: Default::default(),
+                })
+                .into(),
+        }
+    }
+
+    #[test]
+    fn convex_solids() {
+        let mut scene = Scene::default();
+        scene.load(&scene::World {
+            solids: vec![wedge()],
+            props: Vec::new(),
+            outline: Default::default(),
+        });
+        assert_eq!(scene.solids[&0].face_count(), 5);
+        assert_eq!(scene.solids[&0].point_count(), 6);
+
+        // Pulling the slope out moves the 4 points on it
+        scene.act(Action::SelectFaces(vec![FaceLocator { solid: 0, face: 4 }]));
+        scene.act(Action::Move {
+            kind: ElementKind::Face,
+            delta: vec3(128, 0, 0),
+            texture_lock: false,
+        });
+        let saved = scene.save();
+        let mut xs = saved.solids[0]
+            .points
+            .iter()
+            .map(|point| point.position.x)
+            .collect::<Vec<_>>();
+        xs.sort_unstable();
+        assert_eq!(xs, vec![0, 0, 128, 128, 256, 256]);
+        assert_eq!(saved.solids[0].faces[0].indices, vec![0, 2, 1]);
+
+        scene.act(Action::SelectAll(ElementKind::Solid));
+        let before = corners(&scene);
+        scene.set_texture_lock(true);
+        scene.act(Action::RotateSolids {
+            axis: Axis::Y,
+            iters: 1,
+            reverse: false,
+            snap: 64,
+        });
+        assert_ne!(corners(&scene), before);
+        scene.undo();
+        assert_eq!(corners(&scene), before);
+    }
+
+    fn clip(scene: &mut Scene, plane: ClipPlane, keep: ClipKeep) {
+        let (ids, solids) = scene.clip_selected(&plane, keep, TextureID(5));
+        scene.act(Action::ReplaceSolids { ids, solids });
+    }
+
+    #[test]
+    fn clip_solids() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![
+            cube(vec3(0, 0, 0), 256),
+            cube(vec3(512, 0, 0), 256),
+        ]));
+        scene.act(Action::SelectSolids(vec![0, 1]));
+        let before = corners(&scene);
+
+        // Diagonal through the first cube, the front is where x > z
+        let diagonal = ClipPlane::new(vec3(0, 0, 0), vec3(0, 256, 0), vec3(256, 0, 256)).unwrap();
+        clip(&mut scene, diagonal, ClipKeep::Both);
+        assert_eq!(scene.solids.len(), 3);
+        let wedges = scene
+            .solids
+            .values()
+            .filter(|solid| solid.face_count() == 5)
+            .collect::<Vec<_>>();
+        assert_eq!(wedges.len(), 2);
+        for wedge in wedges {
+            assert_eq!(wedge.point_count(), 6);
+            assert!(wedge.selected());
+            let saved = wedge.save();
+            let caps = saved
+                .faces
+                .iter()
+                .filter(|face| face.texture == TextureID(5))
+                .collect::<Vec<_>>();
+            assert_eq!(caps.len(), 1);
+            let corners = caps[0]
+                .indices
+                .iter()
+                .map(|i| saved.points[*i as usize].position.map(|e| e as f32))
+                .collect::<Vec<_>>();
+            let normal = scene::face_normal(&corners);
+            let center = wedge.center(ElementKind::Solid) * 128.0;
+            // Caps face away from the rest of their piece
+            assert!(normal.dot(corners[0] - center) > 0.0);
+        }
+
+        scene.undo();
+        assert_eq!(corners(&scene), before);
+
+        // Halfway up both cubes, keeping what is below
+        let half = ClipPlane::new(vec3(0, 128, 0), vec3(0, 128, 256), vec3(256, 128, 0)).unwrap();
+        clip(&mut scene, half, ClipKeep::Back);
+        assert_eq!(scene.solids.len(), 2);
+        assert_eq!(
+            corners(&scene)[0],
+            vec![
+                [0, 0, 0],
+                [0, 0, 256],
+                [0, 128, 0],
+                [0, 128, 256],
+                [256, 0, 0],
+                [256, 0, 256],
+                [256, 128, 0],
+                [256, 128, 256]
+            ]
+        );
+        scene.undo();
+
+        // Solids the plane misses are left alone, even when their side is dropped
+        let wall = ClipPlane::new(vec3(128, 0, 0), vec3(128, 256, 0), vec3(128, 0, 256)).unwrap();
+        clip(&mut scene, wall, ClipKeep::Front);
+        assert_eq!(scene.solids.len(), 2);
+        assert!(scene.solids.contains_key(&1));
+        scene.undo();
+        assert_eq!(corners(&scene), before);
+
+        assert!(ClipPlane::new(vec3(0, 0, 0), vec3(1, 1, 1), vec3(2, 2, 2)).is_none());
+    }
+
+    #[test]
+    fn clip_oblique() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![cube(vec3(0, 0, 0), 256)]));
+        scene.act(Action::SelectSolids(vec![0]));
+        let before = corners(&scene);
+
+        // Crosses the three axes at 384, cutting six edges halfway
+        let oblique =
+            ClipPlane::new(vec3(256, 128, 0), vec3(0, 256, 128), vec3(128, 0, 256)).unwrap();
+        clip(&mut scene, oblique, ClipKeep::Both);
+        assert_eq!(scene.solids.len(), 2);
+        for solid in scene.save().solids {
+            for face in &solid.faces {
+                let corner = |i: &u32| solid.points[*i as usize].position.map(|e| e as i64);
+                let (a, b, c) = (
+                    corner(&face.indices[0]),
+                    corner(&face.indices[1]),
+                    corner(&face.indices[2]),
+                );
+                let normal = (b - a).cross(c - a);
+                assert!(face
+                    .indices
+                    .iter()
+                    .all(|i| (corner(i) - a).dot(normal) == 0));
+            }
+        }
+        scene.undo();
+
+        // Would cross the bottom edges a third of the way along, between grid units
+        let between = ClipPlane::new(vec3(0, 256, 0), vec3(0, 256, 256), vec3(1, 253, 0)).unwrap();
+        let (ids, solids) = scene.clip_selected(&between, ClipKeep::Both, TextureID(5));
+        assert!(ids.is_empty() && solids.is_empty());
+        assert_eq!(corners(&scene), before);
+    }
+
+    // Volume of every solid, assuming they are all boxes
+    fn box_volumes(scene: &Scene) -> i64 {
+        corners(scene)
+            .iter()
+            .map(|points| {
+                let (min, max) = (points[0], points[points.len() - 1]);
+                (0..3).map(|i| (max[i] - min[i]) as i64).product::<i64>()
+            })
+            .sum()
+    }
+
+    fn textured_cube(origin: Vector3<i32>, size: i32, texture: TextureID) -> Solid {
+        let mut solid = cube(origin, size);
+        for face in 0..solid.face_count() {
+            solid.retexture(face, texture);
+        }
+        solid
+    }
+
+    #[test]
+    fn carve_solids() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![
+            cube(vec3(0, 0, 0), 384),
+            cube(vec3(1024, 0, 0), 128),
+            textured_cube(vec3(128, 128, 128), 128, TextureID(4)),
+        ]));
+        scene.act(Action::SelectSolids(vec![2]));
+        let before = corners(&scene);
+
+        let (ids, solids) = scene.carve_selected();
+        assert_eq!(ids, vec![0]);
+        scene.act(Action::ReplaceSolids { ids, solids });
+
+        // A piece on every side of the hole, the carver stays
+        assert_eq!(scene.solids.len(), 8);
+        assert!(corners(&scene).iter().all(|points| points.len() == 8));
+        assert_eq!(
+            box_volumes(&scene),
+            384 * 384 * 384 - 128 * 128 * 128 + 128 * 128 * 128 * 2
+        );
+        for solid in scene.solids.values().filter(|solid| !solid.selected()) {
+            let textures = solid
+                .save()
+                .faces
+                .iter()
+                .map(|face| face.texture)
+                .collect::<Vec<_>>();
+            assert!(textures.contains(&TextureID(0)));
+            if solid.center(ElementKind::Solid).x < 4.0 {
+                assert!(textures.contains(&TextureID(4)));
+            }
+        }
+
+        scene.undo();
+        assert_eq!(corners(&scene), before);
+
+        // Nothing overlaps a solid away from the rest
+        scene.act(Action::SelectSolids(vec![2, 1]));
+        let (ids, _) = scene.carve_selected();
+        assert!(ids.is_empty());
+    }
+
+    #[test]
+    fn merge_solids() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![
+            textured_cube(vec3(0, 0, 0), 128, TextureID(2)),
+            textured_cube(vec3(128, 0, 0), 128, TextureID(3)),
+            cube(vec3(0, 0, 128), 128),
+        ]));
+        scene.act(Action::SelectAll(ElementKind::Solid));
+        let before = corners(&scene);
+
+        // The third cube would make an L, which is not convex
+        let (ids, solids) = scene.merge_selected();
+        assert_eq!(ids, vec![0, 1]);
+        scene.act(Action::ReplaceSolids { ids, solids });
+        assert_eq!(scene.solids.len(), 2);
+        let merged = scene
+            .solids
+            .values()
+            .find(|solid| solid.center(ElementKind::Solid).x > 0.75)
+            .unwrap()
+            .save();
+        assert_eq!(merged.points.len(), 8);
+        assert_eq!(merged.faces.len(), 6);
+        let textures = merged
+            .faces
+            .iter()
+            .map(|face| face.texture)
+            .collect::<Vec<_>>();
+        assert!(textures.contains(&TextureID(2)));
+        assert!(textures.contains(&TextureID(3)));
+
+        scene.undo();
+        assert_eq!(corners(&scene), before);
+
+        // Overlapping boxes become the box around both
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![
+            cube(vec3(0, 0, 0), 256),
+            cube(vec3(128, 0, 0), 256),
+        ]));
+        scene.act(Action::SelectAll(ElementKind::Solid));
+        let (ids, solids) = scene.merge_selected();
+        scene.act(Action::ReplaceSolids { ids, solids });
+        assert_eq!(scene.solids.len(), 1);
+        assert_eq!(box_volumes(&scene), 384 * 256 * 256);
+    }
+
+    #[test]
+    fn props() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewProps(vec![Prop::new(PropID(3), vec3(0, 0, 0))]));
+        scene.act(Action::SelectProps(vec![0]));
+        scene.act(Action::Move {
+            kind: ElementKind::Prop,
+            delta: vec3(128, 0, 0),
+            texture_lock: false,
+        });
+        assert_eq!(
+            scene.calc_center(ElementKind::Prop),
+            Some(vec3(1.0, 0.0, 0.0))
+        );
+
+        let quarter = Quaternion::from_angle_y(Deg(90.0));
+        scene.act(Action::RotateProps(quarter));
+        assert_eq!(scene.save().props[0].rotation, quarter);
+
+        scene.undo();
+        let rotation = scene.save().props[0].rotation;
+        assert!((rotation.s - 1.0).abs() < 1e-5);
+        scene.undo();
+        assert_eq!(scene.save().props[0].position, vec3(0, 0, 0));
+
+        scene.act(Action::DeleteProps);
+        assert!(scene.props.is_empty());
+        scene.undo();
+        assert_eq!(scene.save().props[0].asset, PropID(3));
+    }
+
+    #[test]
+    fn save_load() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![
+            cube(vec3(0, 0, 0), 128),
+            cube(vec3(-256, 128, 512), 256),
+        ]));
+        scene.act(Action::NewProps(vec![Prop::new(
+            PropID(1),
+            vec3(64, 0, 64),
+        )]));
+
+        let mut loaded = Scene::default();
+        loaded.load(&scene.save());
+        assert_eq!(corners(&loaded), corners(&scene));
+        assert_eq!(loaded.save().props[0].position, vec3(64, 0, 64));
+
+        // Loading starts a fresh scene, new elements don't collide with loaded ones
+        loaded.act(Action::NewSolids(vec![cube(vec3(0, 512, 0), 128)]));
+        assert_eq!(loaded.solids.len(), 3);
+
+        // Nor does it keep the history of the previous one
+        loaded.take_history_changed();
+        loaded.load(&scene.save());
+        assert!(loaded.take_history_changed());
+        assert!(loaded.history().entries.is_empty());
+        loaded.undo();
+        loaded.redo();
+        assert_eq!(corners(&loaded), corners(&scene));
+    }
+
+    // Three cubes in a row with the first two grouped
+    fn grouped() -> Scene {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![
+            cube(vec3(0, 0, 0), 128),
+            cube(vec3(256, 0, 0), 128),
+            cube(vec3(512, 0, 0), 128),
+        ]));
+        scene.act(Action::SelectSolids(vec![0, 1]));
+        scene.edit_outline(OutlineCommand::Group("pair".into()), ElementKind::Solid);
+        scene.act(Action::DeselectAll(ElementKind::Solid));
+        scene
+    }
+
+    #[test]
+    fn groups() {
+        let mut scene = grouped();
+        assert_eq!(scene.selection_unit(1), vec![0, 1]);
+        assert_eq!(scene.selection_unit(2), vec![2]);
+
+        // Grouping members of a group nests the new group inside it
+        scene.act(Action::SelectSolids(vec![0, 1]));
+        scene.edit_outline(OutlineCommand::Group("inner".into()), ElementKind::Solid);
+        assert_eq!(scene.outline().groups[1].parent, Some(0));
+        assert_eq!(scene.selection_unit(0), vec![0, 1]);
+
+        // Ungrouping hands children to the parent
+        scene.edit_outline(OutlineCommand::Ungroup(0), ElementKind::Solid);
+        assert_eq!(scene.outline().groups[0].parent, None);
+        assert_eq!(scene.selection_unit(1), vec![0, 1]);
+
+        scene.act(Action::DeselectAll(ElementKind::Solid));
+        scene.act(Action::SelectSolids(vec![2]));
+        scene.edit_outline(OutlineCommand::SelectGroup(1), ElementKind::Solid);
+        let mut selected = scene.selected_solid_ids();
+        selected.sort_unstable();
+        assert_eq!(selected, vec![0, 1]);
+
+        // Pieces of a clipped solid stay in its group
+        scene.act(Action::ReplaceSolids {
+            ids: vec![0],
+            solids: vec![cube(vec3(0, 0, 0), 64), cube(vec3(64, 0, 0), 64)],
+        });
+        assert_eq!(scene.selection_unit(3), vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn layers() {
+        let mut scene = grouped();
+        scene.edit_outline(OutlineCommand::NewLayer("walls".into()), ElementKind::Solid);
+        scene.act(Action::SelectSolids(vec![0, 1]));
+        scene.edit_outline(OutlineCommand::AssignLayer(Some(0)), ElementKind::Solid);
+
+        // Locking keeps the layer drawn but out of reach of selection
+        scene.edit_outline(OutlineCommand::LockLayer(0, true), ElementKind::Solid);
+        assert_eq!(selected_solids(&scene), 0);
+        assert!(!scene.hidden(0));
+        assert!(scene.selection_unit(0).is_empty());
+        scene.act(Action::SelectAll(ElementKind::Solid));
+        assert_eq!(scene.selected_solid_ids(), vec![2]);
+
+        // Undoing the lock gives the selection back
+        scene.undo();
+        scene.undo();
+        assert_eq!(selected_solids(&scene), 2);
+
+        scene.edit_outline(OutlineCommand::HideLayer(0, true), ElementKind::Solid);
+        assert!(scene.hidden(1));
+        assert_eq!(selected_solids(&scene), 0);
+        scene.undo();
+        assert!(!scene.hidden(1));
+    }
+
+    #[test]
+    fn outline_undo() {
+        let mut scene = grouped();
+        let before = scene.outline();
+        scene.edit_outline(
+            OutlineCommand::RenameObject(2, "door".into()),
+            ElementKind::Solid,
+        );
+        scene.edit_outline(
+            OutlineCommand::RenameGroup(0, "frame".into()),
+            ElementKind::Solid,
+        );
+        scene.edit_outline(OutlineCommand::Ungroup(0), ElementKind::Solid);
+        let after = scene.outline();
+        assert!(after.groups.is_empty());
+        assert_eq!(after.objects[2].name, "door");
+
+        scene.undo();
+        scene.undo();
+        scene.undo();
+        assert_eq!(scene.outline(), before);
+        scene.redo();
+        scene.redo();
+        scene.redo();
+        assert_eq!(scene.outline(), after);
+
+        // Renaming something that doesn't exist isn't an undo step
+        assert!(scene.take_outline_changed());
+        scene.edit_outline(
+            OutlineCommand::RenameGroup(7, "none".into()),
+            ElementKind::Solid,
+        );
+        assert!(!scene.take_outline_changed());
+    }
+
+    #[test]
+    fn save_load_outline() {
+        let mut scene = grouped();
+        scene.edit_outline(OutlineCommand::NewLayer("walls".into()), ElementKind::Solid);
+        scene.act(Action::SelectSolids(vec![2]));
+        scene.edit_outline(OutlineCommand::AssignLayer(Some(0)), ElementKind::Solid);
+        scene.edit_outline(
+            OutlineCommand::RenameObject(2, "door".into()),
+            ElementKind::Solid,
+        );
+        scene.act(Action::NewProps(vec![Prop::new(PropID(1), vec3(0, 0, 0))]));
+        scene.act(Action::SelectProps(vec![3]));
+        scene.edit_outline(OutlineCommand::Group("props".into()), ElementKind::Prop);
+
+        let world = scene.save();
+        assert_eq!(world.outline.solids[2].name, "door");
+        assert_eq!(world.outline.props[0].group, Some(1));
+
+        let mut loaded = Scene::default();
+        loaded.load(&world);
+        assert_eq!(loaded.outline(), scene.outline());
+        assert_eq!(loaded.selection_unit(0), vec![0, 1]);
+    }
+
+    #[test]
+    fn copy_paste() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![
+            cube(vec3(0, 0, 0), 128),
+            cube(vec3(256, 128, 0), 128),
+        ]));
+        assert!(scene.copy(128).is_none());
+
+        scene.act(Action::SelectAll(ElementKind::Solid));
+        let clipboard = scene.copy(128).unwrap();
+        assert_eq!(clipboard.origin, vec3(256, 0, 128));
+
+        // Pasting into another scene keeps the solids where they are relative to each other
+        let mut other = Scene::default();
+        other.act(Action::NewSolids(vec![cube(vec3(0, 0, 0), 128)]));
+        other.act(Action::SelectSolids(vec![0]));
+        other.paste(&clipboard, vec3(1280, 0, 128), ElementKind::Solid);
+        assert_eq!(other.solids.len(), 3);
+        let mut selected = other.selected_solid_ids();
+        selected.sort_unstable();
+        assert_eq!(selected, vec![1, 2]);
+        assert_eq!(corners(&other)[1][0], [1024, 0, 0]);
+        assert_eq!(corners(&other)[2][0], [1280, 128, 0]);
+
+        other.undo();
+        assert_eq!(other.solids.len(), 1);
+    }
+
+    fn moved(scene: &mut Scene, x: i32) {
+        scene.act(Action::Move {
+            kind: ElementKind::Solid,
+            delta: vec3(x, 0, 0),
+            texture_lock: false,
+        });
+    }
+
+    #[test]
+    fn transactions() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![cube(vec3(0, 0, 0), 128)]));
+        let before = corners(&scene);
+
+        scene.begin("Hollow and move");
+        scene.act(Action::SelectSolids(vec![0]));
+        scene.begin("Inner");
+        scene.act(Action::ReplaceSolids {
+            ids: vec![0],
+            solids: scene.hollow_of(0, 16),
+        });
+        scene.commit();
+        let pieces = scene.solids.len();
+        scene.act(Action::SelectAll(ElementKind::Solid));
+        moved(&mut scene, 256);
+        scene.undo();
+        assert_eq!(scene.history().entries.len(), 1);
+        scene.commit();
+
+        let history = scene.history();
+        assert_eq!(history.entries, vec!["Add solids", "Hollow and move"]);
+        scene.undo();
+        assert_eq!(corners(&scene), before);
+        assert_eq!(selected_solids(&scene), 0);
+        scene.redo();
+        assert_eq!(scene.solids.len(), pieces);
+        assert!(scene.calc_center(ElementKind::Solid).unwrap().x > 2.0);
+
+        // Empty transactions aren't steps
+        scene.begin("Nothing");
+        scene.commit();
+        assert_eq!(scene.history().entries.len(), 2);
+    }
+
+    #[test]
+    fn merged_moves() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![cube(vec3(0, 0, 0), 128)]));
+        scene.act(Action::SelectSolids(vec![0]));
+        let before = corners(&scene);
+
+        moved(&mut scene, 128);
+        moved(&mut scene, 256);
+        assert_eq!(scene.history().position, 3);
+        scene.undo();
+        assert_eq!(corners(&scene), before);
+        scene.redo();
+        assert_eq!(corners(&scene)[0][0], [384, 0, 0]);
+
+        // Moving back where it started leaves no step behind
+        moved(&mut scene, -384);
+        assert_eq!(scene.history().position, 2);
+
+        // Anything in between keeps moves apart
+        moved(&mut scene, 128);
+        scene.act(Action::SelectFaces(vec![FaceLocator { solid: 0, face: 0 }]));
+        moved(&mut scene, 128);
+        assert_eq!(scene.history().position, 5);
+    }
+
+    #[test]
+    fn history_budget() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![cube(vec3(0, 0, 0), 128)]));
+        for _ in 0..70 {
+            scene.act(Action::SelectSolids(vec![0]));
+        }
+        assert_eq!(scene.history().position, 64);
+
+        scene.set_history_budget(10, usize::MAX);
+        assert_eq!(scene.history().entries.len(), 10);
+
+        // The newest step is kept however big it is
+        scene.set_history_budget(10, 0);
+        assert_eq!(scene.history().entries.len(), 1);
+        assert!(scene.take_history_changed());
+        assert!(!scene.take_history_changed());
+
+        // Steps waiting to be redone don't push out the ones that can be undone
+        let mut scene = Scene::default();
+        for x in 0..3 {
+            scene.act(Action::NewSolids(vec![cube(vec3(x * 256, 0, 0), 128)]));
+        }
+        scene.jump_history(2);
+        let undo_size = scene.undo_stack.undo.iter().map(|entry| entry.size).sum();
+        scene.set_history_budget(10, undo_size);
+        assert_eq!(scene.history().position, 2);
+        assert_eq!(scene.history().entries.len(), 3);
+    }
+
+    #[test]
+    fn jump_history() {
+        let mut scene = Scene::default();
+        for x in 0..4 {
+            scene.act(Action::NewSolids(vec![cube(vec3(x * 256, 0, 0), 128)]));
+        }
+
+        scene.jump_history(1);
+        assert_eq!(scene.solids.len(), 1);
+        let history = scene.history();
+        assert!(history.can_undo() && history.can_redo());
+        assert_eq!(history.entries.len(), 4);
+
+        scene.jump_history(3);
+        assert_eq!(scene.solids.len(), 3);
+        scene.jump_history(10);
+        assert_eq!(scene.solids.len(), 4);
+        assert!(!scene.history().can_redo());
+
+        // Undoing waits for open transactions
+        scene.begin("Open");
+        scene.undo();
+        assert_eq!(scene.solids.len(), 4);
+        scene.commit();
     }
 
-    fn pop(&mut self) -> Option<Action> {
-        self.vec.pop()
+    #[test]
+    fn transform() {
+        let mut scene = Scene::default();
+        scene.act(Action::NewSolids(vec![cube(vec3(0, 0, 0), 128)]));
+        scene.act(Action::NewProps(vec![Prop::new(PropID(1), vec3(0, 0, 0))]));
+        scene.act(Action::SelectSolids(vec![0]));
+        scene.act(Action::SelectProps(vec![1]));
+
+        let quarter = Quaternion::from_angle_y(Deg(90.0));
+        scene.transform(ElementKind::Solid, vec3(0, 0, 314), quarter);
+        let center = scene.calc_center(ElementKind::Solid).unwrap();
+        assert!((center - vec3(0.5, 0.5, 0.5 + 314.0 / 128.0)).magnitude() < 1e-5);
+        assert_eq!(scene.save().props[0].position, vec3(0, 0, 0));
+
+        // Props turn as well, and both undo in one step
+        scene.transform(ElementKind::Prop, vec3(-128, 0, 0), quarter);
+        let prop = &scene.save().props[0];
+        assert_eq!(prop.position, vec3(-128, 0, 0));
+        assert!((prop.rotation - quarter).magnitude() < 1e-5);
+        scene.undo();
+        let prop = &scene.save().props[0];
+        assert_eq!(prop.position, vec3(0, 0, 0));
+        assert!((prop.rotation.s - 1.0).abs() < 1e-5);
+        assert!(scene.calc_center(ElementKind::Solid).unwrap().z > 2.9);
     }
 }
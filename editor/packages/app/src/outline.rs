@@ -0,0 +1,52 @@
+/// How the scene's solids and props are organised, sent to the host whenever it changes
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Outline {
+    pub layers: Vec<OutlineLayer>,
+    pub groups: Vec<OutlineGroup>,
+    pub objects: Vec<OutlineObject>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlineLayer {
+    pub name: String,
+    pub hidden: bool,
+    pub locked: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlineGroup {
+    pub id: u32,
+    pub name: String,
+    pub parent: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutlineObject {
+    pub id: usize,
+    /// Solid otherwise
+    pub prop: bool,
+    pub name: String,
+    /// Index into [Outline::layers]
+    pub layer: Option<u32>,
+    pub group: Option<u32>,
+}
+
+/// Changes made from the host's outliner, each of them can be undone
+pub enum OutlineCommand {
+    NewLayer(String),
+    RenameLayer(u32, String),
+    /// Hidden layers are not drawn
+    HideLayer(u32, bool),
+    /// Locked layers can't be selected
+    LockLayer(u32, bool),
+    /// Moves the selected solids or props to a layer, or off every layer
+    AssignLayer(Option<u32>),
+    /// Puts the selection into a new group with a name
+    Group(String),
+    /// Hands the members of a group to its parent and removes it
+    Ungroup(u32),
+    RenameGroup(u32, String),
+    RenameObject(usize, String),
+    /// Selects the members of a group that can be edited in the current mode
+    SelectGroup(u32),
+}
@@ -14,4 +14,420 @@ macro_rules! encdec {
 
 encdec!(crate::Prop);
 encdec!(crate::Gizmo);
-encdec!(crate::scene::Scene);
+encdec!(crate::Lightmap);
+
+/// Scenes start with a header since their layout changed, files without one are read as the first layout
+const SCENE_MAGIC: &[u8; 4] = b"ASCN";
+const SCENE_VERSION: u32 = 3;
+
+impl crate::scene::Scene {
+    pub fn encode(&self) -> Option<Vec<u8>> {
+        let mut buf = SCENE_MAGIC.to_vec();
+        buf.extend(SCENE_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut buf, self).ok()?;
+        Some(buf)
+    }
+
+    pub fn decode(buf: &[u8]) -> Option<Self> {
+        match buf.strip_prefix(SCENE_MAGIC) {
+            Some(rest) => {
+                let version = u32::from_le_bytes(rest.get(..4)?.try_into().ok()?);
+                let rest = &rest[4..];
+                match version {
+                    1 => bincode::deserialize::<v1::Scene>(rest)
+                        .ok()
+                        .map(|scene| v2::Scene::from(scene).into()),
+                    2 => bincode::deserialize::<v2::Scene>(rest).ok().map(Into::into),
+                    SCENE_VERSION => bincode::deserialize::<Self>(rest).ok(),
+                    _ => None,
+                }
+            }
+            None => bincode::deserialize::<v0::Scene>(buf)
+                .ok()
+                .map(|scene| v2::Scene::from(v1::Scene::from(scene)).into()),
+        }
+    }
+}
+
+/// Clipboard contents are passed around by hosts, anything without the header isn't ours
+const CLIPBOARD_MAGIC: &[u8; 4] = b"ACLP";
+const CLIPBOARD_VERSION: u32 = 1;
+
+impl crate::scene::Clipboard {
+    pub fn encode(&self) -> Option<Vec<u8>> {
+        let mut buf = CLIPBOARD_MAGIC.to_vec();
+        buf.extend(CLIPBOARD_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut buf, self).ok()?;
+        Some(buf)
+    }
+
+    pub fn decode(buf: &[u8]) -> Option<Self> {
+        let rest = buf.strip_prefix(CLIPBOARD_MAGIC)?;
+        let version = u32::from_le_bytes(rest.get(..4)?.try_into().ok()?);
+        (version == CLIPBOARD_VERSION)
+            .then(|| bincode::deserialize::<Self>(&rest[4..]).ok())
+            .flatten()
+    }
+}
+
+/// Scenes saved before faces had texture alignments
+mod v0 {
+    use serde::Deserialize;
+
+    use crate::{
+        scene::{Camera, Point, Prop},
+        TextureID,
+    };
+
+    use super::v1;
+
+    #[derive(Deserialize)]
+    pub struct Scene {
+        camera: Camera,
+        world: World,
+    }
+
+    #[derive(Deserialize)]
+    struct World {
+        solids: Vec<Solid>,
+        props: Vec<Prop>,
+    }
+
+    #[derive(Deserialize)]
+    struct Solid {
+        points: [Point; 8],
+        faces: [Face; 6],
+    }
+
+    #[derive(Deserialize)]
+    struct Face {
+        texture: TextureID,
+        indices: [u32; 4],
+    }
+
+    impl From<Scene> for v1::Scene {
+        fn from(scene: Scene) -> Self {
+            let solids = scene
+                .world
+                .solids
+                .into_iter()
+                .map(|solid| v1::Solid {
+                    points: solid.points,
+                    faces: solid.faces.map(|face| v1::Face {
+                        texture: face.texture,
+                        indices: face.indices,
+                        alignment: Default::default(),
+                    }),
+                })
+                .collect();
+
+            Self {
+                camera: scene.camera,
+                world: v1::World {
+                    solids,
+                    props: scene.world.props,
+                },
+            }
+        }
+    }
+}
+
+/// Scenes saved when every solid was a box of 8 points and 6 quads
+mod v1 {
+    use serde::Deserialize;
+
+    use crate::{
+        scene::{self, Camera, Point, Prop, TextureAlignment},
+        TextureID,
+    };
+
+    use super::v2;
+
+    #[derive(Deserialize)]
+    pub struct Scene {
+        pub camera: Camera,
+        pub world: World,
+    }
+
+    #[derive(Deserialize)]
+    pub struct World {
+        pub solids: Vec<Solid>,
+        pub props: Vec<Prop>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Solid {
+        pub points: [Point; 8],
+        pub faces: [Face; 6],
+    }
+
+    #[derive(Deserialize)]
+    pub struct Face {
+        pub texture: TextureID,
+        pub indices: [u32; 4],
+        pub alignment: TextureAlignment,
+    }
+
+    impl From<Scene> for v2::Scene {
+        fn from(scene: Scene) -> Self {
+            let solids = scene
+                .world
+                .solids
+                .into_iter()
+                .map(|solid| scene::Solid {
+                    points: solid.points.into(),
+                    faces: solid
+                        .faces
+                        .into_iter()
+                        .map(|face| scene::Face {
+                            texture: face.texture,
+                            indices: face.indices.into(),
+                            alignment: face.alignment,
+                        })
+                        .collect(),
+                })
+                .collect();
+
+            Self {
+                camera: scene.camera,
+                world: v2::World {
+                    solids,
+                    props: scene.world.props,
+                },
+            }
+        }
+    }
+}
+
+/// Scenes saved before solids and props could be organised into layers and groups
+mod v2 {
+    use serde::Deserialize;
+
+    use crate::scene::{self, Camera, Prop, Solid};
+
+    #[derive(Deserialize)]
+    pub struct Scene {
+        pub camera: Camera,
+        pub world: World,
+    }
+
+    #[derive(Deserialize)]
+    pub struct World {
+        pub solids: Vec<Solid>,
+        pub props: Vec<Prop>,
+    }
+
+    impl From<Scene> for scene::Scene {
+        fn from(scene: Scene) -> Self {
+            Self {
+                camera: scene.camera,
+                world: scene::World {
+                    solids: scene.world.solids,
+                    props: scene.world.props,
+                    outline: Default::default(),
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec2, vec3, Quaternion};
+    use serde::Serialize;
+
+    use crate::{
+        scene::{
+            Camera, Clipboard, Face, Group, Layer, Object, Outline, Point, Prop, Scene, Solid,
+            TextureAlignment, World,
+        },
+        PropID, TextureID,
+    };
+
+    #[test]
+    fn legacy_scene() {
+        // Written field by field like the first layout
+        #[derive(Serialize)]
+        struct Face(TextureID, [u32; 4]);
+
+        let points = [[0, 0, 0]; 8].map(|position| Point {
+            position: position.into(),
+        });
+        let mut buf = bincode::serialize(&Camera {
+            position: vec3(1.0, 2.0, 3.0),
+            rotation: vec2(10.0, 20.0),
+        })
+        .unwrap();
+        buf.extend(bincode::serialize(&1u64).unwrap());
+        buf.extend(bincode::serialize(&points).unwrap());
+        let faces = [(); 6].map(|_| Face(TextureID(7), [0, 1, 2, 3]));
+        buf.extend(bincode::serialize(&faces).unwrap());
+        buf.extend(
+            bincode::serialize(&vec![Prop {
+                asset: PropID(2),
+                position: vec3(1, 2, 3),
+                rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            }])
+            .unwrap(),
+        );
+
+        let scene = Scene::decode(&buf).unwrap();
+        assert_eq!(scene.camera.position, vec3(1.0, 2.0, 3.0));
+        assert_eq!(scene.world.solids.len(), 1);
+        let face = &scene.world.solids[0].faces[5];
+        assert_eq!(face.texture, TextureID(7));
+        assert_eq!(face.alignment, TextureAlignment::default());
+        assert_eq!(scene.world.props[0].position, vec3(1, 2, 3));
+    }
+
+    #[test]
+    fn box_scene() {
+        // Fixed size solids with aligned textures
+        #[derive(Serialize)]
+        struct Face(TextureID, [u32; 4], TextureAlignment);
+
+        let mut buf = b"ASCN".to_vec();
+        buf.extend(1u32.to_le_bytes());
+        buf.extend(
+            bincode::serialize(&Camera {
+                position: vec3(0.0, 0.0, 0.0),
+                rotation: vec2(0.0, 0.0),
+            })
+            .unwrap(),
+        );
+        let points = [
+            [0, 0, 0],
+            [1, 0, 0],
+            [1, 0, 1],
+            [0, 0, 1],
+            [0, 1, 0],
+            [1, 1, 0],
+            [1, 1, 1],
+            [0, 1, 1],
+        ]
+        .map(|position| Point {
+            position: position.into(),
+        });
+        let alignment = TextureAlignment {
+            rotation: 45.0,
+            ..Default::default()
+        };
+        let faces = [
+            [1, 5, 6, 2],
+            [4, 0, 3, 7],
+            [5, 4, 7, 6],
+            [0, 1, 2, 3],
+            [3, 2, 6, 7],
+            [1, 0, 4, 5],
+        ]
+        .map(|indices| Face(TextureID(3), indices, alignment));
+        buf.extend(bincode::serialize(&1u64).unwrap());
+        buf.extend(bincode::serialize(&points).unwrap());
+        buf.extend(bincode::serialize(&faces).unwrap());
+        buf.extend(bincode::serialize(&0u64).unwrap());
+
+        let scene = Scene::decode(&buf).unwrap();
+        let solid = &scene.world.solids[0];
+        assert_eq!(solid.points.len(), 8);
+        assert_eq!(solid.points[6].position, vec3(1, 1, 1));
+        assert_eq!(solid.faces.len(), 6);
+        assert_eq!(solid.faces[2].indices, vec![5, 4, 7, 6]);
+        assert_eq!(solid.faces[2].alignment, alignment);
+    }
+
+    #[test]
+    fn convex_scene() {
+        // Solids of any shape, without an outline
+        let mut buf = b"ASCN".to_vec();
+        buf.extend(2u32.to_le_bytes());
+        buf.extend(
+            bincode::serialize(&Camera {
+                position: vec3(0.0, 0.0, 0.0),
+                rotation: vec2(0.0, 0.0),
+            })
+            .unwrap(),
+        );
+        buf.extend(
+            bincode::serialize(&vec![Solid {
+                points: [[0, 0, 0], [1, 0, 0], [0, 1, 0], [0, 0, 1]]
+                    .map(|position| Point {
+                        position: position.into(),
+                    })
+                    .into(),
+                faces: vec![Face {
+                    texture: TextureID(4),
+                    indices: vec![0, 2, 1],
+                    alignment: Default::default(),
+                }],
+            }])
+            .unwrap(),
+        );
+        buf.extend(bincode::serialize(&0u64).unwrap());
+
+        let scene = Scene::decode(&buf).unwrap();
+        assert_eq!(scene.world.solids[0].points.len(), 4);
+        assert_eq!(scene.world.solids[0].faces[0].texture, TextureID(4));
+        assert_eq!(scene.world.outline, Outline::default());
+    }
+
+    #[test]
+    fn round_trip() {
+        let outline = Outline {
+            layers: vec![Layer {
+                name: "walls".into(),
+                hidden: true,
+                locked: false,
+            }],
+            groups: vec![Group {
+                name: "house".into(),
+                parent: None,
+            }],
+            solids: Vec::new(),
+            props: vec![Object {
+                name: "chair".into(),
+                layer: Some(0),
+                group: Some(0),
+            }],
+        };
+        let scene = Scene {
+            camera: Camera {
+                position: vec3(0.0, 0.0, 0.0),
+                rotation: vec2(0.0, 0.0),
+            },
+            world: World {
+                solids: Vec::new(),
+                props: Vec::new(),
+                outline: outline.clone(),
+            },
+        };
+        let buf = scene.encode().unwrap();
+        assert!(buf.starts_with(b"ASCN"));
+        assert_eq!(Scene::decode(&buf).unwrap().world.outline, outline);
+
+        // Layouts from the future are rejected instead of misread
+        let mut future = buf.clone();
+        future[4] = 99;
+        assert!(Scene::decode(&future).is_none());
+    }
+
+    #[test]
+    fn clipboard() {
+        let clipboard = Clipboard {
+            origin: vec3(128, 0, -256),
+            solids: Vec::new(),
+            props: vec![Prop {
+                asset: PropID(5),
+                position: vec3(128, 64, -256),
+                rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            }],
+        };
+        let buf = clipboard.encode().unwrap();
+        let decoded = Clipboard::decode(&buf).unwrap();
+        assert_eq!(decoded.origin, clipboard.origin);
+        assert_eq!(decoded.props[0].asset, PropID(5));
+
+        // Whatever else a host had on its clipboard doesn't paste
+        assert!(Clipboard::decode(b"some text").is_none());
+        assert!(Clipboard::decode(&buf[..buf.len() - 1]).is_none());
+    }
+}
@@ -0,0 +1,136 @@
+use std::f32::consts::PI;
+
+use cgmath::{vec2, Vector2};
+use serde::{Deserialize, Serialize};
+
+/// Baked lighting of the faces of a scene's solids, packed into an atlas
+#[derive(Serialize, Deserialize)]
+pub struct Lightmap {
+    pub width: u32,
+    pub height: u32,
+    /// Texels along each side of a face's square patch
+    pub patch: u32,
+    pub faces: Vec<LightmapFace>,
+    /// Light of every texel, see [Lightmap::encode_light]
+    pub rgba8: Vec<u8>,
+}
+
+/// Faces are found by their corners, so the lightmap survives solids being reordered
+#[derive(Serialize, Deserialize)]
+pub struct LightmapFace {
+    /// Corners in the order of the face's indices, in grid units
+    pub corners: Vec<[i32; 3]>,
+    /// Top left texel of the patch
+    pub origin: [u32; 2],
+}
+
+impl Lightmap {
+    /// Lays the patches out in rows of a roughly square atlas, unlit until texels are set
+    pub fn new(faces: Vec<Vec<[i32; 3]>>, patch: u32) -> Self {
+        let columns = (faces.len() as f32).sqrt().ceil().max(1.0) as u32;
+        let rows = (faces.len() as u32).div_ceil(columns);
+        let width = columns * patch;
+        let height = rows.max(1) * patch;
+        let faces = faces
+            .into_iter()
+            .enumerate()
+            .map(|(i, corners)| LightmapFace {
+                corners,
+                origin: [i as u32 % columns * patch, i as u32 / columns * patch],
+            })
+            .collect();
+        let mut rgba8 = vec![0; (width * height * 4) as usize];
+        for alpha in rgba8.iter_mut().skip(3).step_by(4) {
+            *alpha = 255;
+        }
+
+        Self {
+            width,
+            height,
+            patch,
+            faces,
+            rgba8,
+        }
+    }
+
+    pub fn find(&self, corners: &[[i32; 3]]) -> Option<&LightmapFace> {
+        self.faces.iter().find(|face| face.corners == corners)
+    }
+
+    /// Where the corners of a face lie on its patch, from 0 to 1. Quads take the corners of the patch,
+    /// other faces are spread around a circle touching its sides. Points in between are interpolated
+    /// over the triangles of [face_triangles](crate::scene::face_triangles), like the face is drawn.
+    pub fn patch_corners(corners: usize) -> Vec<Vector2<f32>> {
+        if corners == 4 {
+            return vec![
+                vec2(0.0, 0.0),
+                vec2(1.0, 0.0),
+                vec2(1.0, 1.0),
+                vec2(0.0, 1.0),
+            ];
+        }
+        (0..corners)
+            .map(|i| {
+                let angle = 1.25 * PI + i as f32 * 2.0 * PI / corners as f32;
+                vec2(0.5 + 0.5 * angle.cos(), 0.5 + 0.5 * angle.sin())
+            })
+            .collect()
+    }
+
+    /// Where a point of a patch is baked, given from 0 to 1 like [Lightmap::patch_corners]
+    pub fn texel_position(&self, s: f32, t: f32) -> Vector2<f32> {
+        let edge = self.patch.saturating_sub(1) as f32;
+        vec2(s * edge, t * edge)
+    }
+
+    /// Atlas coordinates of the corners of a face, at the centers of the patch's corner texels
+    pub fn texcoords(&self, face: &LightmapFace) -> Vec<Vector2<f32>> {
+        Self::patch_corners(face.corners.len())
+            .into_iter()
+            .map(|corner| {
+                let texel = self.texel_position(corner.x, corner.y);
+                vec2(
+                    (face.origin[0] as f32 + texel.x + 0.5) / self.width as f32,
+                    (face.origin[1] as f32 + texel.y + 0.5) / self.height as f32,
+                )
+            })
+            .collect()
+    }
+
+    pub fn set_texel(&mut self, face: usize, x: u32, y: u32, light: [f32; 3]) {
+        let [ox, oy] = self.faces[face].origin;
+        let index = (((oy + y) * self.width + ox + x) * 4) as usize;
+        for (value, light) in self.rgba8[index..index + 3].iter_mut().zip(light) {
+            *value = Self::encode_light(light);
+        }
+    }
+
+    pub fn texel(&self, face: usize, x: u32, y: u32) -> [f32; 3] {
+        let [ox, oy] = self.faces[face].origin;
+        let index = (((oy + y) * self.width + ox + x) * 4) as usize;
+        [0, 1, 2].map(|i| Self::decode_light(self.rgba8[index + i]))
+    }
+
+    /// Light is compressed into 0..1 as `light / (light + 1)`, then stored with the sRGB curve
+    /// so the atlas can be uploaded as an ordinary sRGB texture
+    pub fn encode_light(light: f32) -> u8 {
+        let light = light.max(0.0);
+        let value = light / (light + 1.0);
+        let srgb = if value <= 0.0031308 {
+            value * 12.92
+        } else {
+            1.055 * value.powf(1.0 / 2.4) - 0.055
+        };
+        (srgb * 255.0).round() as u8
+    }
+
+    pub fn decode_light(value: u8) -> f32 {
+        let srgb = value as f32 / 255.0;
+        let value = if srgb <= 0.04045 {
+            srgb / 12.92
+        } else {
+            ((srgb + 0.055) / 1.055).powf(2.4)
+        };
+        value / (1.0 - value).max(1.0 / 255.0)
+    }
+}
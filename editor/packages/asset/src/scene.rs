@@ -1,4 +1,6 @@
-use cgmath::{Quaternion, Vector2, Vector3};
+use cgmath::{
+    vec2, Deg, InnerSpace, Matrix, Matrix2, Quaternion, SquareMatrix, Vector2, Vector3, Zero,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::{PropID, TextureID};
@@ -13,6 +15,51 @@ pub struct Scene {
 pub struct World {
     pub solids: Vec<Solid>,
     pub props: Vec<Prop>,
+    pub outline: Outline,
+}
+
+/// How the editor organises the solids and props of a world, renderers ignore it
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Debug)]
+pub struct Outline {
+    pub layers: Vec<Layer>,
+    pub groups: Vec<Group>,
+    /// Indexed like [World::solids], solids past the end are on no layer and in no group
+    pub solids: Vec<Object>,
+    /// Indexed like [World::props]
+    pub props: Vec<Object>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Eq, Debug)]
+pub struct Layer {
+    pub name: String,
+    /// Not drawn in the editor
+    pub hidden: bool,
+    /// Can't be selected in the editor
+    pub locked: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Eq, Debug)]
+pub struct Group {
+    pub name: String,
+    /// Index of the group this one is nested in
+    pub parent: Option<u32>,
+}
+
+/// Name and place of a solid or prop in the outline
+#[derive(Serialize, Deserialize, Default, Clone, PartialEq, Eq, Debug)]
+pub struct Object {
+    pub name: String,
+    pub layer: Option<u32>,
+    pub group: Option<u32>,
+}
+
+/// Solids and props copied out of a world, where they were copied from
+#[derive(Serialize, Deserialize)]
+pub struct Clipboard {
+    /// Grid point under the middle of the copied elements, pasting moves it to the target
+    pub origin: Vector3<i32>,
+    pub solids: Vec<Solid>,
+    pub props: Vec<Prop>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -21,16 +68,151 @@ pub struct Camera {
     pub rotation: Vector2<f32>,
 }
 
+/// Convex polyhedron, older scenes only had boxes of 8 points and 6 quads
 #[derive(Serialize, Deserialize)]
 pub struct Solid {
-    pub points: [Point; 8],
-    pub faces: [Face; 6],
+    pub points: Vec<Point>,
+    pub faces: Vec<Face>,
 }
 
+/// Convex polygon, wound like the quads of a box so its normal points out of the solid
 #[derive(Serialize, Deserialize)]
 pub struct Face {
     pub texture: TextureID,
-    pub indices: [u32; 4],
+    /// At least 3 points of the solid
+    pub indices: Vec<u32>,
+    pub alignment: TextureAlignment,
+}
+
+/// Outward normal of a face from its corners, in the order of its indices
+pub fn face_normal(corners: &[Vector3<f32>]) -> Vector3<f32> {
+    let edge0 = corners[1] - corners[0];
+    let edge1 = corners[corners.len() - 1] - corners[0];
+    edge0.cross(edge1).normalize()
+}
+
+/// Triangles covering a convex face as indices into its corners, wound like the face
+pub fn face_triangles(corners: usize) -> impl Iterator<Item = [usize; 3]> {
+    (1..corners.saturating_sub(1)).map(|i| [0, i, i + 1])
+}
+
+/// Placement of a face's texture on top of the world aligned planar projection
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct TextureAlignment {
+    /// In texture repeats, added last
+    pub offset: Vector2<f32>,
+    /// Meters covered by one repeat of the texture, negative to mirror it
+    pub scale: Vector2<f32>,
+    /// Degrees, counterclockwise
+    pub rotation: f32,
+}
+
+impl Default for TextureAlignment {
+    fn default() -> Self {
+        Self {
+            offset: Vector2::zero(),
+            scale: vec2(1.0, 1.0),
+            rotation: 0.0,
+        }
+    }
+}
+
+impl TextureAlignment {
+    /// Texture coordinate of a point of a face, both in meters before the raytracer flips z.
+    /// The editor and the raytracer both use this, so faces look the same in each.
+    pub fn texcoord(&self, position: Vector3<f32>, normal: Vector3<f32>) -> Vector2<f32> {
+        self.transform() * project(position, normal) + self.offset
+    }
+
+    /// Keeps the texture in place on a face that was moved or rotated, given its first, second and last corners before and after.
+    /// Distorting the face can't be followed exactly, the closest rotation and scale are kept then.
+    pub fn locked(
+        &self,
+        before: [Vector3<f32>; 3],
+        normal_before: Vector3<f32>,
+        after: [Vector3<f32>; 3],
+        normal_after: Vector3<f32>,
+    ) -> Self {
+        let uv = before.map(|corner| self.texcoord(corner, normal_before));
+        let projected = after.map(|corner| project(corner, normal_after));
+
+        let from = Matrix2::from_cols(projected[1] - projected[0], projected[2] - projected[0]);
+        let to = Matrix2::from_cols(uv[1] - uv[0], uv[2] - uv[0]);
+        let transform = match from.invert() {
+            Some(inverse) => to * inverse,
+            None => return *self,
+        };
+
+        // Rows of the transform are the rows of the rotation divided by the scale
+        let (row0, row1) = (transform.row(0), transform.row(1));
+        if row0.magnitude2() < 1e-12 {
+            return *self;
+        }
+        let angle = (-row0.y).atan2(row0.x);
+        let scale = vec2(
+            1.0 / row0.magnitude(),
+            1.0 / row1.dot(vec2(angle.sin(), angle.cos())),
+        );
+        if !scale.y.is_finite() {
+            return *self;
+        }
+
+        let mut locked = Self {
+            offset: Vector2::zero(),
+            scale,
+            rotation: angle.to_degrees(),
+        };
+        locked.offset = uv[0] - locked.transform() * projected[0];
+        locked
+    }
+
+    /// Stretches one repeat of the texture over the whole face, keeping its rotation and mirroring
+    pub fn fit(&self, corners: &[Vector3<f32>], normal: Vector3<f32>) -> Self {
+        let rotation = Matrix2::from_angle(Deg(self.rotation));
+        let (mut min, mut max) = (vec2(f32::MAX, f32::MAX), vec2(f32::MIN, f32::MIN));
+        for corner in corners {
+            let rotated = rotation * project(*corner, normal);
+            min = vec2(min.x.min(rotated.x), min.y.min(rotated.y));
+            max = vec2(max.x.max(rotated.x), max.y.max(rotated.y));
+        }
+
+        let size = max - min;
+        if size.x < 1e-6 || size.y < 1e-6 {
+            return *self;
+        }
+
+        let scale = vec2(size.x.copysign(self.scale.x), size.y.copysign(self.scale.y));
+        // The smallest texture coordinate lands on zero, which is the smaller end after mirroring
+        let start = vec2(
+            if scale.x > 0.0 { min.x } else { max.x },
+            if scale.y > 0.0 { min.y } else { max.y },
+        );
+        Self {
+            offset: vec2(-start.x / scale.x, -start.y / scale.y),
+            scale,
+            rotation: self.rotation,
+        }
+    }
+
+    fn transform(&self) -> Matrix2<f32> {
+        Matrix2::from_cols(vec2(1.0 / self.scale.x, 0.0), vec2(0.0, 1.0 / self.scale.y))
+            * Matrix2::from_angle(Deg(self.rotation))
+    }
+}
+
+/// World aligned planar projection onto the axis plane the face is closest to
+fn project(position: Vector3<f32>, normal: Vector3<f32>) -> Vector2<f32> {
+    if normal.x.abs() > normal.y.abs() {
+        if normal.x.abs() > normal.z.abs() {
+            vec2(position.z, position.y)
+        } else {
+            vec2(position.x, position.y)
+        }
+    } else if normal.y.abs() > normal.z.abs() {
+        vec2(position.x, position.z)
+    } else {
+        vec2(position.x, position.y)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -44,3 +226,112 @@ pub struct Prop {
     pub position: Vector3<i32>,
     pub rotation: Quaternion<f32>,
 }
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{vec2, vec3, Vector2, Vector3};
+
+    use super::TextureAlignment;
+
+    fn assert_close(a: Vector2<f32>, b: Vector2<f32>) {
+        assert!(
+            (a - b).x.abs() < 1e-4 && (a - b).y.abs() < 1e-4,
+            "{a:?} != {b:?}"
+        );
+    }
+
+    // The top of a box, seen from above
+    const TOP: [Vector3<f32>; 4] = [
+        vec3(1.0, 1.0, 0.0),
+        vec3(0.0, 1.0, 0.0),
+        vec3(0.0, 1.0, 2.0),
+        vec3(1.0, 1.0, 2.0),
+    ];
+    const UP: Vector3<f32> = vec3(0.0, 1.0, 0.0);
+
+    #[test]
+    fn default_projection() {
+        let alignment = TextureAlignment::default();
+        assert_close(alignment.texcoord(vec3(1.0, 2.0, 3.0), UP), vec2(1.0, 3.0));
+        assert_close(
+            alignment.texcoord(vec3(1.0, 2.0, 3.0), vec3(-1.0, 0.0, 0.0)),
+            vec2(3.0, 2.0),
+        );
+        assert_close(
+            alignment.texcoord(vec3(1.0, 2.0, 3.0), vec3(0.0, 0.0, 1.0)),
+            vec2(1.0, 2.0),
+        );
+    }
+
+    #[test]
+    fn transform() {
+        let alignment = TextureAlignment {
+            offset: vec2(0.25, 0.0),
+            scale: vec2(2.0, 0.5),
+            rotation: 90.0,
+        };
+        // Rotated to (-z, x), then scaled and offset
+        assert_close(
+            alignment.texcoord(vec3(1.0, 0.0, 1.0), UP),
+            vec2(-0.25, 2.0),
+        );
+    }
+
+    #[test]
+    fn lock_translation() {
+        let alignment = TextureAlignment {
+            offset: vec2(0.1, 0.2),
+            scale: vec2(0.5, 2.0),
+            rotation: 30.0,
+        };
+        let delta = vec3(0.5, 3.0, -1.25);
+        let before = [TOP[0], TOP[1], TOP[3]];
+        let locked = alignment.locked(before, UP, before.map(|p| p + delta), UP);
+
+        assert_close(locked.scale, alignment.scale);
+        assert!((locked.rotation - alignment.rotation).abs() < 1e-3);
+        for corner in TOP {
+            assert_close(
+                locked.texcoord(corner + delta, UP),
+                alignment.texcoord(corner, UP),
+            );
+        }
+    }
+
+    #[test]
+    fn lock_rotation() {
+        let alignment = TextureAlignment {
+            offset: vec2(0.1, 0.2),
+            scale: vec2(0.5, 2.0),
+            rotation: 0.0,
+        };
+        // A quarter turn around y keeps the face on top, but swaps x and z
+        let rotate = |p: Vector3<f32>| vec3(p.z, p.y, -p.x);
+        let before = [TOP[0], TOP[1], TOP[3]];
+        let locked = alignment.locked(before, UP, before.map(rotate), UP);
+
+        for corner in TOP {
+            assert_close(
+                locked.texcoord(rotate(corner), UP),
+                alignment.texcoord(corner, UP),
+            );
+        }
+        assert_close(locked.scale, vec2(0.5, 2.0));
+    }
+
+    #[test]
+    fn fit() {
+        let fitted = TextureAlignment::default().fit(&TOP, UP);
+        assert_close(fitted.scale, vec2(1.0, 2.0));
+        assert_close(fitted.texcoord(TOP[1], UP), vec2(0.0, 0.0));
+        assert_close(fitted.texcoord(TOP[3], UP), vec2(1.0, 1.0));
+
+        let mirrored = TextureAlignment {
+            scale: vec2(-1.0, 1.0),
+            ..Default::default()
+        }
+        .fit(&TOP, UP);
+        assert_close(mirrored.texcoord(TOP[0], UP), vec2(0.0, 0.0));
+        assert_close(mirrored.texcoord(TOP[2], UP), vec2(1.0, 1.0));
+    }
+}
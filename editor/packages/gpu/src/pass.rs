@@ -156,9 +156,9 @@ impl<'a> RenderPass<'a> {
         self.pass.set_vertex_buffer(0, vertices.buffer.slice(..));
     }
 
-    pub fn draw_face(&mut self, face: u32) {
-        let start = face * 6;
-        let end = start + 6;
-        self.pass.draw_indexed(start..end, 0, 0..1);
+    /// Draws part of the triangles set by [RenderPass::set_triangles]
+    pub fn draw_triangle_range(&mut self, triangles: Range<u32>) {
+        self.pass
+            .draw_indexed(triangles.start * 3..triangles.end * 3, 0, 0..1);
     }
 }
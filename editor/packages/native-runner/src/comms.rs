@@ -5,7 +5,7 @@ use std::{
     thread::{spawn, JoinHandle},
 };
 
-use app::{Ascn, FromHost};
+use app::{Ascn, FromHost, OutlineCommand, Resource, ResourceKind, Transform};
 
 pub struct AsyncStdin {
     thread: Option<JoinHandle<()>>,
@@ -43,6 +43,35 @@ impl AsyncStdin {
                             sender.send(FromHost::LoadScene(scene)).unwrap();
                             println!("[native-runner] loading `{}`", path);
                         }
+                        "lightmap" => {
+                            let name = tokens.next().unwrap();
+                            let path = format!("{}.alm", name);
+                            let buf = fs::read(&path).unwrap();
+                            sender
+                                .send(FromHost::LoadResource(Resource {
+                                    id: 0,
+                                    buf,
+                                    kind: ResourceKind::Lightmap,
+                                }))
+                                .unwrap();
+                            println!("[native-runner] loading `{}`", path);
+                        }
+                        "lit" => {
+                            let lit = tokens.next() == Some("on");
+                            sender.send(FromHost::LitPreview(lit)).unwrap();
+                            println!(
+                                "[native-runner] lit preview {}",
+                                if lit { "on" } else { "off" }
+                            );
+                        }
+                        "texlock" => {
+                            let lock = tokens.next() == Some("on");
+                            sender.send(FromHost::TextureLock(lock)).unwrap();
+                            println!(
+                                "[native-runner] texture lock {}",
+                                if lock { "on" } else { "off" }
+                            );
+                        }
                         "texture" => {
                             let texture = tokens.next().unwrap().parse().unwrap();
                             sender.send(FromHost::Texture(texture)).unwrap();
@@ -58,6 +87,105 @@ impl AsyncStdin {
                             sender.send(FromHost::Button(button)).unwrap();
                             println!("[native-runner] pressed button {}", button);
                         }
+                        "layer" => {
+                            let command = match tokens.next().unwrap() {
+                                "new" => OutlineCommand::NewLayer(rest(tokens)),
+                                "rename" => {
+                                    let layer = tokens.next().unwrap().parse().unwrap();
+                                    OutlineCommand::RenameLayer(layer, rest(tokens))
+                                }
+                                "hide" => {
+                                    let layer = tokens.next().unwrap().parse().unwrap();
+                                    OutlineCommand::HideLayer(layer, tokens.next() == Some("on"))
+                                }
+                                "lock" => {
+                                    let layer = tokens.next().unwrap().parse().unwrap();
+                                    OutlineCommand::LockLayer(layer, tokens.next() == Some("on"))
+                                }
+                                "assign" => OutlineCommand::AssignLayer(
+                                    tokens.next().and_then(|layer| layer.parse().ok()),
+                                ),
+                                _ => {
+                                    println!("[native-runner] bad input");
+                                    continue;
+                                }
+                            };
+                            sender.send(FromHost::Outline(command)).unwrap();
+                            println!("[native-runner] edited layers");
+                        }
+                        "group" => {
+                            let command = match tokens.next().unwrap() {
+                                "new" => OutlineCommand::Group(rest(tokens)),
+                                "remove" => {
+                                    OutlineCommand::Ungroup(tokens.next().unwrap().parse().unwrap())
+                                }
+                                "rename" => {
+                                    let group = tokens.next().unwrap().parse().unwrap();
+                                    OutlineCommand::RenameGroup(group, rest(tokens))
+                                }
+                                "select" => OutlineCommand::SelectGroup(
+                                    tokens.next().unwrap().parse().unwrap(),
+                                ),
+                                _ => {
+                                    println!("[native-runner] bad input");
+                                    continue;
+                                }
+                            };
+                            sender.send(FromHost::Outline(command)).unwrap();
+                            println!("[native-runner] edited groups");
+                        }
+                        "name" => {
+                            let id = tokens.next().unwrap().parse().unwrap();
+                            let name = rest(tokens);
+                            println!("[native-runner] named object {} `{}`", id, name);
+                            sender
+                                .send(FromHost::Outline(OutlineCommand::RenameObject(id, name)))
+                                .unwrap();
+                        }
+                        "undo" => {
+                            sender.send(FromHost::Undo).unwrap();
+                            println!("[native-runner] undo");
+                        }
+                        "redo" => {
+                            sender.send(FromHost::Redo).unwrap();
+                            println!("[native-runner] redo");
+                        }
+                        "history" => {
+                            let position = tokens.next().unwrap().parse().unwrap();
+                            sender.send(FromHost::JumpHistory(position)).unwrap();
+                            println!("[native-runner] jumped to step {}", position);
+                        }
+                        "budget" => {
+                            let entries = tokens.next().unwrap().parse().unwrap();
+                            let megabytes: usize = tokens.next().unwrap().parse().unwrap();
+                            sender
+                                .send(FromHost::HistoryBudget {
+                                    entries,
+                                    bytes: megabytes << 20,
+                                })
+                                .unwrap();
+                            println!(
+                                "[native-runner] history keeps {} steps in {} MB",
+                                entries, megabytes
+                            );
+                        }
+                        "transform" => {
+                            let mut values = tokens.map(|token| token.parse().unwrap());
+                            let mut next = || values.next().unwrap_or(0.0);
+                            let transform = Transform {
+                                translation: [next(), next(), next()],
+                                rotation: [next(), next(), next()],
+                            };
+                            sender.send(FromHost::Transform(transform)).unwrap();
+                            println!("[native-runner] transformed selection");
+                        }
+                        "keymap" => {
+                            let name = tokens.next().unwrap();
+                            let path = format!("{}.json", name);
+                            let json = fs::read_to_string(&path).unwrap();
+                            sender.send(FromHost::Keymap(json)).unwrap();
+                            println!("[native-runner] loading keymap `{}`", path);
+                        }
                         "exit" => {
                             println!("[native-runner] closed stdin");
                             break;
@@ -72,6 +200,11 @@ impl AsyncStdin {
     }
 }
 
+// Remaining words, for names with spaces in them
+fn rest<'a>(tokens: impl Iterator<Item = &'a str>) -> String {
+    tokens.collect::<Vec<_>>().join(" ")
+}
+
 impl Drop for AsyncStdin {
     fn drop(&mut self) {
         self.thread.take().unwrap().join().unwrap()
@@ -1,8 +1,9 @@
 mod comms;
 
 use std::{
+    cell::RefCell,
     fs,
-    sync::mpsc::channel,
+    sync::mpsc::{channel, Sender},
     time::{SystemTime, UNIX_EPOCH},
 };
 
@@ -17,16 +18,24 @@ fn main() {
         sender.send(FromHost::LoadResource(resource)).unwrap();
     }
 
+    let host = NativeHost {
+        sender: sender.clone(),
+        clipboard: RefCell::new(None),
+    };
     let _stdin = AsyncStdin::new(sender);
 
     run(Init {
         winit: winit(),
-        host: Box::new(NativeHost),
+        host: Box::new(host),
         receiver,
     });
 }
 
-pub struct NativeHost;
+pub struct NativeHost {
+    sender: Sender<FromHost>,
+    /// Kept for as long as the runner is open, so it can be pasted into any scene loaded meanwhile
+    clipboard: RefCell<Option<Vec<u8>>>,
+}
 
 impl Host for NativeHost {
     fn callback(&self, data: ToHost) {
@@ -47,6 +56,55 @@ impl Host for NativeHost {
             ToHost::PointerLocked(locked) => {
                 println!("[native-runner] pointer locked: {}", locked);
             }
+            ToHost::Outline(outline) => {
+                for (i, layer) in outline.layers.iter().enumerate() {
+                    println!(
+                        "[native-runner] layer {} `{}`{}{}",
+                        i,
+                        layer.name,
+                        if layer.hidden { " hidden" } else { "" },
+                        if layer.locked { " locked" } else { "" }
+                    );
+                }
+                for group in &outline.groups {
+                    println!(
+                        "[native-runner] group {} `{}` in {:?}",
+                        group.id, group.name, group.parent
+                    );
+                }
+                println!(
+                    "[native-runner] outline has {} objects",
+                    outline.objects.len()
+                );
+            }
+            ToHost::Copied(buf) => {
+                println!("[native-runner] copied {} bytes", buf.len());
+                *self.clipboard.borrow_mut() = Some(buf);
+            }
+            ToHost::History(history) => {
+                println!(
+                    "[native-runner] history at {} of {}, undo {}, redo {}",
+                    history.position,
+                    history.entries.len(),
+                    history.can_undo(),
+                    history.can_redo()
+                );
+                for (i, name) in history.entries.iter().enumerate() {
+                    let marker = if i < history.position { "*" } else { " " };
+                    println!("[native-runner] {} {} {}", marker, i + 1, name);
+                }
+            }
+            ToHost::NumericInput(Some(numeric)) => {
+                println!("[native-runner] {}", numeric);
+            }
+            ToHost::NumericInput(None) => (),
+            ToHost::KeymapError(err) => {
+                println!("[native-runner] keymap rejected: {}", err);
+            }
+            ToHost::PasteRequested => match self.clipboard.borrow().clone() {
+                Some(buf) => self.sender.send(FromHost::Paste(buf)).unwrap(),
+                None => println!("[native-runner] nothing to paste"),
+            },
         }
     }
 }
@@ -2,10 +2,13 @@
 
 use std::sync::mpsc;
 
-use js_sys::{Function, Uint8Array};
+use js_sys::{Array, Function, Object, Reflect, TypeError, Uint8Array};
 use wasm_bindgen::{prelude::*, JsCast};
 
-use app::{builtin_resources, Ascn, FromHost, Host, Init, Resource, ResourceKind, ToHost, Winit};
+use app::{
+    builtin_resources, Ascn, FromHost, Host, Init, Outline, OutlineCommand, Resource, ResourceKind,
+    ToHost, Transform, Winit,
+};
 use winit::{event_loop::EventLoop, platform::web::WindowBuilderExtWebSys, window::WindowBuilder};
 
 #[wasm_bindgen]
@@ -117,6 +120,124 @@ impl Sender {
             }))
             .unwrap();
     }
+
+    #[wasm_bindgen(js_name = "loadLightmap")]
+    pub fn load_lightmap(&self, buf: Vec<u8>) {
+        self.tx
+            .send(FromHost::LoadResource(Resource {
+                id: 0,
+                buf,
+                kind: ResourceKind::Lightmap,
+            }))
+            .unwrap();
+    }
+
+    #[wasm_bindgen(js_name = "setLitPreview")]
+    pub fn set_lit_preview(&self, lit: bool) {
+        self.tx.send(FromHost::LitPreview(lit)).unwrap();
+    }
+
+    #[wasm_bindgen(js_name = "setTextureLock")]
+    pub fn set_texture_lock(&self, lock: bool) {
+        self.tx.send(FromHost::TextureLock(lock)).unwrap();
+    }
+
+    pub fn undo(&self) {
+        self.tx.send(FromHost::Undo).unwrap();
+    }
+
+    pub fn redo(&self) {
+        self.tx.send(FromHost::Redo).unwrap();
+    }
+
+    /// Undoes or redoes until `position` steps can be undone
+    #[wasm_bindgen(js_name = "jumpHistory")]
+    pub fn jump_history(&self, position: usize) {
+        self.tx.send(FromHost::JumpHistory(position)).unwrap();
+    }
+
+    #[wasm_bindgen(js_name = "setHistoryBudget")]
+    pub fn set_history_budget(&self, entries: usize, bytes: usize) {
+        self.tx
+            .send(FromHost::HistoryBudget { entries, bytes })
+            .unwrap();
+    }
+
+    /// Moves the selection by meters and turns selected props by degrees around each axis
+    pub fn transform(&self, x: f32, y: f32, z: f32, rx: f32, ry: f32, rz: f32) {
+        self.tx
+            .send(FromHost::Transform(Transform {
+                translation: [x, y, z],
+                rotation: [rx, ry, rz],
+            }))
+            .unwrap();
+    }
+
+    /// Rebinds commands, for example `{"undo": ["Ctrl+Z"], "redo": ["Ctrl+Y", "Ctrl+Shift+Z"]}`
+    #[wasm_bindgen(js_name = "setKeymap")]
+    pub fn set_keymap(&self, json: String) {
+        self.tx.send(FromHost::Keymap(json)).unwrap();
+    }
+
+    /// Contents of the clipboard in reply to a paste request
+    pub fn paste(&self, buf: Vec<u8>) {
+        self.tx.send(FromHost::Paste(buf)).unwrap();
+    }
+
+    #[wasm_bindgen(js_name = "newLayer")]
+    pub fn new_layer(&self, name: String) {
+        self.outline(OutlineCommand::NewLayer(name));
+    }
+
+    #[wasm_bindgen(js_name = "renameLayer")]
+    pub fn rename_layer(&self, layer: u32, name: String) {
+        self.outline(OutlineCommand::RenameLayer(layer, name));
+    }
+
+    #[wasm_bindgen(js_name = "hideLayer")]
+    pub fn hide_layer(&self, layer: u32, hidden: bool) {
+        self.outline(OutlineCommand::HideLayer(layer, hidden));
+    }
+
+    #[wasm_bindgen(js_name = "lockLayer")]
+    pub fn lock_layer(&self, layer: u32, locked: bool) {
+        self.outline(OutlineCommand::LockLayer(layer, locked));
+    }
+
+    /// Moves the selection off every layer when `layer` is undefined
+    #[wasm_bindgen(js_name = "assignLayer")]
+    pub fn assign_layer(&self, layer: Option<u32>) {
+        self.outline(OutlineCommand::AssignLayer(layer));
+    }
+
+    pub fn group(&self, name: String) {
+        self.outline(OutlineCommand::Group(name));
+    }
+
+    pub fn ungroup(&self, group: u32) {
+        self.outline(OutlineCommand::Ungroup(group));
+    }
+
+    #[wasm_bindgen(js_name = "renameGroup")]
+    pub fn rename_group(&self, group: u32, name: String) {
+        self.outline(OutlineCommand::RenameGroup(group, name));
+    }
+
+    #[wasm_bindgen(js_name = "renameObject")]
+    pub fn rename_object(&self, id: usize, name: String) {
+        self.outline(OutlineCommand::RenameObject(id, name));
+    }
+
+    #[wasm_bindgen(js_name = "selectGroup")]
+    pub fn select_group(&self, group: u32) {
+        self.outline(OutlineCommand::SelectGroup(group));
+    }
+}
+
+impl Sender {
+    fn outline(&self, command: OutlineCommand) {
+        self.tx.send(FromHost::Outline(command)).unwrap();
+    }
 }
 
 #[wasm_bindgen]
@@ -124,18 +245,67 @@ pub struct Callback {
     scene_saved: Function,
     button_feedback: Function,
     pointer_locked: Function,
+    outline_changed: Function,
+    copied: Function,
+    paste_requested: Function,
+    history_changed: Function,
+    numeric_input: Function,
+    keymap_error: Function,
 }
 
 #[wasm_bindgen]
 impl Callback {
     #[allow(clippy::new_without_default)]
     #[wasm_bindgen(constructor)]
-    pub fn new(scene_saved: Function, button_feedback: Function, pointer_locked: Function) -> Self {
-        Self {
+    pub fn new(
+        scene_saved: Function,
+        button_feedback: Function,
+        pointer_locked: Function,
+        outline_changed: Function,
+        copied: Function,
+        paste_requested: Function,
+        history_changed: Function,
+        numeric_input: Function,
+        keymap_error: Function,
+    ) -> Result<Callback, JsValue> {
+        let callback = Self {
             scene_saved,
             button_feedback,
             pointer_locked,
+            outline_changed,
+            copied,
+            paste_requested,
+            history_changed,
+            numeric_input,
+            keymap_error,
+        };
+        // Missing callbacks would only fail once the editor calls them
+        let functions = [
+            ("sceneSaved", &callback.scene_saved),
+            ("buttonFeedback", &callback.button_feedback),
+            ("pointerLocked", &callback.pointer_locked),
+            ("outlineChanged", &callback.outline_changed),
+            ("copied", &callback.copied),
+            ("pasteRequested", &callback.paste_requested),
+            ("historyChanged", &callback.history_changed),
+            ("numericInput", &callback.numeric_input),
+            ("keymapError", &callback.keymap_error),
+        ];
+        for (name, function) in functions {
+            if !function.is_function() {
+                return Err(
+                    TypeError::new(&format!("`{}` callback is not a function", name)).into(),
+                );
+            }
         }
+        Ok(callback)
+    }
+}
+
+// Errors thrown by the host's callbacks are its own, they are logged instead of stopping the editor
+fn report(result: Result<JsValue, JsValue>) {
+    if let Err(err) = result {
+        web_sys::console::error_1(&err);
     }
 }
 
@@ -143,26 +313,117 @@ impl Host for Callback {
     fn callback(&self, data: ToHost) {
         match data {
             ToHost::SceneSaved(id, buf) => {
-                self.scene_saved
-                    .call2(
-                        &JsValue::NULL,
-                        &JsValue::from(id),
-                        &Uint8Array::from(buf.as_slice()),
-                    )
-                    .ok();
+                report(self.scene_saved.call2(
+                    &JsValue::NULL,
+                    &JsValue::from(id),
+                    &Uint8Array::from(buf.as_slice()),
+                ));
             }
             ToHost::Button(button) => {
-                self.button_feedback
-                    .call1(&JsValue::NULL, &JsValue::from(button))
-                    .ok();
+                report(
+                    self.button_feedback
+                        .call1(&JsValue::NULL, &JsValue::from(button)),
+                );
             }
             ToHost::PointerLocked(locked) => {
-                self.pointer_locked
-                    .call1(&JsValue::NULL, &JsValue::from(locked))
-                    .ok();
+                report(
+                    self.pointer_locked
+                        .call1(&JsValue::NULL, &JsValue::from(locked)),
+                );
+            }
+            ToHost::Outline(outline) => {
+                report(
+                    self.outline_changed
+                        .call1(&JsValue::NULL, &outline_object(&outline)),
+                );
+            }
+            ToHost::Copied(buf) => {
+                report(
+                    self.copied
+                        .call1(&JsValue::NULL, &Uint8Array::from(buf.as_slice())),
+                );
+            }
+            ToHost::PasteRequested => {
+                report(self.paste_requested.call0(&JsValue::NULL));
+            }
+            ToHost::History(history) => {
+                let entries = history
+                    .entries
+                    .iter()
+                    .map(|name| JsValue::from(name.as_str()))
+                    .collect::<Array>();
+                report(self.history_changed.call2(
+                    &JsValue::NULL,
+                    &entries,
+                    &JsValue::from(history.position),
+                ));
             }
+            ToHost::NumericInput(numeric) => {
+                // null once the tool ends
+                let numeric = numeric.map_or(JsValue::NULL, JsValue::from);
+                report(self.numeric_input.call1(&JsValue::NULL, &numeric));
+            }
+            ToHost::KeymapError(err) => {
+                report(
+                    self.keymap_error
+                        .call1(&JsValue::NULL, &JsValue::from(err.as_str())),
+                );
+            }
+        }
+    }
+}
+
+// Plain object with `layers`, `groups` and `objects` arrays, groups and layers are referenced by id and index
+fn outline_object(outline: &Outline) -> JsValue {
+    fn object(fields: &[(&str, JsValue)]) -> JsValue {
+        let object = Object::new();
+        for (key, value) in fields {
+            Reflect::set(&object, &JsValue::from(*key), value).ok();
         }
+        object.into()
     }
+
+    let layers = outline
+        .layers
+        .iter()
+        .map(|layer| {
+            object(&[
+                ("name", JsValue::from(layer.name.as_str())),
+                ("hidden", JsValue::from(layer.hidden)),
+                ("locked", JsValue::from(layer.locked)),
+            ])
+        })
+        .collect::<Array>();
+    let groups = outline
+        .groups
+        .iter()
+        .map(|group| {
+            object(&[
+                ("id", JsValue::from(group.id)),
+                ("name", JsValue::from(group.name.as_str())),
+                ("parent", JsValue::from(group.parent)),
+            ])
+        })
+        .collect::<Array>();
+    let objects = outline
+        .objects
+        .iter()
+        .map(|object_| {
+            object(&[
+                ("id", JsValue::from(object_.id)),
+                ("prop", JsValue::from(object_.prop)),
+                ("name", JsValue::from(object_.name.as_str())),
+                ("layer", JsValue::from(object_.layer)),
+                ("group", JsValue::from(object_.group)),
+            ])
+        })
+        .collect::<Array>();
+
+    object(&[
+        ("layers", layers.into()),
+        ("groups", groups.into()),
+        ("objects", objects.into()),
+    ])
 }
 
 fn winit() -> Winit {
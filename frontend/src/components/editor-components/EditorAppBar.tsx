@@ -28,9 +28,26 @@ const CustomEditorAppBar = styled(AppBar)(({ theme }) => ({
 interface EditorAppBarProps {
   onSave: () => Promise<void>;
   onRender: (width: number, height: number, samples: number) => Promise<void>;
+  onBake: () => Promise<void>;
+  litPreview: boolean;
+  onLitPreviewChange: (lit: boolean) => void;
+  canUndo: boolean;
+  canRedo: boolean;
+  onUndo: () => void;
+  onRedo: () => void;
 }
 
-export default function EditorAppBar({ onSave, onRender }: EditorAppBarProps) {
+export default function EditorAppBar({
+  onSave,
+  onRender,
+  onBake,
+  litPreview,
+  onLitPreviewChange,
+  canUndo,
+  canRedo,
+  onUndo,
+  onRedo,
+}: EditorAppBarProps) {
   const { t } = useTranslation();
   const tooltipText: string =
     t("archytex") + " " + t("version") + " " + t("version_number");
@@ -78,6 +95,24 @@ export default function EditorAppBar({ onSave, onRender }: EditorAppBarProps) {
                 >
                   {t("save")}
                 </Button>
+                <Button
+                  variant='text'
+                  color='inherit'
+                  sx={{ textTransform: "none" }}
+                  disabled={!canUndo}
+                  onClick={onUndo}
+                >
+                  {t("undo")}
+                </Button>
+                <Button
+                  variant='text'
+                  color='inherit'
+                  sx={{ textTransform: "none" }}
+                  disabled={!canRedo}
+                  onClick={onRedo}
+                >
+                  {t("redo")}
+                </Button>
                 <Button
                   variant='text'
                   color='inherit'
@@ -86,6 +121,22 @@ export default function EditorAppBar({ onSave, onRender }: EditorAppBarProps) {
                 >
                   {t("render")}
                 </Button>
+                <Button
+                  variant='text'
+                  color='inherit'
+                  sx={{ textTransform: "none" }}
+                  onClick={() => onBake()}
+                >
+                  {t("bake_lighting")}
+                </Button>
+                <Button
+                  variant='text'
+                  color={litPreview ? "primary" : "inherit"}
+                  sx={{ textTransform: "none" }}
+                  onClick={() => onLitPreviewChange(!litPreview)}
+                >
+                  {t("lit_preview")}
+                </Button>
                 <Button
                   variant='text'
                   color='inherit'
@@ -6,8 +6,18 @@ import Box from "@mui/material/Box";
 import Button from "@mui/material/Button";
 import Typography from "@mui/material/Typography";
 import Divider from "@mui/material/Divider";
-
-import { Chair, Settings, Texture as TextureIcon } from "@mui/icons-material";
+import IconButton from "@mui/material/IconButton";
+
+import {
+  Chair,
+  Layers,
+  Lock,
+  LockOpen,
+  Settings,
+  Texture as TextureIcon,
+  Visibility,
+  VisibilityOff,
+} from "@mui/icons-material";
 
 import LibraryDialog from "./library/LibraryDialog";
 
@@ -26,6 +36,9 @@ interface Props {
   handlePropChange: (prop: Prop) => void;
   textures: Texture[];
   props: Prop[];
+  layers: { name: string; hidden: boolean; locked: boolean }[];
+  onHideLayer: (layer: number, hidden: boolean) => void;
+  onLockLayer: (layer: number, locked: boolean) => void;
 }
 
 export default function EditorMenu({
@@ -35,6 +48,9 @@ export default function EditorMenu({
   handlePropChange,
   textures,
   props,
+  layers,
+  onHideLayer,
+  onLockLayer,
 }: Props) {
   const { t } = useTranslation();
 
@@ -154,6 +170,35 @@ export default function EditorMenu({
             </Button>
           </Box>
         </Box>
+
+        <Divider />
+
+        <Box display='flex' p={1}>
+          <Layers />
+          <Typography ml={1}>{t("layers")}</Typography>
+        </Box>
+        {layers.map((layer, index) => (
+          <Box key={index} display='flex' alignItems='center' px={1}>
+            <Typography
+              flexGrow={1}
+              color={layer.hidden ? "text.disabled" : undefined}
+            >
+              {layer.name}
+            </Typography>
+            <IconButton
+              size='small'
+              onClick={() => onHideLayer(index, !layer.hidden)}
+            >
+              {layer.hidden ? <VisibilityOff /> : <Visibility />}
+            </IconButton>
+            <IconButton
+              size='small'
+              onClick={() => onLockLayer(index, !layer.locked)}
+            >
+              {layer.locked ? <Lock /> : <LockOpen />}
+            </IconButton>
+          </Box>
+        ))}
       </Box>
 
       {/* Library dialog */}
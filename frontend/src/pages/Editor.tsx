@@ -22,11 +22,60 @@ import Environment from "../env";
 
 type EditorMode = "solid" | "face" | "vertex" | "prop";
 
+interface Layer {
+  name: string;
+  hidden: boolean;
+  locked: boolean;
+}
+
+interface History {
+  entries: string[];
+  position: number;
+}
+
 let current_event = 0;
 let listeners: { [key: number]: (value: Uint8Array) => void } = {};
 let rightDown = false;
 let loadedTextures = new Set<number>();
 let loadedProps = new Set<number>();
+// Last copy of this tab, pasted when the system clipboard can't be read
+let clipboard: Uint8Array | null = null;
+
+// Samples per lightmap texel, and how long to wait for a bake
+const BAKE_SAMPLES = 64;
+const BAKE_POLL_INTERVAL = 5000;
+const BAKE_POLL_ATTEMPTS = 120;
+
+// Copied elements go through the system clipboard as text, so they can be pasted
+// into other projects and tabs
+const CLIPBOARD_PREFIX = "archytex:";
+
+function writeClipboard(buf: Uint8Array) {
+  clipboard = buf;
+  let binary = "";
+  buf.forEach((byte) => (binary += String.fromCharCode(byte)));
+  navigator.clipboard
+    ?.writeText(CLIPBOARD_PREFIX + btoa(binary))
+    .catch(() => {});
+}
+
+async function readClipboard(): Promise<Uint8Array | null> {
+  let text;
+  try {
+    text = await navigator.clipboard.readText();
+  } catch {
+    return clipboard;
+  }
+  if (!text.startsWith(CLIPBOARD_PREFIX)) {
+    return null;
+  }
+  try {
+    const binary = atob(text.slice(CLIPBOARD_PREFIX.length));
+    return Uint8Array.from(binary, (c) => c.charCodeAt(0));
+  } catch {
+    return null;
+  }
+}
 
 export default function Editor() {
   const { t } = useTranslation();
@@ -119,6 +168,14 @@ export default function Editor() {
     }
   }, [prop, sender, textures]);
 
+  // Scene state reported by the viewport
+  const [layers, setLayers] = useState<Layer[]>([]);
+  const [history, setHistory] = useState<History>({
+    entries: [],
+    position: 0,
+  });
+  const [numericInput, setNumericInput] = useState<string | null>(null);
+
   const [width, setWidth] = useState(1);
   const [height, setHeight] = useState(1);
 
@@ -141,7 +198,8 @@ export default function Editor() {
   useEffect(() => {
     import("viewport").then((viewport) => {
       const channel = new viewport.Channel();
-      setSender(channel.sender());
+      const sender = channel.sender();
+      setSender(sender);
 
       const callback = new viewport.Callback(
         (id: number, scene: Uint8Array) => {
@@ -179,6 +237,28 @@ export default function Editor() {
             document.exitPointerLock();
             rightDown = false;
           }
+        },
+        (outline: { layers: Layer[] }) => {
+          setLayers(outline.layers);
+        },
+        (buf: Uint8Array) => {
+          writeClipboard(buf);
+        },
+        () => {
+          readClipboard().then((buf) => {
+            if (buf !== null) {
+              sender.paste(buf);
+            }
+          });
+        },
+        (entries: string[], position: number) => {
+          setHistory({ entries, position });
+        },
+        (numeric: string | null) => {
+          setNumericInput(numeric);
+        },
+        (error: string) => {
+          addNotification(`${t("invalid_keymap")}: ${error}`, "error");
         }
       );
       setVp(viewport);
@@ -282,6 +362,30 @@ export default function Editor() {
     })();
   }, [api, sender, textures, props, vp]);
 
+  // Baked lighting, shown once the raytracer has finished it
+  const [litPreview, setLitPreview] = useState(false);
+  useEffect(() => {
+    if (sender !== null) {
+      sender.setLitPreview(litPreview);
+    }
+  }, [litPreview, sender]);
+
+  const loadLightmap = useCallback(async () => {
+    if (api?.state !== "logged-in" || sender === null) {
+      return false;
+    }
+    const lightmap = await api.lightmap(projectId);
+    if (lightmap === undefined) {
+      return false;
+    }
+    sender.loadLightmap(lightmap);
+    return true;
+  }, [api, sender, projectId]);
+
+  useEffect(() => {
+    loadLightmap().catch(() => {});
+  }, [loadLightmap]);
+
   let save = useCallback(
     () =>
       new Promise((resolve: (value: Uint8Array) => void) => {
@@ -303,6 +407,34 @@ export default function Editor() {
     }
   };
 
+  const onBake = async () => {
+    if (api?.state === "logged-in") {
+      addNotification(t("baking_started"), "info");
+      const data = await save();
+      try {
+        await api.bake(data, projectId, BAKE_SAMPLES);
+      } catch {
+        addNotification(t("could_not_bake"), "error");
+        return;
+      }
+      let attempts = 0;
+      const poll = setInterval(async () => {
+        attempts++;
+        const loaded = await loadLightmap().catch(() => false);
+        if (loaded) {
+          clearInterval(poll);
+          setLitPreview(true);
+          addNotification(t("lightmap_loaded"), "success");
+        } else if (attempts >= BAKE_POLL_ATTEMPTS) {
+          clearInterval(poll);
+          addNotification(t("could_not_bake"), "error");
+        }
+      }, BAKE_POLL_INTERVAL);
+    } else {
+      addNotification(t("not_logged_in"), "error");
+    }
+  };
+
   // App bar button click
   const handleSaveProject = async () => {
     console.log("Got Save event");
@@ -360,7 +492,17 @@ export default function Editor() {
 
   return (
     <>
-      <EditorAppBar onSave={handleSaveProject} onRender={onRender} />
+      <EditorAppBar
+        onSave={handleSaveProject}
+        onRender={onRender}
+        onBake={onBake}
+        litPreview={litPreview}
+        onLitPreviewChange={setLitPreview}
+        canUndo={history.position > 0}
+        canRedo={history.position < history.entries.length}
+        onUndo={() => sender?.undo()}
+        onRedo={() => sender?.redo()}
+      />
       <Box width='100%' height='48px'></Box>
       <Box display='flex' height={`calc(100vh - 48px)`} overflow='hidden'>
         <Box width='100%' height='100%' ref={observe} bgcolor='#0c0c0c' />
@@ -372,6 +514,9 @@ export default function Editor() {
             handlePropChange={handlePropChange}
             textures={textures}
             props={props}
+            layers={layers}
+            onHideLayer={(layer, hidden) => sender?.hideLayer(layer, hidden)}
+            onLockLayer={(layer, locked) => sender?.lockLayer(layer, locked)}
           />
         )}
       </Box>
@@ -388,6 +533,18 @@ export default function Editor() {
       ></canvas>
 
       {/* Viewport UI */}
+      {numericInput !== null && (
+        <Box
+          position='absolute'
+          bottom={16}
+          left={16}
+          px={1}
+          borderRadius={1}
+          bgcolor='background.paper'
+        >
+          <Typography fontFamily='monospace'>{numericInput}</Typography>
+        </Box>
+      )}
       <EditorModeButtons
         editorMode={editorMode}
         handleEditorModeChange={handleEditorModeChange}
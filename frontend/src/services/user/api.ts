@@ -26,6 +26,8 @@ interface UserLoggedIn {
   save: (data: Uint8Array, id: string) => Promise<void>;
   load: (id: string) => Promise<Uint8Array | undefined>;
   render: (data: Uint8Array, id: string, width: number, height: number, samples: number) => Promise<void>;
+  bake: (data: Uint8Array, id: string, samples: number) => Promise<void>;
+  lightmap: (id: string) => Promise<Uint8Array | undefined>;
   modifyUser: (data: ModifyUserType) => Promise<void>;
 }
 interface UserNotLoggedIn {
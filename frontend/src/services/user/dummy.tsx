@@ -88,6 +88,12 @@ const DummyProvider = ({
           render: async (data: Uint8Array, id: string) => {
             console.log(data.length);
           },
+          bake: async (data: Uint8Array, id: string) => {
+            console.log(data.length);
+          },
+          lightmap: async (id: string) => {
+            return undefined;
+          },
           load: async(id: string) => {
             return undefined;
           },
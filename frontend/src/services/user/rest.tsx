@@ -239,6 +239,24 @@ const RestProvider = ({
           headers: { "Content-Type": "application/octet-stream" },
         });
       },
+      bake: async (data: Uint8Array, id: string, samples: number) => {
+        await internal.fetch(`${Environment.base_url}auth/project/${id}/lightmap/${samples}`, {
+          method: "POST",
+          body: data,
+          headers: { "Content-Type": "application/octet-stream" },
+        });
+      },
+      lightmap: async (id: string) => {
+        const result = await internal.fetch(
+          `${Environment.base_url}auth/project/${id}/lightmap`,
+          {
+            method: "GET",
+          }
+        );
+        // Empty until a bake has finished
+        const lightmap = new Uint8Array(await result.arrayBuffer());
+        return lightmap.length > 0 ? lightmap : undefined;
+      },
       load: async (id: string) => {
         const result = await internal.fetch(
           `${Environment.base_url}auth/project/${id}/data`,
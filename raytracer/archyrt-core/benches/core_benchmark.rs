@@ -1,13 +1,14 @@
 use archyrt_core::{
     api::fragment_collector::FragmentCollector,
     collector::image_collector::ImageCollector,
-    intersectables::sphere::Sphere,
-    renderers::basic_renderer::BasicRenderer,
-    textures::texture_repo::TextureRepository,
+    intersectables::{bvh::BVH, packet::PacketBVH, sphere::Sphere, triangle::Triangle},
+    renderers::{basic_renderer::BasicRenderer, path_tracer::Material},
+    textures::{texture_repo::TextureRepository, TextureID},
     utilities::{
-        math::Vec3,
+        math::{Float, Vec3},
         ray::{Intersectable, Ray},
     },
+    vector,
 };
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
@@ -23,12 +24,57 @@ pub fn sphere_ray_intersection(c: &mut Criterion) {
             let ray = Ray {
                 origin: Vec3::from_single(0.0),
                 direction: Vec3::new(0.0, 0.0, 1.0),
+                ..Default::default()
             };
             sphere.intersect(ray).unwrap();
         })
     });
 }
 
+fn triangle_grid() -> Vec<Triangle> {
+    let uv = [vector![0.0, 0.0], vector![0.0, 1.0], vector![1.0, 0.0]];
+    let mut triangles = Vec::new();
+    for x in -32..32 {
+        for z in -32..32 {
+            let (x, z) = (x as Float, z as Float);
+            let a = Vec3::new(x, -1.0, z);
+            let b = Vec3::new(x + 1.0, -1.0, z);
+            let c = Vec3::new(x, -1.0, z + 1.0);
+            triangles.push(Triangle::new([a, c, b], uv, TextureID::new(&0), Material::Diffuse));
+        }
+    }
+    triangles
+}
+
+pub fn triangle_ray_intersection(c: &mut Criterion) {
+    let triangles = triangle_grid();
+    let bvh = BVH::from_triangles(&triangles).unwrap();
+    let packet4 = PacketBVH::<4>::from_triangles(&triangles).unwrap();
+    let packet8 = PacketBVH::<8>::from_triangles(&triangles).unwrap();
+    let ray = Ray {
+        origin: Vec3::new(0.3, 2.0, 0.3),
+        direction: Vec3::new(0.2, -1.0, 0.1).normalized(),
+        ..Default::default()
+    };
+    c.bench_function("bvh-ray", |b| b.iter(|| black_box(bvh.intersect(ray)).unwrap()));
+    c.bench_function("packet4-bvh-ray", |b| b.iter(|| black_box(packet4.intersect(ray)).unwrap()));
+    c.bench_function("packet8-bvh-ray", |b| b.iter(|| black_box(packet8.intersect(ray)).unwrap()));
+}
+
+//Compare `cargo bench` with `cargo bench --features single-precision`
+pub fn precision(c: &mut Criterion) {
+    let triangles = triangle_grid();
+    println!(
+        "{} bit floats: Vec3 is {} bytes, Triangle is {} bytes, {} triangles are {} bytes",
+        std::mem::size_of::<Float>() * 8,
+        std::mem::size_of::<Vec3>(),
+        std::mem::size_of::<Triangle>(),
+        triangles.len(),
+        std::mem::size_of_val(triangles.as_slice())
+    );
+    c.bench_function("bvh-build", |b| b.iter(|| black_box(BVH::from_triangles(&triangles)).unwrap()));
+}
+
 pub fn rendering(c: &mut Criterion) {
     c.bench_function("rendering", |b| {
         b.iter(|| {
@@ -43,5 +89,5 @@ pub fn rendering(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, sphere_ray_intersection, rendering);
+criterion_group!(benches, sphere_ray_intersection, triangle_ray_intersection, precision, rendering);
 criterion_main!(benches);
@@ -1,11 +1,17 @@
 use crate::{
-    utilities::{math::Vec2, ray::Ray},
+    utilities::{math::{Float, Vec2}, ray::Ray},
 };
 
 use super::fragment_render::FragmentContext;
 
 pub trait Camera {
     fn get_ray(&self, ctx: &FragmentContext, pos: Vec2) -> Ray;
+    /// Generates a ray at a given point in time. Cameras that move during the shutter interval override this.
+    fn get_ray_at(&self, ctx: &FragmentContext, pos: Vec2, time: Float) -> Ray {
+        let mut ray = self.get_ray(ctx, pos);
+        ray.time = time;
+        ray
+    }
 }
 
 impl<T> Camera for &T
@@ -15,4 +21,7 @@ where
     fn get_ray(&self, ctx: &FragmentContext, pos: Vec2) -> Ray {
         (*self).get_ray(ctx, pos)
     }
+    fn get_ray_at(&self, ctx: &FragmentContext, pos: Vec2, time: Float) -> Ray {
+        (*self).get_ray_at(ctx, pos, time)
+    }
 }
@@ -1,12 +1,21 @@
 use crate::{
-    textures::texture_repo::TextureRepository,
-    utilities::math::{Vec2, Vec3},
+    textures::texture_repo::TextureHandles,
+    utilities::math::{Float, Vec2, Vec3},
+    vector,
 };
 
 pub struct FragmentContext<'a> {
-    pub width: f64,
-    pub height: f64,
-    pub repo: &'a TextureRepository,
+    pub width: Float,
+    pub height: Float,
+    pub textures: &'a TextureHandles<'a>,
+}
+
+impl FragmentContext<'_> {
+    /// Position of a pixel in the 0 to 1 range renderers take, the only pixel of a 1 pixel wide or high image is in the middle
+    pub fn to_unit(&self, x: Float, y: Float) -> Vec2 {
+        let unit = |pixel: Float, size: Float| if size > 1.0 { pixel / (size - 1.0) } else { 0.5 };
+        vector![unit(x, self.width), unit(y, self.height)]
+    }
 }
 
 pub trait FragmentRender {
@@ -18,3 +27,9 @@ impl<T: FragmentRender> FragmentRender for &T {
         (*self).render_fragment(ctx, pos)
     }
 }
+
+impl<T: FragmentRender + ?Sized> FragmentRender for Box<T> {
+    fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+        self.as_ref().render_fragment(ctx, pos)
+    }
+}
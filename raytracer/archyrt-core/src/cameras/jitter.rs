@@ -1,13 +1,13 @@
-use rand::thread_rng;
-use rand_distr::{Uniform, Distribution};
+use rand_distr::Uniform;
 
 use crate::utilities::math::Matrix3x3;
 use crate::{
     api::{camera::Camera, fragment_render::FragmentContext},
     matrix,
     utilities::{
-        math::{Matrix, Vec2, Vec3},
+        math::{Float, Matrix, Vec2, Vec3},
         ray::Ray,
+        rng,
     },
     vector,
 };
@@ -15,14 +15,14 @@ use crate::{
 #[derive(Debug, Clone)]
 pub struct JitterCamera<C: Camera> {
     pub inner: C,
-    pub x_dist: Uniform<f64>,
-    pub y_dist: Uniform<f64>,
+    pub x_dist: Uniform<Float>,
+    pub y_dist: Uniform<Float>,
 }
 
 impl<C: Camera> JitterCamera<C> {
     pub fn new(inner: C, width: usize, height: usize) -> Self {
-        let width = 1.0/(width as f64)*0.5;
-        let height = 1.0/(height as f64)*0.5;
+        let width = 1.0/(width as Float)*0.5;
+        let height = 1.0/(height as Float)*0.5;
         let x_dist = Uniform::new_inclusive(-width, width);
         let y_dist = Uniform::new_inclusive(-height, height);
         Self {
@@ -33,10 +33,15 @@ impl<C: Camera> JitterCamera<C> {
 
 impl<C: Camera> Camera for JitterCamera<C> {
     fn get_ray(&self, ctx: &FragmentContext, pos: Vec2) -> Ray {
-        let mut rng = thread_rng();
-        let x: f64 = self.x_dist.sample(&mut rng);
-        let y: f64 = self.y_dist.sample(&mut rng);
+        let x: Float = rng::sample(&self.x_dist);
+        let y: Float = rng::sample(&self.y_dist);
         let jitter = vector![x, y];
         self.inner.get_ray(ctx, pos + jitter)
     }
+    fn get_ray_at(&self, ctx: &FragmentContext, pos: Vec2, time: Float) -> Ray {
+        let x: Float = rng::sample(&self.x_dist);
+        let y: Float = rng::sample(&self.y_dist);
+        let jitter = vector![x, y];
+        self.inner.get_ray_at(ctx, pos + jitter, time)
+    }
 }
@@ -1,2 +1,4 @@
 pub mod perspective;
 pub mod jitter;
+pub mod motion;
+pub mod shutter;
@@ -0,0 +1,62 @@
+use cgmath::{Matrix3, Quaternion};
+
+use crate::{
+    api::{camera::Camera, fragment_render::FragmentContext},
+    utilities::{
+        math::{Float, Matrix3x3, Vec2},
+        ray::Ray,
+    },
+};
+
+use super::perspective::PerspectiveCamera;
+
+/// Camera moving between two poses during the shutter interval
+#[derive(Debug, Clone)]
+pub struct MotionCamera {
+    pub start: PerspectiveCamera,
+    pub end: PerspectiveCamera,
+    pub start_time: Float,
+    pub end_time: Float,
+}
+
+impl MotionCamera {
+    pub fn new(start: PerspectiveCamera, end: PerspectiveCamera) -> Self {
+        Self {
+            start,
+            end,
+            start_time: 0.0,
+            end_time: 1.0,
+        }
+    }
+
+    /// Interpolates the pose of the camera at a given point in time
+    pub fn at(&self, time: Float) -> PerspectiveCamera {
+        let span = self.end_time - self.start_time;
+        let t = if span.abs() > 0.0 {
+            ((time - self.start_time) / span).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let matrix = if self.start.matrix == self.end.matrix {
+            self.start.matrix
+        } else {
+            let a: Quaternion<Float> = Matrix3::from(self.start.matrix).into();
+            let b: Quaternion<Float> = Matrix3::from(self.end.matrix).into();
+            Matrix3x3::from(a.slerp(b, t))
+        };
+        PerspectiveCamera {
+            matrix,
+            position: self.start.position.lerp(self.end.position, t),
+            focal_distance: self.start.focal_distance * (1.0 - t) + self.end.focal_distance * t,
+        }
+    }
+}
+
+impl Camera for MotionCamera {
+    fn get_ray(&self, ctx: &FragmentContext, pos: Vec2) -> Ray {
+        self.get_ray_at(ctx, pos, self.start_time)
+    }
+    fn get_ray_at(&self, ctx: &FragmentContext, pos: Vec2, time: Float) -> Ray {
+        self.at(time).get_ray_at(ctx, pos, time)
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
     api::{camera::Camera, fragment_render::FragmentContext},
     matrix,
     utilities::{
-        math::{Matrix, Vec2, Vec3},
+        math::{Float, Matrix, Vec2, Vec3},
         ray::Ray,
     },
     vector,
@@ -13,11 +13,11 @@ use crate::{
 pub struct PerspectiveCamera {
     pub matrix: Matrix<3, 3>,
     pub position: Vec3,
-    pub focal_distance: f64,
+    pub focal_distance: Float,
 }
 
 impl PerspectiveCamera {
-    pub fn new(position: Vec3, direction: Vec3, focal_distance: f64) -> Self {
+    pub fn new(position: Vec3, direction: Vec3, focal_distance: Float) -> Self {
         Self {
             matrix: Self::look_at_matrix(direction),
             position,
@@ -31,7 +31,7 @@ impl PerspectiveCamera {
         matrix!(left, up, forward)
     }
 
-    pub fn from_euler(position: Vec3, euler_direction: Vec3, focal_distance: f64) -> Self {
+    pub fn from_euler(position: Vec3, euler_direction: Vec3, focal_distance: Float) -> Self {
         Self {
             position,
             focal_distance,
@@ -55,9 +55,6 @@ impl Camera for PerspectiveCamera {
         let dir = vector!(uv.x(), uv.y(), self.focal_distance).normalized();
         //Apply rotation matrix
         let dir = self.matrix * dir;
-        Ray {
-            origin: self.position,
-            direction: dir,
-        }
+        Ray::new(self.position, dir)
     }
 }
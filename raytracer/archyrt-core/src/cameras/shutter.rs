@@ -0,0 +1,40 @@
+use rand_distr::Uniform;
+
+use crate::{
+    api::{camera::Camera, fragment_render::FragmentContext},
+    utilities::{math::{Float, Vec2}, ray::Ray, rng},
+};
+
+/// Samples a point in time within the shutter interval for every generated ray
+#[derive(Debug, Clone)]
+pub struct ShutterCamera<C: Camera> {
+    pub inner: C,
+    pub open: Float,
+    /// None if the shutter closes as soon as it opens, rays are then cast when it opens
+    pub time_dist: Option<Uniform<Float>>,
+}
+
+impl<C: Camera> ShutterCamera<C> {
+    pub fn new(inner: C, open: Float, close: Float) -> Self {
+        let (open, close) = (open.min(close), open.max(close));
+        let time_dist = (close > open).then(|| Uniform::new_inclusive(open, close));
+        Self {
+            inner,
+            open,
+            time_dist,
+        }
+    }
+}
+
+impl<C: Camera> Camera for ShutterCamera<C> {
+    fn get_ray(&self, ctx: &FragmentContext, pos: Vec2) -> Ray {
+        let time = match &self.time_dist {
+            Some(time_dist) => rng::sample(time_dist),
+            None => self.open,
+        };
+        self.inner.get_ray_at(ctx, pos, time)
+    }
+    fn get_ray_at(&self, ctx: &FragmentContext, pos: Vec2, time: Float) -> Ray {
+        self.inner.get_ray_at(ctx, pos, time)
+    }
+}
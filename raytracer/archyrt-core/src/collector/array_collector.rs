@@ -4,8 +4,7 @@ use crate::{
         fragment_render::{FragmentContext, FragmentRender},
     },
     textures::texture_repo::TextureRepository,
-    utilities::math::Vec3,
-    vector,
+    utilities::math::{Float, Vec3},
 };
 
 pub struct ArrayCollector {}
@@ -19,18 +18,18 @@ impl<T: FragmentRender> FragmentCollector<T> for ArrayCollector {
         width: usize,
         height: usize,
     ) -> Self::Output {
+        let textures = texture_repo.handles();
         let ctx = FragmentContext {
             width: width as _,
             height: height as _,
-            repo: texture_repo,
+            textures: &textures,
         };
         let mut rows = Vec::with_capacity(height);
         for y in 0..height {
             let mut row = Vec::with_capacity(width);
-            let y = y as f64 / (ctx.height - 1.0);
             for x in 0..width {
-                let x = x as f64 / (ctx.width - 1.0);
-                let fragment = fragment_render.render_fragment(&ctx, vector!(x, y));
+                let pos = ctx.to_unit(x as Float, y as Float);
+                let fragment = fragment_render.render_fragment(&ctx, pos);
                 row.push(fragment);
             }
             rows.push(row);
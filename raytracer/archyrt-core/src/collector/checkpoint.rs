@@ -0,0 +1,304 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    api::fragment_render::{FragmentContext, FragmentRender},
+    textures::texture_repo::TextureRepository,
+    utilities::{math::Float, rng},
+};
+
+const MAGIC: &[u8; 4] = b"ARCP";
+const VERSION: u32 = 3;
+
+/// One sample of one tile
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileSample {
+    pub tile_x: usize,
+    pub tile_y: usize,
+    /// Which sample of the tile it is, the random numbers are seeded from it
+    pub index: u32,
+}
+
+/// Sum of the samples rendered so far, split into tiles that can be sampled independently.
+/// Every sample reseeds the random numbers from the render seed, the tile and the sample index,
+/// so the seed and the finished sample indices are all the random state needed to continue a render.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accumulator {
+    pub width: usize,
+    pub height: usize,
+    pub tiles_x: usize,
+    pub tiles_y: usize,
+    pub seed: u64,
+    /// Hash of the scene and settings the render was started with, set by the caller.
+    /// A checkpoint only continues the render whose key it has.
+    pub key: u64,
+    //RGB rows of the full image
+    radiance: Vec<f32>,
+    //Indices of the samples added to each tile as bitsets, row by row
+    finished: Vec<Vec<u64>>,
+}
+
+//SplitMix64 finalizer, neighbouring samples get unrelated seeds
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        if self.bytes.len() < N {
+            return Err(anyhow!("Checkpoint is truncated"));
+        }
+        let (value, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(value.try_into().unwrap())
+    }
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+    fn usize(&mut self) -> Result<usize> {
+        Ok(self.u32()? as usize)
+    }
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+}
+
+impl Accumulator {
+    /// The image is split as evenly as possible, tiles differ in size by at most a pixel
+    pub fn new(width: usize, height: usize, tiles_x: usize, tiles_y: usize, seed: u64) -> Result<Self> {
+        if tiles_x == 0 || tiles_y == 0 {
+            return Err(anyhow!("The image has to be split into at least one tile along each axis"));
+        }
+        Ok(Self {
+            width,
+            height,
+            tiles_x,
+            tiles_y,
+            seed,
+            key: 0,
+            radiance: vec![0.0; width * height * 3],
+            finished: vec![Vec::new(); tiles_x * tiles_y],
+        })
+    }
+    /// Top left pixel and size of a tile
+    pub fn tile_rect(&self, tile_x: usize, tile_y: usize) -> (usize, usize, usize, usize) {
+        let x = tile_x * self.width / self.tiles_x;
+        let y = tile_y * self.height / self.tiles_y;
+        let w = (tile_x + 1) * self.width / self.tiles_x - x;
+        let h = (tile_y + 1) * self.height / self.tiles_y - y;
+        (x, y, w, h)
+    }
+    /// Tile that starts at the given pixel
+    pub fn tile_at(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let tile_x = (0..self.tiles_x).find(|&tile_x| self.tile_rect(tile_x, 0).0 == x)?;
+        let tile_y = (0..self.tiles_y).find(|&tile_y| self.tile_rect(0, tile_y).1 == y)?;
+        Some((tile_x, tile_y))
+    }
+    pub fn samples(&self, tile_x: usize, tile_y: usize) -> u32 {
+        let finished = &self.finished[tile_y * self.tiles_x + tile_x];
+        finished.iter().map(|word| word.count_ones()).sum()
+    }
+    /// Whether the sample was added already
+    pub fn is_finished(&self, sample: TileSample) -> bool {
+        let finished = &self.finished[sample.tile_y * self.tiles_x + sample.tile_x];
+        let word = finished.get(sample.index as usize / 64).copied().unwrap_or(0);
+        word & (1 << (sample.index % 64)) != 0
+    }
+    /// Samples every tile has at least
+    pub fn min_samples(&self) -> u32 {
+        self.tiles()
+            .map(|(tile_x, tile_y)| self.samples(tile_x, tile_y))
+            .min()
+            .unwrap_or(0)
+    }
+    /// Samples rendered over all tiles
+    pub fn total_samples(&self) -> u64 {
+        self.tiles()
+            .map(|(tile_x, tile_y)| self.samples(tile_x, tile_y) as u64)
+            .sum()
+    }
+    fn tiles(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.tiles_y).flat_map(move |tile_y| (0..self.tiles_x).map(move |tile_x| (tile_x, tile_y)))
+    }
+    /// Samples with an index below the given amount that weren't added yet, one round of all tiles after another.
+    /// Samples can finish out of order, so a tile may miss an earlier sample while having later ones.
+    pub fn pending(&self, samples: u32) -> Vec<TileSample> {
+        (0..samples)
+            .flat_map(|index| {
+                self.tiles().map(move |(tile_x, tile_y)| TileSample {
+                    tile_x,
+                    tile_y,
+                    index,
+                })
+            })
+            .filter(|&sample| !self.is_finished(sample))
+            .collect()
+    }
+    pub fn sample_seed(&self, sample: TileSample) -> u64 {
+        let tile = (sample.tile_y * self.tiles_x + sample.tile_x) as u64;
+        mix(mix(self.seed ^ tile) ^ sample.index as u64)
+    }
+    /// Renders one sample of a tile on the current thread, RGB rows of the tile
+    pub fn render_sample<T: FragmentRender>(
+        &self,
+        fragment_render: &T,
+        texture_repo: &TextureRepository,
+        sample: TileSample,
+    ) -> Vec<f32> {
+        let textures = texture_repo.handles();
+        let ctx = FragmentContext {
+            width: self.width as _,
+            height: self.height as _,
+            textures: &textures,
+        };
+        let (x0, y0, w, h) = self.tile_rect(sample.tile_x, sample.tile_y);
+        rng::seed(self.sample_seed(sample));
+        let mut output = Vec::with_capacity(w * h * 3);
+        for y in y0..y0 + h {
+            for x in x0..x0 + w {
+                let pos = ctx.to_unit(x as Float, y as Float);
+                let fragment = fragment_render.render_fragment(&ctx, pos);
+                output.extend(fragment.inner.iter().map(|&c| c as f32));
+            }
+        }
+        output
+    }
+    /// Adds a sample of a tile given as RGB rows, every sample is only added once
+    pub fn add(&mut self, sample: TileSample, data: &[f32]) -> Result<()> {
+        let TileSample { tile_x, tile_y, index } = sample;
+        if tile_x >= self.tiles_x || tile_y >= self.tiles_y {
+            return Err(anyhow!("Tile ({}, {}) is outside of the image", tile_x, tile_y));
+        }
+        if self.is_finished(sample) {
+            return Err(anyhow!("Sample {} of tile ({}, {}) was already added", index, tile_x, tile_y));
+        }
+        let (x0, y0, w, h) = self.tile_rect(tile_x, tile_y);
+        if data.len() != w * h * 3 {
+            return Err(anyhow!(
+                "Expected {} values for a tile, got {}",
+                w * h * 3,
+                data.len()
+            ));
+        }
+        for (row, values) in data.chunks(w.max(1) * 3).enumerate() {
+            let start = ((y0 + row) * self.width + x0) * 3;
+            for (sum, value) in self.radiance[start..start + w * 3].iter_mut().zip(values) {
+                *sum += value;
+            }
+        }
+        let finished = &mut self.finished[tile_y * self.tiles_x + tile_x];
+        let word = index as usize / 64;
+        if finished.len() <= word {
+            finished.resize(word + 1, 0);
+        }
+        finished[word] |= 1 << (index % 64);
+        Ok(())
+    }
+    /// Renders until every tile has the given amount of samples
+    pub fn render<T: FragmentRender>(
+        &mut self,
+        fragment_render: &T,
+        texture_repo: &TextureRepository,
+        samples: u32,
+    ) {
+        for sample in self.pending(samples) {
+            let data = self.render_sample(fragment_render, texture_repo, sample);
+            self.add(sample, &data).unwrap();
+        }
+    }
+    /// Average of the samples, RGB rows like the [RawCollector](super::raw_collector::RawCollector)
+    pub fn image(&self) -> Vec<f32> {
+        let mut image = self.radiance.clone();
+        for tile_y in 0..self.tiles_y {
+            for tile_x in 0..self.tiles_x {
+                let samples = self.samples(tile_x, tile_y);
+                let (x0, y0, w, h) = self.tile_rect(tile_x, tile_y);
+                for y in y0..y0 + h {
+                    let start = (y * self.width + x0) * 3;
+                    for value in &mut image[start..start + w * 3] {
+                        *value = if samples == 0 {
+                            0.0
+                        } else {
+                            *value / samples as f32
+                        };
+                    }
+                }
+            }
+        }
+        image
+    }
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let words: usize = self.finished.iter().map(|finished| finished.len()).sum();
+        let mut bytes = Vec::with_capacity(40 + self.finished.len() * 4 + words * 8 + self.radiance.len() * 4);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        for value in [self.width, self.height, self.tiles_x, self.tiles_y] {
+            bytes.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.key.to_le_bytes());
+        //Every bitset is prefixed by its length in words
+        for finished in &self.finished {
+            bytes.extend_from_slice(&(finished.len() as u32).to_le_bytes());
+            for word in finished {
+                bytes.extend_from_slice(&word.to_le_bytes());
+            }
+        }
+        for value in &self.radiance {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader { bytes };
+        if &reader.take::<4>()? != MAGIC {
+            return Err(anyhow!("Not a checkpoint"));
+        }
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(anyhow!("Unsupported checkpoint version {}", version));
+        }
+        let width = reader.usize()?;
+        let height = reader.usize()?;
+        let tiles_x = reader.usize()?;
+        let tiles_y = reader.usize()?;
+        let seed = reader.u64()?;
+        let key = reader.u64()?;
+        //Grows as the bitsets are read, so a corrupt header can't ask for a huge allocation
+        let mut finished = Vec::new();
+        for _ in 0..tiles_x as u128 * tiles_y as u128 {
+            let words = reader.usize()?;
+            let tile = (0..words).map(|_| reader.u64()).collect::<Result<Vec<_>>>()?;
+            finished.push(tile);
+        }
+        //Checked before allocating the image for the same reason
+        if reader.bytes.len() as u128 != width as u128 * height as u128 * 3 * 4 {
+            return Err(anyhow!("Checkpoint size does not match its header"));
+        }
+        let mut accumulator = Self::new(width, height, tiles_x, tiles_y, seed)?;
+        accumulator.key = key;
+        accumulator.finished = finished;
+        for value in accumulator.radiance.iter_mut() {
+            *value = f32::from_le_bytes(reader.take()?);
+        }
+        Ok(accumulator)
+    }
+    /// Writes to a temporary file first, an interrupted save leaves the previous checkpoint intact
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, self.to_bytes())?;
+        fs::rename(&temp, path)?;
+        Ok(())
+    }
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
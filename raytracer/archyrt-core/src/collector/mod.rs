@@ -1,4 +1,5 @@
 pub mod array_collector;
+pub mod checkpoint;
 pub mod image_collector;
 pub mod raw_collector;
 #[cfg(test)]
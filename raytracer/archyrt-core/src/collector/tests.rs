@@ -18,10 +18,10 @@ mod array_collector {
         api::fragment_collector::FragmentCollector,
         collector::{array_collector::ArrayCollector, tests::DummyRenderer},
         textures::texture_repo::TextureRepository,
-        utilities::math::Vec3,
+        utilities::math::{Float, Vec3},
     };
 
-    const EPSILON: f64 = 0.0001;
+    const EPSILON: Float = 0.0001;
 
     fn image_eq(image1: &Vec<Vec<Vec3>>, image2: &Vec<Vec<Vec3>>) -> bool {
         if image1.len() != image2.len() {
@@ -83,3 +83,155 @@ mod image_collector {
         assert_eq!(image.get_pixel(3, 1).0[0], 170);
     }
 }
+
+mod checkpoint {
+    use crate::{
+        api::{
+            fragment_collector::FragmentCollector,
+            fragment_render::{FragmentContext, FragmentRender},
+        },
+        collector::{
+            array_collector::ArrayCollector,
+            checkpoint::{Accumulator, TileSample},
+        },
+        textures::texture_repo::TextureRepository,
+        utilities::{
+            math::{Float, Vec2, Vec3},
+            rng,
+        },
+    };
+
+    use super::DummyRenderer;
+
+    //Noise on top of the gradient, only reproducible through the seeded random numbers
+    struct NoisyRenderer {}
+
+    impl FragmentRender for NoisyRenderer {
+        fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+            DummyRenderer {}.render_fragment(ctx, pos) + Vec3::from_single(rng::random::<Float>())
+        }
+    }
+
+    #[test]
+    fn average() {
+        let repo = TextureRepository::new();
+        let mut accumulator = Accumulator::new(4, 4, 2, 2, 0).unwrap();
+        accumulator.render(&DummyRenderer {}, &repo, 3);
+        assert_eq!(accumulator.min_samples(), 3);
+        assert_eq!(accumulator.total_samples(), 12);
+        let image = accumulator.image();
+        let expected = [0.0, 1.0 / 6.0, 2.0 / 6.0, 3.0 / 6.0, 1.0 / 6.0];
+        for (pixel, expected) in expected.iter().enumerate() {
+            assert!((image[pixel * 3] - expected).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn uneven_tiles() {
+        let repo = TextureRepository::new();
+        let mut accumulator = Accumulator::new(10, 7, 4, 3, 0).unwrap();
+        assert_eq!(accumulator.tile_rect(3, 2), (7, 4, 3, 3));
+        assert_eq!(accumulator.tile_at(7, 4), Some((3, 2)));
+        assert_eq!(accumulator.tile_at(6, 4), None);
+        accumulator.render(&DummyRenderer {}, &repo, 1);
+        //Every pixel is covered by a tile
+        let expected = ArrayCollector {}.collect(DummyRenderer {}, &repo, 10, 7);
+        for (color, expected) in accumulator.image().chunks(3).zip(expected.iter().flatten()) {
+            assert!((color[0] as Float - expected.x()).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn resume() {
+        let repo = TextureRepository::new();
+        let renderer = NoisyRenderer {};
+        let mut uninterrupted = Accumulator::new(8, 6, 2, 3, 42).unwrap();
+        uninterrupted.render(&renderer, &repo, 4);
+
+        //Stop in the middle of a round, save, load and extend the render
+        let mut interrupted = Accumulator::new(8, 6, 2, 3, 42).unwrap();
+        for sample in interrupted.pending(4).into_iter().take(9) {
+            let data = interrupted.render_sample(&renderer, &repo, sample);
+            interrupted.add(sample, &data).unwrap();
+        }
+        let mut resumed = Accumulator::from_bytes(&interrupted.to_bytes()).unwrap();
+        assert_eq!(resumed, interrupted);
+        resumed.render(&renderer, &repo, 2);
+        assert_eq!(resumed.min_samples(), 2);
+        resumed.render(&renderer, &repo, 4);
+        assert_eq!(resumed, uninterrupted);
+
+        //Samples can also finish in any order, only the summation order changes
+        let mut reordered = interrupted.clone();
+        for sample in reordered.pending(4).into_iter().rev() {
+            let data = reordered.render_sample(&renderer, &repo, sample);
+            reordered.add(sample, &data).unwrap();
+        }
+        for (a, b) in reordered.image().iter().zip(uninterrupted.image()) {
+            assert!((a - b).abs() < 0.0001);
+        }
+
+        //A later sample finishing first isn't rendered again after resuming, the missing one is
+        let mut gap = Accumulator::new(8, 6, 2, 3, 42).unwrap();
+        let later = TileSample {
+            tile_x: 1,
+            tile_y: 2,
+            index: 1,
+        };
+        let data = gap.render_sample(&renderer, &repo, later);
+        gap.add(later, &data).unwrap();
+        assert!(gap.add(later, &data).is_err());
+        let mut gap = Accumulator::from_bytes(&gap.to_bytes()).unwrap();
+        assert_eq!(gap.samples(1, 2), 1);
+        let pending = gap.pending(2);
+        assert!(!pending.contains(&later));
+        assert!(pending.contains(&TileSample { index: 0, ..later }));
+        assert_eq!(pending.len(), 11);
+        gap.render(&renderer, &repo, 4);
+        for (a, b) in gap.image().iter().zip(uninterrupted.image()) {
+            assert!((a - b).abs() < 0.0001);
+        }
+
+        let other_seed = {
+            let mut accumulator = Accumulator::new(8, 6, 2, 3, 43).unwrap();
+            accumulator.render(&renderer, &repo, 4);
+            accumulator
+        };
+        assert_ne!(other_seed.image(), uninterrupted.image());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let repo = TextureRepository::new();
+        let mut accumulator = Accumulator::new(8, 8, 4, 4, 7).unwrap();
+        accumulator.key = 0x0123456789abcdef;
+        accumulator.render(&NoisyRenderer {}, &repo, 2);
+        let path = std::env::temp_dir().join(format!("archyrt-checkpoint-{}.ckpt", std::process::id()));
+        accumulator.save(&path).unwrap();
+        let loaded = Accumulator::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, accumulator);
+    }
+
+    #[test]
+    fn invalid() {
+        let accumulator = Accumulator::new(4, 4, 2, 2, 0).unwrap();
+        let bytes = accumulator.to_bytes();
+        assert!(Accumulator::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Accumulator::from_bytes(b"not a checkpoint").is_err());
+        assert!(Accumulator::new(4, 4, 0, 2, 0).is_err());
+        assert!(Accumulator::new(4, 4, 2, 0, 0).is_err());
+        //A header without tiles
+        let mut no_tiles = bytes.clone();
+        no_tiles[16..20].copy_from_slice(&0u32.to_le_bytes());
+        assert!(Accumulator::from_bytes(&no_tiles).is_err());
+        let mut accumulator = accumulator;
+        let sample = |tile_x, tile_y| TileSample {
+            tile_x,
+            tile_y,
+            index: 0,
+        };
+        assert!(accumulator.add(sample(0, 0), &[0.0; 3]).is_err());
+        assert!(accumulator.add(sample(2, 0), &[0.0; 12]).is_err());
+    }
+}
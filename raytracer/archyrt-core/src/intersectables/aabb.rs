@@ -1,4 +1,4 @@
-use crate::utilities::math::Axis3;
+use crate::utilities::math::{Axis3, Float};
 use crate::vector;
 use crate::{
     textures::color_provider::SolidColor,
@@ -8,7 +8,7 @@ use crate::{
     },
 };
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct AABB {
     pub min: Vec3,
     pub max: Vec3,
@@ -33,7 +33,7 @@ impl AABB {
 }
 
 impl AABB {
-    pub fn intersect(&self, ray: Ray) -> Option<f64> {
+    pub fn intersect(&self, ray: Ray) -> Option<Float> {
         let invdir = vector![
             1. / ray.direction[0],
             1. / ray.direction[1],
@@ -111,7 +111,7 @@ impl Intersectable for AABBRay {
         let (i, _) = d
             .inner
             .iter()
-            .map(|v| f64::abs(*v))
+            .map(|v| Float::abs(*v))
             .enumerate()
             .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
             .unwrap();
@@ -12,9 +12,15 @@ impl<T: Intersectable> Intersectable for ApplyMatrix<T>{
     fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
         let origin = self.matrix*ray.origin;
         let direction = self.matrix*ray.direction;
-        let ray = Ray::new(origin, direction);
-        let mut result = self.inner.intersect(ray)?.to_builder();
+        let local_ray = Ray::with_time(origin, direction, ray.time);
+        let mut result = self.inner.intersect(local_ray)?.to_builder();
+        //Report the hit in world space so it can be offset correctly
+        if let Some(pos) = result.pos{
+            result.pos = Some(self.inverse_matrix*pos);
+        }
+        result.ray = ray;
         result.normal = self.inverse_matrix*result.normal;
+        result.geometric_normal = result.geometric_normal.map(|normal| self.inverse_matrix*normal);
         Some(result.build())
         
     }
@@ -1,19 +1,30 @@
 use crate::intersectables::aabb::AABB;
 use crate::intersectables::triangle::Triangle;
 
-use crate::utilities::math::Vec3;
+use crate::utilities::math::{Float, Vec3};
+use crate::matrix;
 use crate::utilities::ray::{Intersectable, Intersection, Ray};
+use crate::utilities::stats;
 use std::cmp::Ordering;
 
-use super::triangle::TriangleColor;
+use super::csg::{Crossing, Solid, Span};
+use super::triangle::{ShearedRay, TriangleColor, TriangleHit, TriangleRecord};
 
-pub enum BVH {
+#[derive(Clone)]
+enum BVHNode {
     Branch {
-        left: Box<BVH>,
-        right: Box<BVH>,
+        left: Box<BVHNode>,
+        right: Box<BVHNode>,
         aabb: AABB,
     },
-    Leaf(Triangle),
+    Leaf(TriangleRecord),
+}
+
+/// The nodes only keep what the intersection test needs, whole triangles are looked up for the closest hit
+#[derive(Clone)]
+pub struct BVH {
+    root: BVHNode,
+    triangles: Vec<Triangle>,
 }
 
 fn bounding(triangles: &[Triangle]) -> AABB {
@@ -27,46 +38,56 @@ fn bounding(triangles: &[Triangle]) -> AABB {
     bounds
 }
 
+fn build(mut triangles: Vec<Triangle>, out: &mut Vec<Triangle>) -> BVHNode {
+    if triangles.len() == 1 {
+        let record = TriangleRecord::new(&triangles[0], out.len());
+        out.append(&mut triangles);
+        return BVHNode::Leaf(record);
+    }
+    let bounds = bounding(&triangles);
+    let maxis = bounds.max_axis();
+    let index = triangles.len() / 2;
+    triangles.select_nth_unstable_by(index, |a, b| {
+        a.centroid()
+            .get(maxis)
+            .partial_cmp(&b.centroid().get(maxis))
+            .unwrap_or(Ordering::Equal)
+    });
+    let right = triangles.split_off(index);
+    let left = build(triangles, out);
+    let right = build(right, out);
+    BVHNode::Branch {
+        left: Box::new(left),
+        right: Box::new(right),
+        aabb: bounds,
+    }
+}
+
 impl BVH {
     pub fn from_triangles(triangles: &[Triangle]) -> Option<Self> {
         if triangles.is_empty() {
             return None;
         }
-        if triangles.len() == 1 {
-            let triangle = triangles.iter().next().unwrap().clone();
-            return Some(BVH::Leaf(triangle));
-        }
-        let bounds = bounding(triangles);
-        let maxis = bounds.max_axis();
-        let mut along_maxis: Vec<_> = triangles
-            .iter()
-            .map(|t| (t.centroid().get(maxis), t.clone()))
-            .collect();
-        let index = along_maxis.len() / 2;
-        along_maxis.select_nth_unstable_by(index, |a, b| {
-            a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal)
-        });
-        let along_maxis: Vec<_> = along_maxis.into_iter().map(|(_, t)| t).collect();
-        let (a, b) = along_maxis.split_at(index);
-        let a = BVH::from_triangles(&a.to_vec())?;
-        let b = BVH::from_triangles(&b.to_vec())?;
-        Some(BVH::Branch {
-            left: Box::new(a),
-            right: Box::new(b),
-            aabb: bounds,
+        let mut ordered = Vec::with_capacity(triangles.len());
+        let root = build(triangles.to_vec(), &mut ordered);
+        Some(Self {
+            root,
+            triangles: ordered,
         })
     }
-}
-
-impl Intersectable for BVH {
-    type C = TriangleColor;
-    fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
-        match self {
-            BVH::Leaf(triangle) => triangle.intersect(ray),
-            BVH::Branch { left, right, aabb } => {
+    /// Closest hit that survives culling and alpha testing
+    fn intersect_node(&self, node: &BVHNode, ray: Ray, sheared: &ShearedRay) -> Option<Intersection<TriangleColor>> {
+        stats::node();
+        match node {
+            BVHNode::Leaf(record) => {
+                stats::triangles(1);
+                let hit = record.intersect(sheared)?;
+                self.triangles[record.index].shade(ray, hit)
+            }
+            BVHNode::Branch { left, right, aabb } => {
                 aabb.intersect(ray)?;
-                let a = left.intersect(ray);
-                let b = right.intersect(ray);
+                let a = self.intersect_node(left, ray, sheared);
+                let b = self.intersect_node(right, ray, sheared);
                 match (a, b) {
                     (None, None) => None,
                     (None, Some(b)) => Some(b),
@@ -77,4 +98,88 @@ impl Intersectable for BVH {
             }
         }
     }
+    /// Every triangle in front of the ray
+    fn hits<'a>(&'a self, node: &BVHNode, ray: Ray, sheared: &ShearedRay, hits: &mut Vec<(TriangleHit, &'a Triangle)>) {
+        stats::node();
+        match node {
+            BVHNode::Leaf(record) => {
+                stats::triangles(1);
+                if let Some(hit) = record.intersect(sheared) {
+                    hits.push((hit, &self.triangles[record.index]));
+                }
+            }
+            BVHNode::Branch { left, right, aabb } => {
+                if aabb.intersect(ray).is_some() {
+                    self.hits(left, ray, sheared, hits);
+                    self.hits(right, ray, sheared, hits);
+                }
+            }
+        }
+    }
+}
+
+impl Intersectable for BVH {
+    type C = TriangleColor;
+    fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
+        //The ray is prepared once for all the triangles it's tested against
+        self.intersect_node(&self.root, ray, &ShearedRay::new(ray))
+    }
+}
+
+/// A closed mesh with its triangles wound counter-clockwise when seen from the outside
+impl Solid for BVH {
+    type C = TriangleColor;
+    fn spans(&self, ray: Ray) -> Vec<Span<Self::C>> {
+        //The line is traced in both directions, hits behind the origin get negative distances
+        let mut hits = Vec::new();
+        self.hits(&self.root, ray, &ShearedRay::new(ray), &mut hits);
+        let forward = hits.len();
+        let backward = Ray { direction: -ray.direction, ..ray };
+        self.hits(&self.root, backward, &ShearedRay::new(backward), &mut hits);
+        let mut crossings: Vec<(bool, Crossing<TriangleColor>)> = hits
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, (hit, triangle))| {
+                let distance = if i < forward {
+                    hit.t
+                } else if hit.t > 0.0 {
+                    -hit.t
+                } else {
+                    //Already found going forward
+                    return None;
+                };
+                let crossing = Crossing {
+                    distance,
+                    normal: matrix![triangle.bn, triangle.cn, triangle.an] * hit.barycentric,
+                    color_provider: TriangleColor {
+                        uv: triangle.uv,
+                        barycentric: hit.barycentric,
+                        texture: triangle.texture,
+                        material: triangle.material,
+                    },
+                };
+                Some((triangle.normal.dot(ray.direction) < 0.0, crossing))
+            })
+            .collect();
+        crossings.sort_by(|a, b| a.1.distance.total_cmp(&b.1.distance));
+        //A ray through a shared edge hits both triangles, only the first one counts
+        crossings.dedup_by(|b, a| {
+            a.0 == b.0 && (b.1.distance - a.1.distance).abs() <= Float::EPSILON * 16.0 * (1.0 + a.1.distance.abs())
+        });
+        let mut spans = Vec::new();
+        let mut enter = None;
+        for (entering, crossing) in crossings {
+            match (entering, enter.take()) {
+                (true, None) => enter = Some(crossing),
+                (false, Some(enter)) => spans.push(Span {
+                    enter,
+                    exit: crossing,
+                }),
+                //The mesh is not closed along this ray, stray crossings are skipped
+                (true, Some(previous)) => enter = Some(previous),
+                (false, None) => {}
+            }
+        }
+        spans
+    }
 }
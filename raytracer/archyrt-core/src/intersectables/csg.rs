@@ -0,0 +1,194 @@
+use crate::{
+    textures::color_provider::ColorProvider,
+    utilities::{
+        math::{Float, Vec3},
+        ray::{Intersectable, Intersection, IntersectionBuilder, Ray},
+    },
+};
+
+use super::union::UnionColorProvider;
+
+/// Where a ray crosses the surface of a solid, the normal points out of the solid
+#[derive(Clone)]
+pub struct Crossing<C: ColorProvider + Clone> {
+    pub distance: Float,
+    pub normal: Vec3,
+    pub color_provider: C,
+}
+
+/// Part of the line through a ray that is inside a solid
+#[derive(Clone)]
+pub struct Span<C: ColorProvider + Clone> {
+    pub enter: Crossing<C>,
+    pub exit: Crossing<C>,
+}
+
+/// Closed object with a well defined inside, the building block of [Csg]
+pub trait Solid {
+    type C: ColorProvider + Clone;
+    /// Sorted, disjoint spans along the whole line of the ray, including the part behind its origin
+    fn spans(&self, ray: Ray) -> Vec<Span<Self::C>>;
+}
+
+impl<T: Solid> Solid for &T {
+    type C = T::C;
+    fn spans(&self, ray: Ray) -> Vec<Span<Self::C>> {
+        (*self).spans(ray)
+    }
+}
+
+/// The first surface of a solid in front of the ray, the exit if the ray starts inside
+pub fn first_crossing<S: Solid + ?Sized>(solid: &S, ray: Ray) -> Option<Intersection<S::C>> {
+    let crossing = solid
+        .spans(ray)
+        .into_iter()
+        .find(|span| span.exit.distance >= 0.0)
+        .map(|span| {
+            if span.enter.distance >= 0.0 {
+                span.enter
+            } else {
+                span.exit
+            }
+        })?;
+    Some(
+        IntersectionBuilder {
+            ray,
+            pos: None,
+            distance: Some(crossing.distance),
+            distance_squared: None,
+            normal: crossing.normal,
+            geometric_normal: None,
+            instance: None,
+            front_face: None,
+            color_provider: crossing.color_provider,
+        }
+        .build(),
+    )
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    /// The second solid is cut out of the first one
+    Difference,
+}
+
+impl CsgOperation {
+    fn inside(self, a: bool, b: bool) -> bool {
+        match self {
+            CsgOperation::Union => a || b,
+            CsgOperation::Intersection => a && b,
+            CsgOperation::Difference => a && !b,
+        }
+    }
+}
+
+/// Boolean combination of two solids. The result is a solid itself, so operations can be nested.
+pub struct Csg<A: Solid, B: Solid> {
+    pub a: A,
+    pub b: B,
+    pub operation: CsgOperation,
+}
+
+impl<A: Solid, B: Solid> Csg<A, B> {
+    pub fn union(a: A, b: B) -> Self {
+        Self {
+            a,
+            b,
+            operation: CsgOperation::Union,
+        }
+    }
+    pub fn intersection(a: A, b: B) -> Self {
+        Self {
+            a,
+            b,
+            operation: CsgOperation::Intersection,
+        }
+    }
+    pub fn difference(a: A, b: B) -> Self {
+        Self {
+            a,
+            b,
+            operation: CsgOperation::Difference,
+        }
+    }
+}
+
+struct Event<C: ColorProvider + Clone> {
+    crossing: Crossing<C>,
+    from_b: bool,
+    enter: bool,
+}
+
+fn events<C: ColorProvider + Clone, K: ColorProvider + Clone, F: Fn(C) -> K>(
+    spans: Vec<Span<C>>,
+    from_b: bool,
+    wrap: F,
+) -> impl Iterator<Item = Event<K>> {
+    spans.into_iter().flat_map(move |span| {
+        let crossing = |crossing: Crossing<C>| Crossing {
+            distance: crossing.distance,
+            normal: crossing.normal,
+            color_provider: wrap(crossing.color_provider),
+        };
+        [
+            Event {
+                crossing: crossing(span.enter),
+                from_b,
+                enter: true,
+            },
+            Event {
+                crossing: crossing(span.exit),
+                from_b,
+                enter: false,
+            },
+        ]
+    })
+}
+
+impl<A: Solid, B: Solid> Solid for Csg<A, B> {
+    type C = UnionColorProvider<A::C, B::C>;
+    fn spans(&self, ray: Ray) -> Vec<Span<Self::C>> {
+        let mut events: Vec<_> = events(self.a.spans(ray), false, UnionColorProvider::A)
+            .chain(events(self.b.spans(ray), true, UnionColorProvider::B))
+            .collect();
+        events.sort_by(|a, b| a.crossing.distance.total_cmp(&b.crossing.distance));
+        //Walk along the ray, a span starts and ends wherever the result of the operation changes
+        let (mut in_a, mut in_b) = (false, false);
+        let mut enter = None;
+        let mut spans = Vec::new();
+        for event in events {
+            let was_inside = self.operation.inside(in_a, in_b);
+            if event.from_b {
+                in_b = event.enter;
+            } else {
+                in_a = event.enter;
+            }
+            let inside = self.operation.inside(in_a, in_b);
+            if inside == was_inside {
+                continue;
+            }
+            let mut crossing = event.crossing;
+            //Surfaces of the cut out solid face into it
+            if event.from_b && self.operation == CsgOperation::Difference {
+                crossing.normal = -crossing.normal;
+            }
+            match enter.take() {
+                Some(enter) => spans.push(Span {
+                    enter,
+                    exit: crossing,
+                }),
+                None => enter = Some(crossing),
+            }
+        }
+        spans
+    }
+}
+
+impl<A: Solid, B: Solid> Intersectable for Csg<A, B> {
+    type C = UnionColorProvider<A::C, B::C>;
+    fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
+        first_crossing(self, ray)
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{
+    renderers::path_tracer::Material,
+    textures::color_provider::SolidColor,
+    utilities::{
+        math::{Float, Vec3},
+        ray::{Intersectable, Intersection, Ray},
+    },
+};
+
+use super::{
+    aabb::AABB,
+    csg::{first_crossing, Crossing, Solid, Span},
+};
+
+/// Solid axis-aligned box
+pub struct Cuboid {
+    pub bounds: AABB,
+    pub color: Vec3,
+    pub material: Material,
+}
+
+impl Cuboid {
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Self {
+            bounds: AABB::new(a, b),
+            color: Vec3::from_single(1.0),
+            material: Material::default(),
+        }
+    }
+}
+
+impl Solid for Cuboid {
+    type C = SolidColor;
+    fn spans(&self, ray: Ray) -> Vec<Span<Self::C>> {
+        //Entry and exit of the slabs between the faces on every axis, with the axis they were found on
+        let mut near = (Float::NEG_INFINITY, 0, 0.0);
+        let mut far = (Float::INFINITY, 0, 0.0);
+        for axis in 0..3 {
+            let origin = ray.origin[axis];
+            let direction = ray.direction[axis];
+            let (min, max) = (self.bounds.min[axis], self.bounds.max[axis]);
+            if direction == 0.0 {
+                //Parallel to the faces, either always or never between them
+                if origin < min || origin > max {
+                    return Vec::new();
+                }
+                continue;
+            }
+            let t1 = (min - origin) / direction;
+            let t2 = (max - origin) / direction;
+            //The sign of the outward normal of the face that is entered
+            let (enter, exit, sign) = if t1 < t2 { (t1, t2, -1.0) } else { (t2, t1, 1.0) };
+            if enter > near.0 {
+                near = (enter, axis, sign);
+            }
+            if exit < far.0 {
+                far = (exit, axis, -sign);
+            }
+        }
+        if near.0 > far.0 || !near.0.is_finite() {
+            return Vec::new();
+        }
+        let crossing = |(distance, axis, sign): (Float, usize, Float)| {
+            let mut normal = Vec3::default();
+            normal[axis] = sign;
+            Crossing {
+                distance,
+                normal,
+                color_provider: SolidColor(self.color, self.material),
+            }
+        };
+        vec![Span {
+            enter: crossing(near),
+            exit: crossing(far),
+        }]
+    }
+}
+
+impl Intersectable for Cuboid {
+    type C = SolidColor;
+    fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
+        first_crossing(self, ray)
+    }
+}
@@ -0,0 +1,18 @@
+use crate::utilities::ray::{Intersectable, Intersection, Ray};
+
+/// Tags every hit of the inner object with an id, used to tell objects apart in renders
+pub struct Instance<T: Intersectable> {
+    pub inner: T,
+    pub id: u32,
+}
+
+impl<T: Intersectable> Intersectable for Instance<T> {
+    type C = T::C;
+
+    fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
+        let mut result = self.inner.intersect(ray)?.to_builder();
+        //Nested instances keep the innermost id
+        result.instance.get_or_insert(self.id);
+        Some(result.build())
+    }
+}
@@ -1,5 +1,10 @@
 pub mod aabb;
 pub mod bvh;
+pub mod csg;
+pub mod cuboid;
+pub mod instance;
+pub mod motion;
+pub mod packet;
 pub mod sphere;
 pub mod surface;
 #[cfg(test)]
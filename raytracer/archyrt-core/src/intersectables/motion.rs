@@ -0,0 +1,83 @@
+use cgmath::Quaternion;
+
+use crate::utilities::{
+    math::{Float, Matrix3x3, Vec3},
+    ray::{Intersectable, Intersection, Ray},
+};
+
+/// Pose of an instance at a given point in time
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub time: Float,
+    pub position: Vec3,
+    /// Local to world rotation
+    pub rotation: Quaternion<Float>,
+}
+
+impl Keyframe {
+    pub fn new(time: Float, position: Vec3, rotation: Quaternion<Float>) -> Self {
+        Self {
+            time,
+            position,
+            rotation,
+        }
+    }
+}
+
+/// Instance interpolating between keyframed transforms based on the time of the incoming ray
+pub struct MotionTransform<T: Intersectable> {
+    pub inner: T,
+    /// Sorted by time
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl<T: Intersectable> MotionTransform<T> {
+    /// Keyframes without a valid time are dropped
+    pub fn new(inner: T, keyframes: Vec<Keyframe>) -> Self {
+        let mut keyframes = keyframes;
+        keyframes.retain(|keyframe| !keyframe.time.is_nan());
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self { inner, keyframes }
+    }
+
+    /// Returns the interpolated position and local to world rotation at a given point in time
+    pub fn pose(&self, time: Float) -> Option<(Vec3, Quaternion<Float>)> {
+        let first = self.keyframes.first()?;
+        let last = self.keyframes.last()?;
+        if time <= first.time {
+            return Some((first.position, first.rotation));
+        }
+        if time >= last.time {
+            return Some((last.position, last.rotation));
+        }
+        let next = self.keyframes.iter().position(|k| k.time > time)?;
+        let a = self.keyframes[next - 1];
+        let b = self.keyframes[next];
+        let t = (time - a.time) / (b.time - a.time);
+        Some((a.position.lerp(b.position, t), a.rotation.slerp(b.rotation, t)))
+    }
+}
+
+impl<T: Intersectable> Intersectable for MotionTransform<T> {
+    type C = T::C;
+
+    fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
+        let (position, rotation) = self.pose(ray.time)?;
+        let matrix = Matrix3x3::from(rotation);
+        let inverse_matrix = matrix.transpose();
+        let local = Ray::with_time(
+            inverse_matrix * (ray.origin - position),
+            inverse_matrix * ray.direction,
+            ray.time,
+        );
+        let mut result = self.inner.intersect(local)?.to_builder();
+        result.normal = matrix * result.normal;
+        result.geometric_normal = result.geometric_normal.map(|normal| matrix * normal);
+        //Rigid transformations preserve distances, so the world space ray can be restored
+        result.ray = ray;
+        if let Some(pos) = result.pos {
+            result.pos = Some(matrix * pos + position);
+        }
+        Some(result.build())
+    }
+}
@@ -0,0 +1,187 @@
+use std::cmp::Ordering;
+
+use crate::intersectables::aabb::AABB;
+use crate::intersectables::triangle::{ShearedRay, Triangle, TriangleColor, TriangleHit};
+use crate::utilities::math::{Float, Vec3};
+use crate::utilities::ray::{Intersectable, Intersection, Ray};
+use crate::utilities::stats;
+
+/// Vertices of up to N triangles stored per axis, so that all lanes are tested at once
+#[derive(Clone)]
+pub struct TrianglePacket<const N: usize> {
+    a: [[Float; N]; 3],
+    b: [[Float; N]; 3],
+    c: [[Float; N]; 3],
+    count: usize,
+}
+
+impl<const N: usize> TrianglePacket<N> {
+    pub fn new(triangles: &[Triangle]) -> Self {
+        assert!(triangles.len() <= N, "Too many triangles for a packet");
+        let mut packet = Self {
+            a: [[0.0; N]; 3],
+            b: [[0.0; N]; 3],
+            c: [[0.0; N]; 3],
+            count: triangles.len(),
+        };
+        for (lane, triangle) in triangles.iter().enumerate() {
+            for axis in 0..3 {
+                packet.a[axis][lane] = triangle.a[axis];
+                packet.b[axis][lane] = triangle.b[axis];
+                packet.c[axis][lane] = triangle.c[axis];
+            }
+        }
+        packet
+    }
+    /// Same test as `ShearedRay::intersect` for every lane
+    pub fn intersect(&self, ray: &ShearedRay) -> [Option<TriangleHit>; N] {
+        let [kx, ky, kz] = ray.axes;
+        let [sx, sy, sz] = ray.shear.inner;
+        let mut az = [0.0; N];
+        let mut bz = [0.0; N];
+        let mut cz = [0.0; N];
+        let mut u = [0.0; N];
+        let mut v = [0.0; N];
+        let mut w = [0.0; N];
+        for lane in 0..N {
+            az[lane] = self.a[kz][lane] - ray.origin[kz];
+            bz[lane] = self.b[kz][lane] - ray.origin[kz];
+            cz[lane] = self.c[kz][lane] - ray.origin[kz];
+            let ax = self.a[kx][lane] - ray.origin[kx] - sx * az[lane];
+            let ay = self.a[ky][lane] - ray.origin[ky] - sy * az[lane];
+            let bx = self.b[kx][lane] - ray.origin[kx] - sx * bz[lane];
+            let by = self.b[ky][lane] - ray.origin[ky] - sy * bz[lane];
+            let cx = self.c[kx][lane] - ray.origin[kx] - sx * cz[lane];
+            let cy = self.c[ky][lane] - ray.origin[ky] - sy * cz[lane];
+            u[lane] = cx * by - cy * bx;
+            v[lane] = ax * cy - ay * cx;
+            w[lane] = bx * ay - by * ax;
+        }
+        let mut hits = [None; N];
+        for lane in 0..self.count {
+            let (u, v, w) = (u[lane], v[lane], w[lane]);
+            if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+                continue;
+            }
+            let det = u + v + w;
+            if det == 0.0 {
+                continue;
+            }
+            let t = sz * (u * az[lane] + v * bz[lane] + w * cz[lane]) / det;
+            if t < 0.0 {
+                continue;
+            }
+            hits[lane] = Some(TriangleHit {
+                t,
+                barycentric: Vec3::new(v / det, w / det, u / det),
+            });
+        }
+        hits
+    }
+}
+
+enum PacketNode<const N: usize> {
+    Branch {
+        left: Box<PacketNode<N>>,
+        right: Box<PacketNode<N>>,
+        aabb: AABB,
+    },
+    Leaf {
+        packet: TrianglePacket<N>,
+        first: usize,
+    },
+}
+
+/// BVH with up to N triangles per leaf, tested as a packet. Gives the same results as `BVH`.
+pub struct PacketBVH<const N: usize> {
+    root: PacketNode<N>,
+    triangles: Vec<Triangle>,
+}
+
+fn build<const N: usize>(mut triangles: Vec<Triangle>, out: &mut Vec<Triangle>) -> PacketNode<N> {
+    if triangles.len() <= N {
+        let packet = TrianglePacket::new(&triangles);
+        let first = out.len();
+        out.append(&mut triangles);
+        return PacketNode::Leaf { packet, first };
+    }
+    let mut bounds = triangles[0].bounds();
+    for triangle in &triangles {
+        bounds = bounds.union(triangle.bounds());
+    }
+    let maxis = bounds.max_axis();
+    let index = triangles.len() / 2;
+    triangles.select_nth_unstable_by(index, |a, b| {
+        a.centroid()
+            .get(maxis)
+            .partial_cmp(&b.centroid().get(maxis))
+            .unwrap_or(Ordering::Equal)
+    });
+    let right = triangles.split_off(index);
+    let left = build(triangles, out);
+    let right = build(right, out);
+    PacketNode::Branch {
+        left: Box::new(left),
+        right: Box::new(right),
+        aabb: bounds,
+    }
+}
+
+impl<const N: usize> PacketBVH<N> {
+    pub fn from_triangles(triangles: &[Triangle]) -> Option<Self> {
+        if triangles.is_empty() {
+            return None;
+        }
+        let mut ordered = Vec::with_capacity(triangles.len());
+        let root = build(triangles.to_vec(), &mut ordered);
+        Some(Self {
+            root,
+            triangles: ordered,
+        })
+    }
+    fn intersect_node(
+        &self,
+        node: &PacketNode<N>,
+        ray: Ray,
+        sheared: &ShearedRay,
+    ) -> Option<Intersection<TriangleColor>> {
+        stats::node();
+        match node {
+            PacketNode::Leaf { packet, first } => {
+                stats::triangles(packet.count as u64);
+                let mut hits = packet.intersect(sheared);
+                //Closest hit that survives culling and alpha testing
+                loop {
+                    let (lane, hit) = hits
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(lane, hit)| Some((lane, (*hit)?)))
+                        .min_by(|a, b| a.1.t.partial_cmp(&b.1.t).unwrap_or(Ordering::Equal))?;
+                    hits[lane] = None;
+                    if let Some(intersection) = self.triangles[first + lane].shade(ray, hit) {
+                        return Some(intersection);
+                    }
+                }
+            }
+            PacketNode::Branch { left, right, aabb } => {
+                aabb.intersect(ray)?;
+                let a = self.intersect_node(left, ray, sheared);
+                let b = self.intersect_node(right, ray, sheared);
+                match (a, b) {
+                    (None, None) => None,
+                    (None, Some(b)) => Some(b),
+                    (Some(a), None) => Some(a),
+                    (Some(a), Some(b)) if a.get_distance() < b.get_distance() => Some(a),
+                    (Some(_), Some(b)) => Some(b),
+                }
+            }
+        }
+    }
+}
+
+impl<const N: usize> Intersectable for PacketBVH<N> {
+    type C = TriangleColor;
+    fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
+        self.intersect_node(&self.root, ray, &ShearedRay::new(ray))
+    }
+}
@@ -1,8 +1,9 @@
 use crate::{
+    intersectables::csg::{Crossing, Solid, Span},
     renderers::path_tracer::Material,
     textures::color_provider::SolidColor,
     utilities::{
-        math::{solve_quadratic, QuadraticResult, Vec3},
+        math::{Float, QuadraticResult, Vec3, solve_quadratic},
         ray::{Intersectable, Intersection, IntersectionBuilder, Ray},
     },
     vector,
@@ -10,7 +11,7 @@ use crate::{
 
 pub struct Sphere {
     pub origin: Vec3,
-    pub radius: f64,
+    pub radius: Float,
     pub color: Vec3,
     pub material: Material,
 }
@@ -26,7 +27,7 @@ impl Default for Sphere {
     }
 }
 
-fn find_closest(solutions: QuadraticResult) -> Option<f64> {
+fn find_closest(solutions: QuadraticResult) -> Option<Float> {
     match solutions {
         QuadraticResult::TwoResults(a, b) => {
             match (a, b) {
@@ -57,12 +58,12 @@ impl Intersectable for Sphere {
     type C = SolidColor;
     fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
         //Solving the equation |ray.origin+ray.direction*t-self.origin|=self.radius for t
-        //Rewritten: t^2*(ray.direction^2) + t*(2*ray.origin*ray.direction-2*ray.direction*self.origin)+(ray.origin^2+self.origin^2-2*ray.origin*self.origin)=self.radius^2
+        //Rewritten with o=ray.origin-self.origin: t^2*(ray.direction^2) + t*(2*o*ray.direction)+o^2=self.radius^2
+        //Subtracting first avoids cancellation when the sphere is far from the world origin
+        let offset = ray.origin - self.origin;
         let t2 = 1.0; //Assuming ray is normalized. Otherwise ray.direction.length_squared()
-        let t = 2.0 * ray.direction.dot(ray.origin - self.origin);
-        let c = ray.origin.length_squared() + self.origin.length_squared()
-            - 2.0 * ray.origin.dot(self.origin)
-            - self.radius.powi(2);
+        let t = 2.0 * ray.direction.dot(offset);
+        let c = offset.length_squared() - self.radius.powi(2);
         let solutions = solve_quadratic(t2, t, c);
         let distance = find_closest(solutions)?;
         let pos = ray.direction * distance + ray.origin;
@@ -81,3 +82,26 @@ impl Intersectable for Sphere {
         )
     }
 }
+
+impl Solid for Sphere {
+    type C = SolidColor;
+    fn spans(&self, ray: Ray) -> Vec<Span<Self::C>> {
+        let offset = ray.origin - self.origin;
+        let a = ray.direction.length_squared();
+        let b = 2.0 * ray.direction.dot(offset);
+        let c = offset.length_squared() - self.radius.powi(2);
+        let (near, far) = match solve_quadratic(a, b, c) {
+            QuadraticResult::TwoResults(t1, t2) => (t1.min(t2), t1.max(t2)),
+            _ => return Vec::new(),
+        };
+        let crossing = |distance: Float| Crossing {
+            distance,
+            normal: (ray.origin + ray.direction * distance - self.origin) / self.radius,
+            color_provider: SolidColor(self.color, self.material),
+        };
+        vec![Span {
+            enter: crossing(near),
+            exit: crossing(far),
+        }]
+    }
+}
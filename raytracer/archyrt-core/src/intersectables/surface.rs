@@ -1,15 +1,17 @@
 use crate::{
     textures::color_provider::SolidColor,
     utilities::{
-        math::Vec3,
+        math::{Float, Vec3},
         ray::{Intersectable, Intersection, IntersectionBuilder, Ray},
     },
 };
 
 pub struct Surface {
     pub normal: Vec3,
-    pub distance: f64,
+    pub distance: Float,
     pub color: Vec3,
+    /// Whether the plane can be hit from below
+    pub two_sided: bool,
 }
 
 impl Default for Surface {
@@ -18,6 +20,7 @@ impl Default for Surface {
             normal: Vec3::new(0.0, 1.0, 0.0),
             distance: 0.0,
             color: Vec3::from_single(1.0),
+            two_sided: false,
         }
     }
 }
@@ -33,6 +36,7 @@ impl Surface {
             color,
             normal,
             distance,
+            two_sided: false,
         }
     }
 }
@@ -46,7 +50,7 @@ impl Intersectable for Surface {
         //          t=(self.distance-self.normal*ray.origin)/(self.normal*ray.direction)
         let left_side = self.normal.dot(ray.direction);
         //Backface culling
-        if left_side >= 0.0 {
+        if left_side == 0.0 || (left_side > 0.0 && !self.two_sided) {
             return None;
         }
         let right_side = self.distance - self.normal.dot(ray.origin);
@@ -54,10 +58,15 @@ impl Intersectable for Surface {
         if t < 0.0 {
             return None;
         }
+        let normal = if left_side > 0.0 {
+            -self.normal
+        } else {
+            self.normal
+        };
         Some(
             IntersectionBuilder {
                 distance: Some(t),
-                normal: self.normal,
+                normal,
                 ray,
                 ..Default::default()
             }
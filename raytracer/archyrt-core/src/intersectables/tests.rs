@@ -12,6 +12,7 @@ mod sphere {
         let ray = Ray {
             origin: Vec3::new(1.0, 1.0, 1.0),
             direction: Vec3::new(0.0, 0.0, 1.0),
+            ..Default::default()
         };
         let sphere = Sphere {
             origin: Vec3::new(1.0, 1.0, 4.5),
@@ -28,6 +29,7 @@ mod sphere {
         let ray = Ray {
             origin: Vec3::new(1.0, 1.0, 1.0),
             direction: Vec3::new(0.0, 0.0, 1.0),
+            ..Default::default()
         };
         //Sphere behind the ray
         let sphere = Sphere {
@@ -53,6 +55,7 @@ mod surface {
         let ray = Ray {
             origin: Vec3::new(0.0, 2.0, 0.0),
             direction: Vec3::new(0.0, -1.0, 0.0),
+            ..Default::default()
         };
         let surface = Surface {
             normal: Vec3::new(0.0, 1.0, 0.0),
@@ -69,6 +72,7 @@ mod surface {
         let ray = Ray {
             origin: Vec3::new(0.0, 2.0, 0.0),
             direction: Vec3::new(0.0, -1.0, 0.0),
+            ..Default::default()
         };
         let surface = Surface::from_points(
             [
@@ -88,6 +92,7 @@ mod surface {
         let ray = Ray {
             origin: Vec3::new(0.0, 2.0, 0.0),
             direction: Vec3::new(0.0, 1.0, 0.0),
+            ..Default::default()
         };
         let surface = Surface {
             normal: Vec3::new(0.0, 1.0, 0.0),
@@ -97,20 +102,47 @@ mod surface {
         let intersection = surface.intersect(ray);
         assert!(intersection.is_none());
     }
+    #[test]
+    fn intersect_two_sided() {
+        let ray = Ray {
+            origin: Vec3::new(0.0, -2.0, 0.0),
+            direction: Vec3::new(0.0, 1.0, 0.0),
+            ..Default::default()
+        };
+        let mut surface = Surface::default();
+        assert!(surface.intersect(ray).is_none());
+        surface.two_sided = true;
+        let intersection = surface.intersect(ray).unwrap();
+        assert_eq!(intersection.get_distance(), 2.0);
+        assert_eq!(intersection.get_normal(), Vec3::new(0.0, -1.0, 0.0));
+    }
 }
 
 mod triangle {
     use crate::{
-        intersectables::triangle::Triangle,
+        intersectables::{bvh::BVH, triangle::Triangle},
         renderers::path_tracer::Material,
-        textures::TextureID,
+        textures::{texture::Texture, texture_repo::TextureRepository, TextureID},
         utilities::{
-            math::Vec3,
-            ray::{Intersectable, Ray},
+            math::{Float, Vec3},
+            ray::{offset_origin, Intersectable, Ray},
         },
         vector,
     };
 
+    fn floor(height: Float, offset: Vec3, material: Material) -> Triangle {
+        Triangle::new(
+            [
+                Vec3::new(0.0, height, 1.0) + offset,
+                Vec3::new(1.0, height, -1.0) + offset,
+                Vec3::new(-1.0, height, -1.0) + offset,
+            ],
+            [vector!(0.0, 0.0), vector!(0.0, 1.0), vector!(1.0, 0.0)],
+            TextureID::new(&0),
+            material,
+        )
+    }
+
     #[test]
     fn intersect() {
         let triangle = Triangle::new(
@@ -126,6 +158,7 @@ mod triangle {
         let ray = Ray {
             origin: Vec3::from_single(0.0),
             direction: Vec3::new(0.0, -1.0, 0.0),
+            ..Default::default()
         };
         let intersection = triangle.intersect(ray).unwrap();
         assert_eq!(intersection.get_pos(), Vec3::new(0.0, -1.0, 0.0));
@@ -145,10 +178,238 @@ mod triangle {
         let ray = Ray {
             origin: Vec3::new(0.0, 0.0, 5.0),
             direction: Vec3::new(0.0, -1.0, 0.0),
+            ..Default::default()
         };
         let intersection = triangle.intersect(ray);
         assert!(intersection.is_none());
     }
+
+    #[test]
+    fn intersect_backface() {
+        let ray = Ray {
+            origin: Vec3::new(0.0, -2.0, 0.0),
+            direction: Vec3::new(0.0, 1.0, 0.0),
+            ..Default::default()
+        };
+        let triangle = floor(-1.0, Vec3::default(), Material::Diffuse);
+        assert!(triangle.two_sided);
+        let intersection = triangle.intersect(ray).unwrap();
+        assert_eq!(intersection.get_pos(), Vec3::new(0.0, -1.0, 0.0));
+        //Normals face the incoming ray
+        assert_eq!(intersection.get_normal(), Vec3::new(0.0, -1.0, 0.0));
+        assert_eq!(intersection.get_geometric_normal(), Vec3::new(0.0, -1.0, 0.0));
+    }
+    #[test]
+    fn intersect_backface_culled() {
+        let ray = Ray {
+            origin: Vec3::new(0.0, -2.0, 0.0),
+            direction: Vec3::new(0.0, 1.0, 0.0),
+            ..Default::default()
+        };
+        let triangle = floor(-1.0, Vec3::default(), Material::Emissive { power: 1.0 });
+        assert!(!triangle.two_sided);
+        assert!(triangle.intersect(ray).is_none());
+    }
+    #[test]
+    fn offset_no_acne() {
+        //Far from the origin, where a fixed epsilon is lost in rounding
+        let offset = Vec3::new(100000.0, 0.0, -100000.0);
+        let triangle = floor(-1.0, offset, Material::Diffuse);
+        let ray = Ray {
+            origin: offset + Vec3::new(0.1, 5.0, 0.1),
+            direction: Vec3::new(0.01, -1.0, 0.02),
+            ..Default::default()
+        };
+        let intersection = triangle.intersect(ray).unwrap();
+        let origin = offset_origin(intersection.get_pos(), intersection.get_geometric_normal());
+        //Grazing rays leaving the surface must not hit it again
+        for direction in [
+            Vec3::new(1.0, 0.000001, 0.0),
+            Vec3::new(-1.0, 0.001, 1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ] {
+            let ray = Ray {
+                origin,
+                direction,
+                ..Default::default()
+            };
+            assert!(triangle.intersect(ray).is_none());
+        }
+    }
+    #[test]
+    fn offset_no_leaking() {
+        //Two sheets close together, the offset origin has to stay between them
+        let offset = Vec3::new(100000.0, 0.0, -100000.0);
+        let lower = floor(-1.0, offset, Material::Diffuse);
+        let upper = floor(-0.999, offset, Material::Diffuse);
+        let ray = Ray {
+            origin: offset + Vec3::new(0.1, -0.9995, 0.1),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            ..Default::default()
+        };
+        let intersection = lower.intersect(ray).unwrap();
+        let origin = offset_origin(intersection.get_pos(), intersection.get_geometric_normal());
+        let up = Ray {
+            origin,
+            direction: Vec3::new(0.0, 1.0, 0.0),
+            ..Default::default()
+        };
+        assert!(lower.intersect(up).is_none());
+        assert!(upper.intersect(up).is_some());
+        let down = Ray {
+            origin,
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            ..Default::default()
+        };
+        assert!(lower.intersect(down).is_some());
+    }
+
+    #[test]
+    fn watertight_shared_edge() {
+        //Quad split along its diagonal the same way ASCNLoader does it
+        let offset = Vec3::new(1234.5, -67.0, 891.0);
+        let corners = [
+            Vec3::new(0.0, 0.0, 0.0) + offset,
+            Vec3::new(3.0, 0.0, 0.0) + offset,
+            Vec3::new(3.0, 0.0, 7.0) + offset,
+            Vec3::new(0.0, 0.0, 7.0) + offset,
+        ];
+        let uv = [vector!(0.0, 0.0), vector!(0.0, 1.0), vector!(1.0, 0.0)];
+        let quad = vec![
+            Triangle::new([corners[0], corners[2], corners[1]], uv, TextureID::new(&0), Material::Diffuse),
+            Triangle::new([corners[0], corners[3], corners[2]], uv, TextureID::new(&0), Material::Diffuse),
+        ];
+        //Rays aimed exactly at points along the shared edge
+        for i in 1..1000 {
+            let target = corners[0].lerp(corners[2], i as Float / 1000.0);
+            let origin = offset + Vec3::new(-5.0 + i as Float * 0.013, 11.0, 2.0);
+            let ray = Ray {
+                origin,
+                direction: (target - origin).normalized(),
+                ..Default::default()
+            };
+            assert!(quad.intersect(ray).is_some(), "Ray {} leaked through the edge", i);
+        }
+    }
+
+    fn cutout_repo() -> TextureRepository {
+        //Left half transparent, right half opaque
+        let mut texture = Texture::new(2, 1);
+        texture.alpha = Some(vec![0, 255]);
+        let mut repo = TextureRepository::new();
+        repo.insert(TextureID::new(&0), texture);
+        repo
+    }
+    fn cutout_triangle(depth: Float, u: Float, repo: &TextureRepository) -> Triangle {
+        let mut triangle = Triangle::new(
+            [
+                Vec3::new(0.0, -depth, 1.0),
+                Vec3::new(1.0, -depth, -1.0),
+                Vec3::new(-1.0, -depth, -1.0),
+            ],
+            [vector!(u, 0.5), vector!(u, 0.5), vector!(u, 0.5)],
+            TextureID::new(&0),
+            Material::Diffuse,
+        );
+        triangle.apply_alpha_mask(repo);
+        triangle
+    }
+    #[test]
+    fn intersect_alpha_cutout() {
+        let repo = cutout_repo();
+        let ray = Ray {
+            origin: Vec3::from_single(0.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            ..Default::default()
+        };
+        let transparent = cutout_triangle(1.0, 0.25, &repo);
+        assert!(transparent.material.alpha_threshold().is_some());
+        assert!(transparent.intersect(ray).is_none());
+        let opaque = cutout_triangle(1.0, 0.75, &repo);
+        assert!(opaque.intersect(ray).is_some());
+    }
+    #[test]
+    fn intersect_alpha_cutout_bvh() {
+        let repo = cutout_repo();
+        let ray = Ray {
+            origin: Vec3::from_single(0.0),
+            direction: Vec3::new(0.0, -1.0, 0.0),
+            ..Default::default()
+        };
+        //The ray passes through the transparent triangle and hits the one behind it
+        let bvh = BVH::from_triangles(&[
+            cutout_triangle(1.0, 0.25, &repo),
+            cutout_triangle(2.0, 0.75, &repo),
+        ])
+        .unwrap();
+        let intersection = bvh.intersect(ray).unwrap();
+        assert_eq!(intersection.get_pos(), Vec3::new(0.0, -2.0, 0.0));
+    }
+}
+
+mod packet {
+    use crate::{
+        intersectables::{bvh::BVH, packet::PacketBVH, triangle::Triangle},
+        renderers::path_tracer::Material,
+        textures::TextureID,
+        utilities::{
+            math::{Float, Vec3},
+            ray::{Intersectable, Ray},
+        },
+        vector,
+    };
+
+    fn scene() -> Vec<Triangle> {
+        let uv = [vector!(0.0, 0.0), vector!(0.0, 1.0), vector!(1.0, 0.0)];
+        let mut triangles = Vec::new();
+        for x in -4..4 {
+            for z in -4..4 {
+                let height = -1.0 - ((x * 7 + z * 3) % 5) as Float * 0.1;
+                let a = Vec3::new(x as Float, height, z as Float);
+                let b = Vec3::new(x as Float + 1.0, height + 0.2, z as Float);
+                let c = Vec3::new(x as Float, height - 0.1, z as Float + 1.0);
+                let material = if (x + z) % 3 == 0 {
+                    Material::Emissive { power: 1.0 }
+                } else {
+                    Material::Diffuse
+                };
+                triangles.push(Triangle::new([a, b, c], uv, TextureID::new(&0), material));
+            }
+        }
+        triangles
+    }
+    fn compare<const N: usize>() {
+        let triangles = scene();
+        let bvh = BVH::from_triangles(&triangles).unwrap();
+        let packet = PacketBVH::<N>::from_triangles(&triangles).unwrap();
+        for i in 0..40 {
+            for j in 0..40 {
+                for origin in [Vec3::new(0.0, 3.0, 0.0), Vec3::new(0.5, -4.0, -0.5)] {
+                    let target = Vec3::new(i as Float * 0.2 - 4.0, -1.2, j as Float * 0.2 - 4.0);
+                    let ray = Ray {
+                        origin,
+                        direction: (target - origin).normalized(),
+                        ..Default::default()
+                    };
+                    let expected = bvh.intersect(ray).map(|i| i.get_distance());
+                    let actual = packet.intersect(ray).map(|i| i.get_distance());
+                    assert_eq!(expected, actual);
+                }
+            }
+        }
+    }
+    #[test]
+    fn matches_bvh_4() {
+        compare::<4>();
+    }
+    #[test]
+    fn matches_bvh_8() {
+        compare::<8>();
+    }
+    #[test]
+    fn empty() {
+        assert!(PacketBVH::<4>::from_triangles(&[]).is_none());
+    }
 }
 
 mod aabb {
@@ -174,3 +435,243 @@ mod aabb {
         assert!(aabb.intersect(ray).is_none());
     }
 }
+
+mod motion {
+    use cgmath::{Deg, Quaternion, Rotation3};
+
+    use crate::{
+        intersectables::{
+            motion::{Keyframe, MotionTransform},
+            sphere::Sphere,
+        },
+        utilities::{
+            math::{Float, Vec3},
+            ray::{Intersectable, Ray},
+        },
+    };
+
+    const EPSILON: Float = 0.00001;
+
+    fn moving_sphere() -> MotionTransform<Sphere> {
+        let identity = Quaternion::new(1.0, 0.0, 0.0, 0.0);
+        MotionTransform::new(
+            Sphere {
+                radius: 1.0,
+                ..Default::default()
+            },
+            vec![
+                Keyframe::new(1.0, Vec3::new(10.0, 0.0, 0.0), identity),
+                Keyframe::new(0.0, Vec3::new(0.0, 0.0, 0.0), identity),
+            ],
+        )
+    }
+
+    #[test]
+    fn intersect() {
+        let sphere = moving_sphere();
+        let ray = Ray::with_time(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let intersection = sphere.intersect(ray).unwrap();
+        assert!((intersection.get_pos() - Vec3::new(0.0, 0.0, -1.0)).length() < EPSILON);
+
+        let ray = Ray::with_time(Vec3::new(10.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 1.0);
+        let intersection = sphere.intersect(ray).unwrap();
+        assert!((intersection.get_pos() - Vec3::new(10.0, 0.0, -1.0)).length() < EPSILON);
+        assert!((intersection.get_distance() - 4.0).abs() < EPSILON);
+    }
+    #[test]
+    fn intersect_fail() {
+        let sphere = moving_sphere();
+        //The sphere has already left by the time the ray is cast
+        let ray = Ray::with_time(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 1.0);
+        assert!(sphere.intersect(ray).is_none());
+    }
+    #[test]
+    fn pose() {
+        let sphere = moving_sphere();
+        let (position, _) = sphere.pose(0.25).unwrap();
+        assert!((position - Vec3::new(2.5, 0.0, 0.0)).length() < EPSILON);
+        //Times outside the keyframes are clamped
+        let (position, _) = sphere.pose(2.0).unwrap();
+        assert!((position - Vec3::new(10.0, 0.0, 0.0)).length() < EPSILON);
+    }
+    #[test]
+    fn nan_keyframe() {
+        let identity = Quaternion::new(1.0, 0.0, 0.0, 0.0);
+        let sphere = MotionTransform::new(
+            Sphere {
+                radius: 1.0,
+                ..Default::default()
+            },
+            vec![
+                Keyframe::new(Float::NAN, Vec3::new(5.0, 0.0, 0.0), identity),
+                Keyframe::new(1.0, Vec3::new(10.0, 0.0, 0.0), identity),
+                Keyframe::new(0.0, Vec3::new(0.0, 0.0, 0.0), identity),
+            ],
+        );
+        assert_eq!(sphere.keyframes.len(), 2);
+        let (position, _) = sphere.pose(0.5).unwrap();
+        assert!((position - Vec3::new(5.0, 0.0, 0.0)).length() < EPSILON);
+    }
+    #[test]
+    fn rotated_normal() {
+        let rotation = Quaternion::from_angle_y(Deg(90.0));
+        let sphere = MotionTransform::new(
+            Sphere {
+                origin: Vec3::new(0.0, 0.0, 3.0),
+                radius: 1.0,
+                ..Default::default()
+            },
+            vec![Keyframe::new(0.0, Vec3::default(), rotation)],
+        );
+        //Rotating the sphere around the Y axis moves it from +Z to +X
+        let ray = Ray::new(Vec3::default(), Vec3::new(1.0, 0.0, 0.0));
+        let intersection = sphere.intersect(ray).unwrap();
+        assert!((intersection.get_pos() - Vec3::new(2.0, 0.0, 0.0)).length() < EPSILON);
+        assert!((intersection.get_normal() - Vec3::new(-1.0, 0.0, 0.0)).length() < EPSILON);
+    }
+}
+
+mod csg {
+    use crate::{
+        intersectables::{
+            bvh::BVH,
+            csg::{Csg, Solid},
+            cuboid::Cuboid,
+            sphere::Sphere,
+            triangle::Triangle,
+        },
+        renderers::path_tracer::Material,
+        textures::TextureID,
+        utilities::{
+            math::{Float, Vec3},
+            ray::{Intersectable, Ray},
+        },
+        vector,
+    };
+
+    const EPSILON: Float = 0.0001;
+
+    fn sphere(origin: Vec3, radius: Float) -> Sphere {
+        Sphere {
+            origin,
+            radius,
+            ..Default::default()
+        }
+    }
+
+    //Cube from -1 to 1 as a closed mesh
+    fn cube_mesh() -> BVH {
+        let mut triangles = Vec::new();
+        for axis in 0..3 {
+            for sign in [-1.0, 1.0] {
+                let corner = |a: Float, b: Float| {
+                    let mut p = Vec3::default();
+                    p[axis] = sign;
+                    p[(axis + 1) % 3] = a;
+                    p[(axis + 2) % 3] = b;
+                    p
+                };
+                let mut quad = [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)];
+                if sign < 0.0 {
+                    quad.reverse();
+                }
+                let uv = [vector![0.0, 0.0]; 3];
+                for [a, b, c] in [[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]] {
+                    triangles.push(Triangle::new([a, b, c], uv, TextureID::new(&0), Material::Diffuse));
+                }
+            }
+        }
+        BVH::from_triangles(&triangles).unwrap()
+    }
+
+    fn distances<S: Solid>(solid: &S, ray: Ray) -> Vec<(Float, Float)> {
+        solid
+            .spans(ray)
+            .iter()
+            .map(|span| (span.enter.distance, span.exit.distance))
+            .collect()
+    }
+
+    fn assert_spans(actual: Vec<(Float, Float)>, expected: &[(Float, Float)]) {
+        assert_eq!(actual.len(), expected.len(), "{:?}", actual);
+        for (a, b) in actual.iter().zip(expected) {
+            assert!((a.0 - b.0).abs() < EPSILON && (a.1 - b.1).abs() < EPSILON, "{:?}", actual);
+        }
+    }
+
+    fn hit<T: Intersectable>(object: &T, ray: Ray) -> (Float, Vec3) {
+        let intersection = object.intersect(ray).unwrap();
+        (intersection.get_distance(), intersection.get_normal())
+    }
+
+    #[test]
+    fn primitives() {
+        //Starting inside, the entry is behind the origin
+        let ray = Ray::new(Vec3::default(), Vec3::new(0.0, 0.0, 1.0));
+        assert_spans(distances(&sphere(Vec3::default(), 1.0), ray), &[(-1.0, 1.0)]);
+        assert_spans(distances(&Cuboid::new(Vec3::from_single(-1.0), Vec3::from_single(1.0)), ray), &[(-1.0, 1.0)]);
+        assert_spans(distances(&cube_mesh(), ray), &[(-1.0, 1.0)]);
+
+        //Through an edge shared by two triangles of the mesh
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert_spans(distances(&cube_mesh(), ray), &[(4.0, 6.0)]);
+
+        //Parallel to the faces of the box, but outside of it
+        let ray = Ray::new(Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        assert!(distances(&Cuboid::new(Vec3::from_single(-1.0), Vec3::from_single(1.0)), ray).is_empty());
+        assert!(distances(&cube_mesh(), ray).is_empty());
+    }
+
+    #[test]
+    fn union() {
+        let csg = Csg::union(sphere(Vec3::new(0.0, 0.0, 5.0), 1.0), sphere(Vec3::new(0.0, 0.0, 6.0), 1.0));
+        let ray = Ray::new(Vec3::default(), Vec3::new(0.0, 0.0, 1.0));
+        //Overlapping parts merge into one
+        assert_spans(distances(&csg, ray), &[(4.0, 7.0)]);
+        let intersection = csg.intersect(ray).unwrap();
+        assert!((intersection.get_distance() - 4.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn intersection() {
+        let csg = Csg::intersection(sphere(Vec3::new(0.0, 0.0, 5.0), 1.0), sphere(Vec3::new(0.0, 0.0, 6.0), 1.0));
+        let ray = Ray::new(Vec3::default(), Vec3::new(0.0, 0.0, 1.0));
+        //The lens starts on the second sphere and ends on the first
+        assert_spans(distances(&csg, ray), &[(5.0, 6.0)]);
+        let intersection = csg.intersect(ray).unwrap();
+        assert!((intersection.get_normal() - Vec3::new(0.0, 0.0, -1.0)).length() < EPSILON);
+
+        let disjoint = Csg::intersection(sphere(Vec3::new(0.0, 0.0, 5.0), 1.0), sphere(Vec3::new(0.0, 0.0, 9.0), 1.0));
+        assert!(disjoint.intersect(ray).is_none());
+    }
+
+    #[test]
+    fn difference() {
+        //A round window cut into a wall
+        let wall = Cuboid::new(Vec3::new(-2.0, -2.0, 4.0), Vec3::new(2.0, 2.0, 5.0));
+        let window = Csg::difference(wall, sphere(Vec3::new(0.0, 0.0, 4.5), 1.0));
+        let through = Ray::new(Vec3::default(), Vec3::new(0.0, 0.0, 1.0));
+        assert!(window.intersect(through).is_none());
+
+        let beside = Ray::new(Vec3::new(1.5, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let intersection = hit(&window, beside);
+        assert!((intersection.0 - 4.0).abs() < EPSILON);
+        assert!((intersection.1 - Vec3::new(0.0, 0.0, -1.0)).length() < EPSILON);
+
+        //Entering the hole at an angle hits its surface, which faces into the hole
+        let angled = Ray::new(Vec3::default(), Vec3::new(0.0, 0.95, 4.5).normalized());
+        let (distance, normal) = hit(&window, angled);
+        let pos = angled.direction * distance;
+        assert!(pos.z() > 4.5 && pos.z() < 5.0);
+        assert!(normal.y() < 0.0 && normal.z() < 0.0);
+    }
+
+    #[test]
+    fn nested_mesh() {
+        //A sphere cut out of a box mesh, then a smaller box put back in
+        let hollow = Csg::difference(cube_mesh(), sphere(Vec3::default(), 0.9));
+        let filled = Csg::union(hollow, Cuboid::new(Vec3::from_single(-0.5), Vec3::from_single(0.5)));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        assert_spans(distances(&filled, ray), &[(4.0, 4.1), (4.5, 5.5), (5.9, 6.0)]);
+    }
+}
@@ -10,8 +10,9 @@ impl<T: Intersectable> Intersectable for Transform<T>{
 
     fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
         let origin = ray.origin-self.transformation;
-        let ray = Ray::new(origin, ray.direction);
-        let mut result = self.inner.intersect(ray)?.to_builder();
+        let local_ray = Ray::with_time(origin, ray.direction, ray.time);
+        let mut result = self.inner.intersect(local_ray)?.to_builder();
+        result.ray = ray;
 
         if let Some(pos) = result.pos{
             result.pos = Some(pos + self.transformation);
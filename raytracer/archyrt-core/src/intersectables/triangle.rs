@@ -1,21 +1,24 @@
-use crate::renderers::path_tracer::Material;
+use crate::renderers::path_tracer::{Material, DEFAULT_ALPHA_THRESHOLD};
 use crate::textures::color_provider::ColorProvider;
 
 use crate::textures::samplers::linear::LinearSampler;
 use crate::textures::samplers::nearest::NearestSampler;
 use crate::textures::samplers::TextureSampler;
-use crate::textures::texture_repo::TextureRepository;
+use crate::textures::texture::Texture;
+use crate::textures::texture_repo::{TextureHandles, TextureRepository};
 use crate::textures::TextureID;
-use crate::utilities::math::{Axis3, Vec2};
+use crate::utilities::math::{Axis3, Float, Vec2};
 use crate::vector;
 use crate::{
     matrix,
     utilities::{
-        math::{Matrix3x3, Vec3},
+        math::Vec3,
         ray::{Intersectable, Intersection, IntersectionBuilder, Ray},
+        stats,
     },
 };
 use std::cmp::Ordering;
+use std::sync::Arc;
 
 use super::aabb::AABB;
 
@@ -31,6 +34,10 @@ pub struct Triangle {
     pub uv: [Vec2; 3],
     pub texture: TextureID,
     pub material: Material,
+    /// Coverage used for alpha testing, only set for alpha cutout materials
+    pub alpha_mask: Option<Arc<Texture>>,
+    /// Whether the back face can be hit, defaults to the material's setting
+    pub two_sided: bool,
 }
 
 impl Triangle {
@@ -53,6 +60,23 @@ impl Triangle {
             uv,
             texture,
             material,
+            alpha_mask: None,
+            two_sided: material.two_sided(),
+        }
+    }
+    /// Looks up the alpha mask of the triangle's texture. Diffuse triangles with transparent textures become alpha cutouts.
+    pub fn apply_alpha_mask(&mut self, repo: &TextureRepository) {
+        let mask = match repo.alpha_mask(self.texture) {
+            Some(mask) => mask,
+            None => return,
+        };
+        if let Material::Diffuse = self.material {
+            self.material = Material::AlphaCutout {
+                threshold: DEFAULT_ALPHA_THRESHOLD,
+            };
+        }
+        if self.material.alpha_threshold().is_some() {
+            self.alpha_mask = Some(mask);
         }
     }
     pub fn bounds(&self) -> AABB {
@@ -65,7 +89,7 @@ impl Triangle {
         (self.a + self.b + self.c) / 3.0
     }
 
-    pub fn side(&self, a: Axis3, divider: f64) -> Ordering {
+    pub fn side(&self, a: Axis3, divider: Float) -> Ordering {
         let o = self.a.get(a) >= divider;
         for p in [self.b, self.c].iter() {
             if (p.get(a) >= divider) != o {
@@ -98,55 +122,182 @@ pub struct TriangleColor {
     pub material: Material,
 }
 
+impl TriangleColor {
+    pub fn texcoord(&self) -> Vec2 {
+        self.uv[1] * self.barycentric[0]
+            + self.uv[2] * self.barycentric[1]
+            + self.uv[0] * self.barycentric[2]
+    }
+}
+
 impl ColorProvider for TriangleColor {
-    fn get_color(&self, repo: &TextureRepository) -> Vec3 {
-        self.sample(repo, self.texture)
+    fn get_color(&self, textures: &TextureHandles) -> Vec3 {
+        self.sample(textures, self.texture)
     }
 
     fn get_material(&self) -> Material {
         self.material
     }
 
-    fn sample(&self, repo: &TextureRepository, id: TextureID) -> Vec3 {
+    fn sample(&self, textures: &TextureHandles, id: TextureID) -> Vec3 {
         let sampler = LinearSampler {};
-        let coords = self.uv[1] * self.barycentric[0]
-            + self.uv[2] * self.barycentric[1]
-            + self.uv[0] * self.barycentric[2];
-        sampler.sample_or_default(repo.get(id), coords)
+        textures.with(id, |texture| sampler.sample_or_default(texture, self.texcoord()))
+    }
+
+    fn get_uv(&self) -> Option<Vec2> {
+        Some(self.texcoord())
+    }
+
+    fn get_texture(&self) -> Option<TextureID> {
+        Some(self.texture)
     }
 }
 
-impl Intersectable for Triangle {
-    type C = TriangleColor;
-    fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
-        //Based on https://www.scratchapixel.com/lessons/3d-basic-rendering/ray-tracing-rendering-a-triangle/moller-trumbore-ray-triangle-intersection
-        //Backface culling
-        if self.normal.dot(ray.direction) > 0.0 {
+/// Ray prepared for the watertight triangle test, see "Watertight Ray/Triangle Intersection" (Woop, Benthin, Wald)
+#[derive(Clone, Copy)]
+pub struct ShearedRay {
+    pub origin: Vec3,
+    /// Permutation of the axes so that the last one is the ray's dominant axis
+    pub axes: [usize; 3],
+    pub shear: Vec3,
+}
+
+/// Distance and barycentric coordinates of a ray-triangle hit, before any shading
+#[derive(Clone, Copy)]
+pub struct TriangleHit {
+    pub t: Float,
+    pub barycentric: Vec3,
+}
+
+impl ShearedRay {
+    pub fn new(ray: Ray) -> Self {
+        let direction = ray.direction;
+        let kz = match Vec3::new(direction.x().abs(), direction.y().abs(), direction.z().abs()).max_axis() {
+            Axis3::X => 0,
+            Axis3::Y => 1,
+            Axis3::Z => 2,
+        };
+        let mut kx = (kz + 1) % 3;
+        let mut ky = (kx + 1) % 3;
+        //Preserve the winding direction
+        if direction[kz] < 0.0 {
+            std::mem::swap(&mut kx, &mut ky);
+        }
+        let shear = Vec3::new(
+            direction[kx] / direction[kz],
+            direction[ky] / direction[kz],
+            1.0 / direction[kz],
+        );
+        Self {
+            origin: ray.origin,
+            axes: [kx, ky, kz],
+            shear,
+        }
+    }
+    /// Hits both sides of the triangle. Edges shared by two triangles can't be missed by both.
+    pub fn intersect(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<TriangleHit> {
+        let [kx, ky, kz] = self.axes;
+        let [sx, sy, sz] = self.shear.inner;
+        let a = a - self.origin;
+        let b = b - self.origin;
+        let c = c - self.origin;
+        let ax = a[kx] - sx * a[kz];
+        let ay = a[ky] - sy * a[kz];
+        let bx = b[kx] - sx * b[kz];
+        let by = b[ky] - sy * b[kz];
+        let cx = c[kx] - sx * c[kz];
+        let cy = c[ky] - sy * c[kz];
+        //Scaled barycentric coordinates
+        let u = cx * by - cy * bx;
+        let v = ax * cy - ay * cx;
+        let w = bx * ay - by * ax;
+        if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
             return None;
         }
-        let mat: Matrix3x3 = matrix!(-ray.direction, self.b - self.a, self.c - self.a);
-        let v = ray.origin - self.a;
-        let solution = mat.cramer(v)?;
-        let [t, u, v] = solution.inner;
-        if t < 0.0 || u < 0.0 || v < 0.0 || u + v > 1.0 {
+        let det = u + v + w;
+        if det == 0.0 {
             return None;
         }
-        let barycentric = Vec3::new(u, v, 1.0 - u - v);
-        let normal = matrix![self.bn, self.cn, self.an] * barycentric;
+        let t = sz * (u * a[kz] + v * b[kz] + w * c[kz]) / det;
+        if t < 0.0 {
+            return None;
+        }
+        Some(TriangleHit {
+            t,
+            barycentric: Vec3::new(v / det, w / det, u / det),
+        })
+    }
+}
+
+/// Vertices of a triangle stored apart from its shading data, with the index of the whole triangle
+#[derive(Clone, Copy)]
+pub struct TriangleRecord {
+    pub vertices: [Vec3; 3],
+    pub index: usize,
+}
+
+impl TriangleRecord {
+    pub fn new(triangle: &Triangle, index: usize) -> Self {
+        Self {
+            vertices: [triangle.a, triangle.b, triangle.c],
+            index,
+        }
+    }
+    pub fn intersect(&self, ray: &ShearedRay) -> Option<TriangleHit> {
+        let [a, b, c] = self.vertices;
+        ray.intersect(a, b, c)
+    }
+}
+
+impl Triangle {
+    /// Applies culling and alpha testing to a hit and builds the intersection
+    pub fn shade(&self, ray: Ray, hit: TriangleHit) -> Option<Intersection<TriangleColor>> {
+        //Backface culling
+        let backface = self.normal.dot(ray.direction) > 0.0;
+        if backface && !self.two_sided {
+            return None;
+        }
+        let barycentric = hit.barycentric;
+        let color_provider = TriangleColor {
+            uv: self.uv,
+            barycentric,
+            texture: self.texture,
+            material: self.material,
+        };
+        //Alpha testing
+        if let (Some(mask), Some(threshold)) = (&self.alpha_mask, self.material.alpha_threshold()) {
+            let sampler = NearestSampler {};
+            if sampler.sample_alpha(mask, color_provider.texcoord()) < threshold {
+                return None;
+            }
+        }
+        let mut normal = matrix![self.bn, self.cn, self.an] * barycentric;
+        let mut geometric_normal = self.normal;
+        //Both normals face the ray on the back side
+        if backface {
+            normal = -normal;
+            geometric_normal = -geometric_normal;
+        }
         Some(
             IntersectionBuilder {
                 ray,
-                distance: Some(t),
+                distance: Some(hit.t),
                 normal,
-                color_provider: TriangleColor {
-                    uv: self.uv,
-                    barycentric,
-                    texture: self.texture,
-                    material: self.material,
-                },
+                geometric_normal: Some(geometric_normal),
+                front_face: Some(!backface),
+                color_provider,
                 ..Default::default()
             }
             .build(),
         )
     }
 }
+
+impl Intersectable for Triangle {
+    type C = TriangleColor;
+    fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
+        stats::triangles(1);
+        let hit = ShearedRay::new(ray).intersect(self.a, self.b, self.c)?;
+        self.shade(ray, hit)
+    }
+}
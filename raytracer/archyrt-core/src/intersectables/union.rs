@@ -1,8 +1,8 @@
 use crate::{
     renderers::path_tracer::Material,
-    textures::{color_provider::ColorProvider, texture_repo::TextureRepository},
+    textures::{color_provider::ColorProvider, texture_repo::TextureHandles},
     utilities::{
-        math::Vec3,
+        math::{Vec2, Vec3},
         ray::{Intersectable, Intersection, Ray},
     },
 };
@@ -18,10 +18,10 @@ pub enum UnionColorProvider<A: ColorProvider + Clone, B: ColorProvider + Clone>
 impl<A: ColorProvider + Clone, B: ColorProvider + Clone> ColorProvider
     for UnionColorProvider<A, B>
 {
-    fn get_color(&self, repo: &TextureRepository) -> Vec3 {
+    fn get_color(&self, textures: &TextureHandles) -> Vec3 {
         match self {
-            UnionColorProvider::A(a) => a.get_color(repo),
-            UnionColorProvider::B(b) => b.get_color(repo),
+            UnionColorProvider::A(a) => a.get_color(textures),
+            UnionColorProvider::B(b) => b.get_color(textures),
         }
     }
     fn get_material(&self) -> Material {
@@ -31,10 +31,24 @@ impl<A: ColorProvider + Clone, B: ColorProvider + Clone> ColorProvider
         }
     }
 
-    fn sample(&self, repo: &TextureRepository, id: crate::textures::TextureID) -> Vec3 {
+    fn sample(&self, textures: &TextureHandles, id: crate::textures::TextureID) -> Vec3 {
         match self {
-            UnionColorProvider::A(a) => a.sample(repo, id),
-            UnionColorProvider::B(b) => b.sample(repo, id),
+            UnionColorProvider::A(a) => a.sample(textures, id),
+            UnionColorProvider::B(b) => b.sample(textures, id),
+        }
+    }
+
+    fn get_uv(&self) -> Option<Vec2> {
+        match self {
+            UnionColorProvider::A(a) => a.get_uv(),
+            UnionColorProvider::B(b) => b.get_uv(),
+        }
+    }
+
+    fn get_texture(&self) -> Option<crate::textures::TextureID> {
+        match self {
+            UnionColorProvider::A(a) => a.get_texture(),
+            UnionColorProvider::B(b) => b.get_texture(),
         }
     }
 }
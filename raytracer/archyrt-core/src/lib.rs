@@ -4,6 +4,8 @@ pub mod collector;
 pub mod intersectables;
 pub mod loaders;
 pub mod renderers;
+pub mod spectrum;
 pub mod textures;
 pub mod utilities;
-pub mod tonemapping;
\ No newline at end of file
+pub mod tonemapping;
+pub mod validation;
\ No newline at end of file
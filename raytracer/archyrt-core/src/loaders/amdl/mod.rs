@@ -1,6 +1,6 @@
 use std::{path::Path, fs::File, io::Read};
 
-use crate::{intersectables::triangle::Triangle, renderers::path_tracer::Material, textures::texture_repo::TextureRepository};
+use crate::{intersectables::triangle::Triangle, renderers::path_tracer::Material, textures::texture_repo::TextureRepository, utilities::cache::MemorySize};
 use anyhow::{anyhow, Result};
 use asset::Prop;
 
@@ -39,7 +39,7 @@ impl AMDLLoader{
                 }else{
                     Material::Diffuse
                 };
-                let triangle = Triangle::with_normals(
+                let mut triangle = Triangle::with_normals(
                     [
                         v1.position.into(),
                         v2.position.into(),
@@ -58,6 +58,7 @@ impl AMDLLoader{
                     texture,
                     material
                 );
+                triangle.apply_alpha_mask(textures);
                 triangles.push(triangle);
             }
         }
@@ -66,4 +67,10 @@ impl AMDLLoader{
         })
     }
 
-}
\ No newline at end of file
+}
+
+impl MemorySize for AMDLLoader {
+    fn memory_size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.triangles.len() * std::mem::size_of::<Triangle>()
+    }
+}
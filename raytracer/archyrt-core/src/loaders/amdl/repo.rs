@@ -1,9 +1,9 @@
-use std::{collections::{hash_map::DefaultHasher, HashMap}, hash::{Hash, Hasher}, path::Path, fs::File};
+use std::{collections::{hash_map::DefaultHasher, HashMap}, hash::{Hash, Hasher}, path::{Path, PathBuf}, fs::File, sync::Arc};
 
 use anyhow::{Result, anyhow};
 use serde::{Serialize, Deserialize};
 
-use crate::{intersectables::{bvh::{self, BVH}, apply_matrix::ApplyMatrix, transform::Transform}, utilities::math::{Vec3, Matrix3x3}, textures::texture_repo::TextureRepository};
+use crate::{intersectables::{bvh::{self, BVH}, apply_matrix::ApplyMatrix, instance::Instance, motion::{Keyframe, MotionTransform}, transform::Transform, triangle::TriangleColor}, utilities::{cache::SharedCache, math::{Vec3, Matrix3x3}, ray::{Intersectable, Intersection, Ray}}, textures::texture_repo::TextureRepository};
 
 use super::AMDLLoader;
 
@@ -35,26 +35,73 @@ impl Default for PropID {
     }
 }
 
+/// Props are loaded on first use and evicted once the memory budget is exceeded.
+/// The repository can be shared between threads.
 pub struct PropRepository {
-    pub objects: HashMap<PropID, AMDLLoader>,
+    sources: HashMap<PropID, PathBuf>,
+    cache: SharedCache<PropID, AMDLLoader>,
 }
 
 impl PropRepository {
     pub fn new() -> Self {
-        let t = HashMap::new();
-        Self { objects: t }
+        Self::with_budget(usize::MAX)
     }
-    pub fn get(&self, id: PropID) -> Option<&AMDLLoader> {
-        let object = self.objects.get(&id)?;
-        Some(object)
+    /// Budget is in bytes
+    pub fn with_budget(budget: usize) -> Self {
+        Self {
+            sources: HashMap::new(),
+            cache: SharedCache::new(budget),
+        }
+    }
+    /// Textures are needed to set up the materials of props that haven't been loaded yet
+    pub fn get(&self, id: PropID, textures: &TextureRepository) -> Option<Arc<AMDLLoader>> {
+        let path = match self.sources.get(&id) {
+            Some(path) => path,
+            None => return self.cache.get(id),
+        };
+        self.cache.get_or_load(id, || match AMDLLoader::from_path(path, textures) {
+            Ok(object) => Some(object),
+            Err(err) => {
+                println!("Could not load prop {:?}: {}", path, err);
+                None
+            }
+        })
+    }
+    pub fn exists(&self, id: PropID) -> bool {
+        self.sources.contains_key(&id) || self.cache.contains(id)
     }
+    /// Adds a loaded prop. It is kept in memory regardless of the budget.
     pub fn insert(&mut self, id: PropID, object: AMDLLoader) {
-        self.objects.insert(id, object);
+        self.sources.remove(&id);
+        self.cache.insert_pinned(id, object);
     }
-    pub fn fulfill(&self, req: &PropRequest) -> Result<Transform<ApplyMatrix<BVH>>>{
-        let object = self.get(req.prop).ok_or(anyhow!("Invalid prop id"))?;
+    /// Adds a prop to be loaded on first use
+    pub fn register(&mut self, id: PropID, path: PathBuf) -> Result<()> {
+        if !path.is_file() {
+            return Err(anyhow!("Prop {:?} does not exist", path));
+        }
+        self.sources.insert(id, path);
+        Ok(())
+    }
+    /// Bytes used by the currently loaded props
+    pub fn memory_usage(&self) -> usize {
+        self.cache.memory_usage()
+    }
+    pub fn fulfill(&self, req: &PropRequest, textures: &TextureRepository) -> Result<PlacedProp>{
+        let object = self.get(req.prop, textures).ok_or(anyhow!("Invalid prop id"))?;
         let object = &object.triangles;
         let object = BVH::from_triangles(object).ok_or(anyhow!("Invalid geometry"))?;
+        if !req.keyframes.is_empty() {
+            //Flipping z mirrors the prop, what's left of its pose is a rotation
+            let mut mirror = Matrix3x3::identity();
+            mirror[2][2] = -1.0;
+            let object = ApplyMatrix{
+                inner: object,
+                matrix: mirror,
+                inverse_matrix: mirror
+            };
+            return Ok(PlacedProp::Moving(MotionTransform::new(object, req.keyframes.clone())));
+        }
         let object = ApplyMatrix{
             inner: object, 
             matrix: req.matrix,
@@ -64,13 +111,17 @@ impl PropRepository {
             inner: object,
             transformation: req.position
         };
-        Ok(object)
+        Ok(PlacedProp::Static(object))
     }
-    pub fn fulfill_all(&self, requests: &[PropRequest]) -> Result<Vec<Transform<ApplyMatrix<BVH>>>>{
+    /// Props are numbered from 1 in the order of the requests, 0 is left for the scene's own geometry
+    pub fn fulfill_all(&self, requests: &[PropRequest], textures: &TextureRepository) -> Result<Vec<Instance<PlacedProp>>>{
         let mut output = Vec::with_capacity(requests.len());
-        for req in requests{
-            let prop = self.fulfill(req)?;
-            output.push(prop);
+        for (index, req) in requests.iter().enumerate(){
+            let prop = self.fulfill(req, textures)?;
+            output.push(Instance{
+                inner: prop,
+                id: index as u32 + 1
+            });
         }
         Ok(output)
     }
@@ -87,16 +138,14 @@ struct Repo{
     pub props: Vec<PropInfo>
 }
 
-pub fn load_into(repo: &mut PropRepository, textures: &TextureRepository, directory: &str) -> Result<()> {
+/// Registers the props of an asset directory to be loaded on first use
+pub fn load_into(repo: &mut PropRepository, directory: &str) -> Result<()> {
     let propsjson = Path::new(directory).join("repo.json");
     let propsjson = File::open(propsjson)?;
     let json: Repo = serde_json::from_reader(propsjson)?;
     for prop in json.props {
         let path = Path::new(directory).join("props").join(prop.name).with_extension("amdl");
-        repo.insert(
-            PropType::default(prop.id),
-            AMDLLoader::from_path(path, textures)?
-        );
+        repo.register(PropType::default(prop.id), path)?;
     }
     Ok(())
 }
@@ -108,4 +157,23 @@ pub struct PropRequest{
     pub position: Vec3,
     pub matrix: Matrix3x3,
     pub inverse_matrix: Matrix3x3,
+    /// Poses over time, which replace the position and matrices if there are any
+    pub keyframes: Vec<Keyframe>,
+}
+
+/// Prop put into a scene, standing still or moving between keyframes
+pub enum PlacedProp {
+    Static(Transform<ApplyMatrix<BVH>>),
+    Moving(MotionTransform<ApplyMatrix<BVH>>),
+}
+
+impl Intersectable for PlacedProp {
+    type C = TriangleColor;
+
+    fn intersect(&self, ray: Ray) -> Option<Intersection<Self::C>> {
+        match self {
+            PlacedProp::Static(object) => object.intersect(ray),
+            PlacedProp::Moving(object) => object.intersect(ray),
+        }
+    }
 }
\ No newline at end of file
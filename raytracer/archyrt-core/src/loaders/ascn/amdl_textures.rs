@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use std::{fs::File, path::Path};
 
 use crate::textures::{
-    texture_repo::{png, TextureRepository},
+    texture_repo::{png, TextureRepository, TextureSource},
     TextureID,
 };
 
@@ -42,15 +42,15 @@ pub fn load_into(repo: &mut TextureRepository, directory: &str) -> Result<()> {
         let textures_directory = Path::new(directory).join("textures");
         let textures_directory = textures_directory.to_str().ok_or(anyhow!("Unable to decode path string"))?;
         if let Some(emissive) = tex.emissive{
-            repo.insert(
+            repo.register(
                 AMDLTextureType::emissive(tex.id),
-                png::load(textures_directory, &emissive)?,
-            );
+                TextureSource::Png(png::path(textures_directory, &emissive)),
+            )?;
         }
-        repo.insert(
+        repo.register(
             AMDLTextureType::diffuse(tex.id),
-            png::load(textures_directory, &tex.name)?,
-        );
+            TextureSource::Png(png::path(textures_directory, &tex.name)),
+        )?;
     }
     Ok(())
 }
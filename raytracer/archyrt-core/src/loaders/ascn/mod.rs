@@ -1,14 +1,20 @@
 pub mod amdl_textures;
+pub mod settings;
+#[cfg(test)]
+mod tests;
+use crate::intersectables::bvh::BVH;
 use crate::intersectables::triangle::Triangle;
 use crate::loaders::Loader;
 
-use crate::renderers::path_tracer::Material;
+use crate::renderers::path_tracer::medium::{Media, Volume};
+use crate::textures::texture_repo::{self, TextureRepository};
+use crate::textures::TextureID;
 
-use crate::utilities::math::{Vec2, Vec3, Matrix3x3};
+use crate::utilities::math::{Float, Vec2, Vec3, Matrix3x3};
 use crate::{cameras::perspective::PerspectiveCamera, vector};
 use anyhow::{anyhow, Result};
-use asset::scene::{Scene, Point};
-use cgmath::{Rotation, Matrix3, Matrix, SquareMatrix};
+use asset::scene::{face_normal, face_triangles, Scene, Point};
+use cgmath::{Matrix3, Matrix, SquareMatrix, Vector3};
 
 use std::collections::HashMap;
 use std::fs::File;
@@ -17,27 +23,29 @@ use std::io::Read;
 use std::path::Path;
 
 use self::amdl_textures::AMDLTextureType;
+use self::settings::{MediumSettings, SceneSettings};
 
 use super::amdl::repo::{PropRequest, PropType};
 
+/// Texture the workers use as the sky
+pub fn skybox() -> TextureID {
+    TextureID::new(&"skybox")
+}
+
+/// Registers the material textures and the skybox of an assets directory, everything a render needs
+pub fn load_textures(repo: &mut TextureRepository, directory: &str) -> Result<()> {
+    amdl_textures::load_into(repo, directory)?;
+    texture_repo::exr::load_into(repo, directory, &[(skybox(), "skybox.exr")])
+}
 
 pub struct ASCNLoader {
     triangles: Vec<Triangle>,
     camera: PerspectiveCamera,
-    prop_requests: Vec<PropRequest>
-}
-fn texcoord(position: Vec3, normal: Vec3) -> Vec2 {
-    if normal.x().abs() > normal.y().abs() {
-        if normal.x().abs() > normal.z().abs() {
-          vector!(position.z(), position.y())
-        } else {
-          vector!(position.x(), position.y())
-        }
-      } else if normal.y().abs() > normal.z().abs() {
-        vector!(position.x(), position.z())
-      } else {
-          vector!(position.x(), position.y())
-      }
+    shutter: (Float, Float),
+    prop_requests: Vec<PropRequest>,
+    fog: Option<MediumSettings>,
+    /// Triangles of the solids filled with media
+    volumes: Vec<(Vec<Triangle>, MediumSettings)>,
 }
 
 impl ASCNLoader {
@@ -48,11 +56,18 @@ impl ASCNLoader {
         Self::from_bytes(&buf)
     }
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        Self::from_bytes_with(data, &SceneSettings::default())
+    }
+    pub fn from_bytes_with(data: &[u8], settings: &SceneSettings) -> Result<Self> {
         let scene = Scene::decode(data).ok_or_else(||anyhow!("Could not decode scene"))?;
-        Self::from_scene(scene)
+        Self::from_scene_with(scene, settings)
     }
 
     pub fn from_scene(scene: Scene) -> Result<Self> {
+        Self::from_scene_with(scene, &SceneSettings::default())
+    }
+
+    pub fn from_scene_with(scene: Scene, settings: &SceneSettings) -> Result<Self> {
         let mut triangles: Vec<Triangle> = Vec::new();
         let focal_distance = 0.595877;
         let mut camera_pos: Vec3 = scene.camera.position.into();
@@ -60,72 +75,77 @@ impl ASCNLoader {
         let rotation: Vec2 = scene.camera.rotation.into();
         let mut camera = PerspectiveCamera::from_euler(
             camera_pos,
-            vector![rotation.x(), rotation.y(), 0.0] / 180.0 * std::f64::consts::PI,
+            vector![rotation.x(), rotation.y(), 0.0] / 180.0 * crate::utilities::math::consts::PI,
             focal_distance,
         );
         camera.matrix = camera.matrix.transpose();
-        for solid in &scene.world.solids {
+        for volume in &settings.volumes {
+            if volume.solid >= scene.world.solids.len() {
+                return Err(anyhow!("Solid {} is filled with a medium, but the scene has no such solid", volume.solid));
+            }
+        }
+        let mut volumes = Vec::new();
+        for (solid_index, solid) in scene.world.solids.iter().enumerate() {
+            let medium = settings.volume(solid_index);
+            //A volume's boundary has to be closed, so untextured faces are kept
+            let mut solid_triangles = Vec::new();
             for face in &solid.faces {
-                if face.texture.0 == 0 {
+                if face.texture.0 == 0 && medium.is_none() {
                     continue;
                 }
-                //Counterclockwise
                 let points: Vec<&Point> = face
                     .indices
                     .iter()
-                    .map(|id| &solid.points[(*id) as usize])
+                    .filter_map(|id| solid.points.get(*id as usize))
+                    .collect();
+                if points.len() < 3 || points.len() != face.indices.len() {
+                    return Err(anyhow!("Solid {} has a face with invalid indices", solid_index));
+                }
+
+                //Texture coordinates are computed exactly like in the editor, in single precision
+                let meters: Vec<Vector3<f32>> = points
+                    .iter()
+                    .map(|point| point.position.map(|e| e as f32 / 128.0))
                     .collect();
-                let point_positions: Vec<Vec3> = points
+                let normal = face_normal(&meters);
+                let uvs: Vec<Vec2> = meters
                     .iter()
-                    .map(|point| (point.position).into())
-                    .map(|point: Vec3| point/128.0)
+                    .map(|position| face.alignment.texcoord(*position, normal).into())
                     .collect();
-                let edge0 = point_positions[1] - point_positions[0];
-                let edge1 = point_positions[3] - point_positions[0];
-                let normal = edge0.cross(edge1).normalized();
-
-                let uv0 = texcoord(point_positions[0], normal);
-                let uv1 = texcoord(point_positions[1], normal);
-                let uv2 = texcoord(point_positions[2], normal);
-                let uv3 = texcoord(point_positions[3], normal);
-
-                let point0 = {
-                    let mut a = point_positions[0];
-                    a[2] = -a[2];
-                    a
-                };
-                let point1 = {
-                    let mut a = point_positions[1];
-                    a[2] = -a[2];
-                    a
-                };
-                let point2 = {
-                    let mut a = point_positions[2];
-                    a[2] = -a[2];
-                    a
-                };
-                let point3 = {
-                    let mut a = point_positions[3];
-                    a[2] = -a[2];
-                    a
-                };
-                let triangle1 = Triangle::new(
-                    [point0, point2, point1],
-                    [uv0, uv2, uv1],
-                    AMDLTextureType::diffuse(face.texture.0),
-                    Material::Diffuse,
-                );
-                let triangle2 = Triangle::new(
-                    [point0, point3, point2],
-                    [uv0, uv3, uv2],
-                    AMDLTextureType::diffuse(face.texture.0),
-                    Material::Diffuse,
-                );
-                triangles.push(triangle1);
-                triangles.push(triangle2);
+
+                let positions: Vec<Vec3> = points
+                    .iter()
+                    .map(|point| {
+                        let mut position = Vec3::from(point.position) / 128.0;
+                        position[2] = -position[2];
+                        position
+                    })
+                    .collect();
+
+                //Flipping z turns the winding of the face around
+                for [a, b, c] in face_triangles(points.len()) {
+                    solid_triangles.push(Triangle::new(
+                        [positions[a], positions[c], positions[b]],
+                        [uvs[a], uvs[c], uvs[b]],
+                        AMDLTextureType::diffuse(face.texture.0),
+                        settings.material(face.texture.0),
+                    ));
+                }
+            }
+            match medium {
+                Some(medium) => volumes.push((solid_triangles, medium)),
+                None => triangles.append(&mut solid_triangles),
+            }
+        }
+        for motion in &settings.motion {
+            if motion.prop >= scene.world.props.len() {
+                return Err(anyhow!("Prop {} is moved, but the scene has no such prop", motion.prop));
+            }
+            if motion.keyframes.is_empty() {
+                return Err(anyhow!("Prop {} is moved without keyframes", motion.prop));
             }
         }
-        let prop_requests: Vec<PropRequest> = scene.world.props.iter().map(|prop|{
+        let prop_requests: Vec<PropRequest> = scene.world.props.iter().enumerate().map(|(index, prop)|{
             let mut pos: Vec3 = prop.position.into();
             pos.inner[2] = -pos.inner[2];
             pos = pos/128.0;
@@ -139,14 +159,48 @@ impl ASCNLoader {
                 prop: PropType::default(prop.asset.0),
                 position: pos,
                 matrix: matrix.into(),
-                inverse_matrix: inverse_matrix.into()
+                inverse_matrix: inverse_matrix.into(),
+                keyframes: settings.keyframes(index),
             }
         }).collect();
-        Ok(Self { camera, triangles, prop_requests })
+        Ok(Self {
+            camera,
+            triangles,
+            shutter: settings.shutter(),
+            prop_requests,
+            fog: settings.fog,
+            volumes,
+        })
+    }
+    /// Seconds the camera's shutter opens and closes at
+    pub fn get_shutter(&self) -> (Float, Float) {
+        self.shutter
+    }
+    /// Media the renderers move through, volumes are bounded by their solids
+    pub fn get_media(&self) -> Media {
+        Media {
+            global: self.fog.map(MediumSettings::to_medium),
+            volumes: self
+                .volumes
+                .iter()
+                .filter_map(|(triangles, medium)| {
+                    Some(Volume {
+                        boundary: BVH::from_triangles(triangles)?,
+                        medium: medium.to_medium(),
+                    })
+                })
+                .collect(),
+        }
     }
     pub fn get_prop_requests(&self) -> &Vec<PropRequest>{
         &self.prop_requests
     }
+    /// Turns faces with transparent textures into alpha cutouts
+    pub fn apply_alpha_masks(&mut self, repo: &TextureRepository) {
+        for triangle in &mut self.triangles {
+            triangle.apply_alpha_mask(repo);
+        }
+    }
 }
 
 impl Loader for ASCNLoader {
@@ -0,0 +1,196 @@
+use anyhow::{anyhow, Result};
+use cgmath::{InnerSpace, Quaternion};
+use serde::Deserialize;
+
+use crate::{
+    intersectables::motion::Keyframe,
+    renderers::path_tracer::{
+        medium::{Medium, MediumDensity},
+        Material,
+    },
+    utilities::math::{Float, Vec3},
+};
+
+/// Render settings that go with a scene, for what the editor's scene format doesn't hold.
+/// Jobs carry them as JSON, every field is optional.
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneSettings {
+    /// Seconds the shutter opens and closes at, rays are cast at random times in between.
+    /// Without one every ray is cast at 0.
+    pub shutter: Option<[Float; 2]>,
+    /// Props moving while the shutter is open
+    pub motion: Vec<PropMotion>,
+    /// Medium filling the whole scene
+    pub fog: Option<MediumSettings>,
+    /// Media filling solids of the scene, which are not rendered themselves
+    pub volumes: Vec<VolumeSettings>,
+    /// Textures whose faces are glass instead of diffuse
+    pub glass: Vec<GlassSettings>,
+}
+
+/// Poses of a prop over time, they replace where the scene put it
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PropMotion {
+    /// Index of the prop in the scene
+    pub prop: usize,
+    pub keyframes: Vec<PropKeyframe>,
+}
+
+/// Pose of a prop at a point in time, given like the props of the scene
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct PropKeyframe {
+    pub time: Float,
+    /// In grid units
+    pub position: [Float; 3],
+    /// Local to world rotation as w, x, y, z
+    pub rotation: [Float; 4],
+}
+
+/// Homogeneous medium, see [Medium]. Left out fields take the defaults of [Medium].
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MediumSettings {
+    pub absorption: Float,
+    pub scattering: Float,
+    pub color: [Float; 3],
+    pub anisotropy: Float,
+}
+
+impl Default for MediumSettings {
+    fn default() -> Self {
+        let medium = Medium::default();
+        Self {
+            absorption: medium.absorption,
+            scattering: medium.scattering,
+            color: medium.color.inner,
+            anisotropy: medium.anisotropy,
+        }
+    }
+}
+
+impl MediumSettings {
+    fn is_valid(&self) -> bool {
+        let finite = [self.absorption, self.scattering, self.anisotropy]
+            .iter()
+            .chain(&self.color)
+            .all(|value| value.is_finite());
+        finite && self.absorption >= 0.0 && self.scattering >= 0.0 && self.anisotropy.abs() < 1.0
+    }
+    pub fn to_medium(self) -> Medium {
+        let [r, g, b] = self.color;
+        Medium {
+            absorption: self.absorption,
+            scattering: self.scattering,
+            color: Vec3::new(r, g, b),
+            anisotropy: self.anisotropy,
+            density: MediumDensity::Homogeneous,
+        }
+    }
+}
+
+/// Medium bounded by a closed solid
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct VolumeSettings {
+    /// Index of the solid in the scene
+    pub solid: usize,
+    #[serde(default)]
+    pub medium: MediumSettings,
+}
+
+/// Glass the faces with a texture are made of, see [Material::Glass]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GlassSettings {
+    /// Texture id as in the scene, the texture still tints the glass
+    pub texture: u32,
+    pub ior: Float,
+    #[serde(default)]
+    pub dispersion: Float,
+}
+
+impl GlassSettings {
+    fn is_valid(&self) -> bool {
+        self.ior.is_finite() && self.ior >= 1.0 && self.dispersion.is_finite() && self.dispersion >= 0.0
+    }
+}
+
+impl SceneSettings {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let settings: Self = serde_json::from_str(json)?;
+        if let Some([open, close]) = settings.shutter {
+            if !open.is_finite() || !close.is_finite() {
+                return Err(anyhow!("The shutter interval has to be finite"));
+            }
+        }
+        for motion in &settings.motion {
+            for keyframe in &motion.keyframes {
+                let finite = keyframe.position.iter().chain(&keyframe.rotation).all(|value| value.is_finite());
+                if !finite || keyframe.rotation == [0.0; 4] {
+                    return Err(anyhow!("Prop {} has an invalid keyframe", motion.prop));
+                }
+            }
+        }
+        if !settings.fog.iter().all(MediumSettings::is_valid) {
+            return Err(anyhow!("The fog has an invalid medium"));
+        }
+        for (index, volume) in settings.volumes.iter().enumerate() {
+            if !volume.medium.is_valid() {
+                return Err(anyhow!("Solid {} has an invalid medium", volume.solid));
+            }
+            if settings.volumes[..index].iter().any(|other| other.solid == volume.solid) {
+                return Err(anyhow!("Solid {} is filled with more than one medium", volume.solid));
+            }
+        }
+        for (index, glass) in settings.glass.iter().enumerate() {
+            if !glass.is_valid() {
+                return Err(anyhow!("Texture {} is invalid glass", glass.texture));
+            }
+            if settings.glass[..index].iter().any(|other| other.texture == glass.texture) {
+                return Err(anyhow!("Texture {} is more than one glass", glass.texture));
+            }
+        }
+        Ok(settings)
+    }
+    /// Shutter interval, closing at once at 0 if none was given
+    pub fn shutter(&self) -> (Float, Float) {
+        self.shutter.map_or((0.0, 0.0), |[open, close]| (open, close))
+    }
+    /// Medium a solid is filled with, None if it's rendered
+    pub fn volume(&self, solid: usize) -> Option<MediumSettings> {
+        self.volumes.iter().find(|volume| volume.solid == solid).map(|volume| volume.medium)
+    }
+    /// Material of the faces with a texture
+    pub fn material(&self, texture: u32) -> Material {
+        match self.glass.iter().find(|glass| glass.texture == texture) {
+            Some(glass) => Material::Glass {
+                ior: glass.ior,
+                dispersion: glass.dispersion,
+            },
+            None => Material::Diffuse,
+        }
+    }
+    /// Keyframes of a prop in the raytracer's space, empty if it doesn't move
+    pub fn keyframes(&self, prop: usize) -> Vec<Keyframe> {
+        self.motion
+            .iter()
+            .filter(|motion| motion.prop == prop)
+            .flat_map(|motion| motion.keyframes.iter().map(|keyframe| keyframe.to_keyframe()))
+            .collect()
+    }
+}
+
+impl PropKeyframe {
+    /// The raytracer flips z, which mirrors the rotation too. Keyframes place props that were already mirrored,
+    /// see [PlacedProp](crate::loaders::amdl::repo::PlacedProp).
+    pub fn to_keyframe(self) -> Keyframe {
+        let [x, y, z] = self.position;
+        let [w, i, j, k] = self.rotation;
+        //Mirroring along z turns the rotation axis around in x and y
+        let rotation = Quaternion::new(w, -i, -j, k).normalize();
+        Keyframe::new(self.time, Vec3::new(x, y, -z) / 128.0, rotation)
+    }
+}
@@ -0,0 +1,278 @@
+use asset::{
+    scene::{Camera, Face, Point, Prop, Scene, Solid, World},
+    PropID, TextureID,
+};
+use cgmath::{Deg, InnerSpace, Quaternion, Rotation3, Vector2, Vector3};
+
+use crate::{
+    intersectables::triangle::Triangle,
+    loaders::{
+        amdl::{
+            repo::{PropRepository, PropType},
+            AMDLLoader,
+        },
+        Loader,
+    },
+    renderers::path_tracer::Material,
+    textures::texture_repo::TextureRepository,
+    utilities::{
+        math::{Float, Vec3},
+        ray::{Intersectable, Ray},
+    },
+    vector,
+};
+
+use super::{settings::SceneSettings, ASCNLoader};
+
+fn load(solids: Vec<Solid>) -> anyhow::Result<ASCNLoader> {
+    load_with(solids, &SceneSettings::default())
+}
+
+fn load_with(solids: Vec<Solid>, settings: &SceneSettings) -> anyhow::Result<ASCNLoader> {
+    let scene = Scene {
+        camera: Camera {
+            position: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Vector2::new(0.0, 0.0),
+        },
+        world: World {
+            solids,
+            props: Vec::new(),
+            outline: Default::default(),
+        },
+    };
+    ASCNLoader::from_scene_with(scene, settings)
+}
+
+fn solid(points: &[(i32, i32, i32)], faces: &[&[u32]]) -> Solid {
+    Solid {
+        points: points
+            .iter()
+            .map(|&(x, y, z)| Point {
+                position: Vector3::new(x * 128, y * 128, z * 128),
+            })
+            .collect(),
+        faces: faces
+            .iter()
+            .map(|indices| Face {
+                texture: TextureID(1),
+                indices: indices.to_vec(),
+                alignment: Default::default(),
+            })
+            .collect(),
+    }
+}
+
+//Triangular prism along z, the shape of a ramp
+fn wedge() -> Solid {
+    solid(
+        &[(0, 0, 0), (1, 0, 0), (0, 1, 0), (0, 0, 1), (1, 0, 1), (0, 1, 1)],
+        &[&[0, 2, 1], &[3, 4, 5], &[0, 1, 4, 3], &[0, 3, 5, 2], &[1, 2, 5, 4]],
+    )
+}
+
+fn cube() -> Solid {
+    solid(
+        &[(0, 0, 0), (1, 0, 0), (1, 0, 1), (0, 0, 1), (0, 1, 0), (1, 1, 0), (1, 1, 1), (0, 1, 1)],
+        &[&[1, 5, 6, 2], &[4, 0, 3, 7], &[5, 4, 7, 6], &[0, 1, 2, 3], &[3, 2, 6, 7], &[1, 0, 4, 5]],
+    )
+}
+
+#[test]
+fn box_solid() {
+    let loader = load(vec![cube()]).unwrap();
+    let triangles = loader.get_triangles();
+    assert_eq!(triangles.len(), 12);
+    //Normals point out of the box, whose center is flipped along z like the corners
+    let center = Vec3::new(0.5, 0.5, -0.5);
+    for triangle in triangles {
+        assert!(triangle.normal.dot(triangle.centroid() - center) > 0.0);
+    }
+}
+
+#[test]
+fn convex_solid() {
+    let loader = load(vec![wedge()]).unwrap();
+    let triangles = loader.get_triangles();
+    //Two triangles and three quads
+    assert_eq!(triangles.len(), 8);
+    let center = Vec3::new(1.0 / 3.0, 1.0 / 3.0, -0.5);
+    for triangle in triangles {
+        assert!(triangle.normal.dot(triangle.centroid() - center) > 0.0);
+    }
+}
+
+#[test]
+fn invalid_indices() {
+    let mut out_of_range = wedge();
+    out_of_range.faces[0].indices[1] = 6;
+    assert!(load(vec![out_of_range]).is_err());
+
+    let mut line = wedge();
+    line.faces[1].indices.truncate(2);
+    assert!(load(vec![line]).is_err());
+}
+
+#[test]
+fn media() {
+    let settings = settings(r#"{"fog": {"absorption": 0.5}, "volumes": [{"solid": 0, "medium": {"scattering": 2}}]}"#);
+    let mut untextured = cube();
+    untextured.faces[0].texture = TextureID(0);
+    let loader = load_with(vec![untextured, wedge()], &settings).unwrap();
+    //The filled cube isn't rendered, only the wedge is
+    assert_eq!(loader.get_triangles().len(), 8);
+    let media = loader.get_media();
+    let fog = media.global.unwrap();
+    assert_eq!((fog.absorption, fog.scattering), (0.5, 0.1));
+    assert_eq!(media.volumes.len(), 1);
+    assert_eq!(media.volumes[0].medium.scattering, 2.0);
+    //The boundary is closed even where the cube has no texture
+    for x in [0.05, 0.5, 0.95] {
+        let ray = Ray::new(Vec3::new(x, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let (enter, exit) = media.volumes[0].segment(ray).unwrap();
+        assert!((enter - 1.0).abs() < 1e-4 && (exit - 2.0).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn invalid_media() {
+    for json in [
+        r#"{"fog": {"scattering": -1}}"#,
+        r#"{"fog": {"anisotropy": 1}}"#,
+        r#"{"fog": {"density": 1}}"#,
+        r#"{"volumes": [{"solid": 0, "medium": {"absorption": -0.5}}]}"#,
+        r#"{"volumes": [{"solid": 0}, {"solid": 0}]}"#,
+    ] {
+        assert!(SceneSettings::from_json(json).is_err(), "{}", json);
+    }
+    let missing = settings(r#"{"volumes": [{"solid": 1}]}"#);
+    assert!(load_with(vec![cube()], &missing).is_err());
+}
+
+fn prop_scene(rotation: Quaternion<f32>) -> Scene {
+    Scene {
+        camera: Camera {
+            position: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Vector2::new(0.0, 0.0),
+        },
+        world: World {
+            solids: Vec::new(),
+            props: vec![Prop {
+                asset: PropID(1),
+                position: Vector3::new(0, 0, 1280),
+                rotation,
+            }],
+            outline: Default::default(),
+        },
+    }
+}
+
+fn prop_repo() -> PropRepository {
+    let triangle = Triangle::new(
+        [Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)],
+        [vector![0.0, 0.0], vector![0.0, 1.0], vector![1.0, 0.0]],
+        Default::default(),
+        Material::Diffuse,
+    );
+    let mut props = PropRepository::new();
+    props.insert(
+        PropType::default(1),
+        AMDLLoader {
+            triangles: vec![triangle],
+        },
+    );
+    props
+}
+
+fn settings(json: &str) -> SceneSettings {
+    SceneSettings::from_json(json).unwrap()
+}
+
+#[test]
+fn scene_settings() {
+    let settings = settings(r#"{"shutter": [0.5, 0.0]}"#);
+    assert_eq!(settings.shutter(), (0.5, 0.0));
+    assert!(settings.motion.is_empty());
+    assert_eq!(SceneSettings::from_json("{}").unwrap(), SceneSettings::default());
+    assert!(SceneSettings::from_json(r#"{"shuter": [0.0, 1.0]}"#).is_err());
+    let zero = r#"{"motion": [{"prop": 0, "keyframes": [{"time": 0, "position": [0, 0, 0], "rotation": [0, 0, 0, 0]}]}]}"#;
+    assert!(SceneSettings::from_json(zero).is_err());
+}
+
+#[test]
+fn prop_keyframes() {
+    //A keyframe at the prop's own pose puts it where the scene does
+    let rotation = Quaternion::from_axis_angle(Vector3::new(1.0, 2.0, 3.0).normalize(), Deg(40.0));
+    let json = format!(
+        r#"{{"motion": [{{"prop": 0, "keyframes": [{{"time": 0, "position": [0, 0, 1280], "rotation": [{}, {}, {}, {}]}}]}}]}}"#,
+        rotation.s, rotation.v.x, rotation.v.y, rotation.v.z
+    );
+    let textures = TextureRepository::new();
+    let props = prop_repo();
+    let still = ASCNLoader::from_scene(prop_scene(rotation)).unwrap();
+    let still = props.fulfill_all(still.get_prop_requests(), &textures).unwrap();
+    let moving = ASCNLoader::from_scene_with(prop_scene(rotation), &settings(&json)).unwrap();
+    let moving = props.fulfill_all(moving.get_prop_requests(), &textures).unwrap();
+    for (x, y) in [(0.2, 0.1), (-0.3, 0.4), (0.5, -0.6), (0.05, 0.05)] {
+        let ray = Ray::new(Vec3::default(), Vec3::new(x, y, -10.0));
+        let distance = |object: &dyn Fn(Ray) -> Option<Float>| object(ray);
+        let still = distance(&|ray| still.intersect(ray).map(|hit| hit.get_distance()));
+        let moving = distance(&|ray| moving.intersect(ray).map(|hit| hit.get_distance()));
+        match (still, moving) {
+            (Some(a), Some(b)) => assert!((a - b).abs() < 1e-4, "{} != {}", a, b),
+            (a, b) => assert_eq!(a.is_some(), b.is_some()),
+        }
+    }
+}
+
+#[test]
+fn prop_motion() {
+    let identity = Quaternion::new(1.0, 0.0, 0.0, 0.0);
+    let settings = settings(
+        r#"{"shutter": [0, 1], "motion": [{"prop": 0, "keyframes": [
+            {"time": 1, "position": [1280, 0, 1280], "rotation": [1, 0, 0, 0]},
+            {"time": 0, "position": [0, 0, 1280], "rotation": [1, 0, 0, 0]}
+        ]}]}"#,
+    );
+    let loader = ASCNLoader::from_scene_with(prop_scene(identity), &settings).unwrap();
+    assert_eq!(loader.get_shutter(), (0.0, 1.0));
+    let props = prop_repo()
+        .fulfill_all(loader.get_prop_requests(), &TextureRepository::new())
+        .unwrap();
+    let ray = |x: Float, time: Float| Ray::with_time(Vec3::new(x + 0.25, 0.25, 0.0), Vec3::new(0.0, 0.0, -1.0), time);
+    assert!(props.intersect(ray(0.0, 0.0)).is_some());
+    //The prop has moved 10 meters along x by the time the shutter closes
+    assert!(props.intersect(ray(0.0, 1.0)).is_none());
+    assert!(props.intersect(ray(10.0, 1.0)).is_some());
+    assert!(props.intersect(ray(5.0, 0.5)).is_some());
+}
+
+#[test]
+fn invalid_motion() {
+    let identity = Quaternion::new(1.0, 0.0, 0.0, 0.0);
+    let missing = settings(r#"{"motion": [{"prop": 1, "keyframes": [{"time": 0, "position": [0, 0, 0], "rotation": [1, 0, 0, 0]}]}]}"#);
+    assert!(ASCNLoader::from_scene_with(prop_scene(identity), &missing).is_err());
+    let empty = settings(r#"{"motion": [{"prop": 0, "keyframes": []}]}"#);
+    assert!(ASCNLoader::from_scene_with(prop_scene(identity), &empty).is_err());
+}
+
+#[test]
+fn glass() {
+    let settings = settings(r#"{"glass": [{"texture": 2, "ior": 1.5, "dispersion": 0.004}]}"#);
+    let mut cube = cube();
+    cube.faces[0].texture = TextureID(2);
+    let loader = load_with(vec![cube], &settings).unwrap();
+    let (glass, diffuse): (Vec<&Triangle>, Vec<&Triangle>) = loader
+        .get_triangles()
+        .iter()
+        .partition(|triangle| matches!(triangle.material, Material::Glass { .. }));
+    assert_eq!((glass.len(), diffuse.len()), (2, 10));
+    assert!(matches!(glass[0].material, Material::Glass { ior, dispersion } if ior == 1.5 && dispersion == 0.004));
+    assert!(diffuse.iter().all(|triangle| matches!(triangle.material, Material::Diffuse)));
+    for json in [
+        r#"{"glass": [{"texture": 2, "ior": 0.5}]}"#,
+        r#"{"glass": [{"texture": 2, "ior": 1.5, "dispersion": -1}]}"#,
+        r#"{"glass": [{"texture": 2, "ior": 1.5}, {"texture": 2, "ior": 1.3}]}"#,
+    ] {
+        assert!(SceneSettings::from_json(json).is_err(), "{}", json);
+    }
+}
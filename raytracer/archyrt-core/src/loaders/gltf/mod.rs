@@ -11,7 +11,7 @@ use crate::matrix;
 use crate::renderers::path_tracer::Material;
 
 
-use crate::utilities::math::Vector;
+use crate::utilities::math::{Float, Vector};
 
 use super::Loader;
 use super::ascn::amdl_textures::AMDLTextureType;
@@ -35,7 +35,7 @@ impl GltfLoader {
             None
         } else {
             let camera = &scene.cameras[0];
-            let focal_distance = 1.0 / ((camera.fov / 2.0).tan() * 2.0) as f64;
+            let focal_distance = 1.0 / ((camera.fov / 2.0).tan() * 2.0) as Float;
             let camera = PerspectiveCamera {
                 position: camera.position().into(),
                 focal_distance,
@@ -8,6 +8,7 @@ use crate::{
     utilities::{
         math::{Vec2, Vec3},
         ray::Intersectable,
+        stats,
     },
 };
 
@@ -35,11 +36,12 @@ impl Default for BasicRenderer<PerspectiveCamera, Sphere> {
 impl<T: Camera, K: Intersectable> FragmentRender for BasicRenderer<T, K> {
     fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
         let ray = self.camera.get_ray(ctx, pos);
+        stats::ray();
         match self.object.intersect(ray) {
             Some(intersection) => {
                 let pos = intersection.get_pos();
                 let normal = intersection.get_normal();
-                let base = intersection.get_color(ctx.repo);
+                let base = intersection.get_color(ctx.textures);
                 let lamp_direction = self.lamp - pos;
                 let shadow = lamp_direction.dot(normal) / lamp_direction.length();
                 let shadow = shadow.clamp(0.0, 1.0);
@@ -0,0 +1,172 @@
+use asset::{
+    scene::{self, Scene},
+    Lightmap,
+};
+
+use crate::{
+    api::{
+        camera::Camera,
+        fragment_render::{FragmentContext, FragmentRender},
+    },
+    textures::{texture_repo::TextureRepository, TextureID},
+    utilities::{
+        math::{Float, Vec2, Vec3},
+        ray::{offset_origin, Intersectable, Ray},
+        rng,
+    },
+};
+
+use super::path_tracer::{medium::Media, PathTracer};
+
+use rand_distr::UnitSphere;
+
+/// Bakes the light falling onto the faces of a scene's solids into a [Lightmap] for the editor's viewport.
+/// Every texel averages paths leaving the face over the hemisphere, so the lightmap holds irradiance
+/// without the color of the face itself.
+pub struct LightmapBaker<K: Intersectable> {
+    pub object: K,
+    pub bounces: usize,
+    pub skybox: Option<TextureID>,
+    pub samples: usize,
+    /// Texels along each side of a face
+    pub patch: u32,
+}
+
+//Starts every path at a texel, in a cosine weighted direction around the face's normal
+struct TexelCamera {
+    position: Vec3,
+    normal: Vec3,
+}
+
+impl Camera for TexelCamera {
+    fn get_ray(&self, _ctx: &FragmentContext, _pos: Vec2) -> Ray {
+        let p: [Float; 3] = rng::sample(&UnitSphere);
+        let direction = self.normal + Vec3::new(p[0], p[1], p[2]);
+        let direction = if direction.length() < 1e-6 {
+            self.normal
+        } else {
+            direction.normalized()
+        };
+        Ray::new(offset_origin(self.position, self.normal), direction)
+    }
+}
+
+//Same conversion from grid units as the ASCN loader
+fn world_position(corner: [i32; 3]) -> Vec3 {
+    Vec3::new(corner[0] as Float, corner[1] as Float, -corner[2] as Float) / 128.0
+}
+
+//Point of a face at a position on its patch, interpolated over the triangles the editor draws the face with.
+//Points outside of the corners are taken from the closest triangle.
+fn face_position(corners: &[Vec3], patch: &[Vec2], pos: Vec2) -> Vec3 {
+    let (weights, triangle) = scene::face_triangles(corners.len())
+        .filter_map(|triangle| {
+            let [a, b, c] = triangle.map(|corner| patch[corner]);
+            let (ab, ac, ap) = (b - a, c - a, pos - a);
+            let area = ab.x() * ac.y() - ac.x() * ab.y();
+            if area.abs() < 1e-9 {
+                return None;
+            }
+            let wb = (ap.x() * ac.y() - ac.x() * ap.y()) / area;
+            let wc = (ab.x() * ap.y() - ap.x() * ab.y()) / area;
+            Some(([1.0 - wb - wc, wb, wc], triangle))
+        })
+        .max_by(|(a, _), (b, _)| {
+            let min = |weights: &[Float; 3]| weights.iter().copied().fold(Float::INFINITY, Float::min);
+            min(a).total_cmp(&min(b))
+        })
+        .unwrap();
+    let weights = weights.map(|weight| weight.max(0.0));
+    let sum: Float = weights.iter().sum();
+    (0..3).fold(Vec3::default(), |position, i| {
+        position + corners[triangle[i]] * (weights[i] / sum)
+    })
+}
+
+impl<K: Intersectable> LightmapBaker<K> {
+    /// Unlit lightmap with a patch for every face the raytracer draws
+    pub fn layout(&self, scene: &Scene) -> Lightmap {
+        let faces = scene
+            .world
+            .solids
+            .iter()
+            .flat_map(|solid| {
+                solid
+                    .faces
+                    .iter()
+                    .filter(|face| face.texture.0 != 0 && face.indices.len() >= 3)
+                    .map(|face| {
+                        face.indices
+                            .iter()
+                            .map(|&i| solid.points[i as usize].position.into())
+                            .collect()
+                    })
+            })
+            .collect();
+        Lightmap::new(faces, self.patch)
+    }
+
+    /// Light of the texels of one face of the layout, row by row
+    pub fn bake_face(&self, lightmap: &Lightmap, face: usize, repo: &TextureRepository) -> Vec<[f32; 3]> {
+        let corners: Vec<_> = lightmap.faces[face].corners.iter().copied().map(world_position).collect();
+        let patch: Vec<_> = Lightmap::patch_corners(corners.len())
+            .into_iter()
+            .map(|corner| Vec2::new(corner.x as Float, corner.y as Float))
+            .collect();
+        //Same winding as the triangles of the ASCN loader
+        let normal = (corners[2] - corners[0]).cross(corners[1] - corners[0]).normalized();
+        let textures = repo.handles();
+        let ctx = FragmentContext {
+            width: 1.0,
+            height: 1.0,
+            textures: &textures,
+        };
+        let edge = lightmap.patch.saturating_sub(1).max(1) as Float;
+        let mut texels = Vec::with_capacity((lightmap.patch * lightmap.patch) as usize);
+        for y in 0..lightmap.patch {
+            for x in 0..lightmap.patch {
+                let pos = if lightmap.patch == 1 {
+                    Vec2::new(0.5, 0.5)
+                } else {
+                    Vec2::new(x as Float / edge, y as Float / edge)
+                };
+                let tracer = PathTracer {
+                    camera: TexelCamera {
+                        position: face_position(&corners, &patch, pos),
+                        normal,
+                    },
+                    object: &self.object,
+                    bounces: self.bounces,
+                    skybox: self.skybox,
+                    media: Media::default(),
+                };
+                let sum = (0..self.samples).fold(Vec3::default(), |sum, _| {
+                    sum + tracer.render_fragment(&ctx, Vec2::default())
+                });
+                //The path tracer weighs diffuse bounces by the cosine alone, halving what a white surface reflects.
+                //The same is done here, so a baked white face matches a rendered one.
+                let light = sum / (self.samples.max(1) as Float * 2.0);
+                texels.push([light.x() as f32, light.y() as f32, light.z() as f32]);
+            }
+        }
+        texels
+    }
+
+    /// Bakes every face of the scene in turn
+    pub fn bake(&self, scene: &Scene, repo: &TextureRepository) -> Lightmap {
+        let mut lightmap = self.layout(scene);
+        for face in 0..lightmap.faces.len() {
+            let texels = self.bake_face(&lightmap, face, repo);
+            store_face(&mut lightmap, face, &texels);
+        }
+        lightmap
+    }
+}
+
+/// Writes the texels returned by [LightmapBaker::bake_face] into the lightmap
+pub fn store_face(lightmap: &mut Lightmap, face: usize, texels: &[[f32; 3]]) {
+    let patch = lightmap.patch;
+    for (i, &light) in texels.iter().enumerate() {
+        lightmap.set_texel(face, i as u32 % patch, i as u32 / patch, light);
+    }
+}
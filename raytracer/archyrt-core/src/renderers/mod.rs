@@ -1,4 +1,9 @@
 pub mod basic_renderer;
+pub mod lightmap;
+pub mod mode;
 pub mod path_tracer;
+pub mod region;
 pub mod solid_renderers;
-pub mod sampling;
\ No newline at end of file
+pub mod sampling;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,152 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, Error};
+
+use crate::{
+    api::{camera::Camera, fragment_render::FragmentRender},
+    spectrum::color_space::ColorSpace,
+    utilities::ray::Intersectable,
+};
+
+use super::solid_renderers::{
+    albedo::AlbedoRenderer,
+    ambient_occlusion::AmbientOcclusionRenderer,
+    depth::{DepthMode, DepthRenderer},
+    heatmap::{HeatmapMetric, HeatmapRenderer},
+    instance_id::InstanceIdRenderer,
+    normal::NormalRenderer,
+    texture_id::TextureIdRenderer,
+    uv::UvCheckerRenderer,
+};
+
+/// What a render shows. Everything except the path tracers is a quick debug view.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum RenderMode {
+    #[default]
+    PathTraced,
+    /// Path traced with wavelengths instead of RGB, for dispersion and wide gamut output
+    Spectral,
+    Albedo,
+    Normal,
+    Depth,
+    DepthNormalized,
+    UvChecker,
+    TextureId,
+    InstanceId,
+    AmbientOcclusion,
+    Heatmap,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 11] = [
+        RenderMode::PathTraced,
+        RenderMode::Spectral,
+        RenderMode::Albedo,
+        RenderMode::Normal,
+        RenderMode::Depth,
+        RenderMode::DepthNormalized,
+        RenderMode::UvChecker,
+        RenderMode::TextureId,
+        RenderMode::InstanceId,
+        RenderMode::AmbientOcclusion,
+        RenderMode::Heatmap,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::PathTraced => "pathtraced",
+            RenderMode::Spectral => "spectral",
+            RenderMode::Albedo => "albedo",
+            RenderMode::Normal => "normal",
+            RenderMode::Depth => "depth",
+            RenderMode::DepthNormalized => "depth-normalized",
+            RenderMode::UvChecker => "uv",
+            RenderMode::TextureId => "texture-id",
+            RenderMode::InstanceId => "instance-id",
+            RenderMode::AmbientOcclusion => "ao",
+            RenderMode::Heatmap => "heatmap",
+        }
+    }
+    /// Whether this build can render the mode, the heatmap counts BVH nodes which only happens with the stats feature
+    pub fn available(self) -> bool {
+        self != RenderMode::Heatmap || cfg!(feature = "stats")
+    }
+    /// Whether the output is light that should be denoised and tonemapped, debug views are shown as they are
+    pub fn is_lit(self) -> bool {
+        matches!(self, RenderMode::PathTraced | RenderMode::Spectral)
+    }
+    /// Color space the renderer of the mode outputs, lit images are converted from it when they are collected
+    pub fn color_space(self) -> ColorSpace {
+        match self {
+            RenderMode::Spectral => ColorSpace::Xyz,
+            _ => ColorSpace::Srgb,
+        }
+    }
+    /// Renderer of a debug view, None for the path tracers which have to be set up by the caller
+    pub fn debug_renderer<'a, T: Camera + 'a, K: Intersectable + 'a>(
+        self,
+        camera: T,
+        object: K,
+    ) -> Option<Box<dyn FragmentRender + 'a>> {
+        let renderer: Box<dyn FragmentRender + 'a> = match self {
+            RenderMode::PathTraced | RenderMode::Spectral => return None,
+            RenderMode::Albedo => Box::new(AlbedoRenderer { camera, object }),
+            RenderMode::Normal => Box::new(NormalRenderer { camera, object }),
+            RenderMode::Depth => Box::new(DepthRenderer {
+                camera,
+                object,
+                mode: DepthMode::Linear,
+            }),
+            RenderMode::DepthNormalized => Box::new(DepthRenderer {
+                camera,
+                object,
+                mode: DepthMode::Normalized {
+                    near: 0.0,
+                    far: 50.0,
+                },
+            }),
+            RenderMode::UvChecker => Box::new(UvCheckerRenderer {
+                camera,
+                object,
+                checks: 8.0,
+            }),
+            RenderMode::TextureId => Box::new(TextureIdRenderer { camera, object }),
+            RenderMode::InstanceId => Box::new(InstanceIdRenderer { camera, object }),
+            RenderMode::AmbientOcclusion => Box::new(AmbientOcclusionRenderer {
+                camera,
+                object,
+                samples: 16,
+                distance: 1.0,
+            }),
+            RenderMode::Heatmap => Box::new(HeatmapRenderer {
+                inner: NormalRenderer { camera, object },
+                metric: HeatmapMetric::Nodes,
+                max: 200,
+            }),
+        };
+        Some(renderer)
+    }
+}
+
+impl Display for RenderMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for RenderMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mode = RenderMode::ALL
+            .into_iter()
+            .find(|mode| mode.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = RenderMode::ALL.iter().map(|mode| mode.name()).collect();
+                anyhow!("Unknown render mode {}, expected one of {}", s, names.join(", "))
+            })?;
+        if !mode.available() {
+            return Err(anyhow!("Render mode {} needs the renderer built with the stats feature", mode));
+        }
+        Ok(mode)
+    }
+}
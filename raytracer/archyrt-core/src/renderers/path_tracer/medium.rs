@@ -0,0 +1,249 @@
+use crate::utilities::math::consts::PI;
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    intersectables::{aabb::AABB, bvh::BVH},
+    utilities::{
+        math::{Float, Vec3},
+        ray::{offset_origin, Intersectable, Ray},
+        rng,
+    },
+};
+
+/// Density values sampled on a regular grid inside a box, zero outside of it
+#[derive(Clone)]
+pub struct DensityGrid {
+    pub bounds: AABB,
+    resolution: [usize; 3],
+    values: Vec<Float>,
+    max: Float,
+}
+
+impl DensityGrid {
+    /// Values are stored x first, then y, then z
+    pub fn new(bounds: AABB, resolution: [usize; 3], values: Vec<Float>) -> Result<Self> {
+        let [x, y, z] = resolution;
+        if x * y * z != values.len() || values.is_empty() {
+            return Err(anyhow!(
+                "Density grid of size {}x{}x{} got {} values",
+                x,
+                y,
+                z,
+                values.len()
+            ));
+        }
+        let max = values.iter().cloned().fold(0.0, Float::max);
+        Ok(Self {
+            bounds,
+            resolution,
+            values,
+            max,
+        })
+    }
+    /// Nearest grid value at a position
+    pub fn get(&self, pos: Vec3) -> Float {
+        let size = self.bounds.max - self.bounds.min;
+        let mut index = 0;
+        for axis in (0..3).rev() {
+            let relative = (pos[axis] - self.bounds.min[axis]) / size[axis];
+            if !(0.0..=1.0).contains(&relative) {
+                return 0.0;
+            }
+            let resolution = self.resolution[axis];
+            let i = ((relative * resolution as Float) as usize).min(resolution - 1);
+            index = index * resolution + i;
+        }
+        self.values[index]
+    }
+}
+
+#[derive(Clone)]
+pub enum MediumDensity {
+    Homogeneous,
+    Grid(DensityGrid),
+}
+
+impl MediumDensity {
+    pub fn get(&self, pos: Vec3) -> Float {
+        match self {
+            MediumDensity::Homogeneous => 1.0,
+            MediumDensity::Grid(grid) => grid.get(pos),
+        }
+    }
+    pub fn max(&self) -> Float {
+        match self {
+            MediumDensity::Homogeneous => 1.0,
+            MediumDensity::Grid(grid) => grid.max,
+        }
+    }
+}
+
+/// Participating medium, coefficients are per meter at a density of 1
+#[derive(Clone)]
+pub struct Medium {
+    pub absorption: Float,
+    pub scattering: Float,
+    /// Tint applied on every scattering event
+    pub color: Vec3,
+    /// Henyey-Greenstein asymmetry, positive values scatter forward
+    pub anisotropy: Float,
+    pub density: MediumDensity,
+}
+
+impl Default for Medium {
+    fn default() -> Self {
+        Self {
+            absorption: 0.0,
+            scattering: 0.1,
+            color: Vec3::from_single(1.0),
+            anisotropy: 0.0,
+            density: MediumDensity::Homogeneous,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MediumEvent {
+    Scatter(Float),
+    Absorb(Float),
+}
+
+impl MediumEvent {
+    pub fn distance(self) -> Float {
+        match self {
+            MediumEvent::Scatter(t) | MediumEvent::Absorb(t) => t,
+        }
+    }
+}
+
+/// Henyey-Greenstein phase function for the cosine between the incoming and outgoing direction
+pub fn henyey_greenstein(cos_theta: Float, g: Float) -> Float {
+    let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+    (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+}
+
+impl Medium {
+    fn extinction(&self) -> Float {
+        self.absorption + self.scattering
+    }
+    /// Samples the first real collision between start and end using delta tracking
+    pub fn track(&self, ray: Ray, start: Float, end: Float) -> Option<MediumEvent> {
+        let majorant = self.extinction() * self.density.max();
+        if majorant <= 0.0 {
+            return None;
+        }
+        //Distances are sampled in meters, not in multiples of the direction
+        let length = ray.direction.length();
+        let mut t = start;
+        loop {
+            t -= (1.0 - rng::random::<Float>()).ln() / majorant / length;
+            if t >= end {
+                return None;
+            }
+            let pos = ray.origin + ray.direction * t;
+            let density = self.density.get(pos);
+            let xi = rng::random::<Float>() * majorant;
+            if xi < density * self.absorption {
+                return Some(MediumEvent::Absorb(t));
+            }
+            if xi < density * self.extinction() {
+                return Some(MediumEvent::Scatter(t));
+            }
+            //Null collision, keep going
+        }
+    }
+    /// Samples a new direction from the phase function
+    pub fn scatter(&self, direction: Vec3) -> Vec3 {
+        let g = self.anisotropy;
+        let xi = rng::random::<Float>();
+        let cos_theta = if g.abs() < 1e-3 {
+            1.0 - 2.0 * xi
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+            (1.0 + g * g - s * s) / (2.0 * g)
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * rng::random::<Float>();
+        //Build a basis around the incoming direction
+        let w = direction.normalized();
+        let helper = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let u = helper.cross(w).normalized();
+        let v = w.cross(u);
+        u * (sin_theta * phi.cos()) + v * (sin_theta * phi.sin()) + w * cos_theta
+    }
+}
+
+/// Medium filling a closed mesh. The boundary itself is not rendered.
+#[derive(Clone)]
+pub struct Volume {
+    pub boundary: BVH,
+    pub medium: Medium,
+}
+
+const MAX_BOUNDARY_CROSSINGS: usize = 64;
+
+impl Volume {
+    /// First interval of the ray that lies inside the boundary
+    pub fn segment(&self, ray: Ray) -> Option<(Float, Float)> {
+        let mut crossings = Vec::new();
+        let mut current = ray;
+        while crossings.len() < MAX_BOUNDARY_CROSSINGS {
+            let intersection = match self.boundary.intersect(current) {
+                Some(intersection) => intersection,
+                None => break,
+            };
+            //Distances are kept relative to the original ray
+            let pos = intersection.get_pos();
+            crossings.push((pos - ray.origin).dot(ray.direction) / ray.direction.length_squared());
+            current.origin = offset_origin(pos, -intersection.get_geometric_normal());
+        }
+        //An odd number of crossings means the ray starts inside
+        if crossings.len() % 2 == 1 {
+            return Some((0.0, crossings[0]));
+        }
+        match crossings.as_slice() {
+            [enter, exit, ..] => Some((*enter, *exit)),
+            _ => None,
+        }
+    }
+}
+
+/// Media the path tracer moves through: one filling the whole scene and any number bounded by meshes
+#[derive(Default, Clone)]
+pub struct Media {
+    pub global: Option<Medium>,
+    pub volumes: Vec<Volume>,
+}
+
+impl Media {
+    /// Closest collision with any medium before the given distance
+    pub fn sample(&self, ray: Ray, max: Float) -> Option<(MediumEvent, &Medium)> {
+        let mut closest: Option<(MediumEvent, &Medium)> = None;
+        let mut end = max;
+        if let Some(medium) = &self.global {
+            if let Some(event) = medium.track(ray, 0.0, end) {
+                end = event.distance();
+                closest = Some((event, medium));
+            }
+        }
+        for volume in &self.volumes {
+            let (start, exit) = match volume.segment(ray) {
+                Some(segment) => segment,
+                None => continue,
+            };
+            if start >= end {
+                continue;
+            }
+            if let Some(event) = volume.medium.track(ray, start, exit.min(end)) {
+                end = event.distance();
+                closest = Some((event, &volume.medium));
+            }
+        }
+        closest
+    }
+}
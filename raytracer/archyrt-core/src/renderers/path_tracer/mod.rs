@@ -1,6 +1,11 @@
-use std::{f64::consts::PI};
+pub mod medium;
+pub mod spectral;
+#[cfg(test)]
+mod tests;
 
-use rand_distr::{Distribution, UnitSphere};
+use crate::utilities::math::consts::PI;
+
+use rand_distr::UnitSphere;
 
 use crate::{
     api::{
@@ -10,21 +15,29 @@ use crate::{
     textures::{
         color_provider::ColorProvider,
         samplers::{nearest::NearestSampler, TextureSampler},
-        texture_repo::TextureRepository,
+        texture_repo::TextureHandles,
         TextureID,
     },
     utilities::{
-        math::{Vec2, Vec3},
-        ray::{Intersectable, Intersection, Ray},
+        math::{Float, Vec2, Vec3},
+        ray::{offset_origin, Intersectable, Intersection, Ray},
+        rng, stats,
     },
     vector,
 };
 
+use self::medium::{Media, MediumEvent};
+
 #[derive(Clone, Copy, Debug)]
 pub enum Material {
     Diffuse,
-    Emissive { power: f64 },
+    Emissive { power: Float },
     DiffuseAndEmissive {emissive_texture: TextureID},
+    /// Diffuse surface that is ignored wherever its texture's alpha falls below the threshold
+    AlphaCutout { threshold: Float },
+    /// Clear refractive surface, tinted by its color. The index of refraction is given at 587.6nm,
+    /// dispersion is the B coefficient of Cauchy's equation in square micrometers and only shows in spectral renders.
+    Glass { ior: Float, dispersion: Float },
 }
 
 impl Default for Material {
@@ -33,47 +46,222 @@ impl Default for Material {
     }
 }
 
-const EPSILON: f64 = 0.00001;
+pub const DEFAULT_ALPHA_THRESHOLD: Float = 0.5;
+
+//Wavelength of the helium d line in micrometers, where the index of refraction of glass is usually given
+const D_LINE: Float = 0.5876;
+
+/// Reflects or refracts off a glass surface with the given index of refraction, picked by the Fresnel reflectance.
+/// The front face of the surface has to be the outside of the glass.
+pub fn refract<C: ColorProvider>(intersection: &Intersection<C>, ior: Float) -> Ray {
+    let ray = intersection.get_ray();
+    let direction = ray.direction.normalized();
+    let normal = intersection.get_normal();
+    //Turned to face the ray, surfaces hit from the back might have done so already
+    let (normal, cos_in) = match -direction.dot(normal) {
+        cos_in if cos_in > 0.0 => (normal, cos_in),
+        cos_in => (-normal, -cos_in),
+    };
+    let eta = if intersection.is_front_face() { 1.0 / ior } else { ior };
+    let reflected = direction + normal * (2.0 * cos_in);
+    let sin2_out = eta * eta * (1.0 - cos_in * cos_in);
+    let direction = if sin2_out >= 1.0 {
+        //Total internal reflection
+        reflected
+    } else {
+        let cos_out = (1.0 - sin2_out).sqrt();
+        //Schlick's approximation, using the angle on the less dense side
+        let r0 = ((1.0 - ior) / (1.0 + ior)).powi(2);
+        let cos = if eta > 1.0 { cos_out } else { cos_in };
+        let reflectance = r0 + (1.0 - r0) * (1.0 - cos).powi(5);
+        if rng::random::<Float>() < reflectance {
+            reflected
+        } else {
+            direction * eta + normal * (eta * cos_in - cos_out)
+        }
+    };
+    Ray::with_time(intersection.get_pos(), direction, ray.time)
+}
 
 impl Material {
+    /// Whether surfaces with this material are hit from both sides by default. Lights only emit from their front face.
+    pub fn two_sided(self) -> bool {
+        !matches!(self, Material::Emissive { power: _ })
+    }
+    /// Whether rays leave in a single direction instead of being spread over the hemisphere
+    pub fn is_specular(self) -> bool {
+        matches!(self, Material::Glass { .. })
+    }
+    /// Index of refraction at a wavelength in nanometers, None for opaque materials
+    pub fn ior(self, lambda: Float) -> Option<Float> {
+        match self {
+            Material::Glass { ior, dispersion } => {
+                let lambda = lambda / 1000.0;
+                Some(ior + dispersion * (1.0 / (lambda * lambda) - 1.0 / (D_LINE * D_LINE)))
+            }
+            _ => None,
+        }
+    }
+    pub fn alpha_threshold(self) -> Option<Float> {
+        match self {
+            Material::AlphaCutout { threshold } => Some(threshold),
+            _ => None,
+        }
+    }
     pub fn reflect<C: ColorProvider>(self, intersection: Intersection<C>) -> Option<Ray> {
         match self {
-            Material::Diffuse | Material::DiffuseAndEmissive{emissive_texture: _} => {
-                let p: [f64; 3] = UnitSphere.sample(&mut rand::thread_rng());
+            Material::Diffuse
+            | Material::DiffuseAndEmissive { emissive_texture: _ }
+            | Material::AlphaCutout { threshold: _ } => {
+                let p: [Float; 3] = rng::sample(&UnitSphere);
                 let p = Vec3::new(p[0], p[1], p[2]);
                 let p = if intersection.get_normal().dot(p) < 0.0 {
                     -p
                 } else {
                     p
                 };
-                Some(Ray {
-                    origin: intersection.get_pos(),
-                    direction: p,
-                })
+                Some(Ray::with_time(
+                    intersection.get_pos(),
+                    p,
+                    intersection.get_ray().time,
+                ))
             }
+            Material::Glass { ior, dispersion: _ } => Some(refract(&intersection, ior)),
             Material::Emissive { power: _ } => None
         }
     }
-    pub fn color<C: ColorProvider>(
-        self,
-        intersection: &Intersection<C>,
-        repo: &TextureRepository,
-        emissive: &mut Vec3,
-        diffusive: &mut Vec3,
-    ) {
-        match self {
-            Material::Diffuse => {
-                (*diffusive) *= intersection.get_color(repo);
+}
+
+fn sky_color(skybox: Option<TextureID>, textures: &TextureHandles, direction: Vec3, bounce: usize) -> Vec3 {
+    let skybox = match skybox {
+        Some(skybox) => skybox,
+        None => return Vec3::default(),
+    };
+    textures.with(skybox, |texture| match texture {
+        //Skybox color
+        Some(texture) => {
+            let sampler = NearestSampler {};
+            let longitude = direction.x().atan2(direction.z());
+            let latitude = -(direction.y() / direction.length()).asin();
+            let longitude = (longitude / PI + 1.0) * 0.5;
+            let latitude = (latitude / (PI / 2.0) + 1.0) * 0.5;
+            let res = sampler.sample(texture, vector![longitude, latitude]);
+            if bounce == 0{
+                res
+            }else{
+                res * 3.0
+            }
+        }
+        //Default skybox color, also used when the skybox is missing
+        None => Vec3::default(),
+    })
+}
+
+/// Light carried along a path. The path tracers share [trace] and differ only in how they store it.
+pub trait PathThroughput {
+    /// Adds light, given in RGB, that reaches the path
+    fn add_light(&mut self, light: Vec3);
+    /// Filters the path by a reflectance given in RGB
+    fn tint(&mut self, color: Vec3);
+    fn scale(&mut self, factor: Float);
+    /// Whether no more light can reach the camera along the path
+    fn is_black(&self) -> bool;
+    /// Ray leaving the surface, None where the path ends
+    fn leave<C: ColorProvider>(&mut self, material: Material, intersection: Intersection<C>) -> Option<Ray> {
+        material.reflect(intersection)
+    }
+}
+
+/// Follows a path through media and off surfaces until it leaves the scene, ends on a light or runs out of bounces
+pub fn trace<P: PathThroughput, K: Intersectable>(
+    path: &mut P,
+    mut ray: Ray,
+    object: &K,
+    media: &Media,
+    skybox: Option<TextureID>,
+    bounces: usize,
+    textures: &TextureHandles,
+) {
+    let mut segments = 0;
+    for bounce in 0..bounces {
+        stats::ray();
+        segments += 1;
+        let intersection = object.intersect(ray);
+        //Participating media
+        let distance = match &intersection {
+            Some(intersection) => intersection.get_distance(),
+            None => Float::INFINITY,
+        };
+        if let Some((event, medium)) = media.sample(ray, distance) {
+            match event {
+                MediumEvent::Absorb(_) => break,
+                MediumEvent::Scatter(t) => {
+                    path.tint(medium.color);
+                    let origin = ray.origin + ray.direction * t;
+                    ray = Ray::with_time(origin, medium.scatter(ray.direction), ray.time);
+                    continue;
+                }
             }
-            Material::Emissive { power } => {
-                (*emissive) += intersection.get_color(repo) * power * (*diffusive);
+        }
+        let intersection = match intersection {
+            Some(intersection) => intersection,
+            None => {
+                //The sky is blue
+                path.add_light(sky_color(skybox, textures, ray.direction, bounce));
+                break;
             }
+        };
+        let normal = intersection.get_normal();
+        let geometric_normal = intersection.get_geometric_normal();
+        let material = intersection.get_material();
+        let color = intersection.get_color(textures);
+        match material {
+            Material::Emissive { power } => path.add_light(color * power),
             Material::DiffuseAndEmissive { emissive_texture } => {
-                (*emissive) += intersection.ref_color_provider().sample(repo, emissive_texture) * 50.0 * (*diffusive);
-                (*diffusive) *= intersection.get_color(repo);
-                
-            },
+                path.add_light(intersection.ref_color_provider().sample(textures, emissive_texture) * 50.0);
+                path.tint(color);
+            }
+            Material::Diffuse | Material::AlphaCutout { .. } | Material::Glass { .. } => path.tint(color),
+        }
+        let mut next = match path.leave(material, intersection) {
+            Some(ray) => ray,
+            None => break,
+        };
+        if !material.is_specular() {
+            path.scale(next.direction.dot(normal).max(0.0));
+        }
+        if path.is_black() {
+            break;
         }
+        //Offset towards the side the new ray leaves on
+        let side = if next.direction.dot(geometric_normal) < 0.0 {
+            -geometric_normal
+        } else {
+            geometric_normal
+        };
+        next.origin = offset_origin(next.origin, side);
+        ray = next;
+    }
+    stats::path(segments);
+}
+
+struct RgbPath {
+    emissive: Vec3,
+    diffusive: Vec3,
+}
+
+impl PathThroughput for RgbPath {
+    fn add_light(&mut self, light: Vec3) {
+        self.emissive += self.diffusive * light;
+    }
+    fn tint(&mut self, color: Vec3) {
+        self.diffusive *= color;
+    }
+    fn scale(&mut self, factor: Float) {
+        self.diffusive *= factor;
+    }
+    fn is_black(&self) -> bool {
+        self.diffusive == Vec3::default()
     }
 }
 
@@ -82,57 +270,17 @@ pub struct PathTracer<T: Camera, K: Intersectable> {
     pub object: K,
     pub bounces: usize,
     pub skybox: Option<TextureID>,
+    pub media: Media,
 }
 
 impl<T: Camera, K: Intersectable> FragmentRender for PathTracer<T, K> {
     fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
-        let mut ray = self.camera.get_ray(ctx, pos);
-        let mut emissive = Vec3::default();
-        let mut diffusive = Vec3::from_single(1.0);
-        for bounce in 0..self.bounces {
-            match self.object.intersect(ray) {
-                Some(intersection) => {
-                    let normal = intersection.get_normal();
-                    let material = intersection.get_material();
-                    material.color(&intersection, ctx.repo, &mut emissive, &mut diffusive);
-                    ray = match material.reflect(intersection) {
-                        Some(ray) => {
-                            diffusive *= ray.direction.dot(normal);
-                            let mut ray = ray;
-                            ray.origin += normal * EPSILON;
-                            ray
-                        }
-                        None => break,
-                    };
-                }
-                None => {
-                    //The sky is blue
-                    let sky_color = match self.skybox {
-                        //Skybox color
-                        Some(skybox) => { 
-                            let sampler = NearestSampler {};
-                            let texture = ctx.repo.get(skybox).unwrap();
-                            let longitude = ray.direction.x().atan2(ray.direction.z());
-                            let latitude = -(ray.direction.y() / ray.direction.length()).asin();
-                            let longitude = (longitude / PI + 1.0) * 0.5;
-                            let latitude = (latitude / (PI / 2.0) + 1.0) * 0.5;
-                            let res = sampler.sample(texture, vector![longitude, latitude]);
-                            if bounce == 0{
-                                res
-                            }else{
-                                res * 3.0
-                            }
-                        }
-                        //Default skybox color
-                        _ => {
-                            Vec3::default()
-                        }
-                    };
-                    emissive += diffusive * sky_color;
-                    break;
-                }
-            }
-        }
-        emissive
+        let ray = self.camera.get_ray(ctx, pos);
+        let mut path = RgbPath {
+            emissive: Vec3::default(),
+            diffusive: Vec3::from_single(1.0),
+        };
+        trace(&mut path, ray, &self.object, &self.media, self.skybox, self.bounces, ctx.textures);
+        path.emissive
     }
 }
@@ -0,0 +1,73 @@
+use crate::{
+    api::{
+        camera::Camera,
+        fragment_render::{FragmentContext, FragmentRender},
+    },
+    spectrum::{SampledSpectrum, SampledWavelengths},
+    textures::{color_provider::ColorProvider, TextureID},
+    utilities::{
+        math::{Float, Vec2, Vec3},
+        ray::{Intersectable, Intersection, Ray},
+    },
+};
+
+use super::{medium::Media, refract, trace, Material, PathThroughput};
+
+/// Hero wavelength path tracer. Colors are upsampled to spectra and every path carries a few wavelengths,
+/// so glass can disperse light. The output is CIE XYZ, see [ColorSpace](crate::spectrum::color_space::ColorSpace).
+pub struct SpectralPathTracer<T: Camera, K: Intersectable> {
+    pub camera: T,
+    pub object: K,
+    pub bounces: usize,
+    pub skybox: Option<TextureID>,
+    pub media: Media,
+}
+
+impl<T: Camera, K: Intersectable> FragmentRender for SpectralPathTracer<T, K> {
+    fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+        let ray = self.camera.get_ray(ctx, pos);
+        let mut path = SpectralPath {
+            wavelengths: SampledWavelengths::random(),
+            emissive: SampledSpectrum::default(),
+            diffusive: SampledSpectrum::from_single(1.0),
+            dispersed: false,
+        };
+        trace(&mut path, ray, &self.object, &self.media, self.skybox, self.bounces, ctx.textures);
+        path.emissive.to_xyz(&path.wavelengths)
+    }
+}
+
+struct SpectralPath {
+    wavelengths: SampledWavelengths,
+    emissive: SampledSpectrum,
+    diffusive: SampledSpectrum,
+    dispersed: bool,
+}
+
+impl PathThroughput for SpectralPath {
+    fn add_light(&mut self, light: Vec3) {
+        self.emissive += self.diffusive * SampledSpectrum::illuminant(light, &self.wavelengths);
+    }
+    fn tint(&mut self, color: Vec3) {
+        self.diffusive *= SampledSpectrum::reflectance(color, &self.wavelengths);
+    }
+    fn scale(&mut self, factor: Float) {
+        self.diffusive *= factor;
+    }
+    fn is_black(&self) -> bool {
+        self.diffusive.is_black()
+    }
+    fn leave<C: ColorProvider>(&mut self, material: Material, intersection: Intersection<C>) -> Option<Ray> {
+        match material.ior(self.wavelengths.hero()) {
+            Some(ior) => {
+                //Every wavelength bends differently, only the hero can follow this path
+                if !self.dispersed && ior != material.ior(self.wavelengths.lambda[1]).unwrap() {
+                    self.diffusive.terminate_secondary();
+                    self.dispersed = true;
+                }
+                Some(refract(&intersection, ior))
+            }
+            None => material.reflect(intersection),
+        }
+    }
+}
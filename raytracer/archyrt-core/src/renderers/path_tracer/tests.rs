@@ -0,0 +1,331 @@
+mod medium {
+    use crate::utilities::math::consts::PI;
+
+    use crate::{
+        intersectables::{aabb::AABB, bvh::BVH, triangle::Triangle},
+        renderers::path_tracer::{
+            medium::{henyey_greenstein, DensityGrid, Media, Medium, MediumDensity, MediumEvent, Volume},
+            Material,
+        },
+        textures::TextureID,
+        utilities::{math::{Float, Vec3}, ray::Ray},
+        vector,
+    };
+
+    const SAMPLES: usize = 20000;
+
+    fn cube(min: Vec3, max: Vec3) -> BVH {
+        let corner = |i: usize| {
+            Vec3::new(
+                if i & 1 == 0 { min.x() } else { max.x() },
+                if i & 2 == 0 { min.y() } else { max.y() },
+                if i & 4 == 0 { min.z() } else { max.z() },
+            )
+        };
+        let faces = [
+            [0, 1, 3, 2],
+            [4, 6, 7, 5],
+            [0, 4, 5, 1],
+            [2, 3, 7, 6],
+            [0, 2, 6, 4],
+            [1, 5, 7, 3],
+        ];
+        let uv = [vector![0.0, 0.0], vector![0.0, 1.0], vector![1.0, 0.0]];
+        let triangles: Vec<_> = faces
+            .iter()
+            .flat_map(|[a, b, c, d]| {
+                [
+                    Triangle::new([corner(*a), corner(*b), corner(*c)], uv, TextureID::new(&0), Material::Diffuse),
+                    Triangle::new([corner(*a), corner(*c), corner(*d)], uv, TextureID::new(&0), Material::Diffuse),
+                ]
+            })
+            .collect();
+        BVH::from_triangles(&triangles).unwrap()
+    }
+
+    #[test]
+    fn grid() {
+        let bounds = AABB::new(Vec3::from_single(0.0), Vec3::from_single(2.0));
+        let grid = DensityGrid::new(bounds, [2, 1, 1], vec![0.5, 3.0]).unwrap();
+        assert_eq!(grid.get(Vec3::new(0.5, 1.0, 1.0)), 0.5);
+        assert_eq!(grid.get(Vec3::new(1.5, 1.0, 1.0)), 3.0);
+        assert_eq!(grid.get(Vec3::new(2.5, 1.0, 1.0)), 0.0);
+        assert!(DensityGrid::new(bounds, [2, 2, 1], vec![1.0]).is_err());
+    }
+    #[test]
+    fn phase_normalized() {
+        for g in [-0.5, 0.0, 0.8] {
+            let steps = 10000;
+            let integral: Float = (0..steps)
+                .map(|i| {
+                    let cos_theta = -1.0 + (i as Float + 0.5) * 2.0 / steps as Float;
+                    henyey_greenstein(cos_theta, g) * 2.0 * PI * 2.0 / steps as Float
+                })
+                .sum();
+            assert!((integral - 1.0).abs() < 0.01);
+        }
+    }
+    #[test]
+    fn scatter_mean_cosine() {
+        //The mean cosine of Henyey-Greenstein is its asymmetry
+        let direction = Vec3::new(0.0, 0.0, 2.0);
+        for g in [-0.6, 0.0, 0.7] {
+            let medium = Medium {
+                anisotropy: g,
+                ..Default::default()
+            };
+            let mean: Float = (0..SAMPLES)
+                .map(|_| medium.scatter(direction).z())
+                .sum::<Float>()
+                / SAMPLES as Float;
+            assert!((mean - g).abs() < 0.03);
+        }
+    }
+    #[test]
+    fn transmittance() {
+        let medium = Medium {
+            absorption: 0.3,
+            scattering: 0.2,
+            ..Default::default()
+        };
+        let ray = Ray::new(Vec3::default(), Vec3::new(0.0, 0.0, 2.0));
+        //The ray travels 4 meters
+        let transmitted = (0..SAMPLES)
+            .filter(|_| medium.track(ray, 0.0, 2.0).is_none())
+            .count() as Float
+            / SAMPLES as Float;
+        assert!((transmitted - (-0.5 as Float * 4.0).exp()).abs() < 0.02);
+    }
+    #[test]
+    fn empty_grid_is_transparent() {
+        let bounds = AABB::new(Vec3::from_single(0.0), Vec3::from_single(1.0));
+        let medium = Medium {
+            density: MediumDensity::Grid(DensityGrid::new(bounds, [1, 1, 1], vec![0.0]).unwrap()),
+            ..Default::default()
+        };
+        let ray = Ray::new(Vec3::default(), Vec3::new(0.0, 0.0, 1.0));
+        assert!(medium.track(ray, 0.0, Float::INFINITY).is_none());
+    }
+    #[test]
+    fn volume_segment() {
+        let volume = Volume {
+            boundary: cube(Vec3::from_single(-1.0), Vec3::from_single(1.0)),
+            medium: Medium::default(),
+        };
+        let outside = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 2.0));
+        let (start, end) = volume.segment(outside).unwrap();
+        assert!((start - 2.0).abs() < 1e-9);
+        assert!((end - 3.0).abs() < 1e-9);
+        let inside = Ray::new(Vec3::new(0.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let (start, end) = volume.segment(inside).unwrap();
+        assert_eq!(start, 0.0);
+        assert!((end - 1.0).abs() < 1e-9);
+        let miss = Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(volume.segment(miss).is_none());
+    }
+    #[test]
+    fn media_sample() {
+        let dense = || Medium {
+            absorption: 1000.0,
+            scattering: 0.0,
+            ..Default::default()
+        };
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        //Only the volume is dense, collisions happen right at its boundary
+        let media = Media {
+            global: None,
+            volumes: vec![Volume {
+                boundary: cube(Vec3::from_single(-1.0), Vec3::from_single(1.0)),
+                medium: dense(),
+            }],
+        };
+        let (event, _) = media.sample(ray, Float::INFINITY).unwrap();
+        assert!(matches!(event, MediumEvent::Absorb(_)));
+        assert!((event.distance() - 4.0).abs() < 0.05);
+        //A surface in front of the volume blocks it
+        assert!(media.sample(ray, 3.0).is_none());
+        let media = Media {
+            global: Some(dense()),
+            volumes: Vec::new(),
+        };
+        let (event, _) = media.sample(ray, Float::INFINITY).unwrap();
+        assert!(event.distance() < 0.05);
+        assert!(Media::default().sample(ray, Float::INFINITY).is_none());
+    }
+}
+
+mod spectral {
+    use crate::{
+        api::fragment_render::{FragmentContext, FragmentRender},
+        cameras::perspective::PerspectiveCamera,
+        intersectables::cuboid::Cuboid,
+        renderers::path_tracer::{spectral::SpectralPathTracer, Material, PathTracer},
+        spectrum::color_space::ColorSpace,
+        textures::texture_repo::TextureRepository,
+        utilities::{
+            math::{Float, Vec3},
+            rng,
+        },
+        vector,
+    };
+
+    const SAMPLES: usize = 4000;
+
+    //White light behind an optional pane of glass, seen through its center
+    fn scene(glass: Option<Material>) -> Vec<Cuboid> {
+        let mut light = Cuboid::new(Vec3::new(-10.0, -10.0, 5.0), Vec3::new(10.0, 10.0, 6.0));
+        light.material = Material::Emissive { power: 1.0 };
+        let mut objects = vec![light];
+        if let Some(material) = glass {
+            let mut pane = Cuboid::new(Vec3::new(-10.0, -10.0, 2.0), Vec3::new(10.0, 10.0, 3.0));
+            pane.material = material;
+            objects.push(pane);
+        }
+        objects
+    }
+
+    //Seeded, so the tolerances hold on every run
+    fn average<T: FragmentRender>(renderer: T) -> Vec3 {
+        rng::seed(1);
+        let repo = TextureRepository::new();
+        let ctx = FragmentContext {
+            width: 1.0,
+            height: 1.0,
+            textures: &repo.handles(),
+        };
+        (0..SAMPLES).fold(Vec3::default(), |sum, _| {
+            sum + renderer.render_fragment(&ctx, vector![0.5, 0.5])
+        }) / SAMPLES as Float
+    }
+
+    fn rgb(glass: Option<Material>) -> Vec3 {
+        average(PathTracer {
+            camera: PerspectiveCamera::new(Vec3::default(), Vec3::new(0.0, 0.0, 1.0), 1.0),
+            object: scene(glass),
+            bounces: 6,
+            skybox: None,
+            media: Default::default(),
+        })
+    }
+
+    fn spectral(glass: Option<Material>) -> Vec3 {
+        let xyz = average(SpectralPathTracer {
+            camera: PerspectiveCamera::new(Vec3::default(), Vec3::new(0.0, 0.0, 1.0), 1.0),
+            object: scene(glass),
+            bounces: 6,
+            skybox: None,
+            media: Default::default(),
+        });
+        ColorSpace::Xyz.convert(xyz, ColorSpace::Srgb)
+    }
+
+    fn assert_close(a: Vec3, b: Vec3, tolerance: Float) {
+        assert!((a - b).inner.iter().all(|d| d.abs() < tolerance), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn dispersion() {
+        let glass = Material::Glass {
+            ior: 1.5,
+            dispersion: 0.0042,
+        };
+        assert!((glass.ior(587.6).unwrap() - 1.5).abs() < 1e-6);
+        assert!(glass.ior(450.0).unwrap() > glass.ior(650.0).unwrap());
+        assert!(Material::Diffuse.ior(500.0).is_none());
+        assert!(glass.is_specular() && !Material::Diffuse.is_specular());
+    }
+
+    #[test]
+    fn white_light() {
+        let white = Vec3::from_single(1.0);
+        assert_close(rgb(None), white, 1e-6);
+        assert_close(spectral(None), white, 0.05);
+    }
+
+    #[test]
+    fn glass_transmits() {
+        //Both faces reflect 4% at normal incidence
+        let expected = Vec3::from_single(0.92);
+        let clear = Material::Glass {
+            ior: 1.5,
+            dispersion: 0.0,
+        };
+        let dispersive = Material::Glass {
+            ior: 1.5,
+            dispersion: 0.0042,
+        };
+        assert_close(rgb(Some(clear)), expected, 0.03);
+        assert_close(spectral(Some(clear)), expected, 0.06);
+        assert_close(spectral(Some(dispersive)), expected, 0.08);
+    }
+}
+
+mod glass {
+    use crate::{
+        intersectables::{bvh::BVH, triangle::Triangle},
+        renderers::path_tracer::{refract, Material},
+        textures::TextureID,
+        utilities::{
+            math::{Float, Vec3},
+            ray::{Intersectable, Ray},
+        },
+        vector,
+    };
+
+    //Glass cube from -1 to 1 made of triangles, their normals point out of it
+    fn glass_cube() -> BVH {
+        let corner = |i: usize| {
+            Vec3::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { -1.0 } else { 1.0 },
+            )
+        };
+        let faces = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        let uv = [vector![0.0, 0.0], vector![0.0, 1.0], vector![1.0, 0.0]];
+        let material = Material::Glass {
+            ior: 1.5,
+            dispersion: 0.0,
+        };
+        let triangles: Vec<_> = faces
+            .iter()
+            .flat_map(|[a, b, c, d]| {
+                [
+                    Triangle::new([corner(*a), corner(*b), corner(*c)], uv, TextureID::new(&0), material),
+                    Triangle::new([corner(*a), corner(*c), corner(*d)], uv, TextureID::new(&0), material),
+                ]
+            })
+            .collect();
+        assert!(triangles.iter().all(|triangle| triangle.normal.dot(triangle.centroid()) > 0.0));
+        BVH::from_triangles(&triangles).unwrap()
+    }
+
+    #[test]
+    fn total_internal_reflection() {
+        let cube = glass_cube();
+        let entering = cube
+            .intersect(Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0)))
+            .unwrap();
+        assert!(entering.is_front_face());
+
+        //48 degrees from the normal of the top face, past the critical angle of 41.8 degrees
+        let direction = Vec3::new(1.0, 0.0, 0.9).normalized();
+        let leaving = cube
+            .intersect(Ray::new(Vec3::new(-0.5, 0.0, 0.0), direction))
+            .unwrap();
+        assert!(!leaving.is_front_face());
+        //The triangle turned its normal to face the ray
+        assert!(leaving.get_normal().dot(direction) < 0.0);
+        let reflected = refract(&leaving, 1.5);
+        let expected = Vec3::new(direction.x(), 0.0, -direction.z());
+        //A few rounding errors of whichever precision is used
+        assert!((reflected.direction - expected).length() < 16.0 * Float::EPSILON);
+    }
+}
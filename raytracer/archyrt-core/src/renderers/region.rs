@@ -0,0 +1,144 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, Error, Result};
+
+use crate::{
+    api::fragment_render::{FragmentContext, FragmentRender},
+    utilities::math::{Float, Vec2, Vec3},
+};
+
+/// Rectangle of pixels, written as `x,y,width,height`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    pub fn full(width: usize, height: usize) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+    /// Same area in an image scaled by the factor, partially covered pixels are included
+    pub fn scaled(self, scale: Float) -> Self {
+        let x = (self.x as Float * scale).floor() as usize;
+        let y = (self.y as Float * scale).floor() as usize;
+        let right = ((self.x + self.width) as Float * scale).ceil() as usize;
+        let bottom = ((self.y + self.height) as Float * scale).ceil() as usize;
+        Self {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        }
+    }
+    pub fn is_inside(self, width: usize, height: usize) -> bool {
+        self.x + self.width <= width && self.y + self.height <= height
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+impl FromStr for Region {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| anyhow!("Invalid region {}, expected x,y,width,height", s))?;
+        match values[..] {
+            [x, y, width, height] => Ok(Self {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(anyhow!("Invalid region {}, expected x,y,width,height", s)),
+        }
+    }
+}
+
+/// Resolution of a render job and the part of it that is rendered
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderArea {
+    /// Width of the whole view, after scaling
+    pub width: usize,
+    /// Height of the whole view, after scaling
+    pub height: usize,
+    pub region: Region,
+}
+
+impl RenderArea {
+    /// Previews are scaled down by a factor up to 1, the crop is given in pixels of the full resolution
+    pub fn new(width: usize, height: usize, scale: Float, crop: Option<Region>) -> Result<Self> {
+        if !(scale > 0.0 && scale <= 1.0) {
+            return Err(anyhow!("Preview scale has to be above 0 and at most 1, got {}", scale));
+        }
+        let crop = crop.unwrap_or_else(|| Region::full(width, height));
+        if crop.width == 0 || crop.height == 0 || !crop.is_inside(width, height) {
+            return Err(anyhow!("Region {} is not inside the {}x{} image", crop, width, height));
+        }
+        let scaled_width = ((width as Float * scale).round() as usize).max(1);
+        let scaled_height = ((height as Float * scale).round() as usize).max(1);
+        let mut region = crop.scaled(scale);
+        //Rounding can move the region just past the edge of the scaled image
+        region.x = region.x.min(scaled_width - 1);
+        region.y = region.y.min(scaled_height - 1);
+        region.width = region.width.clamp(1, scaled_width - region.x);
+        region.height = region.height.clamp(1, scaled_height - region.y);
+        Ok(Self {
+            width: scaled_width,
+            height: scaled_height,
+            region,
+        })
+    }
+    pub fn is_cropped(&self) -> bool {
+        self.region != Region::full(self.width, self.height)
+    }
+}
+
+/// Renders a region of a larger view, collectors see an image the size of the region.
+/// Fragments are moved to where they are in the full view, so cropped pixels match the uncropped ones.
+pub struct RegionView<T: FragmentRender> {
+    pub inner: T,
+    pub full_width: usize,
+    pub full_height: usize,
+    pub region: Region,
+}
+
+impl<T: FragmentRender> RegionView<T> {
+    pub fn new(inner: T, area: RenderArea) -> Self {
+        Self {
+            inner,
+            full_width: area.width,
+            full_height: area.height,
+            region: area.region,
+        }
+    }
+}
+
+impl<T: FragmentRender> FragmentRender for RegionView<T> {
+    fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+        let full_ctx = FragmentContext {
+            width: self.full_width as Float,
+            height: self.full_height as Float,
+            textures: ctx.textures,
+        };
+        //Pixel within the region, then within the full view
+        let x = pos.x() * (ctx.width - 1.0) + self.region.x as Float;
+        let y = pos.y() * (ctx.height - 1.0) + self.region.y as Float;
+        self.inner.render_fragment(&full_ctx, full_ctx.to_unit(x, y))
+    }
+}
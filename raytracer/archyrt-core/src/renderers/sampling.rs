@@ -1,4 +1,4 @@
-use crate::{api::fragment_render::{FragmentRender, FragmentContext}, utilities::math::{Vec2, Vec3}};
+use crate::{api::fragment_render::{FragmentRender, FragmentContext}, utilities::math::{Float, Vec2, Vec3}};
 
 pub struct SamplingRenderer<Renderer: FragmentRender + Sync + Send> {
     pub inner: Renderer,
@@ -11,11 +11,11 @@ impl<Renderer: FragmentRender + Sync + Send> FragmentRender for SamplingRenderer
         //     .into_par_iter()
         //     .map(|_| self.inner.render_fragment(ctx, pos))
         //     .reduce(Vec3::default, |a, b| a + b)
-        //     / (self.samples as f64)
+        //     / (self.samples as Float)
         (0..self.samples)
             .into_iter()
             .map(|_| self.inner.render_fragment(ctx, pos))
             .fold(Vec3::default(), |a, b| a + b)
-            / (self.samples as f64)
+            / (self.samples as Float)
     }
 }
@@ -0,0 +1,56 @@
+use rand_distr::UnitSphere;
+
+use crate::{
+    api::{
+        camera::Camera,
+        fragment_render::{FragmentContext, FragmentRender},
+    },
+    utilities::{
+        math::{Float, Vec2, Vec3},
+        ray::{offset_origin, Intersectable, Ray},
+        rng, stats,
+    },
+};
+
+/// Clay render, the fraction of the hemisphere above each hit that is not blocked within a distance
+pub struct AmbientOcclusionRenderer<T: Camera, K: Intersectable> {
+    pub camera: T,
+    pub object: K,
+    pub samples: usize,
+    /// Occluders further than this are ignored
+    pub distance: Float,
+}
+
+impl<T: Camera, K: Intersectable> FragmentRender for AmbientOcclusionRenderer<T, K> {
+    fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+        let ray = self.camera.get_ray(ctx, pos);
+        stats::ray();
+        let intersection = match self.object.intersect(ray) {
+            Some(intersection) => intersection,
+            None => return Vec3::from_single(1.0),
+        };
+        //Both normals face the side the camera sees
+        let flip = |normal: Vec3| {
+            if normal.dot(ray.direction) > 0.0 {
+                -normal
+            } else {
+                normal
+            }
+        };
+        let normal = flip(intersection.get_normal());
+        let origin = offset_origin(intersection.get_pos(), flip(intersection.get_geometric_normal()));
+        let unoccluded = (0..self.samples)
+            .filter(|_| {
+                //A point on the unit sphere around the normal gives a cosine weighted direction
+                let p: [Float; 3] = rng::sample(&UnitSphere);
+                let direction = (normal + Vec3::from_array(p)).normalized();
+                stats::ray();
+                match self.object.intersect(Ray::with_time(origin, direction, ray.time)) {
+                    Some(occluder) => occluder.get_distance() > self.distance,
+                    None => true,
+                }
+            })
+            .count();
+        Vec3::from_single(unoccluded as Float / self.samples.max(1) as Float)
+    }
+}
@@ -0,0 +1,42 @@
+use crate::{
+    api::{
+        camera::Camera,
+        fragment_render::{FragmentContext, FragmentRender},
+    },
+    utilities::{
+        math::{Float, Vec2, Vec3},
+        ray::Intersectable,
+        stats,
+    },
+};
+
+#[derive(Clone, Copy, Debug)]
+pub enum DepthMode {
+    /// Distance along the ray, in scene units
+    Linear,
+    /// Distance mapped from near..far to 0..1, misses are 1
+    Normalized { near: Float, far: Float },
+}
+
+pub struct DepthRenderer<T: Camera, K: Intersectable> {
+    pub camera: T,
+    pub object: K,
+    pub mode: DepthMode,
+}
+
+impl<T: Camera, K: Intersectable> FragmentRender for DepthRenderer<T, K> {
+    fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+        let ray = self.camera.get_ray(ctx, pos);
+        stats::ray();
+        let distance = self.object.intersect(ray).map(|intersection| intersection.get_distance());
+        let depth = match (self.mode, distance) {
+            (DepthMode::Linear, Some(distance)) => distance,
+            (DepthMode::Linear, None) => 0.0,
+            (DepthMode::Normalized { near, far }, Some(distance)) => {
+                ((distance - near) / (far - near)).clamp(0.0, 1.0)
+            }
+            (DepthMode::Normalized { near: _, far: _ }, None) => 1.0,
+        };
+        Vec3::from_single(depth)
+    }
+}
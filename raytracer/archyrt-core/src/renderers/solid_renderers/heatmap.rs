@@ -0,0 +1,60 @@
+use crate::{
+    api::fragment_render::{FragmentContext, FragmentRender},
+    utilities::{
+        math::{Float, Vec2, Vec3},
+        stats::{self, RayStats},
+    },
+};
+
+/// The cost shown by a [HeatmapRenderer]
+#[derive(Clone, Copy, Debug)]
+pub enum HeatmapMetric {
+    Rays,
+    Nodes,
+    Triangles,
+}
+
+impl HeatmapMetric {
+    pub fn get(self, stats: RayStats) -> u64 {
+        match self {
+            HeatmapMetric::Rays => stats.rays,
+            HeatmapMetric::Nodes => stats.nodes,
+            HeatmapMetric::Triangles => stats.triangles,
+        }
+    }
+}
+
+/// Visualises the traversal cost of rendering each pixel with the inner renderer.
+/// Requires the `stats` feature, the inner renderer has to do its work on the calling thread.
+pub struct HeatmapRenderer<T: FragmentRender> {
+    pub inner: T,
+    pub metric: HeatmapMetric,
+    /// Cost that is shown as the hottest color
+    pub max: u64,
+}
+
+const RAMP: [[Float; 3]; 5] = [
+    [0.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0],
+    [0.0, 1.0, 0.0],
+    [1.0, 1.0, 0.0],
+    [1.0, 0.0, 0.0],
+];
+
+/// Black, blue, green, yellow, red as t goes from 0 to 1
+pub fn heat_color(t: Float) -> Vec3 {
+    let t = t.clamp(0.0, 1.0) * (RAMP.len() - 1) as Float;
+    let index = (t as usize).min(RAMP.len() - 2);
+    let a = Vec3::from_array(RAMP[index]);
+    let b = Vec3::from_array(RAMP[index + 1]);
+    a.lerp(b, t - index as Float)
+}
+
+impl<T: FragmentRender> FragmentRender for HeatmapRenderer<T> {
+    fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+        let before = stats::local();
+        self.inner.render_fragment(ctx, pos);
+        let cost = self.metric.get(stats::local() - before);
+        heat_color(cost as Float / self.max.max(1) as Float)
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{
+    api::{
+        camera::Camera,
+        fragment_render::{FragmentContext, FragmentRender},
+    },
+    utilities::{
+        math::{Vec2, Vec3},
+        ray::Intersectable,
+        stats,
+    },
+};
+
+use super::false_color;
+
+/// Gives every [Instance](crate::intersectables::instance::Instance) a distinct color.
+/// Geometry outside of instances counts as id 0.
+pub struct InstanceIdRenderer<T: Camera, K: Intersectable> {
+    pub camera: T,
+    pub object: K,
+}
+
+impl<T: Camera, K: Intersectable> FragmentRender for InstanceIdRenderer<T, K> {
+    fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+        let ray = self.camera.get_ray(ctx, pos);
+        stats::ray();
+        match self.object.intersect(ray) {
+            Some(intersection) => false_color(&intersection.get_instance().unwrap_or(0)),
+            None => Vec3::default(),
+        }
+    }
+}
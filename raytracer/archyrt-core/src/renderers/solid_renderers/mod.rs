@@ -1,2 +1,27 @@
 pub mod albedo;
+pub mod ambient_occlusion;
+pub mod depth;
+pub mod heatmap;
+pub mod instance_id;
 pub mod normal;
+pub mod texture_id;
+pub mod uv;
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    utilities::math::{Float, Vec3},
+    vector,
+};
+
+/// Bright, stable color for an id, neighbouring ids get unrelated colors
+pub fn false_color<T: Hash>(id: &T) -> Vec3 {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    let hash = hasher.finish();
+    let channel = |shift: u32| 0.2 + 0.8 * ((hash >> shift) & 0xff) as Float / 255.0;
+    vector![channel(0), channel(8), channel(16)]
+}
@@ -6,6 +6,7 @@ use crate::{
     utilities::{
         math::{Vec2, Vec3},
         ray::Intersectable,
+        stats,
     },
 };
 
@@ -17,6 +18,7 @@ pub struct NormalRenderer<T: Camera, K: Intersectable> {
 impl<T: Camera, K: Intersectable> FragmentRender for NormalRenderer<T, K> {
     fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
         let ray = self.camera.get_ray(ctx, pos);
+        stats::ray();
         match self.object.intersect(ray) {
             Some(intersection) => intersection.get_normal(),
             None => Vec3::default(),
@@ -0,0 +1,34 @@
+use crate::{
+    api::{
+        camera::Camera,
+        fragment_render::{FragmentContext, FragmentRender},
+    },
+    textures::color_provider::ColorProvider,
+    utilities::{
+        math::{Vec2, Vec3},
+        ray::Intersectable,
+        stats,
+    },
+};
+
+use super::false_color;
+
+/// Gives every texture a distinct color
+pub struct TextureIdRenderer<T: Camera, K: Intersectable> {
+    pub camera: T,
+    pub object: K,
+}
+
+impl<T: Camera, K: Intersectable> FragmentRender for TextureIdRenderer<T, K> {
+    fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+        let ray = self.camera.get_ray(ctx, pos);
+        stats::ray();
+        match self.object.intersect(ray) {
+            Some(intersection) => match intersection.ref_color_provider().get_texture() {
+                Some(texture) => false_color(&texture),
+                None => Vec3::from_single(0.5),
+            },
+            None => Vec3::default(),
+        }
+    }
+}
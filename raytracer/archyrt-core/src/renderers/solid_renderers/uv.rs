@@ -0,0 +1,42 @@
+use crate::{
+    api::{
+        camera::Camera,
+        fragment_render::{FragmentContext, FragmentRender},
+    },
+    textures::color_provider::ColorProvider,
+    utilities::{
+        math::{Float, Vec2, Vec3},
+        ray::Intersectable,
+        stats,
+    },
+    vector,
+};
+
+/// Checkerboard tinted by the texture coordinates, for spotting stretched or flipped UVs
+pub struct UvCheckerRenderer<T: Camera, K: Intersectable> {
+    pub camera: T,
+    pub object: K,
+    /// Number of checks along each axis of the 0..1 range
+    pub checks: Float,
+}
+
+impl<T: Camera, K: Intersectable> FragmentRender for UvCheckerRenderer<T, K> {
+    fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+        let ray = self.camera.get_ray(ctx, pos);
+        stats::ray();
+        let intersection = match self.object.intersect(ray) {
+            Some(intersection) => intersection,
+            None => return Vec3::default(),
+        };
+        let uv = match intersection.ref_color_provider().get_uv() {
+            Some(uv) => uv,
+            //Surfaces without texture coordinates are magenta
+            None => return vector![1.0, 0.0, 1.0],
+        };
+        let u = uv.x() - uv.x().floor();
+        let v = uv.y() - uv.y().floor();
+        let check = ((uv.x() * self.checks).floor() + (uv.y() * self.checks).floor()) as i64;
+        let brightness = if check.rem_euclid(2) == 0 { 1.0 } else { 0.5 };
+        vector![u, v, 1.0 - u] * brightness
+    }
+}
@@ -0,0 +1,650 @@
+//Image equivalence between precisions. The references are rendered in double precision,
+//run with UPDATE_REFERENCE=1 to regenerate them.
+mod precision {
+    use std::path::PathBuf;
+
+    use crate::{
+        api::{
+            camera::Camera,
+            fragment_collector::FragmentCollector,
+            fragment_render::{FragmentContext, FragmentRender},
+        },
+        cameras::perspective::PerspectiveCamera,
+        collector::raw_collector::RawCollector,
+        intersectables::{bvh::BVH, sphere::Sphere, triangle::Triangle, union::UnionIntersector},
+        renderers::{path_tracer::Material, solid_renderers::normal::NormalRenderer},
+        textures::{texture_repo::TextureRepository, TextureID},
+        utilities::{
+            math::{Float, Vec2, Vec3},
+            ray::Intersectable,
+        },
+        vector,
+    };
+
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 48;
+    //Maximum difference of a channel that still counts as equal
+    const TOLERANCE: f32 = 0.001;
+    //Fraction of pixels allowed to differ, a ray grazing an edge might hit a different surface
+    const MAX_DIFFERENT: f32 = 0.01;
+
+    struct DepthRenderer<T: Camera, K: Intersectable> {
+        camera: T,
+        object: K,
+    }
+
+    impl<T: Camera, K: Intersectable> FragmentRender for DepthRenderer<T, K> {
+        fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+            let ray = self.camera.get_ray(ctx, pos);
+            match self.object.intersect(ray) {
+                Some(intersection) => Vec3::from_single(intersection.get_distance() / 20.0),
+                None => Vec3::default(),
+            }
+        }
+    }
+
+    //Far from the origin, where single precision loses the most
+    fn world_offset() -> Vec3 {
+        Vec3::new(2000.0, 300.0, -1500.0)
+    }
+
+    fn scene() -> UnionIntersector<BVH, Sphere> {
+        let offset = world_offset();
+        let uv = [vector![0.0, 0.0], vector![0.0, 1.0], vector![1.0, 0.0]];
+        let mut triangles = Vec::new();
+        //Uneven floor
+        let height = |x: i32, z: i32| ((x * 7 + z * 3).rem_euclid(5)) as Float * 0.15;
+        for x in -6..6 {
+            for z in -2..10 {
+                let corner = |dx: i32, dz: i32| {
+                    offset + Vec3::new((x + dx) as Float, height(x + dx, z + dz), (z + dz) as Float)
+                };
+                let (a, b, c, d) = (corner(0, 0), corner(1, 0), corner(1, 1), corner(0, 1));
+                triangles.push(Triangle::new([a, d, b], uv, TextureID::new(&0), Material::Diffuse));
+                triangles.push(Triangle::new([b, d, c], uv, TextureID::new(&0), Material::Diffuse));
+            }
+        }
+        //Pyramid
+        let top = offset + Vec3::new(-2.0, 2.5, 5.0);
+        let base = [
+            Vec3::new(-3.0, 0.5, 4.0),
+            Vec3::new(-1.0, 0.5, 4.0),
+            Vec3::new(-1.0, 0.5, 6.0),
+            Vec3::new(-3.0, 0.5, 6.0),
+        ];
+        for i in 0..4 {
+            let a = offset + base[i];
+            let b = offset + base[(i + 1) % 4];
+            triangles.push(Triangle::new([a, top, b], uv, TextureID::new(&0), Material::Diffuse));
+        }
+        let sphere = Sphere {
+            origin: offset + Vec3::new(2.0, 1.5, 4.0),
+            radius: 1.2,
+            color: Vec3::ones(),
+            ..Default::default()
+        };
+        UnionIntersector(BVH::from_triangles(&triangles).unwrap(), sphere)
+    }
+
+    fn camera() -> PerspectiveCamera {
+        let position = world_offset() + Vec3::new(0.0, 3.0, -6.0);
+        PerspectiveCamera::new(position, Vec3::new(0.0, -0.3, 1.0).normalized(), 0.8)
+    }
+
+    fn reference_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("reference")
+            .join(format!("{}.raw", name))
+    }
+
+    fn compare<T: FragmentRender>(name: &str, renderer: T) {
+        let repo = TextureRepository::new();
+        let image = RawCollector {}.collect(renderer, &repo, WIDTH, HEIGHT);
+        let path = reference_path(name);
+        if std::env::var("UPDATE_REFERENCE").is_ok() {
+            let bytes: Vec<u8> = image.iter().flat_map(|a| a.to_le_bytes()).collect();
+            std::fs::write(&path, bytes).unwrap();
+            return;
+        }
+        let reference: Vec<f32> = std::fs::read(&path)
+            .unwrap()
+            .chunks_exact(4)
+            .map(|a| f32::from_le_bytes([a[0], a[1], a[2], a[3]]))
+            .collect();
+        assert_eq!(reference.len(), image.len());
+        let different = image
+            .chunks_exact(3)
+            .zip(reference.chunks_exact(3))
+            .filter(|(a, b)| a.iter().zip(b.iter()).any(|(a, b)| (a - b).abs() > TOLERANCE))
+            .count();
+        let allowed = (MAX_DIFFERENT * (WIDTH * HEIGHT) as f32) as usize;
+        assert!(
+            different <= allowed,
+            "{}: {} pixels differ from the reference, at most {} are allowed",
+            name,
+            different,
+            allowed
+        );
+    }
+
+    #[test]
+    fn normals() {
+        compare(
+            "normals",
+            NormalRenderer {
+                camera: camera(),
+                object: scene(),
+            },
+        );
+    }
+
+    #[test]
+    fn depth() {
+        compare(
+            "depth",
+            DepthRenderer {
+                camera: camera(),
+                object: scene(),
+            },
+        );
+    }
+}
+
+mod heatmap {
+    use crate::{
+        api::fragment_collector::FragmentCollector,
+        cameras::perspective::PerspectiveCamera,
+        collector::array_collector::ArrayCollector,
+        intersectables::sphere::Sphere,
+        renderers::solid_renderers::{
+            heatmap::{heat_color, HeatmapMetric, HeatmapRenderer},
+            normal::NormalRenderer,
+        },
+        textures::texture_repo::TextureRepository,
+        utilities::math::Vec3,
+    };
+
+    #[test]
+    fn colors() {
+        assert_eq!(heat_color(0.0), Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(heat_color(0.5), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(heat_color(1.0), Vec3::new(1.0, 0.0, 0.0));
+        //Costs above the maximum are clamped
+        assert_eq!(heat_color(3.0), Vec3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rays() {
+        let renderer = HeatmapRenderer {
+            inner: NormalRenderer {
+                camera: PerspectiveCamera::new(Vec3::default(), Vec3::new(0.0, 0.0, 1.0), 1.0),
+                object: Sphere {
+                    origin: Vec3::new(0.0, 0.0, 3.0),
+                    ..Default::default()
+                },
+            },
+            metric: HeatmapMetric::Rays,
+            max: 4,
+        };
+        let image = ArrayCollector {}.collect(renderer, &TextureRepository::new(), 4, 4);
+        let expected = if cfg!(feature = "stats") {
+            //One ray per pixel
+            heat_color(0.25)
+        } else {
+            heat_color(0.0)
+        };
+        assert!(image.iter().flatten().all(|color| *color == expected));
+    }
+}
+
+mod debug_views {
+    use crate::{
+        api::fragment_render::{FragmentContext, FragmentRender},
+        cameras::perspective::PerspectiveCamera,
+        intersectables::{instance::Instance, sphere::Sphere, triangle::Triangle},
+        renderers::{
+            mode::RenderMode,
+            path_tracer::Material,
+            solid_renderers::{
+                ambient_occlusion::AmbientOcclusionRenderer,
+                depth::{DepthMode, DepthRenderer},
+                false_color,
+                instance_id::InstanceIdRenderer,
+                texture_id::TextureIdRenderer,
+                uv::UvCheckerRenderer,
+            },
+        },
+        textures::{texture_repo::TextureRepository, TextureID},
+        utilities::math::{Float, Vec3},
+        vector,
+    };
+
+    //Renders the center of the image
+    fn center<T: FragmentRender>(renderer: T) -> Vec3 {
+        let repo = TextureRepository::new();
+        let ctx = FragmentContext {
+            width: 1.0,
+            height: 1.0,
+            textures: &repo.handles(),
+        };
+        renderer.render_fragment(&ctx, vector![0.5, 0.5])
+    }
+
+    fn camera() -> PerspectiveCamera {
+        PerspectiveCamera::new(Vec3::default(), Vec3::new(0.0, 0.0, 1.0), 1.0)
+    }
+
+    fn sphere() -> Sphere {
+        Sphere {
+            origin: Vec3::new(0.0, 0.0, 3.0),
+            radius: 1.0,
+            ..Default::default()
+        }
+    }
+
+    //Facing the camera at z, texture coordinates go from 0 to 1 over 2 units
+    fn wall(z: Float, texture: TextureID) -> Triangle {
+        Triangle::new(
+            [
+                Vec3::new(-1.0, -1.0, z),
+                Vec3::new(-1.0, 3.0, z),
+                Vec3::new(3.0, -1.0, z),
+            ],
+            [vector![0.0, 0.0], vector![0.0, 2.0], vector![2.0, 0.0]],
+            texture,
+            Material::Diffuse,
+        )
+    }
+
+    #[test]
+    fn depth() {
+        let linear = DepthRenderer {
+            camera: camera(),
+            object: sphere(),
+            mode: DepthMode::Linear,
+        };
+        assert!((center(linear) - Vec3::from_single(2.0)).length() < 1e-4);
+        let normalized = DepthRenderer {
+            camera: camera(),
+            object: sphere(),
+            mode: DepthMode::Normalized { near: 1.0, far: 5.0 },
+        };
+        assert!((center(normalized) - Vec3::from_single(0.25)).length() < 1e-4);
+        let miss = DepthRenderer {
+            camera: camera(),
+            object: Vec::<Sphere>::new(),
+            mode: DepthMode::Normalized { near: 1.0, far: 5.0 },
+        };
+        assert_eq!(center(miss), Vec3::from_single(1.0));
+    }
+
+    #[test]
+    fn uv_checker() {
+        let renderer = UvCheckerRenderer {
+            camera: camera(),
+            object: wall(2.0, TextureID::new(&0)),
+            checks: 4.0,
+        };
+        //The center is at uv (0.5, 0.5), in a bright check
+        let color = center(renderer);
+        assert!((color - vector![0.5, 0.5, 0.5]).length() < 1e-4);
+        //Spheres have no texture coordinates
+        let renderer = UvCheckerRenderer {
+            camera: camera(),
+            object: sphere(),
+            checks: 4.0,
+        };
+        assert_eq!(center(renderer), vector![1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn texture_id() {
+        let color = |texture| {
+            center(TextureIdRenderer {
+                camera: camera(),
+                object: wall(2.0, texture),
+            })
+        };
+        assert_eq!(color(TextureID::new(&1)), false_color(&TextureID::new(&1)));
+        assert_ne!(color(TextureID::new(&1)), color(TextureID::new(&2)));
+    }
+
+    #[test]
+    fn instance_id() {
+        let renderer = InstanceIdRenderer {
+            camera: camera(),
+            object: Instance {
+                inner: Instance {
+                    inner: sphere(),
+                    id: 5,
+                },
+                id: 1,
+            },
+        };
+        //The innermost instance wins
+        assert_eq!(center(renderer), false_color(&5u32));
+        let renderer = InstanceIdRenderer {
+            camera: camera(),
+            object: sphere(),
+        };
+        assert_eq!(center(renderer), false_color(&0u32));
+    }
+
+    #[test]
+    fn ambient_occlusion() {
+        let open = AmbientOcclusionRenderer {
+            camera: camera(),
+            object: wall(2.0, TextureID::new(&0)),
+            samples: 32,
+            distance: 1.0,
+        };
+        assert_eq!(center(open), Vec3::from_single(1.0));
+        //A perpendicular wall right next to the hit blocks about half of the hemisphere
+        let side = Triangle::new(
+            [
+                Vec3::new(0.1, -10.0, -5.0),
+                Vec3::new(0.1, 30.0, -5.0),
+                Vec3::new(0.1, -10.0, 35.0),
+            ],
+            [vector![0.0, 0.0], vector![0.0, 1.0], vector![1.0, 0.0]],
+            TextureID::new(&0),
+            Material::Diffuse,
+        );
+        let occluded = AmbientOcclusionRenderer {
+            camera: camera(),
+            object: vec![wall(2.0, TextureID::new(&0)), side],
+            samples: 256,
+            distance: 1.0,
+        };
+        let visible = center(occluded).x();
+        assert!(visible > 0.2 && visible < 0.8, "{}", visible);
+    }
+
+    #[test]
+    fn modes() {
+        for mode in RenderMode::ALL {
+            match mode.name().parse::<RenderMode>() {
+                Ok(parsed) => assert_eq!(parsed, mode),
+                //Would render black without the node counts
+                Err(_) => assert!(!mode.available()),
+            }
+            assert_eq!(mode.debug_renderer(camera(), sphere()).is_none(), mode.is_lit());
+        }
+        assert!("raytraced".parse::<RenderMode>().is_err());
+        assert_eq!(RenderMode::Heatmap.available(), cfg!(feature = "stats"));
+        assert_eq!(RenderMode::default(), RenderMode::PathTraced);
+    }
+}
+
+mod region {
+    use crate::{
+        api::{
+            fragment_collector::FragmentCollector,
+            fragment_render::{FragmentContext, FragmentRender},
+        },
+        collector::array_collector::ArrayCollector,
+        renderers::region::{Region, RegionView, RenderArea},
+        textures::texture_repo::TextureRepository,
+        utilities::math::{Vec2, Vec3},
+    };
+
+    //Depends on the aspect ratio like a camera would
+    struct AspectRenderer {}
+
+    impl FragmentRender for AspectRenderer {
+        fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+            Vec3::new(pos.x(), pos.y(), ctx.width / ctx.height)
+        }
+    }
+
+    #[test]
+    fn parse() {
+        let region: Region = "3, 2,5,4".parse().unwrap();
+        assert_eq!(
+            region,
+            Region {
+                x: 3,
+                y: 2,
+                width: 5,
+                height: 4
+            }
+        );
+        assert_eq!(region.to_string().parse::<Region>().unwrap(), region);
+        assert!("1,2,3".parse::<Region>().is_err());
+        assert!("1,2,3,-4".parse::<Region>().is_err());
+    }
+
+    #[test]
+    fn area() {
+        let full = RenderArea::new(512, 256, 1.0, None).unwrap();
+        assert_eq!(full.region, Region::full(512, 256));
+        assert!(!full.is_cropped());
+
+        let crop = Region {
+            x: 100,
+            y: 100,
+            width: 50,
+            height: 50,
+        };
+        let preview = RenderArea::new(512, 256, 0.25, Some(crop)).unwrap();
+        assert_eq!((preview.width, preview.height), (128, 64));
+        //Partially covered pixels are part of the scaled region
+        assert_eq!(
+            preview.region,
+            Region {
+                x: 25,
+                y: 25,
+                width: 13,
+                height: 13
+            }
+        );
+        assert!(preview.is_cropped());
+
+        //Rounding the scaled size down must not push the region out of the image
+        let edge = Region {
+            x: 120,
+            y: 0,
+            width: 1,
+            height: 10,
+        };
+        let area = RenderArea::new(121, 10, 0.01, Some(edge)).unwrap();
+        assert!(area.region.is_inside(area.width, area.height));
+
+        assert!(RenderArea::new(512, 256, 0.0, None).is_err());
+        assert!(RenderArea::new(512, 256, 2.0, None).is_err());
+        let outside = Region {
+            x: 500,
+            y: 0,
+            width: 50,
+            height: 50,
+        };
+        assert!(RenderArea::new(512, 256, 1.0, Some(outside)).is_err());
+    }
+
+    #[test]
+    fn crop_matches_full_render() {
+        let repo = TextureRepository::new();
+        let full = ArrayCollector {}.collect(AspectRenderer {}, &repo, 16, 12);
+        let region = Region {
+            x: 3,
+            y: 2,
+            width: 5,
+            height: 4,
+        };
+        let view = RegionView {
+            inner: AspectRenderer {},
+            full_width: 16,
+            full_height: 12,
+            region,
+        };
+        let crop = ArrayCollector {}.collect(view, &repo, region.width, region.height);
+        for (y, row) in crop.iter().enumerate() {
+            for (x, color) in row.iter().enumerate() {
+                let expected = full[region.y + y][region.x + x];
+                assert!((*color - expected).length() < 0.0001);
+            }
+        }
+    }
+
+    #[test]
+    fn single_pixel_column() {
+        let repo = TextureRepository::new();
+        let full = ArrayCollector {}.collect(AspectRenderer {}, &repo, 16, 12);
+        let region = Region {
+            x: 7,
+            y: 0,
+            width: 1,
+            height: 12,
+        };
+        let view = RegionView {
+            inner: AspectRenderer {},
+            full_width: 16,
+            full_height: 12,
+            region,
+        };
+        let crop = ArrayCollector {}.collect(view, &repo, region.width, region.height);
+        for (y, row) in crop.iter().enumerate() {
+            assert_eq!(row.len(), 1);
+            assert!((row[0] - full[y][region.x]).length() < 0.0001);
+        }
+
+        //A 1 pixel wide image is sampled in the middle
+        let column = ArrayCollector {}.collect(AspectRenderer {}, &repo, 1, 3);
+        assert_eq!(column[1][0].x(), 0.5);
+        let view = RegionView {
+            inner: AspectRenderer {},
+            full_width: 1,
+            full_height: 3,
+            region: Region::full(1, 3),
+        };
+        let crop = ArrayCollector {}.collect(view, &repo, 1, 3);
+        assert!(crop.iter().flatten().all(|color| !color.x().is_nan()));
+        assert_eq!(crop, column);
+    }
+}
+
+mod lightmap {
+    use asset::{
+        scene::{Camera, Face, Point, Scene, Solid, World},
+        Lightmap, TextureID,
+    };
+    use cgmath::{vec2, vec3};
+
+    use crate::{
+        intersectables::cuboid::Cuboid,
+        renderers::{lightmap::LightmapBaker, path_tracer::Material},
+        textures::texture_repo::TextureRepository,
+        utilities::math::Vec3,
+    };
+
+    //Box of the editor from -256 to 256 on x and z, with its top at 0. Only the top face is drawn.
+    fn floor() -> Scene {
+        //Corners of the unit cube in the order the editor creates solids in
+        let corners = [(0, 0, 0), (1, 0, 0), (1, 0, 1), (0, 0, 1), (0, 1, 0), (1, 1, 0), (1, 1, 1), (0, 1, 1)];
+        let points = corners.map(|(x, y, z)| Point {
+            position: vec3(-256 + x * 512, -128 + y * 128, -256 + z * 512),
+        });
+        let indices = [[1, 5, 6, 2], [4, 0, 3, 7], [5, 4, 7, 6], [0, 1, 2, 3], [3, 2, 6, 7], [1, 0, 4, 5]];
+        let faces = indices.map(|indices| Face {
+            texture: TextureID(if indices == [5, 4, 7, 6] { 1 } else { 0 }),
+            indices: indices.to_vec(),
+            alignment: Default::default(),
+        });
+        Scene {
+            camera: Camera {
+                position: vec3(0.0, 0.0, 0.0),
+                rotation: vec2(0.0, 0.0),
+            },
+            world: World {
+                solids: vec![Solid {
+                    points: points.into(),
+                    faces: faces.into(),
+                }],
+                props: Vec::new(),
+                outline: Default::default(),
+            },
+        }
+    }
+
+    //Light covering the whole sky, with an optional wall standing over the right half of the floor
+    fn baker(occluder: bool) -> LightmapBaker<Vec<Cuboid>> {
+        let mut light = Cuboid::new(Vec3::new(-100.0, 5.0, -100.0), Vec3::new(100.0, 6.0, 100.0));
+        light.material = Material::Emissive { power: 1.0 };
+        let mut object = vec![light];
+        if occluder {
+            object.push(Cuboid::new(Vec3::new(0.0, 0.2, -100.0), Vec3::new(100.0, 0.5, 100.0)));
+        }
+        LightmapBaker {
+            object,
+            bounces: 2,
+            skybox: None,
+            samples: 256,
+            patch: 4,
+        }
+    }
+
+    #[test]
+    fn layout() {
+        let lightmap = baker(false).layout(&floor());
+        assert_eq!(lightmap.faces.len(), 1);
+        assert_eq!((lightmap.width, lightmap.height), (4, 4));
+        let face = &lightmap.faces[0];
+        assert_eq!(face.corners[0], [256, 0, -256]);
+        assert!(lightmap.find(&face.corners).is_some());
+        //Texture coordinates land on the centers of the corner texels
+        let texcoords = lightmap.texcoords(face);
+        assert_eq!(texcoords[0], vec2(0.125, 0.125));
+        assert_eq!(texcoords[2], vec2(0.875, 0.875));
+    }
+
+    #[test]
+    fn triangle_face() {
+        let mut scene = floor();
+        scene.world.solids[0].faces[2].indices = vec![5, 4, 7];
+        let lightmap = baker(false).bake(&scene, &TextureRepository::new());
+        let face = &lightmap.faces[0];
+        assert_eq!(face.corners.len(), 3);
+        assert_eq!(lightmap.texcoords(face).len(), 3);
+        //Texels outside of the triangle are lit like its closest point
+        for y in 0..4 {
+            for x in 0..4 {
+                let light = lightmap.texel(0, x, y);
+                assert!(light.iter().all(|&c| (c - 0.5).abs() < 0.06), "{:?}", light);
+            }
+        }
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(Lightmap::encode_light(0.0), 0);
+        for light in [0.1, 0.5, 1.0, 3.0] {
+            let decoded = Lightmap::decode_light(Lightmap::encode_light(light));
+            assert!((decoded - light).abs() < light * 0.05, "{} != {}", decoded, light);
+        }
+    }
+
+    #[test]
+    fn open_sky() {
+        let baker = baker(false);
+        let lightmap = baker.bake(&floor(), &TextureRepository::new());
+        //A white face under a white sky reflects half of the light, like in the path tracer
+        for y in 0..4 {
+            for x in 0..4 {
+                let light = lightmap.texel(0, x, y);
+                assert!(light.iter().all(|&c| (c - 0.5).abs() < 0.06), "{:?}", light);
+            }
+        }
+    }
+
+    #[test]
+    fn shadow() {
+        let baker = baker(true);
+        let lightmap = baker.bake(&floor(), &TextureRepository::new());
+        //The patch starts at the corner with the largest x
+        for y in 0..4 {
+            assert!(lightmap.texel(0, 0, y)[0] < 0.05);
+            assert!(lightmap.texel(0, 3, y)[0] > 0.3);
+        }
+    }
+}
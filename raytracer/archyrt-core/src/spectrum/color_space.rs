@@ -0,0 +1,150 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, Error, Result};
+use image::{codecs::png::PngEncoder, ColorType, RgbImage};
+
+use crate::{
+    matrix,
+    utilities::math::{Matrix3x3, Vec3},
+};
+
+/// Linear color space of a rendered image. All share the D65 white point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Primaries of sRGB and Rec. 709, what textures and the RGB path tracer use
+    #[default]
+    Srgb,
+    /// Wide gamut of Display P3
+    DisplayP3,
+    /// CIE 1931 XYZ, what the spectral path tracer produces
+    Xyz,
+}
+
+//Rows are written out and transposed, matrices are stored as columns
+fn from_rows(rows: [[f64; 3]; 3]) -> Matrix3x3 {
+    let row = |i: usize| Vec3::new(rows[i][0] as _, rows[i][1] as _, rows[i][2] as _);
+    matrix![row(0), row(1), row(2)].transpose()
+}
+
+impl ColorSpace {
+    pub const ALL: [ColorSpace; 3] = [ColorSpace::Srgb, ColorSpace::DisplayP3, ColorSpace::Xyz];
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorSpace::Srgb => "srgb",
+            ColorSpace::DisplayP3 => "display-p3",
+            ColorSpace::Xyz => "xyz",
+        }
+    }
+    fn matrix_to_xyz(self) -> Matrix3x3 {
+        match self {
+            ColorSpace::Srgb => from_rows([
+                [0.4124564, 0.3575761, 0.1804375],
+                [0.2126729, 0.7151522, 0.0721750],
+                [0.0193339, 0.1191920, 0.9503041],
+            ]),
+            ColorSpace::DisplayP3 => from_rows([
+                [0.4865709, 0.2656677, 0.1982173],
+                [0.2289746, 0.6917385, 0.0792869],
+                [0.0000000, 0.0451134, 1.0439444],
+            ]),
+            ColorSpace::Xyz => Matrix3x3::identity(),
+        }
+    }
+    fn matrix_from_xyz(self) -> Matrix3x3 {
+        match self {
+            ColorSpace::Srgb => from_rows([
+                [3.2404542, -1.5371385, -0.4985314],
+                [-0.9692660, 1.8760108, 0.0415560],
+                [0.0556434, -0.2040259, 1.0572252],
+            ]),
+            ColorSpace::DisplayP3 => from_rows([
+                [2.4934969, -0.9313836, -0.4027108],
+                [-0.8294890, 1.7626641, 0.0236247],
+                [0.0358458, -0.0761724, 0.9568845],
+            ]),
+            ColorSpace::Xyz => Matrix3x3::identity(),
+        }
+    }
+    pub fn to_xyz(self, color: Vec3) -> Vec3 {
+        self.matrix_to_xyz() * color
+    }
+    pub fn from_xyz(self, color: Vec3) -> Vec3 {
+        self.matrix_from_xyz() * color
+    }
+    pub fn convert(self, color: Vec3, to: ColorSpace) -> Vec3 {
+        if self == to {
+            return color;
+        }
+        to.from_xyz(self.to_xyz(color))
+    }
+    /// Code points of ITU-T H.273 for the primaries, the sRGB transfer function and full range RGB
+    pub fn cicp(self) -> [u8; 4] {
+        let primaries = match self {
+            ColorSpace::Srgb => 1,
+            ColorSpace::DisplayP3 => 12,
+            ColorSpace::Xyz => 10,
+        };
+        [primaries, 13, 0, 1]
+    }
+    /// Encodes a gamma corrected image as a PNG whose cICP chunk tells viewers the color space
+    pub fn encode_png(self, image: &RgbImage) -> Result<Vec<u8>> {
+        let mut png = Vec::new();
+        PngEncoder::new(&mut png).encode(image.as_raw(), image.width(), image.height(), ColorType::Rgb8)?;
+        //The chunk has to come before the image data, right after the header is where the signature and IHDR end
+        let mut chunk = Vec::with_capacity(16);
+        chunk.extend_from_slice(&4u32.to_be_bytes());
+        chunk.extend_from_slice(b"cICP");
+        chunk.extend_from_slice(&self.cicp());
+        chunk.extend_from_slice(&crc32(&chunk[4..]).to_be_bytes());
+        png.splice(PNG_HEADER..PNG_HEADER, chunk);
+        Ok(png)
+    }
+    /// Converts RGB rows as they come out of a collector
+    pub fn convert_image(self, image: &mut [f32], to: ColorSpace) {
+        if self == to {
+            return;
+        }
+        for pixel in image.chunks_mut(3) {
+            let color = Vec3::new(pixel[0] as _, pixel[1] as _, pixel[2] as _);
+            let color = self.convert(color, to);
+            for (value, converted) in pixel.iter_mut().zip(color.inner) {
+                *value = converted as f32;
+            }
+        }
+    }
+}
+
+//Signature and IHDR chunk
+const PNG_HEADER: usize = 8 + 25;
+
+//CRC-32 as used by PNG
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+impl Display for ColorSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for ColorSpace {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ColorSpace::ALL
+            .into_iter()
+            .find(|space| space.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = ColorSpace::ALL.iter().map(|space| space.name()).collect();
+                anyhow!("Unknown color space {}, expected one of {}", s, names.join(", "))
+            })
+    }
+}
@@ -0,0 +1,234 @@
+pub mod color_space;
+#[cfg(test)]
+mod tests;
+
+use std::{
+    ops::{Add, AddAssign, Mul, MulAssign},
+    sync::OnceLock,
+};
+
+use crate::utilities::{
+    math::{Float, Vec3},
+    rng,
+};
+
+/// Shortest wavelength traced, in nanometers
+pub const LAMBDA_MIN: Float = 380.0;
+/// Longest wavelength traced, in nanometers
+pub const LAMBDA_MAX: Float = 720.0;
+/// Wavelengths carried by every path
+pub const WAVELENGTHS: usize = 4;
+
+/// Wavelengths traced together by one path. The first one is the hero, it decides directions
+/// wherever scattering depends on the wavelength.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampledWavelengths {
+    pub lambda: [Float; WAVELENGTHS],
+}
+
+impl SampledWavelengths {
+    /// Hero at `u` in [0, 1) of the range, the others are spaced evenly after it and wrap around
+    pub fn sample(u: Float) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let mut lambda = [0.0; WAVELENGTHS];
+        for (i, lambda) in lambda.iter_mut().enumerate() {
+            let offset = (u + i as Float / WAVELENGTHS as Float).fract();
+            *lambda = LAMBDA_MIN + offset * range;
+        }
+        Self { lambda }
+    }
+    pub fn random() -> Self {
+        Self::sample(rng::random())
+    }
+    pub fn hero(&self) -> Float {
+        self.lambda[0]
+    }
+    /// Probability density of every single wavelength
+    pub fn pdf(&self) -> Float {
+        1.0 / (LAMBDA_MAX - LAMBDA_MIN)
+    }
+}
+
+/// Values of a spectrum at the [SampledWavelengths] of a path
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SampledSpectrum {
+    pub values: [Float; WAVELENGTHS],
+}
+
+impl SampledSpectrum {
+    pub fn from_single(value: Float) -> Self {
+        Self {
+            values: [value; WAVELENGTHS],
+        }
+    }
+    pub fn from_fn<F: Fn(Float) -> Float>(wavelengths: &SampledWavelengths, f: F) -> Self {
+        Self {
+            values: wavelengths.lambda.map(f),
+        }
+    }
+    /// Reflectance spectrum of a linear sRGB color
+    pub fn reflectance(rgb: Vec3, wavelengths: &SampledWavelengths) -> Self {
+        Self::from_fn(wavelengths, |lambda| rgb_to_spectrum(rgb, lambda))
+    }
+    /// Emission spectrum of a linear sRGB color, white light is D65 like the white of sRGB
+    pub fn illuminant(rgb: Vec3, wavelengths: &SampledWavelengths) -> Self {
+        Self::from_fn(wavelengths, |lambda| {
+            rgb_to_spectrum(rgb, lambda) * d65(lambda) / d65_luminance()
+        })
+    }
+    /// Keeps only the hero, for when the path can't be shared by the other wavelengths any more
+    pub fn terminate_secondary(&mut self) {
+        self.values[0] *= WAVELENGTHS as Float;
+        for value in &mut self.values[1..] {
+            *value = 0.0;
+        }
+    }
+    pub fn is_black(&self) -> bool {
+        self.values.iter().all(|&value| value == 0.0)
+    }
+    /// Estimate of the CIE XYZ color of the full spectrum
+    pub fn to_xyz(&self, wavelengths: &SampledWavelengths) -> Vec3 {
+        let sum = self
+            .values
+            .iter()
+            .zip(wavelengths.lambda)
+            .fold(Vec3::default(), |sum, (&value, lambda)| {
+                sum + cie_xyz(lambda) * value
+            });
+        sum / (wavelengths.pdf() * WAVELENGTHS as Float)
+    }
+}
+
+impl Default for SampledSpectrum {
+    fn default() -> Self {
+        Self::from_single(0.0)
+    }
+}
+
+impl Add for SampledSpectrum {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self::Output {
+        self += rhs;
+        self
+    }
+}
+
+impl AddAssign for SampledSpectrum {
+    fn add_assign(&mut self, rhs: Self) {
+        for (a, b) in self.values.iter_mut().zip(rhs.values) {
+            *a += b;
+        }
+    }
+}
+
+impl Mul for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(mut self, rhs: Self) -> Self::Output {
+        self *= rhs;
+        self
+    }
+}
+
+impl MulAssign for SampledSpectrum {
+    fn mul_assign(&mut self, rhs: Self) {
+        for (a, b) in self.values.iter_mut().zip(rhs.values) {
+            *a *= b;
+        }
+    }
+}
+
+impl Mul<Float> for SampledSpectrum {
+    type Output = Self;
+
+    fn mul(mut self, rhs: Float) -> Self::Output {
+        self *= rhs;
+        self
+    }
+}
+
+impl MulAssign<Float> for SampledSpectrum {
+    fn mul_assign(&mut self, rhs: Float) {
+        for value in &mut self.values {
+            *value *= rhs;
+        }
+    }
+}
+
+//Piecewise Gaussian with different widths on the two sides of the peak
+fn lobe(lambda: Float, mean: Float, left: Float, right: Float) -> Float {
+    let width = if lambda < mean { left } else { right };
+    let t = (lambda - mean) / width;
+    (-0.5 * t * t).exp()
+}
+
+/// CIE 1931 2° color matching functions, multi-lobe fit of Wyman, Sloan and Shirley
+pub fn cie_xyz(lambda: Float) -> Vec3 {
+    let x = 1.056 * lobe(lambda, 599.8, 37.9, 31.0) + 0.362 * lobe(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(lambda, 568.8, 46.9, 40.5) + 0.286 * lobe(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(lambda, 437.0, 11.8, 36.0) + 0.681 * lobe(lambda, 459.0, 26.0, 13.8);
+    Vec3::new(x, y, z)
+}
+
+//CIE standard illuminant D65 from 380nm to 780nm in steps of 10nm
+const D65: [Float; 41] = [
+    49.98, 54.65, 82.75, 91.49, 93.43, 86.68, 104.86, 117.01, 117.81, 114.86, 115.92, 108.81,
+    109.35, 107.80, 104.79, 107.69, 104.41, 104.05, 100.00, 96.33, 95.79, 88.69, 90.01, 89.60,
+    87.70, 83.29, 83.70, 80.03, 80.21, 82.28, 78.28, 69.72, 71.61, 74.35, 61.60, 69.89, 75.09,
+    63.59, 46.42, 66.81, 63.38,
+];
+
+/// Relative spectral power of daylight, 100 at 560nm
+pub fn d65(lambda: Float) -> Float {
+    let t = ((lambda - 380.0) / 10.0).clamp(0.0, (D65.len() - 1) as Float);
+    let i = (t as usize).min(D65.len() - 2);
+    let f = t - i as Float;
+    D65[i] * (1.0 - f) + D65[i + 1] * f
+}
+
+//Luminance of D65 over the traced range, scales emission so white light has a luminance of 1
+fn d65_luminance() -> Float {
+    static LUMINANCE: OnceLock<Float> = OnceLock::new();
+    *LUMINANCE.get_or_init(|| {
+        let steps = 1000;
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / steps as Float;
+        (0..steps)
+            .map(|i| {
+                let lambda = LAMBDA_MIN + (i as Float + 0.5) * step;
+                d65(lambda) * cie_xyz(lambda).y() * step
+            })
+            .sum()
+    })
+}
+
+//Basis spectra of Smits' RGB to spectrum conversion, 10 even bins from 380nm to 720nm
+const WHITE: [Float; 10] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const CYAN: [Float; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const MAGENTA: [Float; 10] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const YELLOW: [Float; 10] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const RED: [Float; 10] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const GREEN: [Float; 10] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const BLUE: [Float; 10] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+/// Smooth spectrum of a linear sRGB color after Smits. Colors above 1 are scaled up, negative components are clipped.
+pub fn rgb_to_spectrum(rgb: Vec3, lambda: Float) -> Float {
+    let bin = ((lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN) * WHITE.len() as Float) as isize;
+    let bin = bin.clamp(0, WHITE.len() as isize - 1) as usize;
+    let (r, g, b) = (rgb.x().max(0.0), rgb.y().max(0.0), rgb.z().max(0.0));
+    //The smallest component is white, the rest is made of the secondary and primary color on top of it
+    if r <= g && r <= b {
+        let (secondary, primary) = if g <= b { (g - r, b - g) } else { (b - r, g - b) };
+        let primary_basis = if g <= b { BLUE } else { GREEN };
+        r * WHITE[bin] + secondary * CYAN[bin] + primary * primary_basis[bin]
+    } else if g <= r && g <= b {
+        let (secondary, primary) = if r <= b { (r - g, b - r) } else { (b - g, r - b) };
+        let primary_basis = if r <= b { BLUE } else { RED };
+        g * WHITE[bin] + secondary * MAGENTA[bin] + primary * primary_basis[bin]
+    } else {
+        let (secondary, primary) = if r <= g { (r - b, g - r) } else { (g - b, r - g) };
+        let primary_basis = if r <= g { GREEN } else { RED };
+        b * WHITE[bin] + secondary * YELLOW[bin] + primary * primary_basis[bin]
+    }
+}
@@ -0,0 +1,135 @@
+use crate::utilities::math::{Float, Vec3};
+
+use super::{
+    cie_xyz, color_space::ColorSpace, rgb_to_spectrum, SampledSpectrum, SampledWavelengths,
+    LAMBDA_MAX, LAMBDA_MIN, WAVELENGTHS,
+};
+
+//XYZ of a spectrum averaged over evenly spread hero wavelengths
+fn integrate<F: Fn(&SampledWavelengths) -> SampledSpectrum>(spectrum: F) -> Vec3 {
+    let steps = 500;
+    let sum = (0..steps).fold(Vec3::default(), |sum, i| {
+        let wavelengths = SampledWavelengths::sample((i as Float + 0.5) / steps as Float);
+        sum + spectrum(&wavelengths).to_xyz(&wavelengths)
+    });
+    sum / steps as Float
+}
+
+fn assert_close(a: Vec3, b: Vec3, tolerance: Float) {
+    assert!((a - b).inner.iter().all(|d| d.abs() < tolerance), "{:?} != {:?}", a, b);
+}
+
+#[test]
+fn wavelengths() {
+    let wavelengths = SampledWavelengths::sample(0.0);
+    assert_eq!(wavelengths.hero(), LAMBDA_MIN);
+    let step = (LAMBDA_MAX - LAMBDA_MIN) / WAVELENGTHS as Float;
+    for u in [0.0, 0.3, 0.99] {
+        let wavelengths = SampledWavelengths::sample(u);
+        assert!(wavelengths.lambda.iter().all(|&lambda| (LAMBDA_MIN..LAMBDA_MAX).contains(&lambda)));
+        let mut sorted = wavelengths.lambda;
+        sorted.sort_by(Float::total_cmp);
+        for pair in sorted.windows(2) {
+            assert!((pair[1] - pair[0] - step).abs() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn matching_functions() {
+    //The luminance curve peaks at 555nm and integrates to about 106.9 over the visible range
+    let y = |lambda: Float| cie_xyz(lambda).y();
+    assert!((y(555.0) - 1.0).abs() < 0.02);
+    assert!(y(555.0) > y(500.0) && y(555.0) > y(610.0));
+    let integral: Float = (360..830).map(|lambda| y(lambda as Float + 0.5)).sum();
+    assert!((integral - 106.9).abs() < 2.0, "{}", integral);
+}
+
+#[test]
+fn upsampling() {
+    for lambda in [400.0, 500.0, 600.0, 700.0] {
+        assert!((rgb_to_spectrum(Vec3::from_single(1.0), lambda) - 1.0).abs() < 0.01);
+        assert!((rgb_to_spectrum(Vec3::from_single(0.25), lambda) - 0.25).abs() < 0.01);
+    }
+    let red = Vec3::new(1.0, 0.0, 0.0);
+    assert!(rgb_to_spectrum(red, 680.0) > 0.9);
+    assert!(rgb_to_spectrum(red, 520.0) < 0.1);
+    //Lit by white light, a reflectance spectrum looks like the color it was made from
+    for rgb in [
+        Vec3::from_single(1.0),
+        Vec3::from_single(0.5),
+        Vec3::new(0.8, 0.3, 0.1),
+        Vec3::new(0.1, 0.6, 0.3),
+        Vec3::new(0.2, 0.3, 0.9),
+    ] {
+        let xyz = integrate(|wavelengths| SampledSpectrum::illuminant(rgb, wavelengths));
+        assert_close(ColorSpace::Xyz.convert(xyz, ColorSpace::Srgb), rgb, 0.06);
+    }
+}
+
+#[test]
+fn terminate_secondary() {
+    let mut hero = SampledSpectrum::from_single(0.5);
+    hero.terminate_secondary();
+    assert_eq!(hero.values, [2.0, 0.0, 0.0, 0.0]);
+    //Only the hero is left, its estimate is scaled so the average stays the same
+    let xyz = integrate(|_| hero);
+    let expected = integrate(|_| SampledSpectrum::from_single(0.5));
+    assert_close(xyz, expected, 0.01);
+}
+
+mod color_space {
+    use crate::utilities::math::Vec3;
+
+    use super::{super::color_space::ColorSpace, assert_close};
+
+    #[test]
+    fn names() {
+        for space in ColorSpace::ALL {
+            assert_eq!(space.name().parse::<ColorSpace>().unwrap(), space);
+        }
+        assert!("adobe-rgb".parse::<ColorSpace>().is_err());
+        assert_eq!(ColorSpace::default(), ColorSpace::Srgb);
+    }
+
+    #[test]
+    fn conversions() {
+        let white = Vec3::from_single(1.0);
+        //D65 white is the same in both RGB spaces
+        assert_close(ColorSpace::Srgb.convert(white, ColorSpace::DisplayP3), white, 1e-3);
+        assert_close(ColorSpace::Srgb.to_xyz(white), Vec3::new(0.9505, 1.0, 1.089), 1e-3);
+        for color in [Vec3::new(0.9, 0.1, 0.3), Vec3::new(0.0, 0.5, 1.0)] {
+            for space in ColorSpace::ALL {
+                let back = ColorSpace::Srgb.convert(color, space);
+                assert_close(space.convert(back, ColorSpace::Srgb), color, 1e-3);
+            }
+        }
+        //sRGB fits inside Display P3, its primaries are less saturated there
+        let red = ColorSpace::Srgb.convert(Vec3::new(1.0, 0.0, 0.0), ColorSpace::DisplayP3);
+        assert!(red.inner.iter().all(|&c| (0.0..=1.0).contains(&c)));
+        assert!(red.x() < 0.95 && red.y() > 0.0);
+    }
+
+    #[test]
+    fn image() {
+        let mut image = vec![1.0, 1.0, 1.0, 0.2, 0.4, 0.6];
+        ColorSpace::Srgb.convert_image(&mut image, ColorSpace::Srgb);
+        assert_eq!(image, vec![1.0, 1.0, 1.0, 0.2, 0.4, 0.6]);
+        ColorSpace::Srgb.convert_image(&mut image, ColorSpace::Xyz);
+        let expected = ColorSpace::Srgb.to_xyz(Vec3::new(0.2, 0.4, 0.6));
+        assert!((image[4] - expected.y() as f32).abs() < 1e-5);
+        ColorSpace::Xyz.convert_image(&mut image, ColorSpace::Srgb);
+        assert!(image.iter().zip([1.0, 1.0, 1.0, 0.2, 0.4, 0.6]).all(|(a, b)| (a - b).abs() < 1e-4));
+    }
+
+    #[test]
+    fn png() {
+        let image = image::RgbImage::from_raw(2, 1, vec![255, 0, 0, 10, 20, 30]).unwrap();
+        let png = ColorSpace::DisplayP3.encode_png(&image).unwrap();
+        //Length, type and data of the chunk right after IHDR
+        assert_eq!(png[33..45], [0, 0, 0, 4, b'c', b'I', b'C', b'P', 12, 13, 0, 1]);
+        assert_eq!(png[45..49], 0x6e03e3efu32.to_be_bytes());
+        let decoded = image::load_from_memory(&png).unwrap().to_rgb8();
+        assert_eq!(decoded, image);
+    }
+}
@@ -1,20 +1,28 @@
 use super::TextureID;
 
-use crate::{renderers::path_tracer::Material, utilities::math::Vec3};
+use crate::{renderers::path_tracer::Material, utilities::math::{Vec2, Vec3}};
 
-use super::texture_repo::TextureRepository;
+use super::texture_repo::TextureHandles;
 
 pub trait ColorProvider {
-    fn get_color(&self, repo: &TextureRepository) -> Vec3;
+    fn get_color(&self, textures: &TextureHandles) -> Vec3;
     fn get_material(&self) -> Material;
-    fn sample(&self, repo: &TextureRepository, id: TextureID) -> Vec3;
+    fn sample(&self, textures: &TextureHandles, id: TextureID) -> Vec3;
+    /// Texture coordinates of the hit, if the surface has any
+    fn get_uv(&self) -> Option<Vec2> {
+        None
+    }
+    /// Texture of the hit surface, if it has one
+    fn get_texture(&self) -> Option<TextureID> {
+        None
+    }
 }
 
 #[derive(Default, Clone)]
 pub struct SolidColor(pub Vec3, pub Material);
 
 impl ColorProvider for SolidColor {
-    fn get_color(&self, _: &TextureRepository) -> Vec3 {
+    fn get_color(&self, _: &TextureHandles) -> Vec3 {
         self.0
     }
 
@@ -22,7 +30,7 @@ impl ColorProvider for SolidColor {
         self.1
     }
 
-    fn sample(&self, repo: &TextureRepository, id: TextureID) -> Vec3 {
+    fn sample(&self, textures: &TextureHandles, id: TextureID) -> Vec3 {
         self.0
     }
     
@@ -5,6 +5,8 @@ pub mod color_provider;
 pub mod samplers;
 pub mod texture;
 pub mod texture_repo;
+#[cfg(test)]
+mod tests;
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct TextureID(u64);
@@ -1,6 +1,6 @@
 use crate::{
     textures::{texture::Texture},
-    utilities::math::{Vec2, Vec3},
+    utilities::math::{Float, Vec2, Vec3},
     vector,
 };
 
@@ -8,13 +8,13 @@ use super::{nearest::NearestSampler, TextureSampler};
 
 pub struct LinearSampler {}
 
-impl TextureSampler for LinearSampler {
-    fn sample(&self, texture: &Texture, uv: Vec2) -> Vec3 {
-        let sampler = NearestSampler {};
+impl LinearSampler {
+    /// Returns the four surrounding texel coordinates and the interpolation weights between them
+    fn corners(texture: &Texture, uv: Vec2) -> ([Vec2; 4], Float, Float) {
         let w = texture.width() as usize;
         let h = texture.height() as usize;
-        let x = uv.x() * (w as f64);
-        let y = uv.y() * (h as f64);
+        let x = uv.x() * (w as Float);
+        let y = uv.y() * (h as Float);
 
         let x1 = x.floor();
         let x2 = x1 + 1.0;
@@ -24,19 +24,44 @@ impl TextureSampler for LinearSampler {
         let xt = x - x1;
         let yt = y - y1;
 
-        let x1 = x1 / (w as f64);
-        let x2 = x2 / (w as f64);
-        let y1 = y1 / (h as f64);
-        let y2 = y2 / (h as f64);
+        let x1 = x1 / (w as Float);
+        let x2 = x2 / (w as Float);
+        let y1 = y1 / (h as Float);
+        let y2 = y2 / (h as Float);
 
-        let c1 = sampler.sample(texture, vector![x1, y1]);
-        let c2 = sampler.sample(texture, vector![x2, y1]);
-        let c3 = sampler.sample(texture, vector![x1, y2]);
-        let c4 = sampler.sample(texture, vector![x2, y2]);
+        (
+            [
+                vector![x1, y1],
+                vector![x2, y1],
+                vector![x1, y2],
+                vector![x2, y2],
+            ],
+            xt,
+            yt,
+        )
+    }
+}
+
+impl TextureSampler for LinearSampler {
+    fn sample(&self, texture: &Texture, uv: Vec2) -> Vec3 {
+        let sampler = NearestSampler {};
+        let (corners, xt, yt) = Self::corners(texture, uv);
+        let [c1, c2, c3, c4] = corners.map(|corner| sampler.sample(texture, corner));
 
         let c1 = c1 * (1.0 - xt) + c2 * xt;
         let c2 = c3 * (1.0 - xt) + c4 * xt;
         
         c1 * (1.0 - yt) + c2 * yt
     }
+
+    fn sample_alpha(&self, texture: &Texture, uv: Vec2) -> Float {
+        let sampler = NearestSampler {};
+        let (corners, xt, yt) = Self::corners(texture, uv);
+        let [a1, a2, a3, a4] = corners.map(|corner| sampler.sample_alpha(texture, corner));
+
+        let a1 = a1 * (1.0 - xt) + a2 * xt;
+        let a2 = a3 * (1.0 - xt) + a4 * xt;
+
+        a1 * (1.0 - yt) + a2 * yt
+    }
 }
@@ -1,26 +1,37 @@
 use crate::{
     textures::{texture::Texture},
-    utilities::math::{Vec2, Vec3},
+    utilities::math::{Float, Vec2, Vec3},
 };
 
 use super::TextureSampler;
 
 pub struct NearestSampler {}
 
-impl TextureSampler for NearestSampler {
-    fn sample(&self, texture: &Texture, uv: Vec2) -> Vec3 {
+impl NearestSampler {
+    fn index(texture: &Texture, uv: Vec2) -> usize {
         let w = texture.width() as usize;
         let h = texture.height() as usize;
         let x = uv.x() % 1.0;
         let y = uv.y() % 1.0;
         let x = if x < 0.0 { 1.0 + x } else { x };
         let y = if y < 0.0 { 1.0 + y } else { y };
-        let x = ((x * (w as f64)) as usize) % w;
-        let y = ((y * (h as f64)) as usize) % h;
-        let index = y * w + x;
+        let x = ((x * (w as Float)) as usize) % w;
+        let y = ((y * (h as Float)) as usize) % h;
+        y * w + x
+    }
+}
+
+impl TextureSampler for NearestSampler {
+    fn sample(&self, texture: &Texture, uv: Vec2) -> Vec3 {
+        let index = Self::index(texture, uv);
         texture
             .get(index)
             .or_else(|| Some(Vec3::from_single(1.0)))
             .unwrap()
     }
+
+    fn sample_alpha(&self, texture: &Texture, uv: Vec2) -> Float {
+        let index = Self::index(texture, uv);
+        texture.get_alpha(index).unwrap_or(1.0)
+    }
 }
@@ -0,0 +1,109 @@
+#[cfg(test)]
+mod texture {
+    use crate::textures::texture::{Texels, Texture};
+    use crate::utilities::math::Vec3;
+
+    #[test]
+    fn half() {
+        let mut texture = Texture::new(2, 1);
+        assert_eq!(texture.get(0), Some(Vec3::default()));
+        texture.set(1, Vec3::new(0.5, 2.0, 100.0));
+        assert_eq!(texture.get(1), Some(Vec3::new(0.5, 2.0, 100.0)));
+        assert_eq!(texture.get(2), None);
+    }
+    #[test]
+    fn srgb() {
+        let mut texture = Texture {
+            width: 1,
+            height: 1,
+            data: Texels::Srgb8(vec![[0, 0, 0]]),
+            alpha: None,
+        };
+        let color = Vec3::new(0.0, 0.2, 1.0);
+        texture.set(0, color);
+        assert!((texture.get(0).unwrap() - color).length() < 0.01);
+        assert_eq!(texture.get_alpha(0), Some(1.0));
+    }
+}
+
+#[cfg(test)]
+mod texture_repo {
+    use std::{path::PathBuf, sync::Arc};
+
+    use image::{Rgba, RgbaImage};
+
+    use crate::{
+        textures::{
+            texture_repo::{png, TextureRepository, TextureSource},
+            TextureID,
+        },
+        utilities::cache::MemorySize,
+    };
+
+    fn write_png(name: &str, size: u32) -> PathBuf {
+        let directory = std::env::temp_dir().join("archyrt_texture_repo");
+        std::fs::create_dir_all(&directory).unwrap();
+        let image = RgbaImage::from_pixel(size, size, Rgba([255, 0, 0, 128]));
+        let path = png::path(directory.to_str().unwrap(), name);
+        image.save(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn lazy_loading() {
+        let mut repo = TextureRepository::new();
+        let id = TextureID::new(&"lazy");
+        repo.register(id, TextureSource::Png(write_png("lazy", 4))).unwrap();
+        assert!(repo.exists(id));
+        assert_eq!(repo.memory_usage(), 0);
+        let texture = repo.get(id).unwrap();
+        assert_eq!(texture.get_alpha(0), Some(128.0 / 255.0));
+        assert!((texture.get(0).unwrap()[0] - 1.0).abs() < 1e-9);
+        assert!(repo.memory_usage() > 0);
+    }
+    #[test]
+    fn budget() {
+        let a = TextureID::new(&"a");
+        let b = TextureID::new(&"b");
+        let mut repo = TextureRepository::with_budget(2000);
+        repo.register(a, TextureSource::Png(write_png("a", 16))).unwrap();
+        repo.register(b, TextureSource::Png(write_png("b", 16))).unwrap();
+        let first = repo.get(a).unwrap().width;
+        repo.get(b).unwrap();
+        //Only one 16x16 texture fits, the evicted one is loaded again
+        assert!(repo.memory_usage() <= 2000);
+        assert_eq!(repo.get(a).unwrap().width, first);
+        assert!(repo.memory_usage() <= 2000);
+    }
+    #[test]
+    fn alpha_mask() {
+        let a = TextureID::new(&"mask_a");
+        let b = TextureID::new(&"mask_b");
+        let mut repo = TextureRepository::with_budget(2000);
+        repo.register(a, TextureSource::Png(write_png("mask_a", 16))).unwrap();
+        repo.register(b, TextureSource::Png(write_png("mask_b", 16))).unwrap();
+        let mask = repo.alpha_mask(a).unwrap();
+        assert_eq!(mask.get_alpha(0), Some(128.0 / 255.0));
+        assert!(mask.data.is_empty());
+        //The mask outlives its texture being evicted
+        repo.get(b).unwrap();
+        assert!(Arc::ptr_eq(&mask, &repo.alpha_mask(a).unwrap()));
+        assert!(repo.memory_usage() <= 2000 + mask.memory_size());
+    }
+    #[test]
+    fn handles() {
+        let mut repo = TextureRepository::new();
+        let id = TextureID::new(&"handle");
+        repo.register(id, TextureSource::Png(write_png("handle", 4))).unwrap();
+        let handles = repo.handles();
+        assert_eq!(handles.with(id, |texture| texture.map(|t| t.width)), Some(4));
+        assert!(handles.with(TextureID::new(&"missing"), |texture| texture.is_none()));
+    }
+    #[test]
+    fn missing() {
+        let mut repo = TextureRepository::new();
+        let source = TextureSource::Png(PathBuf::from("missing.png"));
+        assert!(repo.register(TextureID::new(&"missing"), source).is_err());
+        assert!(repo.get(TextureID::new(&"missing")).is_none());
+    }
+}
@@ -1,17 +1,49 @@
-use crate::utilities::math::Vec3;
+use half::f16;
 
+use crate::{
+    utilities::{cache::MemorySize, math::{Float, Vec3}},
+    vector,
+};
+
+/// Compact texel storage
+#[derive(Debug, Clone)]
+pub enum Texels {
+    /// 8 bit sRGB, used for color textures
+    Srgb8(Vec<[u8; 3]>),
+    /// Linear half floats, used for HDR textures
+    Half(Vec<[f16; 3]>),
+}
+
+impl Texels {
+    pub fn len(&self) -> usize {
+        match self {
+            Texels::Srgb8(data) => data.len(),
+            Texels::Half(data) => data.len(),
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug)]
 pub struct Texture {
     pub width: u32,
     pub height: u32,
-    pub data: Vec<Vec3>,
+    pub data: Texels,
+    /// Per-texel coverage, only stored if the texture has any transparent texels
+    pub alpha: Option<Vec<u8>>,
 }
 
 impl Texture {
+    /// Black HDR texture
     pub fn new(width: u32, height: u32) -> Self {
+        let size = (width * height) as usize;
         Self {
             width,
             height,
-            data: (0..width * height).map(|_| Vec3::default()).collect(),
+            data: Texels::Half(vec![[f16::ZERO; 3]; size]),
+            alpha: None,
         }
     }
     pub fn width(&self) -> u32 {
@@ -20,11 +52,66 @@ impl Texture {
     pub fn height(&self) -> u32 {
         self.height
     }
+    /// Linear color of a texel
     pub fn get(&self, index: usize) -> Option<Vec3> {
-        if index >= self.data.len() {
-            None
-        } else {
-            Some(self.data[index])
+        match &self.data {
+            Texels::Srgb8(data) => {
+                let [r, g, b] = data.get(index)?;
+                Some(
+                    vector![
+                        *r as Float / 255.0,
+                        *g as Float / 255.0,
+                        *b as Float / 255.0
+                    ]
+                    .from_srgb(),
+                )
+            }
+            Texels::Half(data) => {
+                let [r, g, b] = data.get(index)?;
+                Some(vector![r.to_f32() as Float, g.to_f32() as Float, b.to_f32() as Float])
+            }
         }
     }
+    /// Stores a linear color, encoding it in the texture's format
+    pub fn set(&mut self, index: usize, color: Vec3) {
+        match &mut self.data {
+            Texels::Srgb8(data) => {
+                let color = color.to_srgb();
+                data[index] = color.inner.map(|a| (a * 255.0).round().clamp(0.0, 255.0) as u8);
+            }
+            Texels::Half(data) => {
+                data[index] = color.inner.map(|a| f16::from_f32(a as f32));
+            }
+        }
+    }
+    pub fn has_alpha(&self) -> bool {
+        self.alpha.is_some()
+    }
+    /// Copy holding only the coverage, for alpha testing without keeping the colors in memory
+    pub fn alpha_mask(&self) -> Option<Texture> {
+        Some(Texture {
+            width: self.width,
+            height: self.height,
+            data: Texels::Srgb8(Vec::new()),
+            alpha: Some(self.alpha.clone()?),
+        })
+    }
+    pub fn get_alpha(&self, index: usize) -> Option<Float> {
+        match &self.alpha {
+            Some(alpha) => alpha.get(index).map(|a| *a as Float / 255.0),
+            None if index < self.data.len() => Some(1.0),
+            None => None,
+        }
+    }
+}
+
+impl MemorySize for Texture {
+    fn memory_size(&self) -> usize {
+        let texels = match &self.data {
+            Texels::Srgb8(data) => data.len() * std::mem::size_of::<[u8; 3]>(),
+            Texels::Half(data) => data.len() * std::mem::size_of::<[f16; 3]>(),
+        };
+        let alpha = self.alpha.as_ref().map(|alpha| alpha.len()).unwrap_or(0);
+        std::mem::size_of::<Self>() + texels + alpha
+    }
 }
@@ -1,34 +1,39 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::{
     textures::{texture::Texture, TextureID},
-    utilities::math::Vec3,
+    utilities::math::{Float, Vec3},
 };
 
-use super::TextureRepository;
+use super::{TextureRepository, TextureSource};
 use anyhow::Result;
 use exr::prelude::*;
 
+/// Registers textures to be loaded on first use
 pub fn load_into(
     repo: &mut TextureRepository,
     base: &str,
     textures: &[(TextureID, &str)],
 ) -> Result<()> {
     for (id, name) in textures {
-        let texture = load(base, *name)?;
-        repo.insert(*id, texture);
+        repo.register(*id, TextureSource::Exr(path(base, name)))?;
     }
     Ok(())
 }
+pub fn path(base: &str, name: &str) -> PathBuf {
+    Path::new(base).join(name)
+}
 pub fn load(base: &str, name: &str) -> Result<Texture> {
-    let path = Path::new(base).join(name);
+    load_file(path(base, name))
+}
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Texture> {
     let image = read_first_rgba_layer_from_file(
         path,
         |size, _| Texture::new(size.x() as u32, size.y() as u32),
         |image, coords, (r, g, b, _): (f32, f32, f32, f32)| {
-            let color = Vec3::new(r as f64, g as f64, b as f64);
+            let color = Vec3::new(r as Float, g as Float, b as Float);
             let index = coords.y() * (image.width as usize) + coords.x();
-            image.data[index] = color;
+            image.set(index, color);
         },
     )?;
     let texture = image.layer_data.channel_data.pixels;
@@ -1,27 +1,125 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap, path::PathBuf, sync::Arc};
+
+use anyhow::{anyhow, Result};
+
+use crate::utilities::cache::SharedCache;
 
 use super::{texture::Texture, TextureID};
 
 pub mod exr;
 pub mod png;
 
+/// Where a lazily loaded texture comes from
+#[derive(Debug, Clone)]
+pub enum TextureSource {
+    Png(PathBuf),
+    Exr(PathBuf),
+}
+
+impl TextureSource {
+    pub fn path(&self) -> &PathBuf {
+        match self {
+            TextureSource::Png(path) | TextureSource::Exr(path) => path,
+        }
+    }
+    pub fn load(&self) -> Result<Texture> {
+        match self {
+            TextureSource::Png(path) => png::load_file(path),
+            TextureSource::Exr(path) => exr::load_file(path),
+        }
+    }
+}
+
+/// Textures are decoded on first use and evicted once the memory budget is exceeded.
+/// The repository can be shared between threads.
 pub struct TextureRepository {
-    pub textures: HashMap<TextureID, Texture>,
+    sources: HashMap<TextureID, TextureSource>,
+    cache: SharedCache<TextureID, Texture>,
+    /// Coverage of the textures with transparent texels, kept outside the budget by the triangles using it
+    masks: SharedCache<TextureID, Texture>,
 }
 
 impl TextureRepository {
     pub fn new() -> Self {
-        let t = HashMap::new();
-        Self { textures: t }
+        Self::with_budget(usize::MAX)
+    }
+    /// Budget is in bytes
+    pub fn with_budget(budget: usize) -> Self {
+        Self {
+            sources: HashMap::new(),
+            cache: SharedCache::new(budget),
+            masks: SharedCache::new(usize::MAX),
+        }
+    }
+    pub fn get(&self, id: TextureID) -> Option<Arc<Texture>> {
+        let source = match self.sources.get(&id) {
+            Some(source) => source,
+            None => return self.cache.get(id),
+        };
+        self.cache.get_or_load(id, || match source.load() {
+            Ok(texture) => Some(texture),
+            Err(err) => {
+                println!("Could not load texture {:?}: {}", source.path(), err);
+                None
+            }
+        })
     }
-    pub fn get(&self, id: TextureID) -> Option<&Texture> {
-        let texture = self.textures.get(&id)?;
-        Some(texture)
+    /// Coverage of a texture, `None` if it has no transparent texels
+    pub fn alpha_mask(&self, id: TextureID) -> Option<Arc<Texture>> {
+        self.masks.get_or_load(id, || self.get(id)?.alpha_mask())
     }
-    pub fn exists(&self, id: TextureID) -> bool{
-        self.textures.contains_key(&id)
+    /// Handles for sampling during a tile
+    pub fn handles(&self) -> TextureHandles<'_> {
+        TextureHandles::new(self)
     }
+    pub fn exists(&self, id: TextureID) -> bool {
+        self.sources.contains_key(&id) || self.cache.contains(id)
+    }
+    /// Adds a decoded texture. It is kept in memory regardless of the budget.
     pub fn insert(&mut self, id: TextureID, texture: Texture) {
-        self.textures.insert(id, texture);
+        self.sources.remove(&id);
+        if let Some(mask) = texture.alpha_mask() {
+            self.masks.insert_pinned(id, mask);
+        }
+        self.cache.insert_pinned(id, texture);
+    }
+    /// Adds a texture to be loaded on first use
+    pub fn register(&mut self, id: TextureID, source: TextureSource) -> Result<()> {
+        if !source.path().is_file() {
+            return Err(anyhow!("Texture {:?} does not exist", source.path()));
+        }
+        self.sources.insert(id, source);
+        Ok(())
+    }
+    /// Bytes used by the currently decoded textures and alpha masks
+    pub fn memory_usage(&self) -> usize {
+        self.cache.memory_usage() + self.masks.memory_usage()
+    }
+}
+
+/// Textures of a repository looked up once and then sampled without going through its shared cache.
+/// Made for a single tile, the textures it looked up stay loaded while it lives.
+pub struct TextureHandles<'a> {
+    repo: &'a TextureRepository,
+    resolved: RefCell<HashMap<TextureID, Option<Arc<Texture>>>>,
+}
+
+impl<'a> TextureHandles<'a> {
+    pub fn new(repo: &'a TextureRepository) -> Self {
+        Self {
+            repo,
+            resolved: RefCell::new(HashMap::new()),
+        }
+    }
+    pub fn repo(&self) -> &'a TextureRepository {
+        self.repo
+    }
+    /// Calls `f` with the texture, or with `None` if it doesn't exist
+    pub fn with<R>(&self, id: TextureID, f: impl FnOnce(Option<&Texture>) -> R) -> R {
+        if !self.resolved.borrow().contains_key(&id) {
+            let texture = self.repo.get(id);
+            self.resolved.borrow_mut().insert(id, texture);
+        }
+        f(self.resolved.borrow()[&id].as_deref())
     }
 }
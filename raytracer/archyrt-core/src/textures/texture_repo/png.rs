@@ -1,43 +1,46 @@
-use std::{path::Path};
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use image::io::Reader as ImageReader;
 
-use crate::{
-    textures::{texture::Texture, TextureID},
-    vector,
+use crate::textures::{
+    texture::{Texels, Texture},
+    TextureID,
 };
 
-use super::TextureRepository;
+use super::{TextureRepository, TextureSource};
 
+/// Registers textures to be loaded on first use
 pub fn load_into(
     repo: &mut TextureRepository,
     base: &str,
     textures: &[(TextureID, &str)],
 ) -> Result<()> {
     for (id, name) in textures {
-        let texture = load(base, *name)?;
-        repo.insert(*id, texture);
+        repo.register(*id, TextureSource::Png(path(base, name)))?;
     }
     Ok(())
 }
+pub fn path(base: &str, name: &str) -> PathBuf {
+    Path::new(base).join(name).with_extension("png")
+}
 pub fn load(base: &str, name: &str) -> Result<Texture> {
-    let path = Path::new(base).join(name).with_extension("png");
+    load_file(path(base, name))
+}
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<Texture> {
     let image = ImageReader::open(path)?.decode()?;
-    let image = image.into_rgb8();
-    let pixels: Vec<_> = image
-        .pixels()
-        .map(|a| {
-            vector![
-                a.0[0] as f64 / 255.0,
-                a.0[1] as f64 / 255.0,
-                a.0[2] as f64 / 255.0
-            ]
-            .from_srgb()
-        })
-        .collect();
+    let image = image.into_rgba8();
+    //Texels are kept in sRGB, they are linearized when sampled
+    let pixels: Vec<_> = image.pixels().map(|a| [a.0[0], a.0[1], a.0[2]]).collect();
+    //Fully opaque textures don't need to store coverage
+    let alpha = if image.pixels().any(|a| a.0[3] < 255) {
+        Some(image.pixels().map(|a| a.0[3]).collect())
+    } else {
+        None
+    };
     Ok(Texture {
-        data: pixels,
+        data: Texels::Srgb8(pixels),
+        alpha,
         width: image.width(),
         height: image.height(),
     })
@@ -0,0 +1,146 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError, RwLock,
+    },
+};
+
+/// Approximate heap size of a cached value in bytes
+pub trait MemorySize {
+    fn memory_size(&self) -> usize;
+}
+
+struct CacheEntry<V> {
+    value: Arc<V>,
+    size: usize,
+    last_used: AtomicU64,
+    /// Pinned values can't be reloaded, so they are never evicted
+    pinned: bool,
+}
+
+struct CacheState<K, V> {
+    entries: HashMap<K, CacheEntry<V>>,
+    failed: HashSet<K>,
+    /// Keys being loaded, threads wanting the same key wait on its lock
+    loading: HashMap<K, Arc<Mutex<()>>>,
+    size: usize,
+}
+
+/// Thread-safe cache that evicts the least recently used values once their total size exceeds the budget
+pub struct SharedCache<K, V> {
+    state: RwLock<CacheState<K, V>>,
+    clock: AtomicU64,
+    budget: usize,
+}
+
+impl<K: Hash + Eq + Copy, V: MemorySize> SharedCache<K, V> {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            state: RwLock::new(CacheState {
+                entries: HashMap::new(),
+                failed: HashSet::new(),
+                loading: HashMap::new(),
+                size: 0,
+            }),
+            clock: AtomicU64::new(0),
+            budget,
+        }
+    }
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+    /// Total size of the values currently held
+    pub fn memory_usage(&self) -> usize {
+        self.state.read().unwrap().size
+    }
+    pub fn contains(&self, key: K) -> bool {
+        self.state.read().unwrap().entries.contains_key(&key)
+    }
+    pub fn get(&self, key: K) -> Option<Arc<V>> {
+        let state = self.state.read().unwrap();
+        state.entries.get(&key).map(|entry| self.touch(entry))
+    }
+    fn touch(&self, entry: &CacheEntry<V>) -> Arc<V> {
+        entry
+            .last_used
+            .store(self.clock.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+        entry.value.clone()
+    }
+    /// Returns the cached value or loads it. Failed loads are remembered and not retried.
+    pub fn get_or_load<F: FnOnce() -> Option<V>>(&self, key: K, load: F) -> Option<Arc<V>> {
+        if let Some(value) = self.get(key) {
+            return Some(value);
+        }
+        let loading = {
+            let mut state = self.state.write().unwrap();
+            if let Some(entry) = state.entries.get(&key) {
+                return Some(self.touch(entry));
+            }
+            if state.failed.contains(&key) {
+                return None;
+            }
+            state.loading.entry(key).or_default().clone()
+        };
+        //Only one thread loads a key, the others wait and then find its result
+        let _loading = loading.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(value) = self.get(key) {
+            return Some(value);
+        }
+        if self.state.read().unwrap().failed.contains(&key) {
+            return None;
+        }
+        let value = match load() {
+            Some(value) => Some(self.insert_entry(key, value, false)),
+            None => {
+                self.state.write().unwrap().failed.insert(key);
+                None
+            }
+        };
+        self.state.write().unwrap().loading.remove(&key);
+        value
+    }
+    /// Inserts a value that stays in the cache regardless of the budget
+    pub fn insert_pinned(&self, key: K, value: V) {
+        self.insert_entry(key, value, true);
+    }
+    fn insert_entry(&self, key: K, value: V, pinned: bool) -> Arc<V> {
+        let mut state = self.state.write().unwrap();
+        if let Some(entry) = state.entries.get(&key) {
+            if !pinned {
+                return entry.value.clone();
+            }
+        }
+        let size = value.memory_size();
+        let value = Arc::new(value);
+        let entry = CacheEntry {
+            value: value.clone(),
+            size,
+            last_used: AtomicU64::new(self.clock.fetch_add(1, Ordering::Relaxed)),
+            pinned,
+        };
+        if let Some(old) = state.entries.insert(key, entry) {
+            state.size -= old.size;
+        }
+        state.failed.remove(&key);
+        state.size += size;
+        //Evict until the budget is met, the new value always stays
+        while state.size > self.budget {
+            let victim = state
+                .entries
+                .iter()
+                .filter(|(k, entry)| !entry.pinned && **k != key)
+                .min_by_key(|(_, entry)| entry.last_used.load(Ordering::Relaxed))
+                .map(|(k, _)| *k);
+            match victim {
+                Some(victim) => {
+                    let entry = state.entries.remove(&victim).unwrap();
+                    state.size -= entry.size;
+                }
+                None => break,
+            }
+        }
+        value
+    }
+}
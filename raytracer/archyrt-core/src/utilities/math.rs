@@ -6,6 +6,21 @@ use std::{
 
 use cgmath::{Vector2, Vector3, Matrix3, Quaternion};
 
+/// Scalar type of all geometry and shading math, f32 with the `single-precision` feature
+#[cfg(not(feature = "single-precision"))]
+pub type Float = f64;
+/// Scalar type of all geometry and shading math, f32 with the `single-precision` feature
+#[cfg(feature = "single-precision")]
+pub type Float = f32;
+
+/// Constants for [Float]
+pub mod consts {
+    #[cfg(not(feature = "single-precision"))]
+    pub use std::f64::consts::*;
+    #[cfg(feature = "single-precision")]
+    pub use std::f32::consts::*;
+}
+
 #[derive(Copy, Clone)]
 pub enum Axis3 {
     X,
@@ -15,32 +30,32 @@ pub enum Axis3 {
 
 #[derive(Clone, Copy, PartialEq)]
 pub struct Vector<const N: usize> {
-    pub inner: [f64; N],
+    pub inner: [Float; N],
 }
 
 impl<const N: usize> Vector<N> {
-    pub fn from_array(array: [f64; N]) -> Self {
+    pub fn from_array(array: [Float; N]) -> Self {
         Self { inner: array }
     }
-    pub fn dot(self, b: Self) -> f64 {
+    pub fn dot(self, b: Self) -> Float {
         self.inner.iter().zip(b.inner).map(|(a, b)| a * b).sum()
     }
-    pub fn length_squared(self) -> f64 {
+    pub fn length_squared(self) -> Float {
         self.dot(self)
     }
-    pub fn length(self) -> f64 {
+    pub fn length(self) -> Float {
         self.length_squared().sqrt()
     }
     pub fn normalized(self) -> Self {
         self / self.length()
     }
-    pub fn from_single(single: f64) -> Self {
+    pub fn from_single(single: Float) -> Self {
         Self { inner: [single; N] }
     }
     pub fn ones() -> Self {
         Self::from_single(1.0)
     }
-    pub fn sum(self) -> f64 {
+    pub fn sum(self) -> Float {
         self.dot(Self::ones())
     }
     pub fn powi(self, n: i32) -> Self {
@@ -50,7 +65,7 @@ impl<const N: usize> Vector<N> {
         }
         o
     }
-    pub fn powf(self, n: f64) -> Self {
+    pub fn powf(self, n: Float) -> Self {
         let mut o = self;
         for v in o.inner.iter_mut() {
             *v = v.powf(n);
@@ -71,6 +86,9 @@ impl<const N: usize> Vector<N> {
         }
         o
     }
+    pub fn lerp(self, rhs: Self, t: Float) -> Self {
+        self * (1.0 - t) + rhs * t
+    }
 }
 
 impl<const N: usize> Default for Vector<N> {
@@ -86,7 +104,7 @@ impl<const N: usize> Debug for Vector<N> {
 }
 
 impl<const N: usize> Index<usize> for Vector<N> {
-    type Output = f64;
+    type Output = Float;
 
     fn index(&self, index: usize) -> &Self::Output {
         &self.inner[index]
@@ -145,10 +163,10 @@ impl<const N: usize> MulAssign for Vector<N> {
         *self = *self * rhs
     }
 }
-impl<const N: usize> Mul<f64> for Vector<N> {
+impl<const N: usize> Mul<Float> for Vector<N> {
     type Output = Self;
 
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: Float) -> Self::Output {
         let mut o = self;
         for a in o.inner.iter_mut() {
             *a *= rhs;
@@ -156,22 +174,22 @@ impl<const N: usize> Mul<f64> for Vector<N> {
         o
     }
 }
-impl<const N: usize> Mul<Vector<N>> for f64 {
+impl<const N: usize> Mul<Vector<N>> for Float {
     type Output = Vector<N>;
 
     fn mul(self, rhs: Vector<N>) -> Self::Output {
         rhs * self
     }
 }
-impl<const N: usize> MulAssign<f64> for Vector<N> {
-    fn mul_assign(&mut self, rhs: f64) {
+impl<const N: usize> MulAssign<Float> for Vector<N> {
+    fn mul_assign(&mut self, rhs: Float) {
         *self = *self * rhs;
     }
 }
-impl<const N: usize> Div<f64> for Vector<N> {
+impl<const N: usize> Div<Float> for Vector<N> {
     type Output = Self;
 
-    fn div(self, rhs: f64) -> Self::Output {
+    fn div(self, rhs: Float) -> Self::Output {
         let mut o = self;
         for a in o.inner.iter_mut() {
             *a /= rhs;
@@ -179,8 +197,8 @@ impl<const N: usize> Div<f64> for Vector<N> {
         o
     }
 }
-impl<const N: usize> DivAssign<f64> for Vector<N> {
-    fn div_assign(&mut self, rhs: f64) {
+impl<const N: usize> DivAssign<Float> for Vector<N> {
+    fn div_assign(&mut self, rhs: Float) {
         *self = *self / rhs;
     }
 }
@@ -313,7 +331,7 @@ macro_rules! matrix {
 pub type Vec3 = Vector<3>;
 
 impl Vec3 {
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub fn new(x: Float, y: Float, z: Float) -> Self {
         Self::from_array([x, y, z])
     }
     pub fn to_srgb(self) -> Self {
@@ -322,13 +340,13 @@ impl Vec3 {
     pub fn from_srgb(self) -> Self {
         self.powf(2.2)
     }
-    pub fn x(self) -> f64 {
+    pub fn x(self) -> Float {
         self[0]
     }
-    pub fn y(self) -> f64 {
+    pub fn y(self) -> Float {
         self[1]
     }
-    pub fn z(self) -> f64 {
+    pub fn z(self) -> Float {
         self[2]
     }
     pub fn cross(self, rhs: Self) -> Self {
@@ -339,7 +357,7 @@ impl Vec3 {
         )
     }
 
-    pub fn get(self, axis: Axis3) -> f64 {
+    pub fn get(self, axis: Axis3) -> Float {
         match axis {
             Axis3::X => self.x(),
             Axis3::Y => self.y(),
@@ -361,35 +379,35 @@ impl Vec3 {
 
 impl From<Vector3<f32>> for Vec3 {
     fn from(a: Vector3<f32>) -> Self {
-        Self::new(a.x as f64, a.y as f64, a.z as f64)
+        Self::new(a.x as Float, a.y as Float, a.z as Float)
     }
 }
 impl From<Vector2<f32>> for Vec2 {
     fn from(a: Vector2<f32>) -> Self {
-        vector!(a.x as f64, a.y as f64)
+        vector!(a.x as Float, a.y as Float)
     }
 }
 impl From<Vector3<i32>> for Vec3 {
     fn from(a: Vector3<i32>) -> Self {
-        Self::new(a.x as f64, a.y as f64, a.z as f64)
+        Self::new(a.x as Float, a.y as Float, a.z as Float)
     }
 }
 impl From<Vector2<i32>> for Vec2 {
     fn from(a: Vector2<i32>) -> Self {
-        vector!(a.x as f64, a.y as f64)
+        vector!(a.x as Float, a.y as Float)
     }
 }
 
 pub type Vec2 = Vector<2>;
 
 impl Vec2 {
-    pub fn new(x: f64, y: f64) -> Self {
+    pub fn new(x: Float, y: Float) -> Self {
         Self::from_array([x, y])
     }
-    pub fn x(self) -> f64 {
+    pub fn x(self) -> Float {
         self[0]
     }
-    pub fn y(self) -> f64 {
+    pub fn y(self) -> Float {
         self[1]
     }
 }
@@ -397,7 +415,7 @@ impl Vec2 {
 pub type Matrix3x3 = Matrix<3, 3>;
 
 impl Matrix3x3 {
-    pub fn det(self) -> f64 {
+    pub fn det(self) -> Float {
         self[0][0] * (self[1][1] * self[2][2] - self[2][1] * self[1][2])
             - self[1][0] * (self[0][1] * self[2][2] - self[2][1] * self[0][2])
             + self[2][0] * (self[0][1] * self[1][2] - self[1][1] * self[0][2])
@@ -418,7 +436,7 @@ impl Matrix3x3 {
         }
         Some(o)
     }
-    pub fn rotate_x(self, angle: f64) -> Self {
+    pub fn rotate_x(self, angle: Float) -> Self {
         let sin = angle.sin();
         let cos = angle.cos();
         let matrix: Matrix3x3 = matrix!(
@@ -428,7 +446,7 @@ impl Matrix3x3 {
         );
         self * matrix
     }
-    pub fn rotate_y(self, angle: f64) -> Self {
+    pub fn rotate_y(self, angle: Float) -> Self {
         let sin = angle.sin();
         let cos = angle.cos();
         let matrix: Matrix3x3 = matrix!(
@@ -438,7 +456,7 @@ impl Matrix3x3 {
         );
         self * matrix
     }
-    pub fn rotate_z(self, angle: f64) -> Self {
+    pub fn rotate_z(self, angle: Float) -> Self {
         let sin = angle.sin();
         let cos = angle.cos();
         let matrix: Matrix3x3 = matrix!(
@@ -458,6 +476,33 @@ impl From<Matrix3<f32>> for Matrix3x3{
     }
 }
 
+impl From<Matrix3<f64>> for Matrix3x3{
+    fn from(m: Matrix3<f64>) -> Self {
+        Self::from_vectors([
+            Vec3::new(m.x.x as Float, m.x.y as Float, m.x.z as Float),
+            Vec3::new(m.y.x as Float, m.y.y as Float, m.y.z as Float),
+            Vec3::new(m.z.x as Float, m.z.y as Float, m.z.z as Float),
+        ])
+    }
+}
+
+impl From<Matrix3x3> for Matrix3<Float>{
+    fn from(m: Matrix3x3) -> Self {
+        Matrix3::from_cols(
+            Vector3::from(m[0].inner),
+            Vector3::from(m[1].inner),
+            Vector3::from(m[2].inner),
+        )
+    }
+}
+
+impl From<Quaternion<f64>> for Matrix3x3{
+    fn from(q: Quaternion<f64>) -> Self {
+        let matrix: Matrix3<f64> = q.into();
+        matrix.into()
+    }
+}
+
 impl From<Quaternion<f32>> for Matrix3x3{
     fn from(q: Quaternion<f32>) -> Self {
         let matrix: Matrix3<f32> = q.into();
@@ -466,16 +511,16 @@ impl From<Quaternion<f32>> for Matrix3x3{
 }
 
 pub enum QuadraticResult {
-    TwoResults(f64, f64),
-    OneResult(f64),
+    TwoResults(Float, Float),
+    OneResult(Float),
     NoResults,
 }
 
-fn solve_for_determinant_sqrt(d: f64, b: f64, a: f64) -> f64 {
+fn solve_for_determinant_sqrt(d: Float, b: Float, a: Float) -> Float {
     (-b + d) / (2.0 * a)
 }
 
-pub fn solve_quadratic(a: f64, b: f64, c: f64) -> QuadraticResult {
+pub fn solve_quadratic(a: Float, b: Float, c: Float) -> QuadraticResult {
     let d = b * b - 4.0 * a * c;
     match d {
         n if n == 0.0 => QuadraticResult::OneResult(solve_for_determinant_sqrt(0.0, b, a)),
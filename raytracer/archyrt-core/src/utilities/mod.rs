@@ -1,3 +1,6 @@
+pub mod cache;
 pub mod math;
 pub mod ray;
+pub mod rng;
+pub mod stats;
 mod tests;
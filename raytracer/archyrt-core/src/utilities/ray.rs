@@ -1,33 +1,92 @@
 use crate::{
     intersectables::union::UnionIntersector,
     renderers::path_tracer::Material,
-    textures::{color_provider::ColorProvider, texture_repo::TextureRepository},
+    textures::{color_provider::ColorProvider, texture_repo::TextureHandles},
 };
 
-use super::math::Vec3;
+use super::math::{Float, Vec3};
 
 #[derive(Clone, Copy, Default)]
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
+    /// Point in time the ray was sampled at, within the camera's shutter interval
+    pub time: Float,
 }
 
 impl Ray {
     pub fn new(origin: Vec3, direction: Vec3) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            time: 0.0,
+        }
+    }
+    pub fn with_time(origin: Vec3, direction: Vec3, time: Float) -> Self {
+        Self {
+            origin,
+            direction,
+            time,
+        }
     }
 }
 
+//Offsetting constants, see "A Fast and Robust Method for Avoiding Self-Intersection" (Wächter, Binder)
+const OFFSET_ORIGIN: Float = 1.0 / 32.0;
+#[cfg(not(feature = "single-precision"))]
+const OFFSET_FLOAT_SCALE: Float = 1.0 / 536870912.0;
+#[cfg(not(feature = "single-precision"))]
+const OFFSET_INT_SCALE: Float = 16777216.0;
+#[cfg(feature = "single-precision")]
+const OFFSET_FLOAT_SCALE: Float = 1.0 / 65536.0;
+#[cfg(feature = "single-precision")]
+const OFFSET_INT_SCALE: Float = 256.0;
+
+/// Moves a float by a signed number of ulps
+#[cfg(not(feature = "single-precision"))]
+fn offset_ulps(p: Float, ulps: Float) -> Float {
+    Float::from_bits((p.to_bits() as i64 + ulps as i64) as u64)
+}
+#[cfg(feature = "single-precision")]
+fn offset_ulps(p: Float, ulps: Float) -> Float {
+    Float::from_bits((p.to_bits() as i32 + ulps as i32) as u32)
+}
+
+/// Moves a hit position off the surface along the normal by a margin proportional to the position's magnitude
+pub fn offset_origin(pos: Vec3, normal: Vec3) -> Vec3 {
+    let mut result = pos;
+    for i in 0..3 {
+        let p = pos.inner[i];
+        let n = normal.inner[i];
+        result.inner[i] = if p.abs() < OFFSET_ORIGIN {
+            p + OFFSET_FLOAT_SCALE * n
+        } else {
+            //Move by a fixed number of ulps in the direction of the normal
+            let offset = (OFFSET_INT_SCALE * n).trunc();
+            offset_ulps(p, if p < 0.0 { -offset } else { offset })
+        };
+    }
+    result
+}
+
 #[derive(Default)]
 pub struct IntersectionBuilder<C: ColorProvider> {
     pub ray: Ray,
     /// At least one of pos, distance or distance_squared are required
     pub pos: Option<Vec3>,
     /// At least one of pos, distance or distance_squared are required
-    pub distance: Option<f64>,
+    pub distance: Option<Float>,
     /// At least one of pos, distance or distance_squared are required
-    pub distance_squared: Option<f64>,
+    pub distance_squared: Option<Float>,
+    /// Shading normal, possibly interpolated
     pub normal: Vec3,
+    /// Normal of the actual surface, defaults to the shading normal
+    pub geometric_normal: Option<Vec3>,
+    /// Set by the closest enclosing [Instance](crate::intersectables::instance::Instance)
+    pub instance: Option<u32>,
+    /// Whether the front of the surface was hit, set by surfaces that turn their normals to face the ray.
+    /// Defaults to whether the geometric normal faces the ray.
+    pub front_face: Option<bool>,
     pub color_provider: C,
 }
 impl<C: ColorProvider> IntersectionBuilder<C> {
@@ -48,6 +107,9 @@ impl<C: ColorProvider> Intersection<C> {
             distance: self.0.distance,
             distance_squared: self.0.distance_squared,
             normal: self.0.normal,
+            geometric_normal: self.0.geometric_normal,
+            instance: self.0.instance,
+            front_face: self.0.front_face,
             color_provider: provider,
         })
     }
@@ -73,7 +135,7 @@ impl<C: ColorProvider> Intersection<C> {
         }
         panic!("Invalid intersection object: could not reconstruct position");
     }
-    pub fn get_distance(&self) -> f64 {
+    pub fn get_distance(&self) -> Float {
         if let Some(distance) = self.0.distance {
             return distance;
         }
@@ -82,7 +144,7 @@ impl<C: ColorProvider> Intersection<C> {
         }
         panic!("Invalid intersection object: could not reconstruct distance")
     }
-    pub fn get_distance_squared(&self) -> f64 {
+    pub fn get_distance_squared(&self) -> Float {
         if let Some(distance_squared) = self.0.distance_squared {
             return distance_squared;
         }
@@ -97,8 +159,19 @@ impl<C: ColorProvider> Intersection<C> {
     pub fn get_normal(&self) -> Vec3 {
         self.0.normal
     }
-    pub fn get_color(&self, repo: &TextureRepository) -> Vec3 {
-        self.0.color_provider.get_color(repo)
+    pub fn get_geometric_normal(&self) -> Vec3 {
+        self.0.geometric_normal.unwrap_or(self.0.normal)
+    }
+    pub fn is_front_face(&self) -> bool {
+        self.0
+            .front_face
+            .unwrap_or_else(|| self.get_geometric_normal().dot(self.0.ray.direction) < 0.0)
+    }
+    pub fn get_instance(&self) -> Option<u32> {
+        self.0.instance
+    }
+    pub fn get_color(&self, textures: &TextureHandles) -> Vec3 {
+        self.0.color_provider.get_color(textures)
     }
     pub fn get_material(&self) -> Material {
         self.0.color_provider.get_material()
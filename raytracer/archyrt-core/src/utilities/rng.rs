@@ -0,0 +1,27 @@
+use std::cell::RefCell;
+
+use rand::{
+    distributions::{Distribution, Standard},
+    rngs::StdRng,
+    Rng, SeedableRng,
+};
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Restarts the random numbers of the current thread, renders are reproducible between two calls
+pub fn seed(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+pub fn random<T>() -> T
+where
+    Standard: Distribution<T>,
+{
+    RNG.with(|rng| rng.borrow_mut().gen())
+}
+
+pub fn sample<T, D: Distribution<T>>(distribution: &D) -> T {
+    RNG.with(|rng| distribution.sample(&mut *rng.borrow_mut()))
+}
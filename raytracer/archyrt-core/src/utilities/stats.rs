@@ -0,0 +1,259 @@
+use std::{
+    fmt::Display,
+    ops::{Add, Sub},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Work done while tracing. Only counted with the `stats` feature, otherwise everything stays zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RayStats {
+    /// Rays cast by renderers
+    pub rays: u64,
+    /// Finished paths of the path tracer
+    pub paths: u64,
+    /// Segments of the finished paths
+    pub path_segments: u64,
+    /// BVH nodes visited, including leaves
+    pub nodes: u64,
+    /// Ray-triangle tests
+    pub triangles: u64,
+}
+
+impl RayStats {
+    pub fn average_path_length(&self) -> f64 {
+        ratio(self.path_segments, self.paths)
+    }
+    pub fn nodes_per_ray(&self) -> f64 {
+        ratio(self.nodes, self.rays)
+    }
+    pub fn triangles_per_ray(&self) -> f64 {
+        ratio(self.triangles, self.rays)
+    }
+}
+
+fn ratio(a: u64, b: u64) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+impl Add for RayStats {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            rays: self.rays + rhs.rays,
+            paths: self.paths + rhs.paths,
+            path_segments: self.path_segments + rhs.path_segments,
+            nodes: self.nodes + rhs.nodes,
+            triangles: self.triangles + rhs.triangles,
+        }
+    }
+}
+
+impl Sub for RayStats {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            rays: self.rays - rhs.rays,
+            paths: self.paths - rhs.paths,
+            path_segments: self.path_segments - rhs.path_segments,
+            nodes: self.nodes - rhs.nodes,
+            triangles: self.triangles - rhs.triangles,
+        }
+    }
+}
+
+impl Display for RayStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} rays, {} nodes ({:.1}/ray), {} triangle tests ({:.1}/ray), average path length {:.2}",
+            self.rays,
+            self.nodes,
+            self.nodes_per_ray(),
+            self.triangles,
+            self.triangles_per_ray(),
+            self.average_path_length()
+        )
+    }
+}
+
+//Every thread counts into its own counters, only read by others when summing up
+#[derive(Default)]
+struct Counters {
+    rays: AtomicU64,
+    paths: AtomicU64,
+    path_segments: AtomicU64,
+    nodes: AtomicU64,
+    triangles: AtomicU64,
+}
+
+impl Counters {
+    fn get(&self) -> RayStats {
+        RayStats {
+            rays: self.rays.load(Ordering::Relaxed),
+            paths: self.paths.load(Ordering::Relaxed),
+            path_segments: self.path_segments.load(Ordering::Relaxed),
+            nodes: self.nodes.load(Ordering::Relaxed),
+            triangles: self.triangles.load(Ordering::Relaxed),
+        }
+    }
+}
+
+const ZERO: RayStats = RayStats {
+    rays: 0,
+    paths: 0,
+    path_segments: 0,
+    nodes: 0,
+    triangles: 0,
+};
+
+//Counters of the running threads, the work of finished threads is added up when they exit
+struct Registry {
+    finished: RayStats,
+    threads: Vec<Arc<Counters>>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    finished: ZERO,
+    threads: Vec::new(),
+});
+
+//Removes the thread's counters from the registry when the thread exits
+struct ThreadCounters(Arc<Counters>);
+
+impl ThreadCounters {
+    fn register() -> Self {
+        let counters = Arc::new(Counters::default());
+        REGISTRY.lock().unwrap().threads.push(counters.clone());
+        Self(counters)
+    }
+}
+
+impl Drop for ThreadCounters {
+    fn drop(&mut self) {
+        //Panicking while a thread exits would abort, so a poisoned lock is used anyway
+        let mut registry = REGISTRY.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        registry.finished = registry.finished + self.0.get();
+        registry.threads.retain(|counters| !Arc::ptr_eq(counters, &self.0));
+    }
+}
+
+thread_local! {
+    static COUNTERS: ThreadCounters = ThreadCounters::register();
+}
+
+#[cfg(feature = "stats")]
+#[inline]
+fn count<F: Fn(&Counters) -> &AtomicU64>(counter: F, n: u64) {
+    COUNTERS.with(|counters| {
+        //Only this thread writes, so there is no need for an atomic add
+        let counter = counter(&counters.0);
+        counter.store(counter.load(Ordering::Relaxed) + n, Ordering::Relaxed);
+    });
+}
+
+#[cfg(not(feature = "stats"))]
+#[inline(always)]
+fn count<F: Fn(&Counters) -> &AtomicU64>(_: F, _: u64) {}
+
+#[inline]
+pub fn ray() {
+    count(|c| &c.rays, 1);
+}
+#[inline]
+pub fn path(segments: u64) {
+    count(|c| &c.paths, 1);
+    count(|c| &c.path_segments, segments);
+}
+#[inline]
+pub fn node() {
+    count(|c| &c.nodes, 1);
+}
+#[inline]
+pub fn triangles(n: u64) {
+    count(|c| &c.triangles, n);
+}
+
+/// Work done by the current thread so far
+pub fn local() -> RayStats {
+    COUNTERS.with(|counters| counters.0.get())
+}
+
+/// Work done by all threads so far
+pub fn total() -> RayStats {
+    let registry = REGISTRY.lock().unwrap();
+    registry
+        .threads
+        .iter()
+        .fold(registry.finished, |sum, counters| sum + counters.get())
+}
+
+/// Number of threads whose counters are still kept apart
+pub fn running_threads() -> usize {
+    REGISTRY.lock().unwrap().threads.len()
+}
+
+pub struct Pass {
+    pub name: String,
+    pub time: Duration,
+    pub stats: RayStats,
+}
+
+/// Measures the time and work of consecutive render passes
+#[derive(Default)]
+pub struct Profiler {
+    pub passes: Vec<Pass>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn pass<T, F: FnOnce() -> T>(&mut self, name: &str, f: F) -> T {
+        let stats = total();
+        let start = Instant::now();
+        let result = f();
+        self.passes.push(Pass {
+            name: name.to_string(),
+            time: start.elapsed(),
+            stats: total() - stats,
+        });
+        result
+    }
+    pub fn time(&self) -> Duration {
+        self.passes.iter().map(|pass| pass.time).sum()
+    }
+    pub fn stats(&self) -> RayStats {
+        self.passes
+            .iter()
+            .fold(RayStats::default(), |sum, pass| sum + pass.stats)
+    }
+}
+
+//Counters are left out when they aren't collected
+fn write_pass(f: &mut std::fmt::Formatter<'_>, name: &str, time: Duration, stats: RayStats) -> std::fmt::Result {
+    if cfg!(feature = "stats") {
+        write!(f, "{}: {:.2?}, {}", name, time, stats)
+    } else {
+        write!(f, "{}: {:.2?}", name, time)
+    }
+}
+
+impl Display for Profiler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for pass in &self.passes {
+            write_pass(f, &pass.name, pass.time, pass.stats)?;
+            writeln!(f)?;
+        }
+        write_pass(f, "Total", self.time(), self.stats())
+    }
+}
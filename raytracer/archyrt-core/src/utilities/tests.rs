@@ -1,9 +1,9 @@
 #[cfg(test)]
 mod vectors {
-    use std::f64::consts::PI;
+    use crate::utilities::math::consts::PI;
 
     use crate::{
-        utilities::math::{Vec2, Vec3, Vector},
+        utilities::math::{Float, Vec2, Vec3, Vector},
         vector,
     };
 
@@ -80,7 +80,7 @@ mod vectors {
     }
     #[test]
     fn normalized() {
-        const EPSILON: f64 = 0.01;
+        const EPSILON: Float = 0.01;
         let v1 = vector!(0.4, 0.3, 0.8);
         //Make sure distance from 1.0 is within error margins
         assert!((v1.normalized().length() - 1.0).abs() < EPSILON);
@@ -248,11 +248,26 @@ mod rays {
         textures::{color_provider::SolidColor, texture_repo::TextureRepository},
         utilities::{
             math::Vec3,
-            ray::{IntersectionBuilder, Ray},
+            ray::{offset_origin, IntersectionBuilder, Ray},
         },
         vector,
     };
 
+    #[test]
+    fn offset() {
+        let normal = Vec3::new(0.0, 1.0, -1.0).normalized();
+        //Small coordinates get a fixed offset, large ones a relative offset
+        for pos in [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, -2.0, 3.0),
+            Vec3::new(-10000.0, 10000.0, 0.001),
+        ] {
+            let offset = offset_origin(pos, normal) - pos;
+            assert!(offset.dot(normal) > 0.0);
+            assert!(offset.length() < 1e-4 * pos.length().max(1.0));
+        }
+    }
+
     #[test]
     fn intersection_builder() {
         let dummy_textures = TextureRepository::new();
@@ -264,6 +279,7 @@ mod rays {
         let ray = Ray {
             origin: vector!(0.1, 0.2, 0.3),
             direction: vector!(0.4, 0.5, 0.5),
+            ..Default::default()
         };
         let intersection = IntersectionBuilder {
             color_provider: SolidColor(color, Material::default()),
@@ -275,7 +291,7 @@ mod rays {
             ..Default::default()
         }
         .build();
-        assert_eq!(color, intersection.get_color(&dummy_textures));
+        assert_eq!(color, intersection.get_color(&dummy_textures.handles()));
         assert_eq!(normal, intersection.get_normal());
         assert_eq!(pos.unwrap(), intersection.get_pos());
         assert_eq!(distance.unwrap(), intersection.get_distance());
@@ -351,3 +367,184 @@ mod quadratic {
         }
     }
 }
+
+#[cfg(test)]
+mod cache {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use crate::utilities::cache::{MemorySize, SharedCache};
+
+    struct Blob(usize);
+
+    impl MemorySize for Blob {
+        fn memory_size(&self) -> usize {
+            self.0
+        }
+    }
+
+    #[test]
+    fn load_once() {
+        let cache: SharedCache<u32, Blob> = SharedCache::new(100);
+        assert_eq!(cache.get_or_load(1, || Some(Blob(10))).unwrap().0, 10);
+        //Cached values aren't loaded again
+        assert_eq!(cache.get_or_load(1, || panic!("Loaded twice")).unwrap().0, 10);
+        assert_eq!(cache.memory_usage(), 10);
+    }
+    #[test]
+    fn failed_load() {
+        let cache: SharedCache<u32, Blob> = SharedCache::new(100);
+        assert!(cache.get_or_load(1, || None).is_none());
+        assert!(cache.get_or_load(1, || panic!("Retried a failed load")).is_none());
+        assert!(!cache.contains(1));
+    }
+    #[test]
+    fn concurrent_load() {
+        let cache: SharedCache<u32, Blob> = SharedCache::new(100);
+        let loads = AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    let value = cache.get_or_load(1, || {
+                        loads.fetch_add(1, Ordering::Relaxed);
+                        std::thread::sleep(Duration::from_millis(20));
+                        Some(Blob(10))
+                    });
+                    assert_eq!(value.unwrap().0, 10);
+                });
+            }
+        });
+        assert_eq!(loads.load(Ordering::Relaxed), 1);
+    }
+    #[test]
+    fn eviction() {
+        let cache: SharedCache<u32, Blob> = SharedCache::new(100);
+        cache.insert_pinned(0, Blob(30));
+        cache.get_or_load(1, || Some(Blob(30)));
+        cache.get_or_load(2, || Some(Blob(30)));
+        //1 becomes the most recently used value
+        cache.get(1);
+        cache.get_or_load(3, || Some(Blob(30)));
+        assert!(cache.contains(0));
+        assert!(cache.contains(1));
+        assert!(!cache.contains(2));
+        assert!(cache.contains(3));
+        assert_eq!(cache.memory_usage(), 90);
+        //Values larger than the budget are still returned
+        assert_eq!(cache.get_or_load(4, || Some(Blob(200))).unwrap().0, 200);
+        assert!(cache.contains(0));
+        assert!(!cache.contains(1));
+    }
+}
+
+#[cfg(test)]
+mod stats {
+    use crate::utilities::stats::{self, Profiler, RayStats};
+
+    #[test]
+    fn profiler() {
+        let mut profiler = Profiler::new();
+        assert_eq!(profiler.pass("first", || 1), 1);
+        profiler.pass("second", || {
+            stats::ray();
+            stats::path(3);
+        });
+        assert_eq!(profiler.passes.len(), 2);
+        assert_eq!(profiler.passes[1].name, "second");
+        assert!(profiler.time() >= profiler.passes[1].time);
+        //Counted only with the stats feature, other tests might count concurrently
+        #[cfg(feature = "stats")]
+        {
+            let stats = profiler.stats();
+            assert!(stats.rays >= 1);
+            assert!(stats.paths >= 1);
+        }
+        #[cfg(not(feature = "stats"))]
+        assert_eq!(profiler.stats(), RayStats::default());
+    }
+    #[test]
+    fn finished_threads() {
+        //Make sure this thread is registered before counting the others
+        stats::local();
+        let threads = stats::running_threads();
+        for _ in 0..8 {
+            std::thread::spawn(stats::ray).join().unwrap();
+        }
+        //Other tests might start threads concurrently, but never eight more
+        assert!(stats::running_threads() < threads + 8);
+        #[cfg(feature = "stats")]
+        assert!(stats::total().rays >= 8);
+    }
+    #[test]
+    fn averages() {
+        let stats = RayStats {
+            rays: 4,
+            paths: 2,
+            path_segments: 4,
+            nodes: 10,
+            triangles: 6,
+        };
+        assert_eq!(stats.average_path_length(), 2.0);
+        assert_eq!(stats.nodes_per_ray(), 2.5);
+        assert_eq!(stats.triangles_per_ray(), 1.5);
+        assert_eq!(RayStats::default().nodes_per_ray(), 0.0);
+        assert_eq!((stats + stats) - stats, stats);
+    }
+    #[cfg(feature = "stats")]
+    #[test]
+    fn counting() {
+        use crate::{
+            intersectables::{bvh::BVH, packet::PacketBVH, triangle::Triangle},
+            renderers::path_tracer::Material,
+            textures::TextureID,
+            utilities::{
+                math::{Float, Vec3},
+                ray::{Intersectable, Ray},
+            },
+            vector,
+        };
+        let uv = [vector![0.0, 0.0], vector![0.0, 1.0], vector![1.0, 0.0]];
+        let triangles: Vec<Triangle> = (0..8)
+            .map(|i| {
+                let x = i as Float * 2.0;
+                Triangle::new(
+                    [
+                        Vec3::new(x, 0.0, 0.0),
+                        Vec3::new(x, 0.0, 1.0),
+                        Vec3::new(x + 1.0, 0.0, 0.0),
+                    ],
+                    uv,
+                    TextureID::new(&0),
+                    Material::Diffuse,
+                )
+            })
+            .collect();
+        let bvh = BVH::from_triangles(&triangles).unwrap();
+        let packet = PacketBVH::<4>::from_triangles(&triangles).unwrap();
+        let hit = Ray::new(Vec3::new(0.2, 1.0, 0.2), Vec3::new(0.0, -1.0, 0.0));
+        let miss = Ray::new(Vec3::new(0.2, 1.0, 0.2), Vec3::new(0.0, 1.0, 0.0));
+
+        //Counters are per thread, so this isn't affected by other tests
+        let before = stats::local();
+        assert!(bvh.intersect(miss).is_none());
+        let cost = stats::local() - before;
+        assert_eq!(cost.nodes, 1);
+        assert_eq!(cost.triangles, 0);
+
+        let before = stats::local();
+        assert!(bvh.intersect(hit).is_some());
+        let cost = stats::local() - before;
+        assert!(cost.nodes > 1);
+        assert!(cost.triangles >= 1 && cost.triangles < 8);
+
+        let before = stats::local();
+        assert!(packet.intersect(hit).is_some());
+        let cost = stats::local() - before;
+        //Leaves don't have bounds of their own, both are tested
+        assert_eq!(cost.nodes, 3);
+        assert_eq!(cost.triangles, 8);
+        assert!(stats::total().nodes >= stats::local().nodes);
+    }
+}
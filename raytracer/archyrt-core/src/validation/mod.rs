@@ -0,0 +1,290 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    intersectables::{aabb::AABB, triangle::Triangle},
+    loaders::{
+        amdl::repo::{PropID, PropRepository},
+        ascn::ASCNLoader,
+        Loader,
+    },
+    renderers::path_tracer::Material,
+    textures::{texture_repo::TextureRepository, TextureID},
+    utilities::math::Vec3,
+};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Part of the scene a diagnostic refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Scene,
+    Prop(PropID),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    MissingTexture {
+        source: Source,
+        texture: TextureID,
+        triangles: usize,
+    },
+    MissingProp {
+        prop: PropID,
+        requests: usize,
+    },
+    MissingSkybox(TextureID),
+    DegenerateTriangles {
+        source: Source,
+        count: usize,
+    },
+    InvalidNormals {
+        source: Source,
+        count: usize,
+    },
+    /// Neither emissive surfaces nor a skybox, nothing would be lit
+    NoLightSource,
+    EmptyScene,
+    InvalidBounds,
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Diagnostic::MissingProp { .. }
+            | Diagnostic::MissingSkybox(_)
+            | Diagnostic::InvalidBounds => Severity::Error,
+            Diagnostic::MissingTexture { .. }
+            | Diagnostic::DegenerateTriangles { .. }
+            | Diagnostic::InvalidNormals { .. }
+            | Diagnostic::NoLightSource
+            | Diagnostic::EmptyScene => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Scene => write!(f, "scene"),
+            Source::Prop(prop) => write!(f, "prop {:?}", prop),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::MissingTexture {
+                source,
+                texture,
+                triangles,
+            } => write!(
+                f,
+                "Texture {:?} used by {} triangles in the {} is missing",
+                texture, triangles, source
+            ),
+            Diagnostic::MissingProp { prop, requests } => {
+                write!(f, "Prop {:?} placed {} times is missing", prop, requests)
+            }
+            Diagnostic::MissingSkybox(texture) => write!(f, "Skybox texture {:?} is missing", texture),
+            Diagnostic::DegenerateTriangles { source, count } => {
+                write!(f, "{} degenerate triangles in the {}", count, source)
+            }
+            Diagnostic::InvalidNormals { source, count } => {
+                write!(f, "{} triangles with invalid normals in the {}", count, source)
+            }
+            Diagnostic::NoLightSource => write!(f, "The scene has no light source"),
+            Diagnostic::EmptyScene => write!(f, "The scene is empty"),
+            Diagnostic::InvalidBounds => write!(f, "The scene contains non-finite positions"),
+        }
+    }
+}
+
+/// Result of validating a scene
+#[derive(Debug, Default)]
+pub struct Report {
+    pub diagnostics: Vec<Diagnostic>,
+    /// World space bounds of the scene including props, if it has any geometry
+    pub bounds: Option<AABB>,
+}
+
+impl Report {
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity() == Severity::Error)
+    }
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity() == Severity::Warning)
+    }
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.diagnostics.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let severity = match diagnostic.severity() {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            write!(f, "{}: {}", severity, diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct TriangleStats {
+    missing_textures: HashMap<TextureID, usize>,
+    degenerate: usize,
+    invalid_normals: usize,
+    emissive: bool,
+}
+
+fn valid_normal(normal: Vec3) -> bool {
+    normal.inner.iter().all(|a| a.is_finite()) && normal.length_squared() > 0.0
+}
+
+fn check_triangles(triangles: &[Triangle], textures: &TextureRepository) -> TriangleStats {
+    let mut stats = TriangleStats::default();
+    for triangle in triangles {
+        let mut used = vec![triangle.texture];
+        match triangle.material {
+            Material::Emissive { power } => stats.emissive |= power > 0.0,
+            Material::DiffuseAndEmissive { emissive_texture } => {
+                stats.emissive = true;
+                used.push(emissive_texture);
+            }
+            _ => {}
+        }
+        for texture in used {
+            if !textures.exists(texture) {
+                *stats.missing_textures.entry(texture).or_default() += 1;
+            }
+        }
+        let area = (triangle.b - triangle.a).cross(triangle.c - triangle.a).length();
+        if !(area.is_finite() && area > 0.0) {
+            stats.degenerate += 1;
+        } else if ![triangle.an, triangle.bn, triangle.cn].into_iter().all(valid_normal) {
+            stats.invalid_normals += 1;
+        }
+    }
+    stats
+}
+
+fn report_triangles(report: &mut Report, source: Source, stats: TriangleStats) {
+    let mut missing: Vec<_> = stats.missing_textures.into_iter().collect();
+    missing.sort_by_key(|(_, triangles)| std::cmp::Reverse(*triangles));
+    for (texture, triangles) in missing {
+        report.diagnostics.push(Diagnostic::MissingTexture {
+            source,
+            texture,
+            triangles,
+        });
+    }
+    if stats.degenerate > 0 {
+        report.diagnostics.push(Diagnostic::DegenerateTriangles {
+            source,
+            count: stats.degenerate,
+        });
+    }
+    if stats.invalid_normals > 0 {
+        report.diagnostics.push(Diagnostic::InvalidNormals {
+            source,
+            count: stats.invalid_normals,
+        });
+    }
+}
+
+fn extend_bounds(bounds: &mut Option<AABB>, other: AABB) {
+    *bounds = Some(match bounds {
+        Some(bounds) => bounds.union(other),
+        None => other,
+    });
+}
+
+/// Checks a loaded scene against the texture and prop repositories it will be rendered with
+pub fn validate(
+    scene: &ASCNLoader,
+    textures: &TextureRepository,
+    props: &PropRepository,
+    skybox: Option<TextureID>,
+) -> Report {
+    let mut report = Report::default();
+    let triangles = scene.get_triangles();
+    let stats = check_triangles(triangles, textures);
+    let mut emissive = stats.emissive;
+    report_triangles(&mut report, Source::Scene, stats);
+    for triangle in triangles {
+        extend_bounds(&mut report.bounds, triangle.bounds());
+    }
+
+    //Props are checked once, no matter how many times they are placed
+    let mut requests: HashMap<PropID, usize> = HashMap::new();
+    for request in scene.get_prop_requests() {
+        *requests.entry(request.prop).or_default() += 1;
+    }
+    let mut requests: Vec<_> = requests.into_iter().collect();
+    requests.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    for (prop, count) in requests {
+        match props.get(prop, textures) {
+            Some(object) => {
+                let stats = check_triangles(&object.triangles, textures);
+                emissive |= stats.emissive;
+                report_triangles(&mut report, Source::Prop(prop), stats);
+            }
+            None => report.diagnostics.push(Diagnostic::MissingProp {
+                prop,
+                requests: count,
+            }),
+        }
+    }
+    for request in scene.get_prop_requests() {
+        let object = match props.get(request.prop, textures) {
+            Some(object) => object,
+            None => continue,
+        };
+        for triangle in &object.triangles {
+            //Props are stored in local space
+            for p in [triangle.a, triangle.b, triangle.c] {
+                let p = request.inverse_matrix * p + request.position;
+                extend_bounds(&mut report.bounds, AABB::new(p, p));
+            }
+        }
+    }
+
+    match skybox {
+        Some(skybox) if !textures.exists(skybox) => {
+            report.diagnostics.push(Diagnostic::MissingSkybox(skybox))
+        }
+        Some(_) => emissive = true,
+        None => {}
+    }
+    if !emissive {
+        report.diagnostics.push(Diagnostic::NoLightSource);
+    }
+    match report.bounds {
+        None => report.diagnostics.push(Diagnostic::EmptyScene),
+        Some(bounds) => {
+            let finite = bounds.min.inner.iter().chain(bounds.max.inner.iter()).all(|a| a.is_finite());
+            if !finite {
+                report.diagnostics.push(Diagnostic::InvalidBounds);
+            }
+        }
+    }
+    report
+}
@@ -0,0 +1,193 @@
+use asset::scene::{Camera, Face, Point, Prop, Scene, Solid, World};
+use cgmath::{Quaternion, Vector2, Vector3};
+
+use crate::{
+    intersectables::triangle::Triangle,
+    loaders::{
+        amdl::{
+            repo::{PropRepository, PropType},
+            AMDLLoader,
+        },
+        ascn::{self, amdl_textures::AMDLTextureType, ASCNLoader},
+    },
+    renderers::path_tracer::Material,
+    textures::{texture::Texture, texture_repo::TextureRepository, TextureID},
+    utilities::math::{Float, Vec3},
+    vector,
+};
+
+use super::{validate, Diagnostic, Severity, Source};
+
+fn solid(texture: u32) -> Solid {
+    let point = |x: i32, y: i32, z: i32| Point {
+        position: Vector3::new(x * 128, y * 128, z * 128),
+    };
+    let face = |indices: [u32; 4]| Face {
+        texture: asset::TextureID(texture),
+        indices: indices.to_vec(),
+        alignment: Default::default(),
+    };
+    Solid {
+        points: vec![
+            point(0, 0, 0),
+            point(1, 0, 0),
+            point(1, 1, 0),
+            point(0, 1, 0),
+            point(0, 0, 1),
+            point(1, 0, 1),
+            point(1, 1, 1),
+            point(0, 1, 1),
+        ],
+        faces: vec![
+            face([0, 1, 2, 3]),
+            face([5, 4, 7, 6]),
+            face([4, 0, 3, 7]),
+            face([1, 5, 6, 2]),
+            face([3, 2, 6, 7]),
+            face([4, 5, 1, 0]),
+        ],
+    }
+}
+
+fn scene(solids: Vec<Solid>, props: Vec<u32>) -> ASCNLoader {
+    let props = props
+        .into_iter()
+        .map(|asset| Prop {
+            asset: asset::PropID(asset),
+            position: Vector3::new(0, 0, 1280),
+            rotation: Quaternion::new(1.0, 0.0, 0.0, 0.0),
+        })
+        .collect();
+    ASCNLoader::from_scene(Scene {
+        camera: Camera {
+            position: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Vector2::new(0.0, 0.0),
+        },
+        world: World {
+            solids,
+            props,
+            outline: Default::default(),
+        },
+    })
+    .unwrap()
+}
+
+fn prop_triangle(a: Vec3, material: Material) -> Triangle {
+    Triangle::new(
+        [a, Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)],
+        [vector![0.0, 0.0], vector![0.0, 1.0], vector![1.0, 0.0]],
+        AMDLTextureType::diffuse(1),
+        material,
+    )
+}
+
+fn repos() -> (TextureRepository, PropRepository) {
+    let mut textures = TextureRepository::new();
+    textures.insert(AMDLTextureType::diffuse(1), Texture::new(1, 1));
+    textures.insert(TextureID::new(&"skybox"), Texture::new(1, 1));
+    let mut props = PropRepository::new();
+    props.insert(
+        PropType::default(1),
+        AMDLLoader {
+            triangles: vec![
+                prop_triangle(Vec3::default(), Material::Diffuse),
+                //Degenerate, all points on a line
+                prop_triangle(Vec3::new(2.0, -1.0, 0.0), Material::Diffuse),
+            ],
+        },
+    );
+    (textures, props)
+}
+
+#[test]
+fn valid() {
+    let (textures, props) = repos();
+    let scene = scene(vec![solid(1)], vec![]);
+    let report = validate(&scene, &textures, &props, Some(TextureID::new(&"skybox")));
+    assert!(report.diagnostics.is_empty(), "{}", report);
+    let bounds = report.bounds.unwrap();
+    assert_eq!(bounds.min, Vec3::new(0.0, 0.0, -1.0));
+    assert_eq!(bounds.max, Vec3::new(1.0, 1.0, 0.0));
+}
+
+#[test]
+fn worker_assets() {
+    //An assets directory laid out like the one the dispatcher and the workers load
+    let directory = std::env::temp_dir().join(format!("archyrt-assets-{}", std::process::id()));
+    std::fs::create_dir_all(directory.join("textures")).unwrap();
+    std::fs::write(
+        directory.join("repo.json"),
+        r#"{"textures": [{"id": 1, "name": "wall", "emissive": null}]}"#,
+    )
+    .unwrap();
+    image::RgbaImage::new(1, 1)
+        .save(directory.join("textures").join("wall.png"))
+        .unwrap();
+    std::fs::write(directory.join("skybox.exr"), []).unwrap();
+
+    let mut textures = TextureRepository::new();
+    ascn::load_textures(&mut textures, directory.to_str().unwrap()).unwrap();
+    let scene = scene(vec![solid(1)], vec![]);
+    let report = validate(&scene, &textures, &PropRepository::new(), Some(ascn::skybox()));
+    assert!(report.diagnostics.is_empty(), "{}", report);
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn missing_assets() {
+    let (textures, props) = repos();
+    let scene = scene(vec![solid(2)], vec![1, 3, 3]);
+    let skybox = TextureID::new(&"missing");
+    let report = validate(&scene, &textures, &props, Some(skybox));
+    assert!(report.has_errors());
+    assert!(report.diagnostics.contains(&Diagnostic::MissingTexture {
+        source: Source::Scene,
+        texture: AMDLTextureType::diffuse(2),
+        triangles: 12,
+    }));
+    assert!(report.diagnostics.contains(&Diagnostic::MissingProp {
+        prop: PropType::default(3),
+        requests: 2,
+    }));
+    assert!(report.diagnostics.contains(&Diagnostic::DegenerateTriangles {
+        source: Source::Prop(PropType::default(1)),
+        count: 1,
+    }));
+    assert!(report.diagnostics.contains(&Diagnostic::MissingSkybox(skybox)));
+    assert!(report.diagnostics.contains(&Diagnostic::NoLightSource));
+    //The prop is placed 10 meters away
+    assert_eq!(report.bounds.unwrap().min, Vec3::new(0.0, -1.0, -10.0));
+}
+
+#[test]
+fn invalid_normals() {
+    let (textures, mut props) = repos();
+    let mut triangle = prop_triangle(Vec3::default(), Material::Emissive { power: 1.0 });
+    triangle.an = Vec3::new(Float::NAN, 0.0, 0.0);
+    props.insert(
+        PropType::default(2),
+        AMDLLoader {
+            triangles: vec![triangle],
+        },
+    );
+    let scene = scene(vec![], vec![2]);
+    let report = validate(&scene, &textures, &props, None);
+    //The emissive prop lights the scene
+    assert_eq!(
+        report.diagnostics,
+        vec![Diagnostic::InvalidNormals {
+            source: Source::Prop(PropType::default(2)),
+            count: 1,
+        }]
+    );
+    assert!(report.warnings().all(|d| d.severity() == Severity::Warning));
+    assert!(!report.has_errors());
+}
+
+#[test]
+fn empty() {
+    let (textures, props) = repos();
+    let report = validate(&scene(vec![], vec![]), &textures, &props, None);
+    assert!(report.diagnostics.contains(&Diagnostic::EmptyScene));
+    assert!(report.bounds.is_none());
+}
@@ -1,10 +1,15 @@
+use std::collections::hash_map::DefaultHasher;
 use std::f64::consts::PI;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
 
 use archyrt_core::api::camera::Camera;
 use archyrt_core::api::fragment_render::{FragmentContext, FragmentRender};
 
 use archyrt_core::cameras::jitter::JitterCamera;
 use archyrt_core::cameras::perspective::PerspectiveCamera;
+use archyrt_core::cameras::shutter::ShutterCamera;
+use archyrt_core::collector::checkpoint::Accumulator;
 use archyrt_core::collector::image_collector::ImageCollector;
 use archyrt_core::collector::raw_collector::RawCollector;
 use archyrt_core::intersectables::apply_matrix::ApplyMatrix;
@@ -13,21 +18,31 @@ use archyrt_core::intersectables::sphere::Sphere;
 use archyrt_core::intersectables::transform::Transform;
 use archyrt_core::loaders::amdl::repo::{PropRepository, PropType};
 use archyrt_core::loaders::amdl::{self, AMDLLoader};
+use archyrt_core::loaders::ascn::settings::SceneSettings;
 use archyrt_core::loaders::ascn::{amdl_textures, ASCNLoader};
 use archyrt_core::renderers::basic_renderer::BasicRenderer;
+use archyrt_core::renderers::lightmap::{self, LightmapBaker};
+use archyrt_core::renderers::mode::RenderMode;
+use archyrt_core::renderers::path_tracer::medium::Media;
+use archyrt_core::renderers::path_tracer::spectral::SpectralPathTracer;
 use archyrt_core::renderers::path_tracer::{Material, PathTracer};
+use archyrt_core::renderers::region::{Region, RegionView, RenderArea};
 use archyrt_core::renderers::sampling::SamplingRenderer;
 use archyrt_core::renderers::solid_renderers::albedo::AlbedoRenderer;
 use archyrt_core::renderers::solid_renderers::normal::NormalRenderer;
+use archyrt_core::spectrum::color_space::ColorSpace;
 use archyrt_core::textures::texture_repo::{self, TextureRepository};
 use archyrt_core::tonemapping::tonemap_fragment;
 use archyrt_core::utilities::math::{Matrix3x3, Vec2, Vector};
 use archyrt_core::utilities::ray::{Intersectable, Ray};
+use archyrt_core::utilities::rng;
+use archyrt_core::utilities::stats::Profiler;
 use archyrt_core::vector;
 use archyrt_core::{
     api::fragment_collector::FragmentCollector, loaders::Loader, textures::TextureID,
     utilities::math::Vec3,
 };
+use asset::scene::Scene;
 use image::{Rgb, RgbImage};
 use rayon::prelude::*;
 
@@ -38,36 +53,99 @@ pub struct ParallelSamplingRenderer<Renderer: FragmentRender + Sync + Send> {
 
 impl<Renderer: FragmentRender + Sync + Send> FragmentRender for ParallelSamplingRenderer<Renderer> {
     fn render_fragment(&self, ctx: &FragmentContext, pos: Vec2) -> Vec3 {
+        //Texture handles belong to a single thread, each one gets its own
+        let (width, height, repo) = (ctx.width, ctx.height, ctx.textures.repo());
         (0..self.samples)
             .into_par_iter()
-            .map(|_| self.inner.render_fragment(ctx, pos))
+            .map_init(
+                || repo.handles(),
+                |textures, _| {
+                    let ctx = FragmentContext {
+                        width,
+                        height,
+                        textures,
+                    };
+                    self.inner.render_fragment(&ctx, pos)
+                },
+            )
             .reduce(Vec3::default, |a, b| a + b)
             / (self.samples as f64)
     }
 }
 
+/// Samples, checkpoint and color space of a path traced render, crop and preview scale of every render
+struct Options {
+    samples: u32,
+    checkpoint: Option<PathBuf>,
+    color_space: ColorSpace,
+    crop: Option<Region>,
+    scale: f64,
+    /// Hash of the scene and everything else the image depends on, only matching checkpoints are resumed
+    key: u64,
+}
+
+/// Resumes the checkpoint if it exists, it is saved after every round of samples
+fn accumulate<R: FragmentRender + Sync>(
+    renderer: R,
+    repo: &TextureRepository,
+    options: &Options,
+    w: usize,
+    h: usize,
+) -> Vec<f32> {
+    let mut accumulator = match &options.checkpoint {
+        Some(path) if path.is_file() => {
+            let accumulator = Accumulator::load(path).unwrap();
+            assert!(
+                accumulator.width == w && accumulator.height == h,
+                "Checkpoint is for a {}x{} image",
+                accumulator.width,
+                accumulator.height
+            );
+            assert!(
+                accumulator.key == options.key,
+                "Checkpoint is for a different scene, settings or mode"
+            );
+            println!("Resuming from {} samples", accumulator.min_samples());
+            accumulator
+        }
+        _ => {
+            let mut accumulator = Accumulator::new(w, h, 8, 8, rng::random()).unwrap();
+            accumulator.key = options.key;
+            accumulator
+        }
+    };
+    for round in accumulator.min_samples()..options.samples {
+        let samples: Vec<_> = accumulator
+            .pending(round + 1)
+            .into_par_iter()
+            .map(|sample| (sample, accumulator.render_sample(&renderer, repo, sample)))
+            .collect();
+        for (sample, data) in samples {
+            accumulator.add(sample, &data).unwrap();
+        }
+        println!("Sample {}/{}", round + 1, options.samples);
+        if let Some(path) = &options.checkpoint {
+            accumulator.save(path).unwrap();
+        }
+    }
+    accumulator.image()
+}
+
 fn render_pathtraced<O: Intersectable + Sync, C: Camera + Sync>(
+    mode: RenderMode,
     object: O,
     camera: C,
+    media: Media,
     mut repo: TextureRepository,
-    w: usize,
-    h: usize,
+    options: &Options,
+    area: RenderArea,
 ) -> image::ImageBuffer<Rgb<u8>, Vec<u8>> {
-    let aa_camera = JitterCamera::new(&camera, w, h); //Camera used for anti-aliasing
+    let (w, h) = (area.region.width, area.region.height);
+    let aa_camera = JitterCamera::new(&camera, area.width, area.height); //Camera used for anti-aliasing
     let skybox_id = TextureID::new(&"skybox");
     texture_repo::exr::load_into(&mut repo, "../assets", &[(skybox_id, "skybox.exr")]).unwrap();
     let skybox = Some(skybox_id);
     //Set renderers up
-    let pathtracer = PathTracer {
-        skybox,
-        object: &object,
-        camera: &aa_camera,
-        bounces: 5,
-    };
-    let pathtracer = ParallelSamplingRenderer {
-        inner: pathtracer,
-        samples: 5,
-    };
     //Albedo and Normal renderers are required by OIDN
     let albedo = AlbedoRenderer {
         object: &object,
@@ -85,10 +163,38 @@ fn render_pathtraced<O: Intersectable + Sync, C: Camera + Sync>(
 
     //Collect images to arrays
     println!("Rendering image");
+    let mut profiler = Profiler::new();
     let collector = RawCollector {};
-    let pathtracer_image = collector.collect(&pathtracer, &repo, w, h);
-    let albedo_image = collector.collect(&albedo, &repo, w, h);
-    let normal_image = collector.collect(&normal, &repo, w, h);
+    let mut pathtracer_image = profiler.pass("Path tracing", || match mode {
+        RenderMode::Spectral => {
+            let spectral = SpectralPathTracer {
+                skybox,
+                object: &object,
+                camera: &aa_camera,
+                bounces: 5,
+                media,
+            };
+            accumulate(RegionView::new(&spectral, area), &repo, options, w, h)
+        }
+        _ => {
+            let pathtracer = PathTracer {
+                skybox,
+                object: &object,
+                camera: &aa_camera,
+                bounces: 5,
+                media,
+            };
+            accumulate(RegionView::new(&pathtracer, area), &repo, options, w, h)
+        }
+    });
+    mode.color_space()
+        .convert_image(&mut pathtracer_image, options.color_space);
+    let albedo_image = profiler.pass("Albedo", || {
+        collector.collect(RegionView::new(&albedo, area), &repo, w, h)
+    });
+    let normal_image = profiler.pass("Normal", || {
+        collector.collect(RegionView::new(&normal, area), &repo, w, h)
+    });
 
     //Using OIDN for denoising
     println!("Denoising");
@@ -105,6 +211,7 @@ fn render_pathtraced<O: Intersectable + Sync, C: Camera + Sync>(
         .clean_aux(true)
         .filter(&pathtracer_image, &mut output)
         .unwrap();
+    println!("{}", profiler);
     //Collect OIDN image
     let mut image = RgbImage::new(w as u32, h as u32);
     //let output = pathtracer_image;
@@ -181,9 +288,95 @@ fn render_albedo<O: Intersectable, C: Camera>(
     image
 }
 
+/// Debug views are saved without tonemapping
+fn render_debug<O: Intersectable, C: Camera>(
+    mode: RenderMode,
+    object: O,
+    camera: C,
+    repo: TextureRepository,
+    area: RenderArea,
+) -> image::ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (w, h) = (area.region.width, area.region.height);
+    let renderer = RegionView::new(mode.debug_renderer(camera, object).unwrap(), area);
+    let mut profiler = Profiler::new();
+    let output = profiler.pass(mode.name(), || RawCollector {}.collect(renderer, &repo, w, h));
+    println!("{}", profiler);
+    let mut image = RgbImage::new(w as u32, h as u32);
+    for (pixel, color) in image.pixels_mut().zip(output.chunks(3)) {
+        *pixel = Rgb([
+            (color[0] * 255.0).clamp(0.0, 255.0) as u8,
+            (color[1] * 255.0).clamp(0.0, 255.0) as u8,
+            (color[2] * 255.0).clamp(0.0, 255.0) as u8,
+        ]);
+    }
+    image
+}
+
+/// Bakes the lighting of the scene's solids for the editor, faces are baked in parallel
+fn bake_lightmap<O: Intersectable + Sync>(object: O, scene: &Scene, mut repo: TextureRepository, samples: u32, output: &str) {
+    let skybox_id = TextureID::new(&"skybox");
+    texture_repo::exr::load_into(&mut repo, "../assets", &[(skybox_id, "skybox.exr")]).unwrap();
+    let baker = LightmapBaker {
+        object,
+        bounces: 5,
+        skybox: Some(skybox_id),
+        samples: samples as usize,
+        patch: 16,
+    };
+    let mut lightmap = baker.layout(scene);
+    println!("Baking {} faces", lightmap.faces.len());
+    let mut profiler = Profiler::new();
+    let faces: Vec<_> = profiler.pass("Baking", || {
+        (0..lightmap.faces.len())
+            .into_par_iter()
+            .map(|face| baker.bake_face(&lightmap, face, &repo))
+            .collect()
+    });
+    println!("{}", profiler);
+    for (face, texels) in faces.iter().enumerate() {
+        lightmap::store_face(&mut lightmap, face, texels);
+    }
+    std::fs::write(output, lightmap.encode().unwrap()).unwrap();
+}
+
 fn main() {
     let w = 512;
     let h = 512;
+    //Usage: archyrt_dev [mode] [--scene FILE] [--output FILE] [--samples N] [--checkpoint FILE] [--crop X,Y,W,H] [--scale S] [--color-space NAME] [--settings FILE]
+    //       archyrt_dev bake [--scene FILE] [--output FILE] [--samples N] bakes a lightmap for the editor
+    let mut bake = false;
+    let mut scene_path = String::from("../assets/ottoman.ascn");
+    let mut output = None;
+    let mut settings = SceneSettings::default();
+    let mut settings_json = String::new();
+    let mut mode = RenderMode::PathTraced;
+    let mut options = Options {
+        samples: 5,
+        checkpoint: None,
+        color_space: ColorSpace::Srgb,
+        crop: None,
+        scale: 1.0,
+        key: 0,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--scene" => scene_path = args.next().unwrap(),
+            "--output" => output = Some(args.next().unwrap()),
+            "--samples" => options.samples = args.next().unwrap().parse().unwrap(),
+            "--checkpoint" => options.checkpoint = Some(args.next().unwrap().into()),
+            "--crop" => options.crop = Some(args.next().unwrap().parse().unwrap()),
+            "--scale" => options.scale = args.next().unwrap().parse().unwrap(),
+            "--color-space" => options.color_space = args.next().unwrap().parse().unwrap(),
+            "--settings" => {
+                settings_json = std::fs::read_to_string(args.next().unwrap()).unwrap();
+                settings = SceneSettings::from_json(&settings_json).unwrap();
+            }
+            "bake" => bake = true,
+            _ => mode = arg.parse().unwrap(),
+        }
+    }
+    let area = RenderArea::new(w, h, options.scale, options.crop).unwrap();
 
     //Loading textures and skybox
     println!("Load file");
@@ -191,18 +384,52 @@ fn main() {
     amdl_textures::load_into(&mut textures, "../assets").unwrap();
 
     let mut props = PropRepository::new();
-    amdl::repo::load_into(&mut props, &textures, "../assets").unwrap();
+    amdl::repo::load_into(&mut props, "../assets").unwrap();
 
     //Load model
-    let loader = ASCNLoader::from_path("../assets/ottoman.ascn").unwrap();
-    let camera = loader.get_camera();
+    let data = std::fs::read(&scene_path).unwrap();
+    let mut hasher = DefaultHasher::new();
+    (&data, &settings_json, mode, options.crop, options.scale.to_bits()).hash(&mut hasher);
+    options.key = hasher.finish();
+    let mut loader = ASCNLoader::from_bytes_with(&data, &settings).unwrap();
+    loader.apply_alpha_masks(&textures);
+    let (open, close) = loader.get_shutter();
+    let camera = ShutterCamera::new(loader.get_camera(), open, close);
     let object = loader.get_triangles();
     let object = BVH::from_triangles(&object);
-    let props = props.fulfill_all(loader.get_prop_requests()).unwrap();
+    let props = props.fulfill_all(loader.get_prop_requests(), &textures).unwrap();
     let object = object.union(props);
 
-    println!("Render");
-    let image = render_pathtraced(object, camera, textures, w, h);
+    if bake {
+        let scene = Scene::decode(&data).unwrap();
+        let output = output.as_deref().unwrap_or("lightmap.alm");
+        bake_lightmap(object, &scene, textures, options.samples, output);
+        return;
+    }
+
+    println!("Render {}", mode);
+    let image = if mode.is_lit() {
+        render_pathtraced(mode, object, camera, loader.get_media(), textures, &options, area)
+    } else {
+        render_debug(mode, object, camera, textures, area)
+    };
     //let image = render_albedo(object, camera, textures, w, h);
-    image.save("image.png").unwrap();
+    //A cropped render is pasted into the previous image
+    let output = output.as_deref().unwrap_or("image.png");
+    let image = match image::open(output) {
+        Ok(base) if area.is_cropped() => {
+            let base = base.to_rgb8();
+            let mut base = if base.dimensions() == (area.width as u32, area.height as u32) {
+                base
+            } else {
+                image::imageops::resize(&base, area.width as u32, area.height as u32, image::imageops::FilterType::Triangle)
+            };
+            image::imageops::replace(&mut base, &image, area.region.x as u32, area.region.y as u32);
+            base
+        }
+        _ => image,
+    };
+    //Debug views aren't converted, they are tagged as sRGB
+    let color_space = if mode.is_lit() { options.color_space } else { ColorSpace::Srgb };
+    std::fs::write(output, color_space.encode_png(&image).unwrap()).unwrap();
 }
@@ -1,17 +1,20 @@
-use std::{env, path::Path, sync::Arc};
+use std::{collections::hash_map::DefaultHasher, env, hash::{Hash, Hasher}, path::{Path, PathBuf}, sync::Arc, time::{Duration, Instant}};
 
 use anyhow::{anyhow, Result};
 use archyrt_core::{
     api::fragment_collector::FragmentCollector,
-    collector::raw_collector::RawCollector,
+    collector::{checkpoint::{Accumulator, TileSample}, raw_collector::RawCollector},
     intersectables::bvh::BVH,
     loaders::{
-        ascn::{amdl_textures, ASCNLoader},
+        ascn::{self, settings::SceneSettings, ASCNLoader},
         Loader, amdl::{repo::PropRepository, self},
     },
-    renderers::{solid_renderers::{albedo::AlbedoRenderer, normal::NormalRenderer}, sampling::SamplingRenderer},
-    textures::texture_repo::TextureRepository, vector, utilities::{math::Vec3, ray::Intersectable}, tonemapping::tonemap_fragment, cameras::jitter::JitterCamera,
+    renderers::{lightmap::LightmapBaker, mode::RenderMode, region::{Region, RegionView, RenderArea}, solid_renderers::{albedo::AlbedoRenderer, normal::NormalRenderer}, sampling::SamplingRenderer},
+    spectrum::color_space::ColorSpace,
+    textures::texture_repo::TextureRepository, vector, utilities::{math::Vec3, ray::Intersectable, rng}, tonemapping::tonemap_fragment, cameras::jitter::JitterCamera,
+    validation::validate,
 };
+use asset::scene::Scene;
 use dotenv::dotenv;
 
 use futures_util::stream::StreamExt;
@@ -30,27 +33,30 @@ use mongodb::{
 use uuid::Uuid;
 
 #[cfg(feature="oidn")]
-fn denoise(width: usize, height: usize, image: Vec<f32>, scene: &str, redis_client: &mut redis::Client, props: &Arc<PropRepository>, render_id: ObjectId, textures: &Arc<TextureRepository>) -> Vec<f32>{
+fn denoise(area: RenderArea, image: Vec<f32>, scene: &str, redis_client: &mut redis::Client, props: &Arc<PropRepository>, render_id: ObjectId, textures: &Arc<TextureRepository>) -> Vec<f32>{
     //Render Albedo and Normal
+    let settings = scene_settings(scene, redis_client).unwrap();
     let scene: Vec<u8> =
     redis::Cmd::get(format!("archyrt:{}:scene", scene)).query(redis_client).unwrap();
-    let scene = ASCNLoader::from_bytes(&scene).unwrap();
+    let mut scene = ASCNLoader::from_bytes_with(&scene, &settings).unwrap();
+    scene.apply_alpha_masks(textures);
     let bvh = BVH::from_triangles(scene.get_triangles());
-    let props = props.fulfill_all(scene.get_prop_requests()).unwrap();
+    let props = props.fulfill_all(scene.get_prop_requests(), textures).unwrap();
     let camera = scene.get_camera();
     let object = bvh.union(props);
     let albedo = AlbedoRenderer {
         object: &object,
-        camera: JitterCamera::new(&camera, width, height),
+        camera: JitterCamera::new(&camera, area.width, area.height),
     };
     let normal = NormalRenderer {
         object: &object,
         camera: &camera,
     };
+    let (width, height) = (area.region.width, area.region.height);
     let collector = RawCollector {};
     println!("[{}] Rendering Albedo and Normal", render_id);
-    let albedo = collector.collect(albedo, &textures, width, height);
-    let normal = collector.collect(normal, &textures, width, height);
+    let albedo = collector.collect(RegionView::new(albedo, area), &textures, width, height);
+    let normal = collector.collect(RegionView::new(normal, area), &textures, width, height);
     let mut output: Vec<f32> = (0..image.len()).into_iter().map(|_| 0f32).collect();
     //Apply denoised
     println!("[{}] Applying denoiser", render_id);
@@ -65,10 +71,84 @@ fn denoise(width: usize, height: usize, image: Vec<f32>, scene: &str, redis_clie
     output
 }
 #[cfg(not(feature="oidn"))]
-fn denoise(width: usize, height: usize, image: Vec<f32>, scene: &str, redis_client: &mut redis::Client, props: &Arc<PropRepository>, render_id: ObjectId, textures: &Arc<TextureRepository>) -> Vec<f32>{
+fn denoise(area: RenderArea, image: Vec<f32>, scene: &str, redis_client: &mut redis::Client, props: &Arc<PropRepository>, render_id: ObjectId, textures: &Arc<TextureRepository>) -> Vec<f32>{
     image
 }
 
+async fn reject_job(
+    users: &Collection<Document>,
+    channel: &Channel,
+    delivery_tag: u64,
+    response_queue: &str,
+    user: ObjectId,
+    project_id: ObjectId,
+    render_id: ObjectId,
+    message: &str,
+) {
+    println!("[{}] Rejected: {}", render_id, message);
+    users.update_many(doc! {"_id": user}, doc!{"$set":{"projects.$[project].renders.$[render].finished": DateTime::now(), "projects.$[project].renders.$[render].error": message}}, UpdateOptions::builder().array_filters(vec![doc!{"render._id": render_id}, doc!{"project._id": project_id}]).build()).await.unwrap();
+    channel
+        .basic_ack(delivery_tag, Default::default())
+        .await.unwrap();
+    channel
+        .queue_delete(response_queue, Default::default())
+        .await.unwrap();
+}
+
+/// Settings stored with the job, jobs without any render a still scene
+fn scene_settings(task: &str, redis_client: &mut redis::Client) -> Result<SceneSettings> {
+    let settings: Option<String> = redis::Cmd::get(format!("archyrt:{}:settings", task)).query(redis_client)?;
+    match settings {
+        Some(settings) => SceneSettings::from_json(&settings),
+        None => Ok(SceneSettings::default()),
+    }
+}
+
+//Checkpoints are only kept when a directory is configured
+fn checkpoint_path(render: &str) -> Option<PathBuf> {
+    let directory = env::var("CHECKPOINTS").ok()?;
+    Some(Path::new(&directory).join(render).with_extension("ckpt"))
+}
+
+/// Hash of every job field the accumulated image depends on. The sample count is left out, so extending a render keeps its key.
+fn render_key(task: &str, redis_client: &mut redis::Client) -> Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    for field in ["scene", "settings", "mode", "width", "height", "scale", "crop"] {
+        let value: Option<Vec<u8>> = redis::Cmd::get(format!("archyrt:{}:{}", task, field)).query(redis_client)?;
+        value.hash(&mut hasher);
+    }
+    Ok(hasher.finish())
+}
+
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
+
+//Texels along each side of a baked face
+const LIGHTMAP_PATCH: u32 = 16;
+
+/// Bakes the lightmap of a project for the editor's lit preview, it's saved next to the renders
+fn bake_lightmap(project: &str, redis_client: &mut redis::Client, textures: &TextureRepository, props: &PropRepository) -> Result<()> {
+    let samples: usize = redis::Cmd::get(format!("archyrt:{}:samples", project)).query(redis_client)?;
+    let data: Vec<u8> = redis::Cmd::get(format!("archyrt:{}:scene", project)).query(redis_client)?;
+    let scene = Scene::decode(&data).ok_or_else(|| anyhow!("The scene can't be decoded"))?;
+    let mut loader = ASCNLoader::from_bytes(&data)?;
+    loader.apply_alpha_masks(textures);
+    let bvh = BVH::from_triangles(loader.get_triangles());
+    let props = props.fulfill_all(loader.get_prop_requests(), textures)?;
+    let baker = LightmapBaker {
+        object: bvh.union(props),
+        bounces: 5,
+        skybox: Some(ascn::skybox()),
+        samples,
+        patch: LIGHTMAP_PATCH,
+    };
+    let lightmap = baker.bake(&scene, textures);
+    let lightmap = lightmap.encode().ok_or_else(|| anyhow!("The lightmap can't be encoded"))?;
+    let images = env::var("IMAGES")?;
+    std::fs::write(Path::new(&images).join(project).with_extension("alm"), lightmap)?;
+    let _: () = redis::Cmd::del(&[format!("archyrt:{}:samples", project), format!("archyrt:{}:scene", project)]).query(redis_client)?;
+    Ok(())
+}
+
 async fn handle_job(
     users: Collection<Document>,
     mut redis_client: redis::Client,
@@ -95,45 +175,126 @@ async fn handle_job(
     let width: usize = redis::Cmd::get(format!("archyrt:{}:width", s)).query(&mut redis_client).unwrap();
     let height: usize =
         redis::Cmd::get(format!("archyrt:{}:height", s)).query(&mut redis_client).unwrap();
-    //Create image storage on Redis
-    let image_key = format!("archyrt:{}:image", s);
-    let _: () = redis::cmd("AI.TENSORSET")
-        .arg(&image_key)
-        .arg("FLOAT")
-        .arg(height)
-        .arg(width)
-        .arg(3)
-        .query(&mut redis_client).unwrap();
-    let payload = s.clone();
-    //Put as many tasks on the queue as there are samples*16
-    for x in 0..4 {
-        for y in 0..4{
-            let x = width/4*x;
-            let y = height/4*y;
-            futures::stream::iter(0..samples)
-                .for_each(|_| async {
-                    let id = Uuid::new_v4();
-                    let payload = format!("{}#{}#{}#{}#{}", payload, id, response_queue, x, y);
-                    let payload = payload.into_bytes();
-                    channel
-                        .clone()
-                        .basic_publish(
-                            "",
-                            task_queue,
-                            Default::default(),
-                            payload,
-                            Default::default(),
-                        )
-                        .await
-                        .unwrap();
-                    
-                })
-                .await;
+    //Jobs without a mode are path traced
+    let mode: Option<String> = redis::Cmd::get(format!("archyrt:{}:mode", s)).query(&mut redis_client).unwrap();
+    let mode = match mode.map(|mode| mode.parse::<RenderMode>()) {
+        Some(Ok(mode)) => mode,
+        Some(Err(err)) => {
+            reject_job(&users, &channel, delivery.delivery_tag, response_queue, user, project_id, render_id, &err.to_string()).await;
+            return Ok(());
+        }
+        None => RenderMode::PathTraced,
+    };
+    //Lit renders are converted to the requested color space, sRGB by default
+    let color_space: Option<String> = redis::Cmd::get(format!("archyrt:{}:colorspace", s)).query(&mut redis_client).unwrap();
+    let color_space = match color_space.map(|space| space.parse::<ColorSpace>()) {
+        Some(Ok(space)) => space,
+        Some(Err(err)) => {
+            reject_job(&users, &channel, delivery.delivery_tag, response_queue, user, project_id, render_id, &err.to_string()).await;
+            return Ok(());
+        }
+        None => ColorSpace::Srgb,
+    };
+    //Previews are scaled down, cropped renders only cover a region of the image
+    let scale: Option<f64> = redis::Cmd::get(format!("archyrt:{}:scale", s)).query(&mut redis_client).unwrap();
+    let crop: Option<String> = redis::Cmd::get(format!("archyrt:{}:crop", s)).query(&mut redis_client).unwrap();
+    let area = crop
+        .map(|crop| crop.parse::<Region>())
+        .transpose()
+        .and_then(|crop| RenderArea::new(width, height, scale.unwrap_or(1.0), crop));
+    let area = match area {
+        Ok(area) => area,
+        Err(err) => {
+            reject_job(&users, &channel, delivery.delivery_tag, response_queue, user, project_id, render_id, &err.to_string()).await;
+            return Ok(());
+        }
+    };
+    //Finished render the crop is pasted into
+    let base: Option<String> = redis::Cmd::get(format!("archyrt:{}:base", s)).query(&mut redis_client).unwrap();
+    let images = env::var("IMAGES").unwrap();
+    if let Some(base) = &base {
+        if !Path::new(&images).join(base).with_extension("png").is_file() {
+            reject_job(&users, &channel, delivery.delivery_tag, response_queue, user, project_id, render_id, "The render to paste the region into does not exist").await;
+            return Ok(());
+        }
+        //The region is pasted as it is, so the PNG's color space tag has to fit both
+        let base_space: Option<String> = redis::Cmd::get(format!("archyrt:{}:colorspace", base)).query(&mut redis_client).unwrap();
+        let base_space = base_space.map_or(Ok(ColorSpace::Srgb), |space| space.parse::<ColorSpace>());
+        if base_space.ok() != Some(color_space) {
+            reject_job(&users, &channel, delivery.delivery_tag, response_queue, user, project_id, render_id, "The render to paste the region into is in another color space").await;
+            return Ok(());
         }
     }
-    channel
-        .basic_ack(delivery.delivery_tag, Default::default())
-        .await.unwrap();
+    //Reject scenes that can't be rendered before dispatching any work
+    let scene: Vec<u8> =
+        redis::Cmd::get(format!("archyrt:{}:scene", s)).query(&mut redis_client).unwrap();
+    let report = match scene_settings(&s, &mut redis_client).and_then(|settings| ASCNLoader::from_bytes_with(&scene, &settings)) {
+        Ok(scene) => validate(&scene, &textures, &props, Some(ascn::skybox())),
+        Err(err) => {
+            reject_job(&users, &channel, delivery.delivery_tag, response_queue, user, project_id, render_id, &err.to_string()).await;
+            return Ok(());
+        }
+    };
+    for warning in report.warnings() {
+        println!("[{}] warning: {}", render_id, warning);
+    }
+    if report.has_errors() {
+        let message = report.errors().map(|error| error.to_string()).collect::<Vec<_>>().join("\n");
+        reject_job(&users, &channel, delivery.delivery_tag, response_queue, user, project_id, render_id, &message).await;
+        return Ok(());
+    }
+    //Continue a render that was interrupted or is extended to more samples, as long as nothing else changed
+    let checkpoint = checkpoint_path(&s);
+    let key = render_key(&s, &mut redis_client)?;
+    let accumulator = match checkpoint.as_ref().filter(|path| path.is_file()).map(Accumulator::load) {
+        Some(Ok(accumulator)) if accumulator.key == key && accumulator.width == area.region.width && accumulator.height == area.region.height => {
+            println!("[{}] Resuming from {} samples", render_id, accumulator.min_samples());
+            Ok(accumulator)
+        }
+        _ => Accumulator::new(
+            area.region.width,
+            area.region.height,
+            area.region.width.min(4),
+            area.region.height.min(4),
+            rng::random(),
+        )
+        .map(|mut accumulator| {
+            accumulator.key = key;
+            accumulator
+        }),
+    };
+    let mut accumulator = match accumulator {
+        Ok(accumulator) => accumulator,
+        Err(err) => {
+            reject_job(&users, &channel, delivery.delivery_tag, response_queue, user, project_id, render_id, &err.to_string()).await;
+            return Ok(());
+        }
+    };
+    let pending = accumulator.pending(samples as u32);
+    let payload = s.clone();
+    //Put a task on the queue for every missing sample of every tile
+    futures::stream::iter(&pending)
+        .for_each(|&sample| {
+            let (x, y, w, h) = accumulator.tile_rect(sample.tile_x, sample.tile_y);
+            let (x, y) = (area.region.x + x, area.region.y + y);
+            let seed = accumulator.sample_seed(sample);
+            let id = Uuid::new_v4();
+            let payload = format!("{}#{}#{}#{}#{}#{}#{}#{}#{}", payload, id, response_queue, x, y, w, h, seed, sample.index);
+            let channel = channel.clone();
+            async move {
+                channel
+                    .basic_publish(
+                        "",
+                        task_queue,
+                        Default::default(),
+                        payload.into_bytes(),
+                        Default::default(),
+                    )
+                    .await
+                    .unwrap();
+            }
+        })
+        .await;
     let consumer_tag = format!("consumer_{}", s);
     let mut consumer = channel
         .basic_consume(
@@ -147,9 +308,14 @@ async fn handle_job(
         )
         .await.unwrap();
     let mut counter = 0;
+    let mut saved = Instant::now();
+    let total = samples as f32 * (accumulator.tiles_x * accumulator.tiles_y) as f32;
     println!("[{}] Waiting for workers to finish", render_id);
-    while let Some(delivery) = consumer.next().await {
-        let (_, _delivery) = delivery.unwrap();
+    while counter < pending.len() {
+        let (_, _delivery) = match consumer.next().await {
+            Some(delivery) => delivery.unwrap(),
+            None => break,
+        };
 
 
         let msg = String::from_utf8(_delivery.data).unwrap();
@@ -157,31 +323,40 @@ async fn handle_job(
         let temp = msg[0];
         let x: usize = msg[1].parse().unwrap();
         let y: usize = msg[2].parse().unwrap();
+        let index: u32 = msg[3].parse().unwrap();
         //Add image to accumulator
-        let _: () = redis::cmd("AI.SCRIPTEXECUTE")
-            .arg("archyrt:scripts")
-            .arg("add")
-            .arg("INPUTS")
-            .arg(2)
-            .arg(&temp)
-            .arg(&image_key)
-            .arg("ARGS")
-            .arg(2)
-            .arg(x)
-            .arg(y)
-            .arg("OUTPUTS")
-            .arg(1)
-            .arg(&image_key)
-            .execute(&mut redis_client);
+        let data: Vec<u8> = redis::cmd("AI.TENSORGET")
+            .arg(temp)
+            .arg("BLOB")
+            .query(&mut redis_client).unwrap();
+        let data: Vec<f32> = data
+            .chunks(4)
+            .map(|a| {
+                let a: [u8; 4] = a.try_into().unwrap();
+                f32::from_le_bytes(a)
+            })
+            .collect();
+        let (tile_x, tile_y) = accumulator
+            .tile_at(x - area.region.x, y - area.region.y)
+            .unwrap();
         //Remove temporary storage
         let _: () = redis::Cmd::del(temp).query(&mut redis_client).unwrap();
+        //A redelivered task can finish twice, the second result is dropped
+        let sample = TileSample { tile_x, tile_y, index };
+        if let Err(err) = accumulator.add(sample, &data) {
+            println!("[{}] {}", render_id, err);
+            continue;
+        }
         counter += 1;
-        if counter >= samples*16 {
-            break;
+        if let Some(path) = &checkpoint {
+            if saved.elapsed() >= CHECKPOINT_INTERVAL {
+                accumulator.save(path).unwrap();
+                saved = Instant::now();
+            }
         }
 
         //Update percentage
-        let percentage = (counter as f32) / (samples as f32) / 16.0;
+        let percentage = accumulator.total_samples() as f32 / total;
         users
             .update_many(
                 doc! {"_id": user},
@@ -198,37 +373,24 @@ async fn handle_job(
     channel
         .queue_delete(response_queue, Default::default())
         .await.unwrap();
+    //The finished checkpoint is kept, so the render can be extended with more samples later
+    if let Some(path) = &checkpoint {
+        accumulator.save(path).unwrap();
+    }
     println!("[{}] Retrieving data", render_id);
-    let _: () = redis::cmd("AI.SCRIPTEXECUTE")
-        .arg("archyrt:scripts")
-        .arg("divide")
-        .arg("INPUTS")
-        .arg(1)
-        .arg(&image_key)
-        .arg("ARGS")
-        .arg(1)
-        .arg(samples)
-        .arg("OUTPUTS")
-        .arg(1)
-        .arg(&image_key)
-        .query(&mut redis_client).unwrap();
-    let image: Vec<u8> = redis::cmd("AI.TENSORGET")
-        .arg(&image_key)
-        .arg("BLOB")
-        .query(&mut redis_client).unwrap();
-    let image: Vec<f32> = image
-        .chunks(4)
-        .map(|a| {
-            let a: [u8; 4] = a.try_into().unwrap();
-            f32::from_le_bytes(a)
-        })
-        .collect();
+    let mut image = accumulator.image();
+    if mode.is_lit() {
+        mode.color_space().convert_image(&mut image, color_space);
+    }
 
-    let width: usize = redis::Cmd::get(format!("archyrt:{}:width", s)).query(&mut redis_client).unwrap();
-    let height: usize =
-        redis::Cmd::get(format!("archyrt:{}:height", s)).query(&mut redis_client).unwrap();
+    let (width, height) = (area.region.width, area.region.height);
 
-    let output: Vec<f32> = denoise(width, height, image, &s, &mut redis_client, &props, render_id, &textures);
+    //Debug views are saved as they are
+    let output: Vec<f32> = if mode.is_lit() {
+        denoise(area, image, &s, &mut redis_client, &props, render_id, &textures)
+    } else {
+        image
+    };
 
     println!("[{}] Saving", render_id);
     let mut image = image::RgbImage::new(width as u32, height as u32);
@@ -240,7 +402,7 @@ async fn handle_job(
         let index = y as usize * width + x as usize;
         let c = output[index];
         
-        let c = tonemap_fragment(c);
+        let c = if mode.is_lit() { tonemap_fragment(c) } else { c };
 
         let r = c.x()*255.0;
         let g = c.y()*255.0;
@@ -253,29 +415,35 @@ async fn handle_job(
         let b = b as u8;
         *color = Rgb([r, g, b]);
     }
-    let path = Path::new(&env::var("IMAGES").unwrap())
+    //Paste a cropped render into the finished one, scaled to the preview if needed
+    let image = match base.filter(|_| area.is_cropped()) {
+        Some(base) => {
+            let base = image::open(Path::new(&images).join(base).with_extension("png")).unwrap().to_rgb8();
+            let mut base = if base.dimensions() == (area.width as u32, area.height as u32) {
+                base
+            } else {
+                image::imageops::resize(&base, area.width as u32, area.height as u32, image::imageops::FilterType::Triangle)
+            };
+            image::imageops::replace(&mut base, &image, area.region.x as u32, area.region.y as u32);
+            base
+        }
+        None => image,
+    };
+    let path = Path::new(&images)
         .join(s)
         .with_extension("png");
-    image.save(path).unwrap();
+    //Debug views aren't converted, they are tagged as sRGB
+    let color_space = if mode.is_lit() { color_space } else { ColorSpace::Srgb };
+    std::fs::write(path, color_space.encode_png(&image).unwrap()).unwrap();
     users.update_many(doc! {"_id": user}, doc!{"$set":{"projects.$[project].renders.$[render].finished": DateTime::now(), "projects.$[project].renders.$[render].status": 1.0, "projects.$[project].renders.$[render].icon": render_id.to_hex()}}, UpdateOptions::builder().array_filters(vec![doc!{"render._id": render_id}, doc!{"project._id": project_id}]).build()).await.unwrap();
-    let _: () = redis::Cmd::del(&image_key).query(&mut redis_client).unwrap();
+    //Only acknowledged once finished, an interrupted job is delivered again and resumes from the checkpoint
+    channel
+        .basic_ack(delivery.delivery_tag, Default::default())
+        .await.unwrap();
     println!("[{}] Done!", render_id);
     Ok(())
 }
 
-static TORCHSCRIPT: &str = "
-def add(tensors: List[Tensor], keys: List[str], args: List[str]):
-    x = int(args[0])
-    y = int(args[1])
-    w = tensors[0].shape[1]
-    h = tensors[0].shape[0]
-    t = torch.clone(tensors[1])
-    t[y:y+h,x:x+w,:] += tensors[0]
-    return t
-def divide(tensors: List[Tensor], keys: List[str], args: List[str]):
-    return tensors[0]/int(args[0])
-";
-
 fn main() -> Result<()> {
     dotenv().ok();
     println!("Hello, world!");
@@ -284,6 +452,9 @@ fn main() -> Result<()> {
     let redis_addr = env::var("REDIS_ADDR").unwrap();
     let mongodb_addr = env::var("MONGODB_ADDR").unwrap();
     env::var("IMAGES").unwrap();
+    if let Ok(checkpoints) = env::var("CHECKPOINTS") {
+        std::fs::create_dir_all(checkpoints)?;
+    }
     async_global_executor::block_on(async {
         let mongodb_options = ClientOptions::parse(mongodb_addr).await.unwrap();
         let mongodb_client = mongodb::Client::with_options(mongodb_options).unwrap();
@@ -295,17 +466,6 @@ fn main() -> Result<()> {
         )
         .await.unwrap();
         let mut redis_client = redis::Client::open(redis_addr).unwrap();
-        let _: () = redis::cmd("AI.SCRIPTSTORE")
-            .arg("archyrt:scripts")
-            .arg("CPU")
-            .arg("ENTRY_POINTS")
-            .arg(2)
-            .arg("add")
-            .arg("divide")
-            .arg("SOURCE")
-            .arg(TORCHSCRIPT)
-            .query(&mut redis_client).unwrap();
-
         let channel = rabbitmq_client.create_channel().await.unwrap();
 
         let queue = channel
@@ -314,6 +474,9 @@ fn main() -> Result<()> {
         let task_queue = channel
             .queue_declare("archyrt:taskqueue", Default::default(), Default::default())
             .await.unwrap();
+        let bake_queue = channel
+            .queue_declare("archyrt:bake", Default::default(), Default::default())
+            .await.unwrap();
 
         let mut consumer = channel
             .basic_consume(
@@ -323,14 +486,48 @@ fn main() -> Result<()> {
                 FieldTable::default(),
             )
             .await.unwrap();
+        //Same textures as the workers, so validation sees the skybox they render with
         let mut textures = TextureRepository::new();
-        amdl_textures::load_into(&mut textures, "../assets").unwrap();
+        ascn::load_textures(&mut textures, "../assets").unwrap();
         let textures = Arc::new(textures);
 
         let mut props = PropRepository::new();
-        amdl::repo::load_into(&mut props, &textures, "../assets").unwrap();
+        amdl::repo::load_into(&mut props, "../assets").unwrap();
         let props = Arc::new(props);
 
+        //Lightmaps are baked here one at a time, off the executor
+        let mut bake_consumer = channel
+            .basic_consume(
+                bake_queue.name().as_str(),
+                "archyrt:baker",
+                BasicConsumeOptions::default(),
+                FieldTable::default(),
+            )
+            .await.unwrap();
+        {
+            let (channel, redis_client, textures, props) = (channel.clone(), redis_client.clone(), textures.clone(), props.clone());
+            async_global_executor::spawn(async move {
+                while let Some(delivery) = bake_consumer.next().await {
+                    let (_, delivery) = delivery.unwrap();
+                    let project = String::from_utf8(delivery.data).unwrap();
+                    println!("[{}] Baking lightmap", project);
+                    let (mut redis_client, textures, props) = (redis_client.clone(), textures.clone(), props.clone());
+                    let result = {
+                        let project = project.clone();
+                        async_global_executor::spawn_blocking(move || bake_lightmap(&project, &mut redis_client, &textures, &props)).await
+                    };
+                    match result {
+                        Ok(()) => println!("[{}] Lightmap baked", project),
+                        Err(err) => println!("[{}] Couldn't bake lightmap: {}", project, err),
+                    }
+                    channel
+                        .basic_ack(delivery.delivery_tag, Default::default())
+                        .await.unwrap();
+                }
+            })
+            .detach();
+        }
+
         while let Some(delivery) = consumer.next().await {
             let (_, delivery) = delivery.unwrap();
             let response_queue = channel
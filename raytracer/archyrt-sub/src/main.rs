@@ -1,22 +1,21 @@
-mod shifted_view;
-
 use std::env;
 
 use anyhow::{anyhow, Result};
 use archyrt_core::{
-    api::fragment_collector::FragmentCollector,
-    cameras::{perspective::PerspectiveCamera, jitter::JitterCamera},
+    api::{fragment_collector::FragmentCollector, fragment_render::FragmentRender},
+    cameras::{perspective::PerspectiveCamera, jitter::JitterCamera, shutter::ShutterCamera},
     collector::array_collector::ArrayCollector,
     intersectables::bvh::BVH,
     loaders::{
-        ascn::{amdl_textures, ASCNLoader},
+        ascn::{self, settings::SceneSettings, ASCNLoader},
         Loader, amdl::{repo::{PropRequest, PropRepository}, self},
     },
-    renderers::{path_tracer::PathTracer},
-    textures::{
-        texture_repo::{self, TextureRepository},
-        TextureID,
-    }, utilities::ray::Intersectable,
+    renderers::{
+        mode::RenderMode,
+        path_tracer::{medium::Media, spectral::SpectralPathTracer, PathTracer},
+        region::{Region, RegionView, RenderArea},
+    },
+    textures::texture_repo::TextureRepository, utilities::{ray::Intersectable, rng, stats::Profiler},
 };
 use dotenv::dotenv;
 use futures::{StreamExt, future::JoinAll, Future};
@@ -24,9 +23,7 @@ use lapin::{message::Delivery, Channel, Connection, ConnectionProperties};
 use lru::LruCache;
 use redis::AsyncCommands;
 
-use crate::shifted_view::ShiftedView;
-
-struct SceneData(Option<BVH>, JitterCamera<PerspectiveCamera>, Vec<PropRequest>);
+struct SceneData(Option<BVH>, ShutterCamera<JitterCamera<PerspectiveCamera>>, Vec<PropRequest>, Media);
 
 async fn render(
     texture_repo: &TextureRepository,
@@ -44,42 +41,87 @@ async fn render(
     let response = s[2].to_string();
     let x: usize = s[3].parse()?;
     let y: usize = s[4].parse()?;
+    let part_width: usize = s[5].parse()?;
+    let part_height: usize = s[6].parse()?;
+    //Seeded tasks render the same sample again when they are repeated
+    let seed: Option<u64> = s.get(7).map(|seed| seed.parse()).transpose()?;
+    //Sample of the tile the part is, sent back with the result so the dispatcher knows which one finished
+    let index: Option<u32> = s.get(8).map(|index| index.parse()).transpose()?;
     let width: usize = redis::Cmd::get(format!("archyrt:{}:width", task)).query(redis_client)?;
     let height: usize = redis::Cmd::get(format!("archyrt:{}:height", task)).query(redis_client)?;
-    let part_width = width/4;
-    let part_height = height/4;
+    //Jobs without a mode are path traced
+    let mode: Option<String> = redis::Cmd::get(format!("archyrt:{}:mode", task)).query(redis_client)?;
+    let mode = match mode {
+        Some(mode) => mode.parse()?,
+        None => RenderMode::PathTraced,
+    };
+    //Parts are given in pixels of the preview, the crop is already applied
+    let scale: Option<f64> = redis::Cmd::get(format!("archyrt:{}:scale", task)).query(redis_client)?;
+    let area = RenderArea::new(width, height, scale.unwrap_or(1.0), None)?;
     let scene = match cache.get(&task) {
         Some(a) => a,
         None => {
             let scene: Vec<u8> =
                 redis::Cmd::get(format!("archyrt:{}:scene", task)).query(redis_client)?;
-            let scene = ASCNLoader::from_bytes(&scene)?;
+            //Jobs without settings render a still scene
+            let settings: Option<String> =
+                redis::Cmd::get(format!("archyrt:{}:settings", task)).query(redis_client)?;
+            let settings = match settings {
+                Some(settings) => SceneSettings::from_json(&settings)?,
+                None => SceneSettings::default(),
+            };
+            let mut scene = ASCNLoader::from_bytes_with(&scene, &settings)?;
+            scene.apply_alpha_masks(texture_repo);
             let bvh = BVH::from_triangles(scene.get_triangles());
             let camera = scene.get_camera().clone();
-            let camera = JitterCamera::new(camera, width, height);
+            let camera = JitterCamera::new(camera, area.width, area.height);
+            let (open, close) = scene.get_shutter();
+            let camera = ShutterCamera::new(camera, open, close);
             let prop_requests = scene.get_prop_requests().clone();
-            let data = SceneData(bvh, camera, prop_requests);
+            let data = SceneData(bvh, camera, prop_requests, scene.get_media());
             cache.put(task.clone(), data);
             cache.get(&task).unwrap()
         }
     };
-    let props = prop_repo.fulfill_all(&scene.2)?;
+    let props = prop_repo.fulfill_all(&scene.2, texture_repo)?;
     let object = &scene.0;
     let object = object.union(props);
-    let renderer = PathTracer {
-        camera: &scene.1,
-        object,
-        bounces: 5,
-        skybox: Some(TextureID::new(&"skybox")),
+    let renderer: Box<dyn FragmentRender> = match mode.debug_renderer(&scene.1, &object) {
+        Some(renderer) => renderer,
+        None if mode == RenderMode::Spectral => Box::new(SpectralPathTracer {
+            camera: &scene.1,
+            object: &object,
+            bounces: 5,
+            skybox: Some(ascn::skybox()),
+            media: scene.3.clone(),
+        }),
+        None => Box::new(PathTracer {
+            camera: &scene.1,
+            object: &object,
+            bounces: 5,
+            skybox: Some(ascn::skybox()),
+            media: scene.3.clone(),
+        }),
     };
-    let renderer = ShiftedView{
+    let renderer = RegionView {
         inner: renderer,
-        full_w: width,
-        full_h: height,
-        x: (x as f64)/(width as f64),
-        y: (y as f64)/(height as f64)
+        full_width: area.width,
+        full_height: area.height,
+        region: Region {
+            x,
+            y,
+            width: part_width,
+            height: part_height,
+        },
     };
-    let image = ArrayCollector {}.collect(renderer, texture_repo, part_width, part_height);
+    if let Some(seed) = seed {
+        rng::seed(seed);
+    }
+    let mut profiler = Profiler::new();
+    let image = profiler.pass(mode.name(), || {
+        ArrayCollector {}.collect(renderer, texture_repo, part_width, part_height)
+    });
+    println!("Rendered part ({}, {}) of {}\n{}", x, y, task, profiler);
     //Convert image into bytes
     let image: Vec<u8> = image
         .into_iter()
@@ -93,6 +135,10 @@ async fn render(
     let image_key = format!("archyrt:{}:image", task);
     let channel = channel.clone();
     let redis_client = redis_client.clone();
+    let ack = match index {
+        Some(index) => format!("{}#{}#{}#{}", temp, x, y, index),
+        None => format!("{}#{}#{}", temp, x, y),
+    };
 
     async_global_executor::spawn(async move {
         let mut con = redis_client.get_async_connection().await.unwrap();
@@ -114,7 +160,7 @@ async fn render(
                 "",
                 &response,
                 Default::default(),
-                format!("{}#{}#{}", temp, x, y).into_bytes(),
+                ack.into_bytes(),
                 Default::default(),
             )
             .await
@@ -128,22 +174,26 @@ async fn render(
     Ok(())
 }
 
+/// Memory budget in bytes from an environment variable given in megabytes, unlimited if unset
+fn budget(key: &str) -> Result<usize> {
+    match env::var(key) {
+        Ok(value) => Ok(value.parse::<usize>()? * 1024 * 1024),
+        Err(_) => Ok(usize::MAX),
+    }
+}
+
 fn main() -> Result<()> {
     dotenv().ok();
     println!("Hello, world!");
 
     let amqp_addr = env::var("AMQP_ADDR").unwrap();
     let redis_addr = env::var("REDIS_ADDR").unwrap();
-    let mut textures = TextureRepository::new();
-    amdl_textures::load_into(&mut textures, "../assets")?;
-    texture_repo::exr::load_into(
-        &mut textures,
-        "../assets",
-        &[(TextureID::new(&"skybox"), "skybox.exr")],
-    )?;
+    //Assets are loaded on demand and shared by all workers
+    let mut textures = TextureRepository::with_budget(budget("TEXTURE_BUDGET_MB")?);
+    ascn::load_textures(&mut textures, "../assets")?;
 
-    let mut props = PropRepository::new();
-    amdl::repo::load_into(&mut props, &textures, "../assets")?;
+    let mut props = PropRepository::with_budget(budget("PROP_BUDGET_MB")?);
+    amdl::repo::load_into(&mut props, "../assets")?;
     
     let cores = num_cpus::get();
     let f = futures::future::join_all((0..cores).map(|instance| async {